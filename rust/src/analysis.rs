@@ -0,0 +1,943 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::colors::{NUM_COLORS, colors};
+#[cfg(feature = "std")]
+use crate::list_coloring_utils::partitions_of_colors;
+use crate::list_coloring_utils::{
+    Branch, FeatureSchema, MaxDegree, NodeFeatures, NonFiniteFeature, apply_all_partitions,
+    apply_list_coloring_partition_with_reductions, group_branches, reduce_branch, star_features,
+};
+use crate::recurrence::Recurrence;
+use crate::reductions::{DEFAULT_REDUCTIONS, Reduction};
+use crate::star_utils::{EnumerationConfig, Star, fold_stars_with_config};
+
+/// Rules that leave stars out of an analysis.
+///
+/// A vertex whose list has more colors than its degree can always be colored after all of its
+/// neighbors, so it can be removed from the graph first and stars with such a vertex need no
+/// case of their own.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CaseFilter {
+    /// Exclude stars whose root list is larger than the number of neighbors.
+    pub root_list_exceeds_degree: bool,
+    /// Exclude stars with a neighbor whose list is larger than its degree, `halfedges + 1`.
+    pub neighbor_list_exceeds_degree: bool,
+}
+
+impl CaseFilter {
+    pub const ROOT_RULE: &'static str = "root-list-exceeds-degree";
+    pub const NEIGHBOR_RULE: &'static str = "neighbor-list-exceeds-degree";
+
+    /// Whether some enabled rule excludes `star`.
+    pub fn excludes(&self, star: &Star) -> bool {
+        let root = self.root_list_exceeds_degree
+            && star.root_colors.count_ones() as usize > star.neighbor_colors.len();
+        let neighbor = self.neighbor_list_exceeds_degree
+            && star
+                .neighbor_colors
+                .iter()
+                .zip(star.neighbor_halfedges.iter())
+                .any(|(&c, &h)| c.count_ones() > h as u32 + 1);
+        root || neighbor
+    }
+
+    /// The names of the enabled rules.
+    pub fn rules(&self) -> Vec<&'static str> {
+        [
+            (self.root_list_exceeds_degree, Self::ROOT_RULE),
+            (self.neighbor_list_exceeds_degree, Self::NEIGHBOR_RULE),
+        ]
+        .into_iter()
+        .filter_map(|(enabled, name)| enabled.then_some(name))
+        .collect()
+    }
+
+    /// Enables the rules with the given names.
+    pub fn from_rules<'a>(rules: impl IntoIterator<Item = &'a str>) -> Result<Self, String> {
+        let mut filter = CaseFilter::default();
+        for rule in rules {
+            match rule {
+                Self::ROOT_RULE => filter.root_list_exceeds_degree = true,
+                Self::NEIGHBOR_RULE => filter.neighbor_list_exceeds_degree = true,
+                _ => return Err(format!("unknown exclusion rule: {rule}")),
+            }
+        }
+        Ok(filter)
+    }
+}
+
+/// What an analysis covers: the stars it enumerates, the degree buckets it measures them
+/// with, the partitions they may branch on, and the reductions that simplify the branches.
+#[derive(Clone)]
+pub struct AnalysisConfig<'a> {
+    /// The stars to analyze. Their maximum degree also sets the degree buckets of the
+    /// measure, see [`AnalysisConfig::max_degree`].
+    pub enumeration: EnumerationConfig,
+    /// The most blocks of a partition a star may branch on, for algorithms with a bounded
+    /// number of branches per step; `None` for no limit.
+    pub max_blocks: Option<usize>,
+    /// The reductions applied to every branch, in order of priority; see
+    /// [`select_reductions`](crate::reductions::select_reductions) for choosing them by name.
+    pub reductions: &'a [&'a dyn Reduction],
+    /// Whether each case also records the Pareto front of its partitions, see
+    /// [`pareto_partitions`].
+    pub pareto: bool,
+    /// The rules by which the analysis reports leave stars out before analyzing them.
+    pub exclusions: CaseFilter,
+}
+
+impl Default for AnalysisConfig<'_> {
+    fn default() -> Self {
+        AnalysisConfig {
+            enumeration: EnumerationConfig::default(),
+            max_blocks: None,
+            reductions: DEFAULT_REDUCTIONS,
+            pareto: false,
+            exclusions: CaseFilter::default(),
+        }
+    }
+}
+
+// Reductions are compared and printed by name, which identifies them in reports.
+impl core::fmt::Debug for AnalysisConfig<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("AnalysisConfig")
+            .field("enumeration", &self.enumeration)
+            .field("max_blocks", &self.max_blocks)
+            .field("reductions", &self.reduction_names())
+            .field("pareto", &self.pareto)
+            .field("exclusions", &self.exclusions)
+            .finish()
+    }
+}
+
+impl PartialEq for AnalysisConfig<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.enumeration == other.enumeration
+            && self.max_blocks == other.max_blocks
+            && self.reduction_names() == other.reduction_names()
+            && self.pareto == other.pareto
+            && self.exclusions == other.exclusions
+    }
+}
+
+impl Eq for AnalysisConfig<'_> {}
+
+impl AnalysisConfig<'_> {
+    /// The analysis of the stars that occur in graphs of maximum degree `max_degree`.
+    pub fn for_max_degree(max_degree: usize) -> Self {
+        AnalysisConfig {
+            enumeration: EnumerationConfig {
+                max_degree: Some(max_degree),
+                ..EnumerationConfig::default()
+            },
+            ..AnalysisConfig::default()
+        }
+    }
+
+    /// Checks that `enumeration` is valid and that the measure has weights for its lists: it
+    /// weighs lists of 2 to [`NUM_COLORS`] colors only, so stars with more colors, which the
+    /// enumeration and the reductions support, cannot be analyzed.
+    pub fn validate(&self) -> Result<(), String> {
+        self.enumeration.validate()?;
+        if self.enumeration.colors > NUM_COLORS {
+            return Err(format!(
+                "the measure has weights for lists of at most {NUM_COLORS} colors, not {}",
+                self.enumeration.colors
+            ));
+        }
+        Ok(())
+    }
+
+    /// The degree buckets of the measure: those of `enumeration.max_degree`, and of the
+    /// default Δ = 5 without a maximum degree.
+    pub fn max_degree(&self) -> MaxDegree {
+        self.enumeration
+            .max_degree
+            .map(MaxDegree)
+            .unwrap_or_default()
+    }
+
+    /// Whether a star may branch on `partition`: it has at least 2 blocks, and at most
+    /// `max_blocks`.
+    pub fn allows(&self, partition: &[u8]) -> bool {
+        partition.len() >= 2 && self.max_blocks.is_none_or(|b| partition.len() <= b)
+    }
+
+    /// The names of `reductions`, in order.
+    pub fn reduction_names(&self) -> Vec<&'static str> {
+        self.reductions.iter().map(|r| r.name()).collect()
+    }
+
+    /// Applies `partition` to `star` like [`apply_list_coloring_partition`], simplifying the
+    /// branches with `reductions`.
+    ///
+    /// [`apply_list_coloring_partition`]: crate::list_coloring_utils::apply_list_coloring_partition
+    pub fn branches(&self, star: &Star, partition: &[u8]) -> Vec<Branch> {
+        apply_list_coloring_partition_with_reductions(star, partition, self.reductions)
+    }
+
+    /// The partitions of the root list of `star` that the config [allows](Self::allows), in
+    /// the order of [`partitions_of_colors`], each with its [branches](Self::branches). The
+    /// partitions are applied together by [`apply_all_partitions`].
+    pub fn all_branches(&self, star: &Star) -> impl Iterator<Item = (Vec<u8>, Vec<Branch>)> {
+        apply_all_partitions(star)
+            .into_iter()
+            .filter(|(partition, _)| self.allows(partition))
+            .map(|(partition, branches)| {
+                let branches = branches
+                    .into_iter()
+                    .map(|b| reduce_branch(b, self.reductions))
+                    .collect();
+                (partition, branches)
+            })
+    }
+}
+
+/// Returns the measure `weights * counts` of a star, with the degree buckets of
+/// `max_degree`.
+pub fn star_measure(star: &Star, weights: NodeFeatures, max_degree: MaxDegree) -> f64 {
+    weights * star_features(star, FeatureSchema::Counts, max_degree)
+}
+
+/// Returns the recurrence of applying `partition` to `star`: for every branch, the drop in the
+/// measure from `star` to the branch, measured with the degree buckets of `config`.
+///
+/// [`Branch::Infeasible`] branches terminate immediately and are only counted as free
+/// branches. A [`Branch::Solved`] branch drops the full measure of `star`.
+pub fn partition_recurrence(
+    star: &Star,
+    partition: &[u8],
+    weights: NodeFeatures,
+    config: &AnalysisConfig,
+) -> Recurrence {
+    branches_recurrence(star, &config.branches(star, partition), weights, config)
+}
+
+/// The drop in the measure from a star of measure `before` to `branch`, measured with the
+/// degree buckets of `max_degree`: all of `before` for a [`Branch::Solved`] branch, and `None`
+/// for a [`Branch::Infeasible`] one.
+pub fn branch_drop(
+    before: f64,
+    branch: &Branch,
+    weights: NodeFeatures,
+    max_degree: MaxDegree,
+) -> Option<f64> {
+    match branch {
+        Branch::Solved => Some(before),
+        Branch::Infeasible => None,
+        Branch::Star(b) => Some(before - star_measure(b, weights, max_degree)),
+    }
+}
+
+/// The recurrence of `star` branching into `branches`, with the drops of [`branch_drop`] under
+/// the degree buckets of `config`.
+pub fn branches_recurrence(
+    star: &Star,
+    branches: &[Branch],
+    weights: NodeFeatures,
+    config: &AnalysisConfig,
+) -> Recurrence {
+    let max_degree = config.max_degree();
+    let before = star_measure(star, weights, max_degree);
+    Recurrence::from_branches(
+        branches
+            .iter()
+            .map(|branch| (branch, branch_drop(before, branch, weights, max_degree))),
+    )
+}
+
+/// A branch containing a vertex of degree larger than the maximum degree the measure was
+/// analyzed for, so its drop would be meaningless.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DegreeWindowViolation {
+    pub partition: Vec<u8>,
+    pub branch: Star,
+    /// The offending neighbor of `branch`, or `None` for the root.
+    pub vertex: Option<usize>,
+    pub degree: usize,
+    pub max_degree: usize,
+}
+
+impl core::fmt::Display for DegreeWindowViolation {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let vertex = match self.vertex {
+            Some(i) => alloc::format!("neighbor {i}"),
+            None => "the root".into(),
+        };
+        write!(
+            f,
+            "a branch gives {vertex} degree {} > {}",
+            self.degree, self.max_degree
+        )
+    }
+}
+
+impl core::error::Error for DegreeWindowViolation {}
+
+/// Returns the first vertex of `star` (`None` for the root) whose degree exceeds
+/// `max_degree`, with that degree. Degrees follow [`star_list_degree_counts`].
+pub fn degree_above(star: &Star, max_degree: usize) -> Option<(Option<usize>, usize)> {
+    if star.neighbor_colors.len() > max_degree {
+        return Some((None, star.neighbor_colors.len()));
+    }
+    star.neighbor_halfedges
+        .iter()
+        .map(|&h| h as usize + 1)
+        .enumerate()
+        .find(|&(_, d)| d > max_degree)
+        .map(|(i, d)| (Some(i), d))
+}
+
+/// Like [`partition_recurrence`], but fails if some branch has a vertex of degree larger than
+/// the maximum degree of `config`, e.g. because reductions merged neighbors into one of
+/// higher degree.
+pub fn partition_recurrence_checked(
+    star: &Star,
+    partition: &[u8],
+    weights: NodeFeatures,
+    config: &AnalysisConfig,
+) -> Result<Recurrence, DegreeWindowViolation> {
+    let max_degree = config.max_degree().0;
+    let branches = config.branches(star, partition);
+    for branch in branches.iter() {
+        let Branch::Star(b) = branch else {
+            continue;
+        };
+        if let Some((vertex, degree)) = degree_above(b, max_degree) {
+            return Err(DegreeWindowViolation {
+                partition: partition.to_vec(),
+                branch: b.clone(),
+                vertex,
+                degree,
+                max_degree,
+            });
+        }
+    }
+    Ok(branches_recurrence(star, &branches, weights, config))
+}
+
+/// Returns the branching vector of applying `partition` to `star`, see
+/// [`partition_recurrence`].
+pub fn partition_drops(
+    star: &Star,
+    partition: &[u8],
+    weights: NodeFeatures,
+    config: &AnalysisConfig,
+) -> Vec<f64> {
+    partition_recurrence(star, partition, weights, config).drops
+}
+
+/// Returns the branching vector of applying `partition` to `star` with one `(drop, multiplicity)`
+/// entry per branch type (see [`group_branches`]). Infeasible branches are left out.
+pub fn partition_branch_types(
+    star: &Star,
+    partition: &[u8],
+    weights: NodeFeatures,
+    config: &AnalysisConfig,
+) -> Vec<(f64, usize)> {
+    let max_degree = config.max_degree();
+    let before = star_measure(star, weights, max_degree);
+    group_branches(config.branches(star, partition))
+        .into_iter()
+        .filter_map(|(branch, multiplicity)| {
+            Some((
+                branch_drop(before, &branch, weights, max_degree)?,
+                multiplicity,
+            ))
+        })
+        .collect()
+}
+
+/// Finds the partition of the root list with the smallest branching number among the
+/// partitions `config` allows (see [`AnalysisConfig::allows`]).
+///
+/// The trivial partition with a single block is skipped, since it does not branch. Ties are
+/// broken by the order of [`partitions_of_colors`].
+///
+/// Returns `None` if the root list has fewer than 2 colors or `config` allows no partition.
+pub fn best_partition(
+    star: &Star,
+    weights: NodeFeatures,
+    config: &AnalysisConfig,
+) -> Option<(Vec<u8>, f64)> {
+    let mut best: Option<(Vec<u8>, f64)> = None;
+    for (partition, branches) in config.all_branches(star) {
+        let bn = branches_recurrence(star, &branches, weights, config).branching_number();
+        if best.as_ref().is_none_or(|(_, best_bn)| bn < *best_bn) {
+            best = Some((partition, bn));
+        }
+    }
+    best
+}
+
+/// The partitions of `root_colors` into one color and the rest, in the order of the colors.
+/// A 2-list has only one such partition. Empty if the list has fewer than 2 colors.
+pub fn singleton_partitions(root_colors: u8) -> Vec<Vec<u8>> {
+    let n = root_colors.count_ones() as usize;
+    if n < 2 {
+        return Vec::new();
+    }
+    // With 2 colors, both singletons give the same partition.
+    colors(root_colors)
+        .take(if n == 2 { 1 } else { n })
+        .map(|c| alloc::vec![1 << c, root_colors & !(1 << c)])
+        .collect()
+}
+
+/// The result of [`adaptive_partition`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct AdaptivePartition {
+    pub partition: Vec<u8>,
+    pub branching_number: f64,
+    /// Whether the singleton partitions missed the bound, so all partitions were searched.
+    pub full_search: bool,
+}
+
+/// Finds a partition of the root list with branching number at most `bound`, trying the cheap
+/// [`singleton_partitions`] that `config` allows first: if the best of them meets the bound, it
+/// is returned without enumerating the other partitions, and otherwise the result of
+/// [`best_partition`]. So the branching number exceeds `bound` only if every allowed partition
+/// does, and the partition is the best one only after a full search.
+///
+/// Returns `None` if the root list has fewer than 2 colors or `config` allows no partition.
+pub fn adaptive_partition(
+    star: &Star,
+    weights: NodeFeatures,
+    config: &AnalysisConfig,
+    bound: f64,
+) -> Option<AdaptivePartition> {
+    let mut best: Option<(Vec<u8>, f64)> = None;
+    for partition in singleton_partitions(star.root_colors) {
+        if !config.allows(&partition) {
+            continue;
+        }
+        let bn = partition_recurrence(star, &partition, weights, config).branching_number();
+        if best.as_ref().is_none_or(|(_, best_bn)| bn < *best_bn) {
+            best = Some((partition, bn));
+        }
+    }
+    if let Some((partition, branching_number)) = best
+        && branching_number <= bound
+    {
+        return Some(AdaptivePartition {
+            partition,
+            branching_number,
+            full_search: false,
+        });
+    }
+    let (partition, branching_number) = best_partition(star, weights, config)?;
+    Some(AdaptivePartition {
+        partition,
+        branching_number,
+        full_search: true,
+    })
+}
+
+/// The result of [`adaptive_analysis`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AdaptiveSummary {
+    /// The number of stars with a root list of at least 2 colors.
+    pub stars: usize,
+    /// The number of those stars that needed the search of all partitions.
+    pub full_searches: usize,
+    /// The stars whose best partition exceeds the bound, with their branching numbers, in the
+    /// order of the enumeration. A star with no partition `config` allows has an infinite
+    /// branching number.
+    pub violations: Vec<(Star, f64)>,
+}
+
+/// Checks that every star of
+/// [`generate_stars_with_config`](crate::star_utils::generate_stars_with_config) has a
+/// partition with branching number at most `bound` by [`adaptive_partition`], counting the
+/// stars that needed the full search. Fails if some weight is not finite.
+pub fn adaptive_analysis(
+    weights: NodeFeatures,
+    bound: f64,
+    degree: usize,
+    config: &AnalysisConfig,
+) -> Result<AdaptiveSummary, NonFiniteFeature> {
+    weights.validate()?;
+    Ok(fold_stars_with_config(
+        degree,
+        &config.enumeration,
+        AdaptiveSummary::default(),
+        |mut summary, star| {
+            if star.root_colors.count_ones() < 2 {
+                return summary;
+            }
+            summary.stars += 1;
+            let branching_number = match adaptive_partition(&star, weights, config, bound) {
+                Some(found) => {
+                    summary.full_searches += found.full_search as usize;
+                    found.branching_number
+                }
+                None => {
+                    summary.full_searches += 1;
+                    f64::INFINITY
+                }
+            };
+            if branching_number > bound {
+                summary.violations.push((star, branching_number));
+            }
+            summary
+        },
+    ))
+}
+
+/// A partition on the Pareto front of [`pareto_partitions`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParetoPartition {
+    pub partition: Vec<u8>,
+    pub branching_number: f64,
+    /// Number of branches that recurse, i.e. all but the infeasible ones.
+    pub branches: usize,
+}
+
+/// Returns the partitions of the root list that `config` allows and that are Pareto-optimal
+/// for (branching number, number of branches): no other partition is at least as good in both
+/// and better in one. Sorted by the number of branches, so the last one has the best
+/// branching number. Among partitions with the same values, the first one of
+/// [`partitions_of_colors`] is kept. Partitions with an infinite branching number are left
+/// out, unless all of them have one.
+///
+/// Returns an empty vector if the root list has fewer than 2 colors.
+pub fn pareto_partitions(
+    star: &Star,
+    weights: NodeFeatures,
+    config: &AnalysisConfig,
+) -> Vec<ParetoPartition> {
+    let mut candidates: Vec<ParetoPartition> = config
+        .all_branches(star)
+        .map(|(partition, branches)| {
+            let recurrence = branches_recurrence(star, &branches, weights, config);
+            ParetoPartition {
+                branching_number: recurrence.branching_number(),
+                branches: recurrence.drops.len(),
+                partition,
+            }
+        })
+        .collect();
+    if candidates.iter().any(|c| c.branching_number.is_finite()) {
+        candidates.retain(|c| c.branching_number.is_finite());
+    }
+    let dominates = |a: &ParetoPartition, b: &ParetoPartition| {
+        a.branching_number <= b.branching_number
+            && a.branches <= b.branches
+            && (a.branching_number < b.branching_number || a.branches < b.branches)
+    };
+    let mut front: Vec<ParetoPartition> = Vec::new();
+    for (i, c) in candidates.iter().enumerate() {
+        let dominated = candidates.iter().any(|other| dominates(other, c));
+        let duplicate = candidates[..i]
+            .iter()
+            .any(|o| o.branches == c.branches && o.branching_number == c.branching_number);
+        if !dominated && !duplicate {
+            front.push(c.clone());
+        }
+    }
+    front.sort_by_key(|p| p.branches);
+    front
+}
+
+/// The result of [`best_partition_with_budget`].
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct BudgetedPartition {
+    pub partition: Vec<u8>,
+    pub branching_number: f64,
+    /// Whether the budget ran out before all partitions were evaluated, so a better
+    /// partition may exist.
+    pub possibly_suboptimal: bool,
+}
+
+/// Like [`best_partition`], but stops evaluating partitions once `budget` has elapsed and
+/// returns the best partition found so far. At least one partition is evaluated.
+///
+/// A partition that an automorphism of the star maps to one already evaluated has the same
+/// branching number, so it is skipped and does not count as left out when the budget runs out.
+/// The first best partition of each orbit comes first, so an unlimited budget finds the
+/// partition of [`best_partition`].
+#[cfg(feature = "std")]
+pub fn best_partition_with_budget(
+    star: &Star,
+    weights: NodeFeatures,
+    config: &AnalysisConfig,
+    budget: std::time::Duration,
+) -> Option<BudgetedPartition> {
+    let start = std::time::Instant::now();
+    let automorphisms = star.automorphisms();
+    let orbit_key = |partition: &[u8]| {
+        automorphisms
+            .iter()
+            .map(|perm| {
+                let mut blocks: Vec<u8> = partition.iter().map(|&b| perm.apply(b)).collect();
+                blocks.sort_unstable();
+                blocks
+            })
+            .min()
+            .expect("the identity is an automorphism")
+    };
+    let mut evaluated: Vec<Vec<u8>> = Vec::new();
+    let mut best: Option<BudgetedPartition> = None;
+    for partition in partitions_of_colors(star.root_colors) {
+        if !config.allows(&partition) {
+            continue;
+        }
+        let key = orbit_key(&partition);
+        if evaluated.contains(&key) {
+            continue;
+        }
+        if let Some(best) = best.as_mut()
+            && start.elapsed() >= budget
+        {
+            best.possibly_suboptimal = true;
+            break;
+        }
+        evaluated.push(key);
+        let bn = partition_recurrence(star, &partition, weights, config).branching_number();
+        if best.as_ref().is_none_or(|b| bn < b.branching_number) {
+            best = Some(BudgetedPartition {
+                partition,
+                branching_number: bn,
+                possibly_suboptimal: false,
+            });
+        }
+    }
+    best
+}
+
+/// Finds the star of the given `degree` among the stars of `config` whose best partition has
+/// the largest branching number, provided that it exceeds `bound`. A star whose root list can
+/// be split but has no partition `config` allows has an infinite branching number.
+///
+/// This is the separation oracle for optimizing `weights`: it returns `None` if every star
+/// admits a partition with branching number at most `bound`, and otherwise a most violated
+/// star together with its best branching number. Fails if some weight is not finite.
+pub fn most_violated_star(
+    weights: NodeFeatures,
+    bound: f64,
+    degree: usize,
+    config: &AnalysisConfig,
+) -> Result<Option<(Star, f64)>, NonFiniteFeature> {
+    weights.validate()?;
+    // Streamed, so that only the running maximum is kept however many stars there are.
+    Ok(fold_stars_with_config(
+        degree,
+        &config.enumeration,
+        None,
+        |worst: Option<(Star, f64)>, star| {
+            let bn = match best_partition(&star, weights, config) {
+                Some((_, bn)) => bn,
+                None if star.root_colors.count_ones() >= 2 => f64::INFINITY,
+                None => return worst,
+            };
+            if bn > bound && worst.as_ref().is_none_or(|(_, worst_bn)| bn > *worst_bn) {
+                Some((star, bn))
+            } else {
+                worst
+            }
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::star_utils::generate_stars;
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    fn weights() -> NodeFeatures {
+        NodeFeatures {
+            n4_ge5: 1.0,
+            n4_4: 1.0,
+            n4_3: 1.0,
+            n3_ge5: 0.7,
+            n3_4: 0.7,
+            n3_3: 0.7,
+            n2_ge5: 0.4,
+            n2_4: 0.4,
+            n2_3: 0.4,
+        }
+    }
+
+    #[test]
+    fn the_measure_covers_at_most_four_colors() {
+        let mut config = AnalysisConfig::default();
+        assert_eq!(config.validate(), Ok(()));
+        config.enumeration.colors = 5;
+        assert_eq!(
+            config.validate().unwrap_err(),
+            "the measure has weights for lists of at most 4 colors, not 5"
+        );
+        config.enumeration.colors = 9;
+        assert!(config.enumeration.validate().is_err());
+        assert_eq!(config.validate(), config.enumeration.validate());
+    }
+
+    #[test]
+    fn pareto_front_trades_branches_for_branching_numbers() {
+        let mut fronts = 0;
+        for star in generate_stars(3).into_iter().take(100) {
+            let front = pareto_partitions(&star, weights(), &AnalysisConfig::default());
+            let Some((_, best)) = best_partition(&star, weights(), &AnalysisConfig::default())
+            else {
+                assert!(front.is_empty());
+                continue;
+            };
+            assert_eq!(front.last().unwrap().branching_number, best);
+            for pair in front.windows(2) {
+                assert!(pair[0].branches < pair[1].branches);
+                assert!(pair[0].branching_number > pair[1].branching_number);
+            }
+            fronts += (front.len() > 1) as usize;
+        }
+        assert!(fronts > 0);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn best_partition_with_budget_stops_early() {
+        let star = generate_stars(2)[0].clone();
+        let unbounded = best_partition(&star, weights(), &AnalysisConfig::default()).unwrap();
+        let full = best_partition_with_budget(
+            &star,
+            weights(),
+            &AnalysisConfig::default(),
+            std::time::Duration::MAX,
+        )
+        .unwrap();
+        assert_eq!((full.partition, full.branching_number), unbounded);
+        assert!(!full.possibly_suboptimal);
+
+        let rushed = best_partition_with_budget(
+            &star,
+            weights(),
+            &AnalysisConfig::default(),
+            Default::default(),
+        )
+        .unwrap();
+        assert!(rushed.possibly_suboptimal);
+        assert!(rushed.branching_number >= unbounded.1);
+
+        // Swapping colors 1 and 2 fixes the star, so {0,1}{2} and {0,2}{1} are one partition
+        // for the budget, and {0}{1,2} is the only other one of 2 blocks.
+        let symmetric = Star {
+            root_colors: 0b0111,
+            neighbor_colors: vec![0b1001, 0b0110, 0b0110],
+            neighbor_halfedges: vec![2, 2, 2],
+            neighbor_adjacency: None,
+        };
+        let config = AnalysisConfig {
+            max_blocks: Some(2),
+            ..AnalysisConfig::default()
+        };
+        let first =
+            best_partition_with_budget(&symmetric, weights(), &config, Default::default()).unwrap();
+        assert!(first.possibly_suboptimal);
+        let full =
+            best_partition_with_budget(&symmetric, weights(), &config, std::time::Duration::MAX)
+                .unwrap();
+        assert_eq!(
+            (full.partition, full.branching_number),
+            best_partition(&symmetric, weights(), &config).unwrap()
+        );
+    }
+
+    #[test]
+    fn adaptive_partition_meets_the_bound_of_the_full_search() {
+        assert_eq!(singleton_partitions(0b0110), vec![vec![0b0010, 0b0100]]);
+        assert_eq!(singleton_partitions(0b0111).len(), 3);
+        let mut full = 0;
+        for star in generate_stars(3).into_iter().take(200) {
+            let Some((_, best)) = best_partition(&star, weights(), &AnalysisConfig::default())
+            else {
+                continue;
+            };
+            let bound = 1.2;
+            let found =
+                adaptive_partition(&star, weights(), &AnalysisConfig::default(), bound).unwrap();
+            assert_eq!(found.branching_number <= bound, best <= bound, "{star:?}");
+            if found.full_search {
+                assert_eq!(found.branching_number, best);
+                full += 1;
+            }
+        }
+        let summary =
+            adaptive_analysis(weights(), f64::INFINITY, 3, &AnalysisConfig::default()).unwrap();
+        assert_eq!(summary.full_searches, 0);
+        assert!(summary.violations.is_empty());
+        let strict = adaptive_analysis(weights(), 1.0, 3, &AnalysisConfig::default()).unwrap();
+        assert_eq!(strict.full_searches, strict.stars);
+        assert_eq!(strict.violations.len(), strict.stars);
+        assert!(full > 0);
+
+        // Singleton partitions of lists of 3 or more colors have 2 blocks, so a limit of 2 keeps
+        // them; a limit below 2 allows no partition, so every star is violated.
+        let two_blocks = AnalysisConfig {
+            max_blocks: Some(2),
+            ..AnalysisConfig::default()
+        };
+        for star in generate_stars(3).into_iter().take(200) {
+            let found = adaptive_partition(&star, weights(), &two_blocks, 1.2).unwrap();
+            assert!(found.partition.len() <= 2);
+            let (_, best) = best_partition(&star, weights(), &two_blocks).unwrap();
+            assert_eq!(found.branching_number <= 1.2, best <= 1.2, "{star:?}");
+        }
+        let none = AnalysisConfig {
+            max_blocks: Some(1),
+            ..AnalysisConfig::default()
+        };
+        let summary = adaptive_analysis(weights(), 100.0, 2, &none).unwrap();
+        assert!(summary.stars > 0);
+        assert_eq!(summary.violations.len(), summary.stars);
+        assert!(summary.violations.iter().all(|(_, bn)| bn.is_infinite()));
+    }
+
+    #[test]
+    fn best_partition_prefers_smallest_branching_number() {
+        let star = Star {
+            root_colors: 0b0011,
+            neighbor_colors: vec![0b0011, 0b0011, 0b0011],
+            neighbor_halfedges: vec![2, 2, 2],
+            neighbor_adjacency: None,
+        };
+        // Only one nontrivial partition of a 2-list.
+        let (partition, bn) = best_partition(&star, weights(), &AnalysisConfig::default())
+            .expect("root has 2 colors");
+        assert_eq!(partition, vec![0b0010, 0b0001]);
+        // Each branch colors the root and all three neighbors: drop 4 * 0.4.
+        let expected = crate::recurrence::branching_number(&[1.6, 1.6]);
+        assert!((bn - expected).abs() < 1e-12);
+
+        let singleton = Star {
+            root_colors: 0b0001,
+            neighbor_colors: vec![0b0011],
+            neighbor_halfedges: vec![2],
+            neighbor_adjacency: None,
+        };
+        assert!(best_partition(&singleton, weights(), &AnalysisConfig::default()).is_none());
+    }
+
+    #[test]
+    fn partition_recurrence_counts_terminal_branches() {
+        let star = Star {
+            root_colors: 0b0111,
+            neighbor_colors: vec![0b0001, 0b0011],
+            neighbor_halfedges: vec![2, 3],
+            neighbor_adjacency: None,
+        };
+        // Root=2 leaves the neighbors alone, root=1 solves the star, root=0 is infeasible.
+        let r = partition_recurrence(
+            &star,
+            &[0b0100, 0b0010, 0b0001],
+            weights(),
+            &AnalysisConfig::default(),
+        );
+        assert_eq!(r.num_branches(), 3);
+        assert_eq!(r.free_branches, 1);
+        assert_eq!(r.solved_branches, 1);
+        assert_eq!(r.drops.len(), 2);
+        assert_eq!(
+            r.drops[1],
+            star_measure(&star, weights(), MaxDegree::default())
+        );
+    }
+
+    #[test]
+    fn partition_branch_types_groups_symmetric_branches() {
+        let star = Star {
+            root_colors: 0b1111,
+            neighbor_colors: vec![0b1111, 0b1111, 0b0111],
+            neighbor_halfedges: vec![2, 2, 2],
+            neighbor_adjacency: None,
+        };
+        let partition = [0b1000, 0b0100, 0b0010, 0b0001];
+        let grouped =
+            partition_branch_types(&star, &partition, weights(), &AnalysisConfig::default());
+        // Root=3 is one type; roots 0, 1 and 2 are the same up to renaming colors.
+        assert_eq!(grouped.len(), 2);
+        assert_eq!(grouped.iter().map(|&(_, m)| m).sum::<usize>(), 4);
+
+        let drops = partition_drops(&star, &partition, weights(), &AnalysisConfig::default());
+        let expected = crate::recurrence::branching_number(&drops);
+        let bn = crate::recurrence::branching_number_grouped(&grouped);
+        assert!((bn - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn checked_recurrence_reports_degree_window_violations() {
+        // In the branch root := {1,2}, both {1,2} neighbors duplicate the root's 2-list and
+        // are merged into one neighbor with 6 halfedges.
+        let star = Star {
+            root_colors: 0b0111,
+            neighbor_colors: vec![0b0110, 0b0110, 0b0011],
+            neighbor_halfedges: vec![3, 3, 3],
+            neighbor_adjacency: None,
+        };
+        let partition = [0b0110, 0b0001];
+        let err = partition_recurrence_checked(
+            &star,
+            &partition,
+            weights(),
+            &AnalysisConfig::for_max_degree(4),
+        )
+        .unwrap_err();
+        assert_eq!(err.degree, 7);
+        assert_eq!(err.branch.neighbor_halfedges[err.vertex.unwrap()], 6);
+
+        let config = AnalysisConfig::for_max_degree(7);
+        let ok = partition_recurrence_checked(&star, &partition, weights(), &config).unwrap();
+        assert_eq!(
+            ok,
+            partition_recurrence(&star, &partition, weights(), &config)
+        );
+    }
+
+    #[test]
+    fn most_violated_star_respects_bound() {
+        let (star, bn) = most_violated_star(weights(), 1.0, 3, &AnalysisConfig::default())
+            .unwrap()
+            .expect("some star branches");
+        assert!(bn > 1.0);
+        for other in generate_stars(3) {
+            let (_, other_bn) =
+                best_partition(&other, weights(), &AnalysisConfig::default()).unwrap();
+            assert!(other_bn <= bn, "{other:?} is worse than {star:?}");
+        }
+
+        assert_eq!(
+            most_violated_star(weights(), bn, 3, &AnalysisConfig::default()),
+            Ok(None)
+        );
+        assert!(
+            most_violated_star(NodeFeatures::default(), 1e9, 3, &AnalysisConfig::default())
+                .unwrap()
+                .is_some()
+        );
+
+        let mut nan = weights().to_array();
+        nan[4] = f64::NAN;
+        let err = most_violated_star(
+            NodeFeatures::from_array(nan),
+            1.0,
+            3,
+            &AnalysisConfig::default(),
+        )
+        .unwrap_err();
+        assert_eq!(err.index, 4);
+        assert_eq!(err.to_string(), "n3_4 is NaN, expected a finite number");
+        assert_eq!(
+            NodeFeatures::try_from_array(nan).map_err(|e| e.index),
+            Err(4)
+        );
+        let zero = NodeFeatures::try_from_array([-0.0; 9]).unwrap();
+        assert_eq!(
+            zero.to_json_string(),
+            NodeFeatures::default().to_json_string()
+        );
+    }
+}
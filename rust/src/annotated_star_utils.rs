@@ -0,0 +1,202 @@
+use alloc::vec::Vec;
+
+use crate::colors::{ALL_COLORS, ColorSet};
+use crate::list_coloring_utils::apply_list_coloring_partition_unreduced;
+use crate::star_utils::{Star, generate_stars, nondecreasing_sequences};
+
+/// The annotation of a halfedge whose far endpoint is unconstrained, for stars with the colors
+/// {0,1,2,3}.
+pub const UNCONSTRAINED: u8 = ALL_COLORS;
+
+/// A star whose halfedges carry coarse information about their unseen endpoints.
+///
+/// `halfedge_colors[i]` has one entry per halfedge of neighbor `i`: the color list the far
+/// endpoint may use, as a bitmask over the colors of the star. All of them, e.g.
+/// [`UNCONSTRAINED`] for 4 colors, means nothing is known.
+/// Entries for one neighbor are kept sorted.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct AnnotatedStar {
+    pub star: Star,
+    pub halfedge_colors: Vec<Vec<u8>>,
+}
+
+impl AnnotatedStar {
+    /// Annotates every halfedge of `star` with all colors of [`Star::color_count`], which is
+    /// [`UNCONSTRAINED`] for stars with the colors {0,1,2,3}.
+    pub fn unconstrained(star: Star) -> Self {
+        let all = u8::first(star.color_count());
+        let halfedge_colors = star
+            .neighbor_halfedges
+            .iter()
+            .map(|&h| alloc::vec![all; h as usize])
+            .collect();
+        AnnotatedStar {
+            star,
+            halfedge_colors,
+        }
+    }
+
+    /// Propagates singleton lists across halfedges until nothing changes: a far endpoint
+    /// fixed to color `c` removes `c` from its neighbor's list, and a neighbor fixed to `c`
+    /// removes `c` from the lists of its far endpoints.
+    pub fn propagate(&self) -> AnnotatedStar {
+        let mut out = self.clone();
+        loop {
+            let mut changed = false;
+            for (colors, ends) in out
+                .star
+                .neighbor_colors
+                .iter_mut()
+                .zip(out.halfedge_colors.iter_mut())
+            {
+                for end in ends.iter_mut() {
+                    if end.count_ones() == 1 && (*colors & *end) != 0 {
+                        *colors &= !*end;
+                        changed = true;
+                    }
+                }
+                if colors.count_ones() == 1 {
+                    for end in ends.iter_mut() {
+                        if (*end & *colors) != 0 && end.count_ones() > 1 {
+                            *end &= !*colors;
+                            changed = true;
+                        }
+                    }
+                }
+                ends.sort();
+            }
+            if !changed {
+                return out;
+            }
+        }
+    }
+}
+
+/// Applies a partition of the root list like [`apply_list_coloring_partition_unreduced`],
+/// keeping the annotations and propagating them in every branch.
+pub fn apply_partition_annotated(star: &AnnotatedStar, partition: &[u8]) -> Vec<AnnotatedStar> {
+    apply_list_coloring_partition_unreduced(&star.star, partition)
+        .into_iter()
+        .map(|branch| {
+            AnnotatedStar {
+                star: branch,
+                halfedge_colors: star.halfedge_colors.clone(),
+            }
+            .propagate()
+        })
+        .collect()
+}
+
+/// Generates all stars of the given `degree` (see [`generate_stars`]) with every halfedge
+/// annotated by one of `annotations`.
+///
+/// The halfedges of one neighbor, and neighbors of the same type, are unordered, so each
+/// annotated star is produced once.
+pub fn generate_annotated_stars(degree: usize, annotations: &[u8]) -> Vec<AnnotatedStar> {
+    if annotations.is_empty() {
+        return Vec::new();
+    }
+    let mut out: Vec<AnnotatedStar> = Vec::new();
+    for star in generate_stars(degree) {
+        // For each neighbor, all sorted annotations of its halfedges.
+        let options: Vec<Vec<Vec<u8>>> = star
+            .neighbor_halfedges
+            .iter()
+            .map(|&h| {
+                nondecreasing_sequences(annotations.len() - 1, h as usize)
+                    .into_iter()
+                    .map(|choice| {
+                        let mut ends: Vec<u8> = choice.iter().map(|&i| annotations[i]).collect();
+                        ends.sort();
+                        ends
+                    })
+                    .collect()
+            })
+            .collect();
+
+        // Mixed-radix counter over the neighbors' options; neighbors of equal type take
+        // nondecreasing option indices.
+        let n = options.len();
+        let mut idx = alloc::vec![0usize; n];
+        'outer: loop {
+            let valid = (1..n).all(|i| {
+                let same = star.neighbor_colors[i] == star.neighbor_colors[i - 1]
+                    && star.neighbor_halfedges[i] == star.neighbor_halfedges[i - 1];
+                !same || idx[i - 1] <= idx[i]
+            });
+            if valid {
+                out.push(AnnotatedStar {
+                    star: star.clone(),
+                    halfedge_colors: (0..n).map(|i| options[i][idx[i]].clone()).collect(),
+                });
+            }
+            for i in (0..n).rev() {
+                idx[i] += 1;
+                if idx[i] < options[i].len() {
+                    continue 'outer;
+                }
+                idx[i] = 0;
+            }
+            break;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn singleton_endpoints_restrict_neighbors() {
+        let star = Star {
+            root_colors: 0b0011,
+            neighbor_colors: vec![0b0011, 0b0111],
+            neighbor_halfedges: vec![2, 2],
+            neighbor_adjacency: None,
+        };
+        let mut annotated = AnnotatedStar::unconstrained(star);
+        annotated.halfedge_colors[1] = vec![0b0100, 0b1100];
+
+        // Neighbor 1 loses color 2 to its fixed endpoint.
+        let propagated = annotated.propagate();
+        assert_eq!(propagated.star.neighbor_colors, vec![0b0011, 0b0011]);
+
+        // Root := 0 leaves neighbor 1 with {1}, which is removed from its endpoints.
+        let branches = apply_partition_annotated(&annotated, &[0b0010, 0b0001]);
+        let root0 = &branches[1];
+        assert_eq!(root0.star.neighbor_colors, vec![0b0010, 0b0010]);
+        assert_eq!(root0.halfedge_colors[0], vec![0b1101, 0b1101]);
+        assert_eq!(root0.halfedge_colors[1], vec![0b0100, 0b1100]);
+    }
+
+    #[test]
+    fn generate_annotated_stars_avoids_duplicates() {
+        let plain = generate_stars(2);
+        assert_eq!(
+            generate_annotated_stars(2, &[UNCONSTRAINED]).len(),
+            plain.len()
+        );
+
+        let annotated = generate_annotated_stars(2, &[UNCONSTRAINED, 0b0011]);
+        let keys: alloc::collections::BTreeSet<_> = annotated
+            .iter()
+            .map(|a| {
+                let mut neighbors: Vec<(u8, u8, Vec<u8>)> = (0..a.halfedge_colors.len())
+                    .map(|i| {
+                        (
+                            a.star.neighbor_colors[i],
+                            a.star.neighbor_halfedges[i],
+                            a.halfedge_colors[i].clone(),
+                        )
+                    })
+                    .collect();
+                neighbors.sort();
+                (a.star.root_colors, neighbors)
+            })
+            .collect();
+        assert_eq!(keys.len(), annotated.len());
+        assert!(annotated.len() > plain.len());
+    }
+}
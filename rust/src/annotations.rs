@@ -0,0 +1,118 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+
+use crate::json::{JsonValue, parse_json};
+use crate::star_utils::{Star, star_from_string, star_to_string};
+
+/// Free-form information attached to a star, e.g. the name of its case in a paper.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StarAnnotation {
+    /// Case name, e.g. `C5.3`.
+    pub name: Option<String>,
+    pub note: Option<String>,
+    /// Reference into the paper, e.g. `Lemma 12`.
+    pub reference: Option<String>,
+}
+
+/// Annotations of stars, read from a sidecar file.
+///
+/// The file is a JSON object mapping star strings (v1 or v2) either to a case name or to an
+/// object with the optional keys `name`, `note`, and `reference`:
+///
+/// ```json
+/// {"star_3_ffff_0222": "C5.3", "star_3_f73c_0222": {"name": "C5.4", "note": "tight"}}
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Annotations {
+    /// Keyed by the v1 string of the star, so both encodings find the same entry.
+    entries: BTreeMap<String, StarAnnotation>,
+}
+
+impl Annotations {
+    pub fn get(&self, star: &Star) -> Option<&StarAnnotation> {
+        self.entries.get(&star_to_string(star).ok()?)
+    }
+
+    pub fn insert(&mut self, star: &Star, annotation: StarAnnotation) {
+        if let Ok(key) = star_to_string(star) {
+            self.entries.insert(key, annotation);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Reads an annotations file, see [`Annotations`].
+pub fn parse_annotations(s: &str) -> Result<Annotations, String> {
+    let JsonValue::Object(fields) = parse_json(s)? else {
+        return Err("annotations must be an object mapping stars to annotations".to_string());
+    };
+    let mut out = Annotations::default();
+    for (key, value) in fields.iter() {
+        let star = star_from_string(key).map_err(|e| e.to_string())?;
+        let text = |field: &str| -> Result<Option<String>, String> {
+            match value.get(field) {
+                None | Some(JsonValue::Null) => Ok(None),
+                Some(JsonValue::String(s)) => Ok(Some(s.clone())),
+                Some(_) => Err(format!("{key}: \"{field}\" must be a string")),
+            }
+        };
+        let annotation = match value {
+            JsonValue::String(name) => StarAnnotation {
+                name: Some(name.clone()),
+                ..StarAnnotation::default()
+            },
+            JsonValue::Object(_) => StarAnnotation {
+                name: text("name")?,
+                note: text("note")?,
+                reference: text("reference")?,
+            },
+            _ => return Err(format!("{key}: expected a case name or an object")),
+        };
+        out.insert(&star, annotation);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::star_utils::star_to_string_v2;
+    use alloc::vec;
+
+    #[test]
+    fn parse_annotations_by_either_encoding() {
+        let star = Star {
+            root_colors: 0b1111,
+            neighbor_colors: vec![0b1111, 0b0011],
+            neighbor_halfedges: vec![2, 3],
+            neighbor_adjacency: None,
+        };
+        let v1 = star_to_string(&star).unwrap();
+        let v2 = star_to_string_v2(&star).unwrap();
+        for key in [v1, v2] {
+            let json = format!(
+                "{{\"{key}\": {{\"name\": \"C5.3\", \"reference\": \"Lemma 12\"}}, \"star_2_333_022\": \"C1\"}}"
+            );
+            let annotations = parse_annotations(&json).unwrap();
+            assert_eq!(annotations.len(), 2);
+            assert_eq!(
+                annotations.get(&star),
+                Some(&StarAnnotation {
+                    name: Some("C5.3".to_string()),
+                    note: None,
+                    reference: Some("Lemma 12".to_string()),
+                })
+            );
+        }
+        assert!(parse_annotations("{\"star_2_333_022\": 1}").is_err());
+        assert!(parse_annotations("{\"nonsense\": \"C1\"}").is_err());
+    }
+}
@@ -0,0 +1,28 @@
+use recurrences::reductions::DEFAULT_REDUCTIONS;
+use recurrences::reductions::certify_reduction;
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let max_degree: usize = match args.next().as_deref() {
+        Some(s) => match s.parse() {
+            Ok(v) => v,
+            Err(_) => {
+                eprintln!("invalid degree: {s}");
+                return;
+            }
+        },
+        None => 4,
+    };
+
+    for &reduction in DEFAULT_REDUCTIONS.iter() {
+        let cert = certify_reduction(reduction, max_degree);
+        if !cert.counterexamples.is_empty() {
+            eprintln!(
+                "reduction {} failed on {} cases",
+                cert.name,
+                cert.counterexamples.len()
+            );
+        }
+        println!("{}", cert.to_json_string());
+    }
+}
@@ -1,28 +1,169 @@
-use recurrences::star_utils::generate_stars;
+use recurrences::colors::NUM_COLORS;
+use recurrences::derived_features::{DEFAULT_DERIVED_FEATURES, hashed_star_features};
+use recurrences::number_format::format_float;
 use recurrences::star_utils::star_to_string;
+use recurrences::star_utils::star_to_string_v2;
+use recurrences::star_utils::{
+    EnumerationConfig, Star, StarIter, generate_star_completions_with_config, star_from_string,
+    star_label, star_to_json_string,
+};
+
+const USAGE: &str =
+    "usage: enumerate-stars <degree> [--v2 | --json] [--hashed <dims>] [--partial <star>]
+       [--colors <k>] [--halfedges <bounds>] [--min-halfedges <h>]
+       [--max-halfedges <h>] [--orbits]
+
+With --partial, prints only the stars of the given degree that extend <star>.
+With --colors, uses only the lists of colors 0, ..., k - 1, for 2 <= k <= 8.
+Star strings have lists of at most 4 colors, so k > 4 needs --json, which
+prints every star as a JSON object instead.
+With --halfedges, skips the stars violating any of the comma-separated bounds
+<min>-<max>:<count>, each allowing at most <count> neighbors with <min> to <max>
+halfedges, e.g. 4-:1.
+With --min-halfedges and --max-halfedges, neighbors have from <h> (default 2)
+to <h> (default the degree) halfedges; 0 allows pendant neighbors.
+With --orbits, prints only one star per orbit under renaming the colors.
+Exits with status 1 on invalid arguments, and on the first star the star strings
+cannot encode, e.g. one with more than 15 halfedges at a neighbor.";
 
 fn main() {
+    let mut v2 = false;
+    let mut json = false;
+    let mut hashed: Option<usize> = None;
+    let mut partial = None;
+    let mut config = EnumerationConfig::default();
+    let mut positional: Vec<String> = Vec::new();
     let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--v2" => v2 = true,
+            "--json" => json = true,
+            "--orbits" => config.orbit_representatives = true,
+            "--hashed" => match args.next().map(|s| s.parse()) {
+                Some(Ok(dims)) => hashed = Some(dims),
+                _ => {
+                    eprintln!("--hashed expects a number of dimensions");
+                    std::process::exit(1);
+                }
+            },
+            "--partial" => match args.next().map(|s| star_from_string(&s)) {
+                Some(Ok(star)) => partial = Some(star),
+                Some(Err(e)) => {
+                    eprintln!("--partial: {e}");
+                    std::process::exit(1);
+                }
+                None => {
+                    eprintln!("--partial expects a star");
+                    std::process::exit(1);
+                }
+            },
+            "--colors" => match args.next().map(|s| s.parse()) {
+                Some(Ok(k)) => config.colors = k,
+                _ => {
+                    eprintln!("--colors expects a number of colors");
+                    std::process::exit(1);
+                }
+            },
+            "--halfedges" => match args
+                .next()
+                .map(|s| s.split(',').map(str::parse).collect::<Result<_, _>>())
+            {
+                Some(Ok(bounds)) => config.halfedge_bounds = bounds,
+                Some(Err(e)) => {
+                    eprintln!("--halfedges: {e}");
+                    std::process::exit(1);
+                }
+                None => {
+                    eprintln!("--halfedges expects halfedge bounds");
+                    std::process::exit(1);
+                }
+            },
+            "--min-halfedges" => match args.next().map(|s| s.parse()) {
+                Some(Ok(h)) => config.min_halfedges = h,
+                _ => {
+                    eprintln!("--min-halfedges expects a number of halfedges");
+                    std::process::exit(1);
+                }
+            },
+            "--max-halfedges" => match args.next().map(|s| s.parse()) {
+                Some(Ok(h)) => config.max_halfedges = Some(h),
+                _ => {
+                    eprintln!("--max-halfedges expects a number of halfedges");
+                    std::process::exit(1);
+                }
+            },
+            _ => positional.push(arg),
+        }
+    }
+    let mut args = positional.into_iter();
     let degree: usize = match args.next().as_deref() {
         Some(s) => match s.parse() {
             Ok(v) => v,
             Err(_) => {
                 eprintln!("invalid degree: {s}");
-                return;
+                std::process::exit(1);
             }
         },
         None => {
-            eprintln!("usage: enumerate-stars <degree>");
-            return;
+            eprintln!("{USAGE}");
+            std::process::exit(1);
         }
     };
 
-    for d in 3..(degree + 1) {
-        for t in generate_stars(d).iter() {
-            let Some(s) = star_to_string(t) else {
-                continue;
-            };
-            println!("{s}");
+    if let Err(e) = config.validate_for_degree(degree) {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
+    if v2 && json {
+        eprintln!("--v2 cannot be combined with --json");
+        std::process::exit(1);
+    }
+    if config.colors > NUM_COLORS && !json {
+        eprintln!(
+            "star strings have lists of at most {NUM_COLORS} colors; use --json for {} colors",
+            config.colors
+        );
+        std::process::exit(1);
+    }
+
+    let stars: Box<dyn Iterator<Item = Star>> = match partial {
+        Some(partial) => match generate_star_completions_with_config(&partial, degree, &config) {
+            Ok(completions) => Box::new(completions.into_iter()),
+            Err(e) => {
+                eprintln!("the stars of degree {degree}: {e}");
+                std::process::exit(1);
+            }
+        },
+        // Lazily, since there are millions of stars from degree 5 on.
+        None => Box::new((3..(degree + 1)).flat_map(|d| StarIter::new(d, &config))),
+    };
+    for t in stars {
+        let encoded = if json {
+            Ok(star_to_json_string(&t))
+        } else if v2 {
+            star_to_string_v2(&t)
+        } else {
+            star_to_string(&t)
+        };
+        let s = match encoded {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("cannot encode {}: {e}", star_label(&t));
+                std::process::exit(1);
+            }
+        };
+        match hashed {
+            // One NDJSON record per star, for ML pipelines.
+            Some(dims) => {
+                let features: Vec<String> =
+                    hashed_star_features(&t, DEFAULT_DERIVED_FEATURES, dims)
+                        .into_iter()
+                        .map(|x| format_float(x, None))
+                        .collect();
+                let star = if json { s } else { format!("\"{s}\"") };
+                println!("{{\"star\":{star},\"features\":[{}]}}", features.join(","));
+            }
+            None => println!("{s}"),
         }
     }
 }
@@ -2,242 +2,142 @@
 // This is currently unused!!
 //
 
-use std::collections::HashMap;
-
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
-pub struct Node {
-    // Bitmask over colors {0,1,2,3}. Bit i set => color i is present.
-    // Example: 0b0011 represents {0,1}.
-    pub colors: u8,
-    /// Number of dangling halfedges at this node.
-    ///
-    /// Invariant:
-    /// - If `children` is non-empty, then `halfedges == 0`.
-    /// - If `children` is empty (leaf), then `halfedges >= 2` (and enumeration
-    ///   additionally enforces `halfedges <= degree`).
-    pub halfedges: u8,
-    pub children: Vec<Node>,
+use recurrences::colors::{MAX_COLORS, NUM_COLORS};
+use recurrences::encoding::v1::encode_tree;
+use recurrences::memory::{MemoryGuard, estimate_memory_trees_with, parse_memory_size};
+use recurrences::star_utils::{EnumerationConfig, Star, generate_stars, star_label};
+use recurrences::tree_utils::{
+    EdgeConstraint, generate_colored_uniform_trees, generate_colored_uniform_trees_with,
+};
+
+/// Returns a key for a star that does not depend on the order of the neighbors: the root
+/// colors followed by the sorted `(colors, halfedges)` pairs.
+fn star_key(star: &Star) -> (u8, Vec<(u8, u8)>) {
+    let mut neighbors: Vec<(u8, u8)> = star
+        .neighbor_colors
+        .iter()
+        .copied()
+        .zip(star.neighbor_halfedges.iter().copied())
+        .collect();
+    neighbors.sort();
+    (star.root_colors, neighbors)
 }
 
-impl Node {
-    pub fn new_internal(colors: u8, children: Vec<Node>) -> Self {
-        debug_assert!(colors != 0, "colors must be non-empty");
-        debug_assert!(colors & !0b1111 == 0, "colors must be in 0..=3");
-        debug_assert!(colors.count_ones() >= 2, "colors must have size >= 2");
-        debug_assert!(!children.is_empty(), "internal node must have children");
-        Self {
-            colors,
-            halfedges: 0,
-            children,
-        }
-    }
-
-    pub fn new_leaf(colors: u8, halfedges: u8) -> Self {
-        debug_assert!(colors != 0, "colors must be non-empty");
-        debug_assert!(colors & !0b1111 == 0, "colors must be in 0..=3");
-        debug_assert!(colors.count_ones() >= 2, "colors must have size >= 2");
-        debug_assert!(halfedges >= 2, "leaf must have at least 2 halfedges");
-        Self {
-            colors,
-            halfedges,
-            children: Vec::new(),
-        }
-    }
-}
-
-pub static ROOT_COLOR_SUBSETS: [u8; 3] = [
-    0b1111, // {0,1,2,3}
-    0b0111, // {0,1,2}
-    0b0011, // {0,1}
-];
-
-pub static COLOR_SUBSETS_GE2: [u8; 11] = [
-    0b1111, // {0,1,2,3}
-    0b0111, // {0,1,2}
-    0b1011, // {0,1,3}
-    0b1101, // {0,2,3}
-    0b1110, // {1,2,3}
-    0b0011, // {0,1}
-    0b0101, // {0,2}
-    0b1001, // {0,3}
-    0b0110, // {1,2}
-    0b1010, // {1,3}
-    0b1100, // {2,3}
-];
-
-fn intersects(a: u8, b: u8) -> bool {
-    (a & b) != 0
-}
-
-fn nondecreasing_sequences(t: usize, n: usize) -> Vec<Vec<usize>> {
-    let mut res: Vec<Vec<usize>> = Vec::new();
-    let mut current = vec![0usize; n];
+/// Compares the depth-1 trees of the given `degree` with the stars of the same degree and
+/// prints every case that only one of the two enumerators produces.
+///
+/// Returns whether both enumerators produce the same case set.
+fn cross_check(degree: usize) -> bool {
+    use std::collections::BTreeSet;
+
+    let trees: BTreeSet<(u8, Vec<(u8, u8)>)> = generate_colored_uniform_trees(1, degree)
+        .iter()
+        .map(|t| star_key(&Star::try_from(t).expect("trees of depth 1 are stars")))
+        .collect();
+    let stars: BTreeSet<(u8, Vec<(u8, u8)>)> =
+        generate_stars(degree).iter().map(star_key).collect();
+
+    let to_star = |(root_colors, neighbors): &(u8, Vec<(u8, u8)>)| Star {
+        root_colors: *root_colors,
+        neighbor_colors: neighbors.iter().map(|&(c, _)| c).collect(),
+        neighbor_halfedges: neighbors.iter().map(|&(_, h)| h).collect(),
+        neighbor_adjacency: None,
+    };
 
-    fn backtrack(
-        idx: usize,
-        start: usize,
-        t: usize,
-        current: &mut [usize],
-        res: &mut Vec<Vec<usize>>,
-    ) {
-        if idx == current.len() {
-            res.push(current.to_vec());
-            return;
-        }
-        for v in start..=t {
-            current[idx] = v;
-            backtrack(idx + 1, v, t, current, res);
-        }
+    println!("trees: {}", trees.len());
+    println!("stars: {}", stars.len());
+    for key in trees.difference(&stars) {
+        println!("only in trees: {}", star_label(&to_star(key)));
     }
-
-    if n == 0 {
-        res.push(Vec::new());
-        return res;
+    for key in stars.difference(&trees) {
+        println!("only in stars: {}", star_label(&to_star(key)));
     }
-
-    backtrack(0, 0, t, &mut current, &mut res);
-    res
+    trees == stars
 }
 
-fn generate_subtrees_with_parent(
-    depth: usize,
-    degree: usize,
-    parent_color_idx: usize,
-    cache: &mut HashMap<(usize, usize, usize), Vec<Node>>,
-) -> Vec<Node> {
-    let key = (depth, degree, parent_color_idx);
-    if let Some(cached) = cache.get(&key) {
-        return cached.clone();
-    }
-
-    let children_count = if depth == 0 {
-        0
-    } else {
-        // For non-root nodes, degree includes the edge to the parent.
-        degree.saturating_sub(1)
-    };
-
-    // Can't realize positive depth without children.
-    if depth > 0 && children_count == 0 {
-        cache.insert(key, Vec::new());
-        return Vec::new();
-    }
-
-    let parent_colors = COLOR_SUBSETS_GE2[parent_color_idx];
-    let mut out: Vec<Node> = Vec::new();
-
-    for (idx, colors) in COLOR_SUBSETS_GE2.iter().enumerate() {
-        if !intersects(parent_colors, *colors) {
-            continue;
-        }
+const USAGE: &str = "usage: rust <depth> <degree> [--edge intersect|equal|not-equal]
+            [--colors <k>] [--memory-limit <size>] [--force]
+       rust cross-check <degree>
 
-        if depth == 0 {
-            // Leaf: vary halfedges from 2..=degree.
-            // If degree < 2, there are no valid leaves.
-            for h in 2..=degree {
-                out.push(Node::new_leaf(*colors, h as u8));
-            }
-            continue;
-        }
+With --colors, uses only the lists of colors 0, ..., k - 1, for 2 <= k <= 8
+(default 4).
 
-        let child_candidates = generate_subtrees_with_parent(depth - 1, degree, idx, cache);
-        if child_candidates.is_empty() {
-            continue;
-        }
+Refuses to enumerate trees that take an estimated more than 4 GiB, or the limit
+given with --memory-limit (in bytes, or with a unit K, M, G or T), unless
+--force is given. Exits with status 1 on invalid arguments, on a refused
+enumeration, and when cross-check finds a difference.";
 
-        for choice in nondecreasing_sequences(child_candidates.len() - 1, children_count) {
-            let children = choice
-                .into_iter()
-                .map(|i| child_candidates[i].clone())
-                .collect::<Vec<_>>();
-            out.push(Node::new_internal(*colors, children));
+fn main() {
+    let mut guard = MemoryGuard::default();
+    let mut colors = NUM_COLORS;
+    let mut rest: Vec<String> = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--force" => guard.force = true,
+            "--memory-limit" => match args.next().map(|s| parse_memory_size(&s)) {
+                Some(Ok(limit)) => guard.limit = limit,
+                Some(Err(e)) => {
+                    eprintln!("{e}");
+                    std::process::exit(1);
+                }
+                None => {
+                    eprintln!("--memory-limit expects a memory size");
+                    std::process::exit(1);
+                }
+            },
+            "--colors" => match args.next().map(|s| s.parse()) {
+                Some(Ok(k)) if (2..=MAX_COLORS).contains(&k) => colors = k,
+                Some(Ok(k)) => {
+                    eprintln!("the number of colors must be between 2 and {MAX_COLORS}, not {k}");
+                    std::process::exit(1);
+                }
+                _ => {
+                    eprintln!("--colors expects a number of colors");
+                    std::process::exit(1);
+                }
+            },
+            _ => rest.push(arg),
         }
     }
-
-    cache.insert(key, out.clone());
-    out
-}
-
-/// Generates all colorings of the unique uniform tree of the given `depth` and `degree`.
-///
-/// - `depth` counts edges from the root to a leaf (so `depth = 0` yields a single node).
-/// - `degree` includes the edge to the parent, so the root has `degree` children and every
-///   other internal node has `degree - 1` children.
-/// - Colors are chosen from `COLOR_SUBSETS_GE2`.
-/// - Constraint: for every parent/child edge, `parent.colors` intersects `child.colors`.
-pub fn generate_colored_uniform_trees(depth: usize, degree: usize) -> Vec<Node> {
-    if degree < 2 {
-        return Vec::new();
-    }
-
-    let root_children_count = if depth == 0 { 0 } else { degree };
-    if depth > 0 && root_children_count == 0 {
-        return Vec::new();
-    }
-
-    let mut cache: HashMap<(usize, usize, usize), Vec<Node>> = HashMap::new();
-    let mut out: Vec<Node> = Vec::new();
-
-    for &root_colors in ROOT_COLOR_SUBSETS.iter() {
-        let Some(root_idx) = COLOR_SUBSETS_GE2.iter().position(|&s| s == root_colors) else {
-            // If this ever happens, ROOT_COLOR_SUBSETS contains something not in COLOR_SUBSETS_GE2.
-            continue;
-        };
-
-        if depth == 0 {
-            // Root is a leaf: vary halfedges from 2..=degree.
-            for h in 2..=degree {
-                out.push(Node::new_leaf(root_colors, h as u8));
+    let mut args = rest.into_iter().peekable();
+    if args.peek().map(|s| s.as_str()) == Some("cross-check") {
+        args.next();
+        let degree: usize = match args.next().as_deref() {
+            Some(s) => match s.parse() {
+                Ok(v) => v,
+                Err(_) => {
+                    eprintln!("invalid degree: {s}");
+                    std::process::exit(1);
+                }
+            },
+            None => {
+                eprintln!("usage: rust cross-check <degree>");
+                std::process::exit(1);
             }
-            continue;
-        }
-
-        let child_candidates =
-            generate_subtrees_with_parent(depth - 1, degree, root_idx, &mut cache);
-        if child_candidates.is_empty() {
-            continue;
+        };
+        if let Err(e) = EnumerationConfig::default().validate_for_degree(degree) {
+            eprintln!("{e}");
+            std::process::exit(1);
         }
-
-        for choice in nondecreasing_sequences(child_candidates.len() - 1, root_children_count) {
-            let children = choice
-                .into_iter()
-                .map(|i| child_candidates[i].clone())
-                .collect::<Vec<_>>();
-            out.push(Node::new_internal(root_colors, children));
+        if !cross_check(degree) {
+            eprintln!("enumerate-trees and enumerate-stars disagree for degree {degree}");
+            std::process::exit(1);
         }
+        return;
     }
 
-    out
-}
-
-fn node_to_json(node: &Node, out: &mut String) {
-    out.push_str("{\"colors\":");
-    out.push_str(&node.colors.to_string());
-    out.push_str(",\"halfedges\":");
-    out.push_str(&node.halfedges.to_string());
-    out.push_str(",\"children\":[");
-    for (i, child) in node.children.iter().enumerate() {
-        if i > 0 {
-            out.push(',');
-        }
-        node_to_json(child, out);
-    }
-    out.push_str("]}");
-}
-
-fn main() {
-    let mut args = std::env::args().skip(1);
     let depth: usize = match args.next().as_deref() {
         Some(s) => match s.parse() {
             Ok(v) => v,
             Err(_) => {
                 eprintln!("invalid depth: {s}");
-                return;
+                std::process::exit(1);
             }
         },
         None => {
-            eprintln!("usage: rust <depth> <degree>");
-            return;
+            eprintln!("{USAGE}");
+            std::process::exit(1);
         }
     };
 
@@ -246,16 +146,50 @@ fn main() {
             Ok(v) => v,
             Err(_) => {
                 eprintln!("invalid degree: {s}");
-                return;
+                std::process::exit(1);
             }
         },
         None => {
-            eprintln!("usage: rust <depth> <degree>");
-            return;
+            eprintln!("{USAGE}");
+            std::process::exit(1);
+        }
+    };
+
+    let constraint = match (args.next().as_deref(), args.next()) {
+        (None, _) => EdgeConstraint::default(),
+        (Some("--edge"), Some(s)) => match s.parse() {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        },
+        _ => {
+            eprintln!("{USAGE}");
+            std::process::exit(1);
+        }
+    };
+
+    let what = format!("the trees of depth {depth} and degree {degree}");
+    let estimate = match estimate_memory_trees_with(depth, degree, constraint, colors) {
+        Ok(estimate) => estimate,
+        Err(e) => {
+            eprintln!("{what}: {e}");
+            std::process::exit(1);
         }
     };
+    let alternative =
+        "use a smaller depth or degree (enumerate-stars streams the trees of depth 1)";
+    match guard.check(estimate, &what, alternative) {
+        Ok(Some(warning)) => eprintln!("{warning}"),
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+    }
 
-    let trees = generate_colored_uniform_trees(depth, degree);
+    let trees = generate_colored_uniform_trees_with(depth, degree, constraint, colors);
 
     let mut out = String::new();
     out.push('[');
@@ -264,7 +198,7 @@ fn main() {
             out.push(',');
             out.push('\n');
         }
-        node_to_json(t, &mut out);
+        out.push_str(&encode_tree(t));
     }
     out.push(']');
     println!("{out}");
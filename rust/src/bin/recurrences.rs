@@ -0,0 +1,2673 @@
+use recurrences::analysis::{
+    AnalysisConfig, adaptive_analysis, best_partition, most_violated_star, pareto_partitions,
+};
+use recurrences::annotations::{Annotations, parse_annotations};
+use recurrences::cache::{Cache, report_cache_key};
+use recurrences::case_set::{CaseSet, canonical_id, case_set_from_text, diff_case_sets};
+use recurrences::closure::reduction_closure;
+use recurrences::color_classes::{RuleComparison, compare_rule_families};
+use recurrences::colors::{fmt_colors, fmt_partition};
+use recurrences::coverage::{audit_coverage, parse_case_list};
+use recurrences::explain::{branch_tree, branch_tree_to_html, branch_tree_to_text};
+use recurrences::feature_join::{join_features, parse_feature_table};
+use recurrences::gadgets::{GADGETS, gadget};
+use recurrences::heuristic::{SELECTORS, gap_report};
+use recurrences::ipc::{STARS_MAGIC, STARS_VERSION, decode_stars, encode_star, serve};
+use recurrences::json::{JsonValue, parse_json};
+use recurrences::list_coloring_utils::{
+    Branch, FeatureSchema, MaxDegree, NodeFeatures, apply_list_coloring_partition,
+    partitions_of_colors, star_features, star_list_degree_counts,
+};
+use recurrences::memory::{
+    MemoryGuard, estimate_failures_memory, estimate_memory_with_config, estimate_report_memory,
+    parse_memory_size,
+};
+use recurrences::microstructure::Microstructure;
+use recurrences::number_format::format_float;
+use recurrences::optimize::{
+    BINDING_TOLERANCE, Optimization, StarConstraints, optimize_weights_with_oracle, random_start,
+    rank_features,
+};
+use recurrences::recurrence::{branching_number, characteristic_polynomial, polynomial_to_string};
+use recurrences::reduction_order::reduction_order;
+use recurrences::reductions::{
+    DEFAULT_REDUCTIONS, Reduction, measure_increases_with_config, reduce_to_fixpoint_with_trace,
+    select_reductions, verify_all_invariants_with_config,
+};
+use recurrences::report::{
+    CaseFilter, CaseReport, analysis_report, analysis_report_parallel,
+    analysis_report_parallel_traced, analysis_report_profiled, analysis_report_traced,
+    analysis_report_with_budget, analysis_report_with_progress, diff_reports, report_diff_to_text,
+    report_from_json_string, report_to_latex, report_to_latex_annotated, report_to_markdown,
+    report_to_markdown_annotated, slowest_cases,
+};
+use recurrences::solver::{MAX_STRESS_VERTICES, SplitMix64, stress_test};
+use recurrences::star_utils::{
+    CountError, EnumerationConfig, HalfedgeBound, Star, StarError, StarIter, StarParseError,
+    fold_stars_with_config, generate_stars_with_config, star_from_json_string, star_from_string,
+    star_label, star_to_json_string, star_to_string, star_to_string_v2,
+};
+use recurrences::trace::{Trace, TraceEvent, TraceKind, decode_trace, encode_trace, replay_trace};
+use recurrences::weights::Weights;
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, Read, Write};
+
+const USAGE: &str = "usage: recurrences [--color | --plain] <command> [args]
+
+commands:
+  explain <degree> <weights> [--html] [--precision <digits>] [--colors <k>]
+        [--max-degree <d>] [--trace <file>]
+      Prints the branch tree of the star of the given degree with the worst best
+      branching number. <weights> are 9 comma-separated numbers in the order
+      n4_ge5,n4_4,n4_3,n3_ge5,n3_4,n3_3,n2_ge5,n2_4,n2_3. With --trace, also
+      writes the decisions of the tree (stars, partitions, branches and
+      reductions) to <file> as a binary trace for replay. --max-degree and
+      --colors are as for proof.
+  replay <trace> [--allow-experimental]
+      Analyzes the first star of a trace written by explain --trace, or every
+      case of a trace written by proof --trace, again with the weights,
+      maximum degree, limit on the blocks and reductions of the trace and
+      compares every decision with the recorded one, branching numbers bit for
+      bit. Fails with exit code 2 at the first
+      decision that differs, and with 3 if the trace is malformed. Traces
+      using reductions that are not proved safe are refused with exit code 2
+      unless --allow-experimental is given.
+  proof <degree> <weights> [--latex | --json] [--precision <digits>]
+        [--threads <n>] [--verify-determinism] [--profile-cases <n>]
+        [--cache-dir <dir>] [--progress] [--annotations <file>]
+        [--exclude <rules>] [--max-degree <d>] [--colors <k>]
+        [--halfedges <bounds>] [--min-halfedges <h>] [--max-halfedges <h>]
+        [--max-blocks <b>] [--reductions <names>] [--allow-experimental]
+        [--budget-ms <ms>] [--memory-limit <size>] [--force] [--pareto]
+        [--trace <file>]
+      Prints the full case analysis of all stars of the given degree as a
+      Markdown document, or as a LaTeX fragment with --latex: one section per
+      root list, a table of branches and drops per star, and an appendix of the
+      reductions used. With --json, prints the analysis as a JSON artifact for
+      proof-diff. With --threads, analyzes the stars on <n> threads; the output
+      is identical to the sequential one. --verify-determinism runs both modes
+      and fails if their hashes differ. --profile-cases times the analysis of
+      every star and prints the <n> slowest ones to stderr; like --progress, it
+      analyzes the stars sequentially and cannot be combined with --threads.
+      With --cache-dir,
+      the analysis is stored in <dir>, keyed by the degree, the weights, the
+      crate version, every analysis option and a fingerprint of the reductions
+      and the measure, and reused by later runs with the same inputs. --progress
+      shows a live dashboard on stderr: progress per root list, the worst
+      branching number so far, the 5 worst stars, and memory usage. With
+      --annotations, cases are titled with the names, notes and references in
+      <file> (see below). --exclude skips the stars matched by the given
+      comma-separated rules without analyzing them and records the rules in
+      the output: with
+      root-list-exceeds-degree, stars whose root list is larger than the number
+      of neighbors; with neighbor-list-exceeds-degree, stars with a neighbor
+      whose list is larger than its degree. Such a vertex can be colored last.
+      With --max-degree, analyzes only the stars that occur in graphs of maximum
+      degree <d> and buckets the vertex degrees into >= d, d - 1 and d - 2 (the
+      weights keep their names for d = 5). --halfedges skips the stars violating
+      any of the given comma-separated bounds <min>-<max>:<count>, each allowing
+      at most <count> neighbors with <min> to <max> halfedges; <max> may be left
+      out, and <h>:<count> is short for <h>-<h>:<count>. With --min-halfedges
+      and --max-halfedges, neighbors have from <h> (default 2) to <h> (default
+      the degree of the star) halfedges, as for enumerate-stars. With --max-blocks,
+      stars only branch on partitions with at most <b> >= 2 blocks, for
+      algorithms with at most <b> branches per step; the output states the worst branching
+      number without the limit. --reductions simplifies the branches with the
+      given comma-separated reductions in this order of priority instead of
+      dominated-root-colors,duplicate-2lists; duplicate-2lists-capped is
+      duplicate-2lists with the merged neighbor kept in the degree bucket of
+      its twins. Fails if a reduction is not proved safe, unless
+      --allow-experimental is given, and if some reduction step increases the
+      measure; the safety level of every reduction used is recorded in the
+      output. With --budget-ms, the search for the best
+      partition of a star stops after <ms> milliseconds and uses the best
+      partition found so far, skipping partitions that a symmetry of the star
+      maps to one already tried; the stars that hit the budget are listed on
+      stderr and their cases are marked as possibly suboptimal in the output.
+      With --pareto, every case also lists the partitions with fewer branches
+      at a worse branching number, its Pareto front. With --trace, also writes
+      the decisions of every case (star, partition, and the reductions of each
+      branch) to <file> as a binary trace for replay, the same with and
+      without --threads; it bypasses --cache-dir and cannot be combined with
+      --budget-ms, --profile-cases or --progress.
+  analyze-all [<weights>] [--max-degree <d>] [--optimize] [--rounds <n>]
+        [--report <dir>] [--colors <k>] [--precision <digits>] [--max-blocks <b>]
+        [--min-halfedges <h>] [--max-halfedges <h>] [--reductions <names>]
+        [--allow-experimental] [--memory-limit <size>] [--force]
+        [--progress-interval <ms>] [--best-weights <file>] [--restarts <n>]
+        [--seed <s>]
+      Runs the whole analysis for the stars of degrees 3 to <d> (default 5) in
+      graphs of maximum degree <d>, printing one progress line per stage to
+      stderr: enumerates the stars and checks the invariants as verify does.
+      With --optimize, precomputes the
+      drops of the worst stars as linear functions of the weights and then
+      minimizes the worst branching number over weights in [0, 1] that are
+      nondecreasing in the list size, alternating a local search on the
+      precomputed stars with a search of all stars for one above the bound
+      reached, for at most <n> rounds (default 20). Every round prints its
+      bound and the best bound over all stars so far, at most once every <ms>
+      milliseconds with --progress-interval; with --best-weights, the weights
+      of the best bound so far are written to <file> whenever they improve,
+      through a temporary file and a rename, so that an interrupted run leaves
+      the last complete weights behind. With --restarts, optimizes <n> times
+      (default 1): first from the given weights, then from random weights drawn
+      from a SplitMix64 generator seeded with <s> (default 0), so a seed always
+      gives the same restarts. Every restart prints its bound, and the weights
+      of the smallest bound over all restarts are kept. The stars whose
+      branching number is within 1e-6 of the bound at the final weights bind
+      the optimum, standing in for the dual values of an LP; they are listed
+      with their best partitions. Then every feature of the measure is dropped
+      in turn, the weights are optimized again over the same stars, and the
+      features are listed by how much the bound worsens without them. Finally
+      analyzes all stars
+      with the weights (default all 1), checks that no reduction step
+      increases the measure, that the JSON artifacts read back to the same
+      analysis and, after --optimize, that no star exceeds the
+      bound reached. The bound (after --optimize) or the worst branching number
+      found, plus 1e-9, is then certified for every star: the characteristic
+      sum of its drops is evaluated at it with all rounding upwards, assuming
+      powers accurate to one ulp, and must be at most 1; the certified bound
+      is printed as certified=<bound>. With --report writes degree-<degree>.json, .md and
+      .tex and weights.txt to <dir>, and after --optimize the binding stars to
+      binding.txt and the ranking of the features to features.txt. With --max-blocks, stars only branch on
+      partitions with at most <b> >= 2 blocks, the weights are optimized under this
+      limit, and the verify stage shows the worst branching number the weights
+      would reach without it. --min-halfedges, --max-halfedges, --reductions and
+      --allow-experimental are as for proof. Fails with exit code 2 if a check fails.
+  check-bound <degree> <weights> <bound> [--colors <k>] [--max-degree <d>]
+        [--precision <digits>]
+      Checks that every star of the given degree has a partition of its root
+      list with branching number at most <bound>. The partitions into one color
+      and the rest are tried first, and all partitions only if none of them
+      meets the bound; prints how many stars needed the full search. Fails with
+      exit code 2 and lists the stars above the bound otherwise, and with exit
+      code 3 if <bound> is not a finite number of at least 1.
+  proof-diff <before.json> <after.json> [--tolerance <t>] [--precision <digits>]
+      Compares two artifacts written by proof --json and lists the stars added
+      or removed, the branching numbers that changed by more than <t> (default
+      1e-9), and the stars whose branches use different reductions.
+  repl
+      Starts an interactive session for exploring single stars; type `help`
+      for its commands.
+  worker [--max-degree <d>]
+      Answers length-prefixed binary batch requests (weights and stars) on stdin
+      with the best partition of every star on stdout, until stdin is closed.
+      See the `ipc` module for the format. --max-degree is as for proof.
+  verify <degree> [--colors <k>] [--max-degree <d>] [--halfedges <bounds>]
+        [--min-halfedges <h>] [--max-halfedges <h>] [--max-blocks <b>]
+        [--weights <weights>] [--reductions <names>] [--allow-experimental]
+        [--memory-limit <size>] [--force]
+      Applies every partition and the reductions to every star of the given
+      degree, validates each star produced, and lists all violations and all
+      reduction steps that increase the measure under <weights> (default all
+      1). The other options are as for proof.
+  reduction-order [<degree>] [--colors <k>]
+      Prints the Hasse diagram of the preorder \"star A reduces to star B\" on the
+      stars of the given degree as a Graphviz DOT graph. Without a degree, the
+      stars are read from stdin, one per line as for convert. Isomorphic stars form
+      one node; the stars the cases reduce to are added as dashed nodes. The
+      minimal nodes, which are the only cases that need separate treatment, have
+      a double border. Their number is printed to stderr.
+  microstructure <star> [--all-edges]
+      Prints the microstructure of the star as a Graphviz DOT graph: a node per
+      color in the list of each vertex, grouped by vertex, and an edge between
+      compatible colors of different vertices, so that the list colorings are
+      the cliques with a node of every vertex. Neighbors are compatible in
+      every color, so their edges are only drawn with --all-edges.
+  check-proof <artifact.json> --bound <b> [--allow-experimental]
+      Recomputes the analysis stored in an artifact written by proof --json,
+      checks that the artifact matches it, and that no branching number
+      exceeds <b>. The exclusion rules recorded in the artifact are applied to
+      the recomputed analysis. <b> must be a finite number of at least 1.
+      Artifacts using reductions that are not proved safe prove nothing and
+      are violated unless --allow-experimental is given.
+  compare-rules <degree> <weights> [--precision <digits>] [--colors <k>]
+        [--max-degree <d>]
+      Prints, for every star of the given degree, the best partition of its root
+      list and the best color class to branch on (the root joins the class or is
+      excluded from it, as in Kempe-chain arguments), each with its branching
+      number, as tab-separated columns. The worst case of both rule families is
+      printed to stderr.
+  heuristic-gap <degree> <weights> [--selector <name>] [--top <n>]
+        [--colors <k>] [--max-degree <d>] [--precision <digits>]
+      Branches every star of the given degree by a partition selector an
+      algorithm can implement without evaluating branching numbers, and
+      compares its branching numbers with the best partitions: prints the
+      quantiles of the gaps, the <n> stars with the largest gaps (default 10),
+      and the worst branching numbers of both. Selectors: most-shared-color
+      (the default; the root's color in the most neighbor lists against the
+      rest) and all-singletons (one branch per color).
+  audit-cases <degree> <cases.csv> [--colors <k>]
+      Maps every star of the given degree, up to isomorphism, to the cases of a
+      prior analysis that treat it, and lists the stars no case covers and the
+      cases no star matches. The CSV file has a header line with the columns
+      case (the case name) and star (a star string), one row per case and star.
+      Fails unless every star is covered and every case matched.
+  stress [--seeds <n>] [--first-seed <s>] [--max-vertices <n>]
+      Generates a random graph with random color lists for each of the <n>
+      seeds starting at <s> (default 1000 seeds from 0, graphs of at most 8
+      vertices, at most 64), decides list colorability with the branch-and-reduce
+      solver, with the solver after preprocessing the graph (forced vertices
+      colored and singleton lists propagated to a fixpoint, vertices with more
+      colors than neighbors removed), and with exhaustive search, and lists the
+      seeds on which they disagree.
+  case-set-fingerprint <degree> [--colors <k>] [--max-degree <d>]
+        [--halfedges <bounds>] [--manifest]
+      Reduces the stars of the given degree to one canonical star per
+      isomorphism class and prints a stable hash of the enumeration parameters
+      and the sorted canonical stars, with the crate version. With --manifest,
+      prints the manifest instead: the version, the parameters, the fingerprint
+      and the canonical stars, one per line, for case-set-diff.
+  bench-features <degree> [--rounds <n>] [--memory-limit <size>] [--force]
+      Times the feature extraction of the stars of the given degree over <n>
+      rounds (default 100) and prints the nanoseconds per star.
+  reduction-closure [--colors <k>] [--max-degree <d>]
+      Reads stars from stdin like convert, applies the default reductions and
+      every branching rule recursively, and prints every star reached, one per
+      isomorphism class. Solved and infeasible stars and stars whose root list
+      has one color end the recursion and are counted as terminal branches.
+      Stars that are not isomorphic to an enumerated star with the same number
+      of neighbors (and maximum degree <d>) are marked as outside; fails with 2
+      if there are any.
+  case-set-diff <before> <after>
+      Compares two manifests written by case-set-fingerprint --manifest, e.g.
+      by two versions of the crate, and lists the canonical stars added and
+      removed. Malformed manifests exit with 3.
+  solve --vector <drops> [--poly]
+      Prints the branching number of a branching vector given as comma-separated
+      drops, e.g. 1,1,2,3. With --poly, also prints the characteristic
+      polynomial, which requires integer drops.
+  compare --file <recurrences.json>
+      Prints a table of the branching numbers of named branching vectors and
+      their difference in percent to the first one. The file holds an object
+      mapping names to arrays of drops, e.g. {\"prior work\": [1,2], \"ours\": [2,2,3]}.
+  convert --to <v1|v2|ndjson|binary> [--from <text|binary>]
+      Reads stars from stdin, by default one per line in any supported format
+      (v1 or v2 star strings, or NDJSON objects), and writes them to stdout in
+      the given format. The binary format is a star file as described in the
+      `ipc` module: a header and then every star as in a worker request; with
+      --from binary, stdin is such a file. Fails with the line number on a star
+      the format cannot encode, e.g. one with more than 15 halfedges at a
+      neighbor for v1 and v2 or with edges among the neighbors for binary.
+  join-features --table <features.csv>
+      Reads stars from stdin like convert and writes one NDJSON object per star
+      with its canonical ID under \"id\" and its row of the CSV table under
+      \"features\" (null without a row). The table has a header line with a
+      column star; rows match every star isomorphic to theirs, and two rows for
+      isomorphic stars are an error. Ends with a summary line on stderr.
+
+Annotation files are JSON objects mapping star strings (v1 or v2) to a case name
+or to an object with the keys name, note and reference, e.g.
+{\"star_3_ffff_0222\": \"C5.3\", \"star_3_f73c_0222\": {\"name\": \"C5.4\", \"reference\": \"Lemma 12\"}}.
+
+With --colors, the commands that enumerate stars use only the lists of colors
+0, ..., k - 1, for 2 <= k <= 8 (default 4). The measure has weights only for
+lists of at most 4 colors, so explain, proof, verify, check-bound,
+compare-rules, heuristic-gap and analyze-all refuse k > 4.
+
+reduction-order, compare-rules, audit-cases and bench-features hold all stars of
+the degree in memory, proof and analyze-all the analysis of every star, and
+verify the failures it finds, at most a few for every star. They refuse to
+start if these take an estimated more than 4 GiB, or the limit given with
+--memory-limit <size> (in bytes, or with a unit K, M, G or T, e.g. 512M); with
+--force they start anyway after a warning on stderr. enumerate-stars streams
+the stars without holding them.
+
+verify, check-bound, check-proof and analyze-all end with a summary line `<command>: status=<status> ...`
+and exit with 0 (verified), 2 (violated: a bound does not hold, an invariant is
+broken, or the artifact does not match the code), 3 (malformed artifact), or
+4 (internal error). Other errors, such as invalid arguments or an option the
+command does not know, exit with 1.
+
+Tables, status lines, warnings and the --progress dashboard of every command are
+aligned and colored when stdout is a terminal (unless NO_COLOR is set), and plain
+otherwise: tab-separated tables, a dashboard that appends its frames, and no
+escape codes. --color and --plain override the detection.
+
+Numbers are printed with `.` as decimal separator, as the shortest string that
+reads back to the same value unless --precision fixes the number of digits after
+the decimal point.";
+
+const BOLD: &str = "1";
+const RED: &str = "31";
+const GREEN: &str = "32";
+const YELLOW: &str = "33";
+
+/// How to print results: aligned and colored for a person at a terminal, or plain for scripts.
+#[derive(Clone, Copy, Debug)]
+struct Output {
+    human: bool,
+}
+
+impl Output {
+    /// Removes `--color` and `--plain` from `args`; without them, output is for humans if
+    /// stdout is a terminal and `NO_COLOR` is not set.
+    fn from_args(args: &mut Vec<String>) -> Output {
+        let color = args.iter().any(|a| a == "--color");
+        let plain = args.iter().any(|a| a == "--plain");
+        args.retain(|a| a != "--color" && a != "--plain");
+        let human = match (color, plain) {
+            (_, true) => false,
+            (true, false) => true,
+            (false, false) => {
+                use std::io::IsTerminal;
+                std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+            }
+        };
+        Output { human }
+    }
+
+    /// Wraps `s` in the ANSI style `code` in human mode.
+    fn paint(&self, code: &str, s: &str) -> String {
+        if self.human && !code.is_empty() {
+            format!("\x1b[{code}m{s}\x1b[0m")
+        } else {
+            s.to_string()
+        }
+    }
+
+    /// The summary line `<command>: status=<status> <details>`, with the status green for a
+    /// success, yellow for a change and red otherwise.
+    fn status(&self, command: &str, status: &str, details: &str) -> String {
+        let code = match status {
+            "verified" | "identical" | "ok" | "complete" | "closed" | "same" => GREEN,
+            "changed" => YELLOW,
+            _ => RED,
+        };
+        let status = self.paint(code, status);
+        if details.is_empty() {
+            format!("{command}: status={status}")
+        } else {
+            format!("{command}: status={status} {details}")
+        }
+    }
+
+    /// `warning: <message>`, with the prefix in yellow.
+    fn warning(&self, message: &str) -> String {
+        format!("{} {message}", self.paint(YELLOW, "warning:"))
+    }
+
+    /// Prints `rows` as a table with the header `rows[0]`: tab-separated in plain mode, and
+    /// with aligned columns (the first one left-aligned) and a bold header in human mode,
+    /// with each other cell in the style `style(row, column, cell)`.
+    fn print_table(
+        &self,
+        rows: &[Vec<String>],
+        style: impl Fn(usize, usize, &str) -> &'static str,
+    ) {
+        if !self.human {
+            for row in rows {
+                println!("{}", row.join("\t"));
+            }
+            return;
+        }
+        let columns = rows.iter().map(Vec::len).max().unwrap_or(0);
+        let widths: Vec<usize> = (0..columns)
+            .map(|i| {
+                rows.iter()
+                    .filter_map(|row| row.get(i))
+                    .map(|cell| cell.chars().count())
+                    .max()
+                    .unwrap_or(0)
+            })
+            .collect();
+        for (r, row) in rows.iter().enumerate() {
+            let cells: Vec<String> = row
+                .iter()
+                .enumerate()
+                .map(|(c, cell)| {
+                    let padded = if c == 0 {
+                        format!("{cell:<w$}", w = widths[c])
+                    } else {
+                        format!("{cell:>w$}", w = widths[c])
+                    };
+                    if r == 0 {
+                        padded
+                    } else {
+                        self.paint(style(r, c, cell), &padded)
+                    }
+                })
+                .collect();
+            let line = cells.join("  ");
+            if r == 0 {
+                println!("{}", self.paint(BOLD, line.trim_end()));
+            } else {
+                println!("{}", line.trim_end());
+            }
+        }
+    }
+}
+
+fn parse_degree(s: Option<&str>) -> Result<usize, String> {
+    let Some(s) = s else {
+        return Err(USAGE.to_string());
+    };
+    s.parse().map_err(|_| format!("invalid degree: {s}"))
+}
+
+fn parse_weights(s: Option<&str>) -> Result<NodeFeatures, String> {
+    let Some(s) = s else {
+        return Err(USAGE.to_string());
+    };
+    let values: Vec<f64> = s
+        .split(',')
+        .map(|x| x.trim().parse::<f64>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("invalid weights: {s}"))?;
+    let values: [f64; 9] = values
+        .try_into()
+        .map_err(|_| format!("expected 9 weights: {s}"))?;
+    NodeFeatures::try_from_array(values).map_err(|e| format!("invalid weights: {e}"))
+}
+
+/// Removes `<flag> <value>` from `args` and returns the value parsed as `T`, if present.
+fn take_value<T: std::str::FromStr>(
+    args: &mut Vec<String>,
+    flag: &str,
+    what: &str,
+) -> Result<Option<T>, String> {
+    let Some(idx) = args.iter().position(|a| a == flag) else {
+        return Ok(None);
+    };
+    args.remove(idx);
+    if idx >= args.len() {
+        return Err(format!("{flag} expects {what}"));
+    }
+    let s = args.remove(idx);
+    s.parse()
+        .map(Some)
+        .map_err(|_| format!("invalid value for {flag}: {s}"))
+}
+
+/// Removes `--colors <k>` from `args` and returns the enumeration of stars with `k` colors
+/// (by default all of them).
+fn take_colors(args: &mut Vec<String>) -> Result<EnumerationConfig, String> {
+    let mut config = EnumerationConfig::default();
+    if let Some(k) = take_value(args, "--colors", "a number of colors")? {
+        config.colors = k;
+        config.validate()?;
+    }
+    Ok(config)
+}
+
+/// Like [`take_colors`], for the commands that weigh the stars with a measure, which has
+/// weights only for lists of at most [`NUM_COLORS`](recurrences::colors::NUM_COLORS) colors.
+fn take_measured_colors(args: &mut Vec<String>) -> Result<EnumerationConfig, String> {
+    let config = AnalysisConfig {
+        enumeration: take_colors(args)?,
+        ..AnalysisConfig::default()
+    };
+    config.validate()?;
+    Ok(config.enumeration)
+}
+
+/// Removes `--max-blocks <b>` from `args` and returns the limit, if present. Every root list
+/// that is split has a partition into 2 blocks, so smaller limits are rejected.
+fn take_max_blocks(args: &mut Vec<String>) -> Result<Option<usize>, String> {
+    let max_blocks: Option<usize> = take_value(args, "--max-blocks", "a number of blocks")?;
+    match max_blocks {
+        Some(b) if b < 2 => Err(format!(
+            "--max-blocks must be at least 2, since a branching has at least 2 branches, not {b}"
+        )),
+        _ => Ok(max_blocks),
+    }
+}
+
+/// Removes `--halfedges <bounds>` from `args` and returns the comma-separated bounds.
+fn take_halfedge_bounds(args: &mut Vec<String>) -> Result<Vec<HalfedgeBound>, String> {
+    match take_value::<String>(args, "--halfedges", "halfedge bounds")? {
+        Some(bounds) => bounds.split(',').map(str::parse).collect(),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Removes `--min-halfedges <h>` and `--max-halfedges <h>` from `args` and sets the range of
+/// halfedges of `config` to them.
+fn take_halfedge_range(
+    args: &mut Vec<String>,
+    config: &mut EnumerationConfig,
+) -> Result<(), String> {
+    if let Some(min) = take_value(args, "--min-halfedges", "a number of halfedges")? {
+        config.min_halfedges = min;
+    }
+    config.max_halfedges = take_value(args, "--max-halfedges", "a number of halfedges")?;
+    config.validate()
+}
+
+/// Removes `--colors`, `--max-degree`, `--halfedges`, `--min-halfedges`, `--max-halfedges`
+/// and `--max-blocks` from `args` and returns the analysis they select.
+fn take_analysis_config(args: &mut Vec<String>) -> Result<AnalysisConfig<'static>, String> {
+    let mut enumeration = take_measured_colors(args)?;
+    enumeration.max_degree = take_value(args, "--max-degree", "a maximum degree")?;
+    enumeration.halfedge_bounds = take_halfedge_bounds(args)?;
+    take_halfedge_range(args, &mut enumeration)?;
+    Ok(AnalysisConfig {
+        enumeration,
+        max_blocks: take_max_blocks(args)?,
+        ..AnalysisConfig::default()
+    })
+}
+
+/// Removes `--reductions <names>` and `--allow-experimental` from `args` and returns the
+/// reductions with the given comma-separated names (see [`select_reductions`]), by default the
+/// [`DEFAULT_REDUCTIONS`]. Fails on reductions that are not proved safe, unless
+/// `--allow-experimental` is given.
+fn take_reductions(
+    args: &mut Vec<String>,
+    max_degree: MaxDegree,
+) -> Result<Vec<Box<dyn Reduction>>, String> {
+    let allow_unproved = take_allow_experimental(args);
+    let names = take_value::<String>(args, "--reductions", "reduction names")?;
+    let names: Vec<&str> = match names.as_deref() {
+        Some(names) => names.split(',').collect(),
+        None => DEFAULT_REDUCTIONS.iter().map(|r| r.name()).collect(),
+    };
+    select_reductions(&names, max_degree, allow_unproved)
+}
+
+/// Removes `--allow-experimental` from `args` and returns whether it was given, i.e. whether
+/// reductions that are not proved safe may be used.
+fn take_allow_experimental(args: &mut Vec<String>) -> bool {
+    take_flag(args, "--allow-experimental")
+}
+
+/// Removes every `flag` from `args` and returns whether it was given.
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    let given = args.iter().any(|a| a == flag);
+    args.retain(|a| a != flag);
+    given
+}
+
+/// Fails on the first argument left in `args` that looks like an option, once a command has
+/// taken its options out of `args`, so that a misspelled option is not silently ignored.
+fn reject_unknown_flags(args: &[String]) -> Result<(), String> {
+    match args.iter().find(|a| a.starts_with("--")) {
+        Some(flag) => Err(format!("unknown option: {flag}")),
+        None => Ok(()),
+    }
+}
+
+/// Removes `--memory-limit <size>` and `--force` from `args` and returns the guard for the
+/// commands that collect all stars of a degree.
+fn take_memory_guard(args: &mut Vec<String>) -> Result<MemoryGuard, String> {
+    let mut guard = MemoryGuard::default();
+    if let Some(limit) = take_value::<String>(args, "--memory-limit", "a memory size")? {
+        guard.limit = parse_memory_size(&limit)?;
+    }
+    if let Some(idx) = args.iter().position(|a| a == "--force") {
+        args.remove(idx);
+        guard.force = true;
+    }
+    Ok(guard)
+}
+
+/// Checks the `estimate` of the memory `what` takes against `guard`, printing the warning if
+/// it is forced past the limit.
+fn check_memory(
+    guard: MemoryGuard,
+    estimate: Result<u128, CountError>,
+    what: &str,
+    alternative: &str,
+    out: Output,
+) -> Result<(), String> {
+    let estimate = estimate.map_err(|e| format!("{what}: {e}"))?;
+    if let Some(warning) = guard.check(estimate, what, alternative)? {
+        let message = warning.strip_prefix("warning: ").unwrap_or(&warning);
+        eprintln!("{}", out.warning(message));
+    }
+    Ok(())
+}
+
+/// The stars of `degree` for `config`, if `config` can enumerate them and their estimated
+/// size passes `guard`.
+fn collect_stars(
+    degree: usize,
+    config: &EnumerationConfig,
+    guard: MemoryGuard,
+    out: Output,
+) -> Result<Vec<Star>, String> {
+    config.validate_for_degree(degree)?;
+    check_memory(
+        guard,
+        estimate_memory_with_config(degree, config),
+        &format!("the stars of degree {degree}"),
+        "stream them with enumerate-stars or fold_stars_with_config instead",
+        out,
+    )?;
+    Ok(generate_stars_with_config(degree, config))
+}
+
+/// Checks that `bound` can bound a branching number: finite and at least 1. `cmd` prints its
+/// malformed summary line otherwise.
+fn check_branching_bound(cmd: &str, bound: f64, out: Output) -> Result<f64, Failure> {
+    if bound.is_finite() && bound >= 1.0 {
+        return Ok(bound);
+    }
+    println!("{}", out.status(cmd, "malformed", ""));
+    Err(Failure {
+        code: EXIT_MALFORMED,
+        message: format!("invalid bound {bound}: branching numbers are finite and at least 1"),
+    })
+}
+
+/// Removes `--precision <digits>` from `args` and returns the digits, if present.
+fn take_precision(args: &mut Vec<String>) -> Result<Option<usize>, String> {
+    take_value(args, "--precision", "a number of digits")
+}
+
+fn explain(args: &[String]) -> Result<(), String> {
+    let mut args = args.to_vec();
+    let precision = take_precision(&mut args)?;
+    let mut config = AnalysisConfig {
+        enumeration: take_measured_colors(&mut args)?,
+        ..AnalysisConfig::default()
+    };
+    config.enumeration.max_degree = take_value(&mut args, "--max-degree", "a maximum degree")?;
+    let trace_path: Option<String> = take_value(&mut args, "--trace", "a file")?;
+    let html = take_flag(&mut args, "--html");
+    reject_unknown_flags(&args)?;
+    let mut positional = args.iter().filter(|a| !a.starts_with("--"));
+    let degree = parse_degree(positional.next().map(|s| s.as_str()))?;
+    let weights = parse_weights(positional.next().map(|s| s.as_str()))?;
+
+    config.enumeration.validate_for_degree(degree)?;
+
+    let Some((star, _)) = most_violated_star(weights, f64::NEG_INFINITY, degree, &config)
+        .map_err(|e| e.to_string())?
+    else {
+        return Err(format!("no stars of degree {degree}"));
+    };
+    let tree = branch_tree(&star, weights, &config);
+    if let Some(path) = trace_path {
+        let trace = Trace::from_branch_tree(&tree, weights, &config);
+        let bytes = encode_trace(&trace).map_err(|e| format!("{path}: {e}"))?;
+        std::fs::write(&path, bytes).map_err(|e| format!("{path}: {e}"))?;
+    }
+    if html {
+        print!("{}", branch_tree_to_html(&tree, precision));
+    } else {
+        print!("{}", branch_tree_to_text(&tree, precision));
+    }
+    Ok(())
+}
+
+/// Live progress display on stderr for [`analysis_report_with_progress`].
+struct Dashboard {
+    /// Per root list: the root colors, stars analyzed, and stars in total.
+    roots: Vec<(u8, usize, usize)>,
+    /// The stars in total.
+    total: usize,
+    /// The worst cases so far, worst first.
+    worst: Vec<(f64, Star)>,
+    lines_drawn: usize,
+    last_draw: Option<std::time::Instant>,
+    /// Redraws the frame in place in human mode, and appends it otherwise.
+    out: Output,
+}
+
+impl Dashboard {
+    const WORST: usize = 5;
+
+    /// A dashboard for the stars of `degree` under `config`, which it counts by root list
+    /// without keeping them.
+    fn new(degree: usize, config: &EnumerationConfig, out: Output) -> Self {
+        let roots = fold_stars_with_config(degree, config, Vec::new(), |mut roots, star| {
+            match roots
+                .iter_mut()
+                .find(|r: &&mut (u8, usize, usize)| r.0 == star.root_colors)
+            {
+                Some(r) => r.2 += 1,
+                None => roots.push((star.root_colors, 0, 1)),
+            }
+            roots
+        });
+        Dashboard {
+            total: roots.iter().map(|r| r.2).sum(),
+            roots,
+            worst: Vec::new(),
+            lines_drawn: 0,
+            last_draw: None,
+            out,
+        }
+    }
+
+    fn update(&mut self, i: usize, star: &Star, case: Option<&CaseReport>) {
+        if let Some(r) = self.roots.iter_mut().find(|r| r.0 == star.root_colors) {
+            r.1 += 1;
+        }
+        if let Some(case) = case {
+            self.worst.push((case.branching_number, case.star.clone()));
+            self.worst.sort_by(|a, b| b.0.total_cmp(&a.0));
+            self.worst.truncate(Self::WORST);
+        }
+        let done = i + 1 == self.total;
+        let due = self
+            .last_draw
+            .is_none_or(|t| t.elapsed() >= std::time::Duration::from_millis(200));
+        if done || due {
+            self.draw(i + 1, self.total);
+        }
+    }
+
+    fn draw(&mut self, done: usize, total: usize) {
+        let mut lines: Vec<String> = vec![format!("analyzed {done}/{total} stars")];
+        for &(root, n, of) in self.roots.iter() {
+            lines.push(format!("  root {:<10} {n:>8}/{of}", fmt_colors(root)));
+        }
+        let worst = self.worst.first().map_or(1.0, |w| w.0);
+        lines.push(format!(
+            "worst branching number: {}",
+            format_float(worst, Some(6))
+        ));
+        for (b, star) in self.worst.iter() {
+            lines.push(format!(
+                "  {}  {}",
+                format_float(*b, Some(6)),
+                star_label(star)
+            ));
+        }
+        lines.push(format!(
+            "memory: {}",
+            resident_memory().unwrap_or("n/a".to_string())
+        ));
+
+        let mut err = std::io::stderr().lock();
+        if !self.out.human {
+            for line in lines.iter() {
+                let _ = writeln!(err, "{line}");
+            }
+        } else {
+            if self.lines_drawn > 0 {
+                // Move back to the first line of the previous frame.
+                let _ = write!(err, "\x1b[{}F", self.lines_drawn);
+            }
+            for line in lines.iter() {
+                let _ = writeln!(err, "\x1b[2K{line}");
+            }
+        }
+        self.lines_drawn = lines.len();
+        self.last_draw = Some(std::time::Instant::now());
+    }
+}
+
+/// The resident memory of this process, where the OS reports it (Linux).
+fn resident_memory() -> Option<String> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmRSS:"))?;
+    Some(line["VmRSS:".len()..].trim().to_string())
+}
+
+/// Reads an annotations sidecar file.
+fn read_annotations(path: &str) -> Result<Annotations, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+    parse_annotations(&text).map_err(|e| format!("{path}: {e}"))
+}
+
+fn proof(args: &[String], out: Output) -> Result<(), String> {
+    let mut args = args.to_vec();
+    let precision = take_precision(&mut args)?;
+    let latex = take_flag(&mut args, "--latex");
+    let json = take_flag(&mut args, "--json");
+    let threads: Option<usize> = take_value(&mut args, "--threads", "a number of threads")?;
+    let verify = take_flag(&mut args, "--verify-determinism");
+    let progress = take_flag(&mut args, "--progress");
+    let pareto = take_flag(&mut args, "--pareto");
+    let trace_path: Option<String> = take_value(&mut args, "--trace", "a file")?;
+    let profile: Option<usize> = take_value(&mut args, "--profile-cases", "a number of cases")?;
+    let cache: Option<Cache> =
+        take_value::<String>(&mut args, "--cache-dir", "a directory")?.map(Cache::new);
+    let exclusions = match take_value::<String>(&mut args, "--exclude", "exclusion rules")? {
+        Some(rules) => CaseFilter::from_rules(rules.split(','))?,
+        None => CaseFilter::default(),
+    };
+    let config = take_analysis_config(&mut args)?;
+    let selected = take_reductions(&mut args, config.max_degree())?;
+    let reductions: Vec<&dyn Reduction> = selected.iter().map(|r| r.as_ref()).collect();
+    let config = AnalysisConfig {
+        reductions: &reductions,
+        pareto,
+        exclusions,
+        ..config
+    };
+    if threads.is_some() && (profile.is_some() || progress) {
+        // Timings and the dashboard follow the stars one after another, which the threads
+        // do not.
+        return Err("--threads cannot be combined with --profile-cases or --progress".to_string());
+    }
+    let guard = take_memory_guard(&mut args)?;
+    let budget: Option<u64> = take_value(&mut args, "--budget-ms", "a number of milliseconds")?;
+    if budget.is_some() && (threads.is_some() || profile.is_some() || progress) {
+        return Err(
+            "--budget-ms cannot be combined with --threads, --profile-cases or --progress"
+                .to_string(),
+        );
+    }
+    if trace_path.is_some() && (budget.is_some() || profile.is_some() || progress) {
+        return Err(
+            "--trace cannot be combined with --budget-ms, --profile-cases or --progress"
+                .to_string(),
+        );
+    }
+    let annotations = match take_value::<String>(&mut args, "--annotations", "a file")? {
+        Some(path) => read_annotations(&path)?,
+        None => Annotations::default(),
+    };
+    reject_unknown_flags(&args)?;
+    let mut positional = args.iter().filter(|a| !a.starts_with("--"));
+    let degree = parse_degree(positional.next().map(|s| s.as_str()))?;
+    let weights = parse_weights(positional.next().map(|s| s.as_str()))?;
+    if degree < 2 {
+        return Err(format!("no stars of degree {degree}"));
+    }
+    config.enumeration.validate_for_degree(degree)?;
+
+    let key = cache
+        .as_ref()
+        .map(|_| report_cache_key(degree, weights, &config));
+    // A report within a time budget depends on the timing, so it is neither read from nor
+    // written to the cache.
+    let cache = cache.filter(|_| budget.is_none());
+    // A trace records the analysis as it runs, so a traced run does not read the cache.
+    let cached = match (&cache, profile) {
+        (Some(cache), None) if trace_path.is_none() => key.and_then(|key| cache.get("report", key)),
+        _ => None,
+    };
+    let mut events: Option<Vec<TraceEvent>> = trace_path.as_ref().map(|_| Vec::new());
+    let hit = cached.is_some();
+    if !hit {
+        check_memory(
+            guard,
+            estimate_report_memory(degree, &config),
+            &format!("the analysis of the stars of degree {degree}"),
+            "analyze fewer stars with --colors, --halfedges or --max-blocks",
+            out,
+        )?;
+        let increases = measure_increases_with_config(degree, &config, weights);
+        for increase in increases.iter() {
+            eprintln!(
+                "{}",
+                out.paint(RED, &format!("measure increase: {increase}"))
+            );
+        }
+        if !increases.is_empty() {
+            return Err(format!(
+                "{} reduction steps increase the measure",
+                increases.len()
+            ));
+        }
+    }
+    let report = match (cached, threads, profile) {
+        (Some(text), _, _) => report_from_json_string(&text)?,
+        (None, _, _) if let Some(ms) = budget => {
+            let budget = std::time::Duration::from_millis(ms);
+            let report = analysis_report_with_budget(degree, weights, &config, budget);
+            let hit: Vec<&CaseReport> = report
+                .cases
+                .iter()
+                .filter(|c| c.possibly_suboptimal)
+                .collect();
+            let notice = format!(
+                "{} of {} cases hit the budget of {ms} ms and may use a suboptimal partition",
+                hit.len(),
+                report.cases.len()
+            );
+            eprintln!("{}", out.paint(YELLOW, &notice));
+            for case in hit.iter() {
+                eprintln!("  {}", star_label(&case.star));
+            }
+            report
+        }
+        (None, _, Some(n)) => {
+            let (report, timings) = analysis_report_profiled(degree, weights, &config);
+            let header = format!("{:>12}  {:>10}  star", "time [us]", "partitions");
+            eprintln!("{}", out.paint(BOLD, &header));
+            for t in slowest_cases(&timings, n) {
+                eprintln!(
+                    "{:>12}  {:>10}  {}",
+                    t.elapsed.as_micros(),
+                    t.partitions,
+                    star_label(&t.star)
+                );
+            }
+            report
+        }
+        (None, _, None) if progress => {
+            let mut dashboard = Dashboard::new(degree, &config.enumeration, out);
+            analysis_report_with_progress(degree, weights, &config, |i, star, case| {
+                dashboard.update(i, star, case)
+            })
+        }
+        (None, Some(threads), None) => {
+            analysis_report_parallel_traced(degree, weights, &config, threads, &mut events)
+        }
+        (None, None, None) => analysis_report_traced(degree, weights, &config, &mut events),
+    };
+    if let (Some(path), Some(events)) = (trace_path, events) {
+        let trace = Trace::new(TraceKind::Analysis, weights, &config, events);
+        let bytes = encode_trace(&trace).map_err(|e| format!("{path}: {e}"))?;
+        std::fs::write(&path, bytes).map_err(|e| format!("{path}: {e}"))?;
+    }
+    if let (Some(cache), Some(key)) = (cache.as_ref().filter(|_| !hit), key) {
+        cache
+            .put("report", key, &report.to_json_string())
+            .map_err(|e| format!("cannot write cache: {e}"))?;
+    }
+    if verify {
+        let threads =
+            threads.unwrap_or_else(|| std::thread::available_parallelism().map_or(2, |n| n.get()));
+        let sequential = analysis_report(degree, weights, &config).content_hash();
+        let parallel = analysis_report_parallel(degree, weights, &config, threads).content_hash();
+        if sequential != parallel {
+            return Err(format!(
+                "sequential and parallel ({threads} threads) analyses differ: {sequential:016x} != {parallel:016x}"
+            ));
+        }
+        let verified =
+            format!("determinism verified: {sequential:016x} with 1 and {threads} threads");
+        eprintln!("{}", out.paint(GREEN, &verified));
+    }
+    if json {
+        println!("{}", report.to_json_string());
+    } else if latex {
+        print!(
+            "{}",
+            report_to_latex_annotated(&report, precision, &annotations)
+        );
+    } else {
+        print!(
+            "{}",
+            report_to_markdown_annotated(&report, precision, &annotations)
+        );
+    }
+    Ok(())
+}
+
+fn proof_diff(args: &[String], out: Output) -> Result<(), String> {
+    let mut args = args.to_vec();
+    let precision = take_precision(&mut args)?;
+    let tolerance = take_value(&mut args, "--tolerance", "a number")?.unwrap_or(1e-9);
+    reject_unknown_flags(&args)?;
+    let [before, after] = args.as_slice() else {
+        return Err(USAGE.to_string());
+    };
+    let read = |path: &String| {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+        report_from_json_string(&text).map_err(|e| format!("{path}: {e}"))
+    };
+    let diff = diff_reports(&read(before)?, &read(after)?, tolerance);
+    if diff.is_empty() {
+        println!("no differences");
+    }
+    for line in report_diff_to_text(&diff, precision).lines() {
+        let (kind, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let code = match kind {
+            "added" => GREEN,
+            "removed" => RED,
+            _ => YELLOW,
+        };
+        println!("{} {rest}", out.paint(code, kind));
+    }
+    Ok(())
+}
+
+const REPL_HELP: &str = "commands:
+  load <star>           load a star given as v1/v2 string, NDJSON object,
+                        <degree>:<index> into the enumeration of that degree
+                        under the maximum degree of the session, or
+                        the name of a gadget
+  gadgets               list the names of the gadgets
+  show                  print the current star
+  features              print the list/degree counts of the current star
+  max-degree <d>        bucket degrees, measure and analyze for graphs of
+                        maximum degree <d> (default 5)
+  partitions            list the partitions of the root list
+  apply <i>             apply partition <i> and list the branches
+  go <j>                make branch <j> of the last `apply` the current star
+  reduce                apply the default reductions to the current star
+  weights <weights>     set the weights (9 comma-separated numbers)
+  best                  print the best partition under the weights
+  pareto                print the partitions that are Pareto-optimal for the
+                        branching number and the number of branches
+  annotations <file>    read case names and notes of stars from <file>
+  help                  print this help
+  quit                  leave the session";
+
+#[derive(Default)]
+struct ReplState {
+    star: Option<Star>,
+    branches: Vec<Branch>,
+    weights: Option<NodeFeatures>,
+    config: AnalysisConfig<'static>,
+    annotations: Annotations,
+}
+
+impl ReplState {
+    fn star(&self) -> Result<&Star, String> {
+        self.star
+            .as_ref()
+            .ok_or_else(|| "no star loaded; use `load`".to_string())
+    }
+
+    fn show(&self) -> Result<String, String> {
+        let star = self.star()?;
+        let mut out = format!(
+            "{}  (root {}, neighbors {})",
+            star_label(star),
+            fmt_colors(star.root_colors),
+            star.neighbor_colors
+                .iter()
+                .zip(star.neighbor_halfedges.iter())
+                .map(|(&c, h)| format!("{}x{h}", fmt_colors(c)))
+                .collect::<Vec<_>>()
+                .join(" ")
+        );
+        if let Some(annotation) = self.annotations.get(star) {
+            let fields = [
+                ("case", &annotation.name),
+                ("note", &annotation.note),
+                ("see", &annotation.reference),
+            ];
+            for (label, value) in fields {
+                if let Some(value) = value {
+                    out.push_str(&format!("\n{label}: {value}"));
+                }
+            }
+        }
+        Ok(out)
+    }
+
+    /// Executes one command line and returns its output.
+    fn execute(&mut self, line: &str) -> Result<String, String> {
+        let mut words = line.split_whitespace();
+        let Some(cmd) = words.next() else {
+            return Ok(String::new());
+        };
+        let arg = words.next();
+        let index = |arg: Option<&str>, len: usize| -> Result<usize, String> {
+            arg.and_then(|a| a.parse().ok())
+                .filter(|&i| i < len)
+                .ok_or_else(|| format!("expected an index below {len}"))
+        };
+        match cmd {
+            "help" => Ok(REPL_HELP.to_string()),
+            "load" => {
+                let arg = arg.ok_or("load expects a star")?;
+                let star = if arg.starts_with('{') {
+                    let json = &line.trim_start()["load".len()..];
+                    star_from_json_string(json).map_err(|e| e.to_string())?
+                } else if let Some((degree, i)) = arg
+                    .split_once(':')
+                    .filter(|(d, _)| d.bytes().all(|b| b.is_ascii_digit()))
+                {
+                    let degree = parse_degree(Some(degree))?;
+                    if degree < 2 {
+                        return Err(format!("no stars of degree {degree}"));
+                    }
+                    let i: usize = i.parse().map_err(|_| format!("invalid index: {i}"))?;
+                    // Streams the enumeration of the session up to the star, without
+                    // collecting the stars before it.
+                    let config = &self.config.enumeration;
+                    config.validate_for_degree(degree)?;
+                    StarIter::new(degree, config).nth(i).ok_or_else(|| {
+                        format!("the enumeration of degree {degree} has no star {i}")
+                    })?
+                } else if let Some(star) = gadget(arg) {
+                    star
+                } else {
+                    star_from_string(arg).map_err(|e| e.to_string())?
+                };
+                star.validate().map_err(|e| e.to_string())?;
+                self.star = Some(star);
+                self.branches.clear();
+                self.show()
+            }
+            "show" => self.show(),
+            "gadgets" => Ok(GADGETS
+                .iter()
+                .map(|g| g.name)
+                .collect::<Vec<_>>()
+                .join("\n")),
+            "features" => Ok(star_features(
+                self.star()?,
+                FeatureSchema::Counts,
+                self.config.max_degree(),
+            )
+            .to_json_string()),
+            "max-degree" => {
+                let d = parse_degree(arg)?;
+                if d < 3 {
+                    return Err(format!("invalid maximum degree: {d}"));
+                }
+                self.config = AnalysisConfig::for_max_degree(d);
+                Ok(String::new())
+            }
+            "partitions" => Ok(partitions_of_colors(self.star()?.root_colors)
+                .iter()
+                .enumerate()
+                .map(|(i, p)| format!("{i}: {}", fmt_partition(p)))
+                .collect::<Vec<_>>()
+                .join("\n")),
+            "apply" => {
+                let star = self.star()?;
+                let partitions = partitions_of_colors(star.root_colors);
+                let partition = &partitions[index(arg, partitions.len())?];
+                self.branches = apply_list_coloring_partition(star, partition);
+                Ok(self
+                    .branches
+                    .iter()
+                    .enumerate()
+                    .map(|(j, b)| match b {
+                        Branch::Solved => format!("{j}: solved"),
+                        Branch::Infeasible => format!("{j}: infeasible"),
+                        Branch::Star(s) => {
+                            format!("{j}: {}", star_label(s))
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"))
+            }
+            "go" => {
+                let j = index(arg, self.branches.len())?;
+                let star = self.branches[j]
+                    .as_star()
+                    .ok_or_else(|| format!("branch {j} is not a star"))?
+                    .clone();
+                self.star = Some(star);
+                self.branches.clear();
+                self.show()
+            }
+            "reduce" => {
+                let (reduced, trace) =
+                    reduce_to_fixpoint_with_trace(self.star()?, DEFAULT_REDUCTIONS);
+                let applied = if trace.is_empty() {
+                    "no reduction applies".to_string()
+                } else {
+                    trace.join(", ")
+                };
+                self.star = Some(reduced);
+                self.branches.clear();
+                Ok(format!("{applied}\n{}", self.show()?))
+            }
+            "weights" => {
+                self.weights = Some(parse_weights(arg)?);
+                Ok(String::new())
+            }
+            "best" => {
+                let weights = self.weights.ok_or("no weights set; use `weights`")?;
+                let star = self.star()?;
+                match best_partition(star, weights, &self.config) {
+                    Some((partition, b)) => Ok(format!(
+                        "{}  branching number {}",
+                        fmt_partition(&partition),
+                        format_float(b, None)
+                    )),
+                    None => Ok("the root list cannot be split".to_string()),
+                }
+            }
+            "pareto" => {
+                let weights = self.weights.ok_or("no weights set; use `weights`")?;
+                let front = pareto_partitions(self.star()?, weights, &self.config);
+                if front.is_empty() {
+                    return Ok("the root list cannot be split".to_string());
+                }
+                Ok(front
+                    .iter()
+                    .map(|p| {
+                        format!(
+                            "{}  branches {}  branching number {}",
+                            fmt_partition(&p.partition),
+                            p.branches,
+                            format_float(p.branching_number, None)
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"))
+            }
+            "annotations" => {
+                self.annotations = read_annotations(arg.ok_or("annotations expects a file")?)?;
+                Ok(format!("{} annotated stars", self.annotations.len()))
+            }
+            _ => Err(format!("unknown command: {cmd}; type `help`")),
+        }
+    }
+}
+
+fn repl(args: &[String], out: Output) -> Result<(), String> {
+    if !args.is_empty() {
+        return Err(USAGE.to_string());
+    }
+    let mut state = ReplState::default();
+    let stdin = std::io::stdin();
+    let mut line = String::new();
+    loop {
+        print!("> ");
+        std::io::stdout().flush().map_err(|e| e.to_string())?;
+        line.clear();
+        if stdin.read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+            println!();
+            return Ok(());
+        }
+        if matches!(line.trim(), "quit" | "exit") {
+            return Ok(());
+        }
+        match state.execute(&line) {
+            Ok(text) if text.is_empty() => {}
+            Ok(text) => println!("{text}"),
+            Err(e) => println!("{}", out.paint(RED, &format!("error: {e}"))),
+        }
+    }
+}
+
+const EXIT_USAGE: u8 = 1;
+const EXIT_VIOLATED: u8 = 2;
+const EXIT_MALFORMED: u8 = 3;
+const EXIT_INTERNAL: u8 = 4;
+
+/// An error that ends the program, with the exit code scripts can gate on.
+#[derive(Debug)]
+struct Failure {
+    code: u8,
+    message: String,
+}
+
+impl From<String> for Failure {
+    fn from(message: String) -> Self {
+        Failure {
+            code: EXIT_USAGE,
+            message,
+        }
+    }
+}
+
+fn reduction_order_dot(args: &[String], out: Output) -> Result<(), String> {
+    let mut args = args.to_vec();
+    let config = take_colors(&mut args)?;
+    let guard = take_memory_guard(&mut args)?;
+    reject_unknown_flags(&args)?;
+    let cases = match args.as_slice() {
+        [] => read_stars_from_stdin()?,
+        [degree] => {
+            let degree = parse_degree(Some(degree))?;
+            if degree < 2 {
+                return Err(format!("no stars of degree {degree}"));
+            }
+            collect_stars(degree, &config, guard, out)?
+        }
+        _ => return Err(USAGE.to_string()),
+    };
+    let order = reduction_order(&cases, DEFAULT_REDUCTIONS);
+    print!("{}", order.to_dot());
+    eprintln!(
+        "{} classes, {} minimal",
+        order.classes.len(),
+        order.minimal().len()
+    );
+    Ok(())
+}
+
+fn microstructure(args: &[String]) -> Result<(), String> {
+    let mut args = args.to_vec();
+    let all_edges = take_flag(&mut args, "--all-edges");
+    reject_unknown_flags(&args)?;
+    let [star] = args.as_slice() else {
+        return Err(USAGE.to_string());
+    };
+    let star = star_from_string(star).map_err(|e| e.to_string())?;
+    print!(
+        "{}",
+        Microstructure::from_star(&star).to_dot(&star, all_edges)
+    );
+    Ok(())
+}
+
+fn verify(args: &[String], out: Output) -> Result<(), Failure> {
+    let mut args = args.to_vec();
+    let config = take_analysis_config(&mut args)?;
+    let selected = take_reductions(&mut args, config.max_degree())?;
+    let reductions: Vec<&dyn Reduction> = selected.iter().map(|r| r.as_ref()).collect();
+    let config = AnalysisConfig {
+        reductions: &reductions,
+        ..config
+    };
+    let weights = match take_value::<String>(&mut args, "--weights", "weights")? {
+        Some(weights) => parse_weights(Some(&weights))?,
+        None => NodeFeatures::from_array([1.0; 9]),
+    };
+    let guard = take_memory_guard(&mut args)?;
+    reject_unknown_flags(&args)?;
+    let [degree] = args.as_slice() else {
+        return Err(USAGE.to_string().into());
+    };
+    let degree = parse_degree(Some(degree))?;
+    if degree < 2 {
+        return Err(format!("no stars of degree {degree}").into());
+    }
+    config.enumeration.validate_for_degree(degree)?;
+    check_memory(
+        guard,
+        estimate_failures_memory(degree, &config),
+        &format!("the failures of the stars of degree {degree}"),
+        "verify fewer stars with --colors, --halfedges or --max-blocks",
+        out,
+    )?;
+    let violations = verify_all_invariants_with_config(degree, &config);
+    for v in violations.iter() {
+        println!("{}", out.paint(RED, &v.to_string()));
+    }
+    let increases = measure_increases_with_config(degree, &config, weights);
+    for increase in increases.iter() {
+        println!(
+            "{}",
+            out.paint(RED, &format!("measure increase: {increase}"))
+        );
+    }
+    let status = if violations.is_empty() && increases.is_empty() {
+        "verified"
+    } else {
+        "violated"
+    };
+    let details = format!(
+        "degree={degree} violations={} measure_increases={}",
+        violations.len(),
+        increases.len()
+    );
+    println!("{}", out.status("verify", status, &details));
+    if !violations.is_empty() {
+        return Err(Failure {
+            code: EXIT_VIOLATED,
+            message: format!("{} invariant violations", violations.len()),
+        });
+    }
+    if !increases.is_empty() {
+        return Err(Failure {
+            code: EXIT_VIOLATED,
+            message: format!("{} reduction steps increase the measure", increases.len()),
+        });
+    }
+    println!(
+        "{}",
+        out.paint(GREEN, &format!("all stars of degree {degree} passed"))
+    );
+    Ok(())
+}
+
+fn check_bound(args: &[String], out: Output) -> Result<(), Failure> {
+    let mut args = args.to_vec();
+    let precision = take_precision(&mut args)?;
+    let mut config = take_measured_colors(&mut args)?;
+    config.max_degree = take_value(&mut args, "--max-degree", "a maximum degree")?;
+    reject_unknown_flags(&args)?;
+    let [degree, weights, bound] = args.as_slice() else {
+        return Err(USAGE.to_string().into());
+    };
+    let degree = parse_degree(Some(degree))?;
+    if degree < 2 {
+        return Err(format!("no stars of degree {degree}").into());
+    }
+    config.validate_for_degree(degree)?;
+    let weights = parse_weights(Some(weights))?;
+    let bound: f64 = bound
+        .parse()
+        .map_err(|_| format!("invalid bound: {bound}"))?;
+    let bound = check_branching_bound("check-bound", bound, out)?;
+
+    let config = AnalysisConfig {
+        enumeration: config,
+        ..AnalysisConfig::default()
+    };
+    let start = std::time::Instant::now();
+    let summary = adaptive_analysis(weights, bound, degree, &config).map_err(|e| e.to_string())?;
+    for (star, bn) in summary.violations.iter() {
+        let line = format!(
+            "{} branching_number={}",
+            star_label(star),
+            format_float(*bn, precision)
+        );
+        println!("{}", out.paint(RED, &line));
+    }
+    let status = if summary.violations.is_empty() {
+        "verified"
+    } else {
+        "violated"
+    };
+    let details = format!(
+        "degree={degree} stars={} full_searches={} violations={} elapsed_ms={}",
+        summary.stars,
+        summary.full_searches,
+        summary.violations.len(),
+        start.elapsed().as_millis()
+    );
+    println!("{}", out.status("check-bound", status, &details));
+    if !summary.violations.is_empty() {
+        return Err(Failure {
+            code: EXIT_VIOLATED,
+            message: format!("{} stars exceed the bound", summary.violations.len()),
+        });
+    }
+    Ok(())
+}
+
+fn compare_rules(args: &[String], out: Output) -> Result<(), String> {
+    let mut args = args.to_vec();
+    let precision = take_precision(&mut args)?;
+    let mut config = take_measured_colors(&mut args)?;
+    config.max_degree = take_value(&mut args, "--max-degree", "a maximum degree")?;
+    let guard = take_memory_guard(&mut args)?;
+    reject_unknown_flags(&args)?;
+    let [degree, weights] = args.as_slice() else {
+        return Err(USAGE.to_string());
+    };
+    let degree = parse_degree(Some(degree))?;
+    let weights = parse_weights(Some(weights))?;
+    if degree < 2 {
+        return Err(format!("no stars of degree {degree}"));
+    }
+
+    let stars = collect_stars(degree, &config, guard, out)?;
+    let config = AnalysisConfig {
+        enumeration: config,
+        ..AnalysisConfig::default()
+    };
+    let comparisons = compare_rule_families(&stars, weights, &config);
+    let num = |x: f64| format_float(x, precision);
+    let header = [
+        "star",
+        "partition",
+        "partition_bn",
+        "color",
+        "color_class_bn",
+    ];
+    let rows: Vec<Vec<String>> = std::iter::once(header.map(String::from).to_vec())
+        .chain(comparisons.iter().map(|c| {
+            vec![
+                star_label(&c.star),
+                fmt_partition(&c.partition.0),
+                num(c.partition.1),
+                c.color_class.0.to_string(),
+                num(c.color_class.1),
+            ]
+        }))
+        .collect();
+    // Color classes that branch worse than the partitions.
+    out.print_table(&rows, |r, c, _| {
+        let worse = comparisons[r - 1].color_class.1 > comparisons[r - 1].partition.1;
+        if c == 4 && worse { RED } else { "" }
+    });
+    let worst = |bn: fn(&RuleComparison) -> f64| comparisons.iter().map(bn).fold(1.0, f64::max);
+    eprintln!(
+        "worst branching number: partitions {}, color classes {}",
+        num(worst(|c| c.partition.1)),
+        num(worst(|c| c.color_class.1))
+    );
+    Ok(())
+}
+
+fn heuristic_gap(args: &[String], out: Output) -> Result<(), String> {
+    let mut args = args.to_vec();
+    let precision = take_precision(&mut args)?;
+    let mut config = take_measured_colors(&mut args)?;
+    config.max_degree = take_value(&mut args, "--max-degree", "a maximum degree")?;
+    let top: usize = take_value(&mut args, "--top", "a number of stars")?.unwrap_or(10);
+    let name: String = take_value(&mut args, "--selector", "a selector")?
+        .unwrap_or_else(|| SELECTORS[0].name().to_string());
+    let Some(selector) = SELECTORS.iter().find(|s| s.name() == name) else {
+        let names: Vec<&str> = SELECTORS.iter().map(|s| s.name()).collect();
+        return Err(format!(
+            "unknown selector: {name} (expected one of {})",
+            names.join(", ")
+        ));
+    };
+    reject_unknown_flags(&args)?;
+    let [degree, weights] = args.as_slice() else {
+        return Err(USAGE.to_string());
+    };
+    let degree = parse_degree(Some(degree))?;
+    let weights = parse_weights(Some(weights))?;
+    if degree < 2 {
+        return Err(format!("no stars of degree {degree}"));
+    }
+    config.validate_for_degree(degree)?;
+
+    let config = AnalysisConfig {
+        enumeration: config,
+        ..AnalysisConfig::default()
+    };
+    let report = gap_report(*selector, weights, degree, &config).map_err(|e| e.to_string())?;
+    let num = |x: f64| format_float(x, precision);
+    let quantiles: Vec<Vec<String>> = std::iter::once(vec!["quantile".into(), "gap".into()])
+        .chain([0.5, 0.9, 0.99, 1.0].map(|q| vec![q.to_string(), num(report.quantile(q))]))
+        .collect();
+    out.print_table(&quantiles, |_, _, _| "");
+    println!();
+    let largest = report.largest(top);
+    let header = [
+        "star",
+        "heuristic",
+        "heuristic_bn",
+        "optimum",
+        "optimum_bn",
+        "gap",
+    ];
+    let rows: Vec<Vec<String>> = std::iter::once(header.map(String::from).to_vec())
+        .chain(largest.iter().map(|case| {
+            vec![
+                star_label(&case.star),
+                fmt_partition(&case.heuristic.0),
+                num(case.heuristic.1),
+                fmt_partition(&case.optimum.0),
+                num(case.optimum.1),
+                num(case.gap()),
+            ]
+        }))
+        .collect();
+    // Stars on which the heuristic misses the optimum.
+    out.print_table(&rows, |r, c, _| {
+        if c == 5 && largest[r - 1].gap() > 0.0 {
+            YELLOW
+        } else {
+            ""
+        }
+    });
+    let (heuristic, optimum) = report.worst();
+    println!(
+        "heuristic-gap: selector={} degree={degree} stars={} optimal={} worst_heuristic={} worst_optimum={}",
+        report.selector,
+        report.cases.len(),
+        report.optimal(),
+        num(heuristic),
+        num(optimum)
+    );
+    Ok(())
+}
+
+fn stress(args: &[String], out: Output) -> Result<(), Failure> {
+    let mut args = args.to_vec();
+    let seeds: u64 = take_value(&mut args, "--seeds", "a number of seeds")?.unwrap_or(1000);
+    let first: u64 = take_value(&mut args, "--first-seed", "a seed")?.unwrap_or(0);
+    let max_vertices: usize =
+        take_value(&mut args, "--max-vertices", "a number of vertices")?.unwrap_or(8);
+    reject_unknown_flags(&args)?;
+    if !args.is_empty() || max_vertices == 0 {
+        return Err(USAGE.to_string().into());
+    }
+    if max_vertices > MAX_STRESS_VERTICES {
+        return Err(format!(
+            "--max-vertices must be at most {MAX_STRESS_VERTICES}, since exhaustive search takes exponential time, not {max_vertices}"
+        )
+        .into());
+    }
+    let seeds = first..first.saturating_add(seeds);
+    let failures = stress_test(seeds.clone(), max_vertices);
+    let colorable = |c: bool| if c { "colorable" } else { "not colorable" };
+    for f in failures.iter() {
+        println!(
+            "{}",
+            out.paint(
+                RED,
+                &format!(
+                    "seed {}: solver {}, preprocessed {}, exhaustive search {}, lists {:?}, neighbors {:?}",
+                    f.seed,
+                    colorable(f.solver.is_some()),
+                    colorable(f.preprocessed.is_some()),
+                    colorable(f.reference),
+                    f.graph.lists,
+                    f.graph.neighbors
+                )
+            )
+        );
+    }
+    let status = if failures.is_empty() { "ok" } else { "failed" };
+    let details = format!(
+        "seeds={}..{} max_vertices={max_vertices} failures={}",
+        seeds.start,
+        seeds.end,
+        failures.len()
+    );
+    println!("{}", out.status("stress", status, &details));
+    if !failures.is_empty() {
+        return Err(Failure {
+            code: EXIT_VIOLATED,
+            message: format!("{} seeds disagree", failures.len()),
+        });
+    }
+    Ok(())
+}
+
+fn audit_cases(args: &[String], out: Output) -> Result<(), Failure> {
+    let mut args = args.to_vec();
+    let config = take_colors(&mut args)?;
+    let guard = take_memory_guard(&mut args)?;
+    reject_unknown_flags(&args)?;
+    let [degree, path] = args.as_slice() else {
+        return Err(USAGE.to_string().into());
+    };
+    let degree = parse_degree(Some(degree))?;
+    if degree < 2 {
+        return Err(format!("no stars of degree {degree}").into());
+    }
+    let malformed = |message: String| Failure {
+        code: EXIT_MALFORMED,
+        message: format!("{path}: {message}"),
+    };
+    let text = std::fs::read_to_string(path).map_err(|e| malformed(e.to_string()))?;
+    let cases = parse_case_list(&text).map_err(malformed)?;
+
+    let audit = audit_coverage(&collect_stars(degree, &config, guard, out)?, &cases);
+    for line in audit.to_text().lines() {
+        if line.starts_with("UNMATCHED") || line.ends_with("UNCOVERED") {
+            println!("{}", out.paint(RED, line));
+        } else {
+            println!("{line}");
+        }
+    }
+    let uncovered = audit.uncovered().count();
+    let status = if audit.is_complete() {
+        "complete"
+    } else {
+        "incomplete"
+    };
+    let details = format!(
+        "degree={degree} stars={} uncovered={uncovered} unmatched={}",
+        audit.stars.len(),
+        audit.unmatched.len()
+    );
+    println!("{}", out.status("audit-cases", status, &details));
+    if !audit.is_complete() {
+        return Err(Failure {
+            code: EXIT_VIOLATED,
+            message: format!(
+                "{uncovered} uncovered stars, {} unmatched cases",
+                audit.unmatched.len()
+            ),
+        });
+    }
+    Ok(())
+}
+
+fn reduction_closure_command(args: &[String], out: Output) -> Result<(), Failure> {
+    let mut args = args.to_vec();
+    let mut config = take_colors(&mut args)?;
+    config.max_degree = take_value(&mut args, "--max-degree", "a maximum degree")?;
+    reject_unknown_flags(&args)?;
+    if !args.is_empty() {
+        return Err(USAGE.to_string().into());
+    }
+    let stars = read_stars_from_stdin()?;
+
+    let closure = reduction_closure(&stars);
+    let outside: HashSet<&Star> = closure.outside_universe(&config).into_iter().collect();
+    for star in closure.stars.iter() {
+        let id = canonical_id(star);
+        if outside.contains(star) {
+            println!("{}", out.paint(RED, &format!("outside {id}")));
+        } else {
+            println!("{id}");
+        }
+    }
+    let status = if outside.is_empty() {
+        "closed"
+    } else {
+        "escaped"
+    };
+    let details = format!(
+        "stars={} outside={} terminal_branches={}",
+        closure.stars.len(),
+        outside.len(),
+        closure.terminal_branches
+    );
+    println!("{}", out.status("reduction-closure", status, &details));
+    if !outside.is_empty() {
+        return Err(Failure {
+            code: EXIT_VIOLATED,
+            message: format!("{} stars outside the enumeration", outside.len()),
+        });
+    }
+    Ok(())
+}
+
+fn check_proof(args: &[String], out: Output) -> Result<(), Failure> {
+    let mut args = args.to_vec();
+    let allow_unproved = take_allow_experimental(&mut args);
+    let bound: f64 =
+        take_value(&mut args, "--bound", "a branching number")?.ok_or_else(|| USAGE.to_string())?;
+    let bound = check_branching_bound("check-proof", bound, out)?;
+    reject_unknown_flags(&args)?;
+    let [path] = args.as_slice() else {
+        return Err(USAGE.to_string().into());
+    };
+    let malformed = |message: String| {
+        println!("{}", out.status("check-proof", "malformed", ""));
+        Failure {
+            code: EXIT_MALFORMED,
+            message: format!("{path}: {message}"),
+        }
+    };
+    let text = std::fs::read_to_string(path).map_err(|e| malformed(e.to_string()))?;
+    let claimed = report_from_json_string(&text).map_err(malformed)?;
+    if claimed.degree < 2 {
+        return Err(malformed(format!("no stars of degree {}", claimed.degree)));
+    }
+
+    let reductions = claimed.reductions(allow_unproved).map_err(|e| {
+        println!("{}", out.status("check-proof", "violated", ""));
+        Failure {
+            code: EXIT_VIOLATED,
+            message: format!("{path}: {e} (use --allow-experimental to check the artifact anyway)"),
+        }
+    })?;
+    let reductions: Vec<&dyn Reduction> = reductions.iter().map(|r| r.as_ref()).collect();
+    let config = claimed.config(&reductions);
+    config.validate().map_err(malformed)?;
+    // The configuration carries the exclusions of the artifact, so the excluded stars are
+    // not analyzed again.
+    let actual = analysis_report(claimed.degree, claimed.weights, &config);
+    let diff = diff_reports(&claimed, &actual, 1e-9);
+    let worst = actual.worst_branching_number();
+    let above: Vec<&CaseReport> = actual
+        .cases
+        .iter()
+        .filter(|c| c.branching_number > bound)
+        .collect();
+    // Cases that the diff does not flag but whose partitions, outcomes, or drops differ.
+    let mut mismatches: Vec<String> = report_diff_to_text(&diff, None)
+        .lines()
+        .map(String::from)
+        .collect();
+    let flagged: HashSet<&Star> = diff
+        .branching_numbers
+        .iter()
+        .map(|(s, _, _)| s)
+        .chain(diff.reductions.iter().map(|(s, _, _)| s))
+        .collect();
+    let actual_cases: HashMap<&Star, &CaseReport> =
+        actual.cases.iter().map(|c| (&c.star, c)).collect();
+    for case in claimed.cases.iter() {
+        let differs = actual_cases.get(&case.star).is_some_and(|&a| a != case);
+        if differs && !flagged.contains(&case.star) {
+            mismatches.push(format!("case {}", star_label(&case.star)));
+        }
+    }
+    for line in mismatches.iter() {
+        println!("{}", out.paint(RED, &format!("mismatch: {line}")));
+    }
+    for case in above.iter() {
+        println!(
+            "{}",
+            out.paint(
+                RED,
+                &format!(
+                    "above bound: {} {}",
+                    star_label(&case.star),
+                    format_float(case.branching_number, None)
+                )
+            )
+        );
+    }
+    let status = if mismatches.is_empty() && above.is_empty() {
+        "verified"
+    } else {
+        "violated"
+    };
+    let details = format!(
+        "degree={} cases={} worst={} bound={} mismatches={} above_bound={}",
+        actual.degree,
+        actual.cases.len(),
+        format_float(worst, None),
+        format_float(bound, None),
+        mismatches.len(),
+        above.len()
+    );
+    println!("{}", out.status("check-proof", status, &details));
+    if status == "violated" {
+        return Err(Failure {
+            code: EXIT_VIOLATED,
+            message: format!("{path}: the artifact does not prove the bound {bound}"),
+        });
+    }
+    Ok(())
+}
+
+/// Writes `contents` to a temporary file next to `path` and renames it to `path`, so that
+/// `path` holds either the old or the new contents, never a part.
+fn write_atomically(path: &str, contents: &str) -> Result<(), String> {
+    let temporary = format!("{path}.tmp");
+    std::fs::write(&temporary, contents).map_err(|e| format!("{temporary}: {e}"))?;
+    std::fs::rename(&temporary, path).map_err(|e| format!("{path}: {e}"))
+}
+
+fn analyze_all(args: &[String], out: Output) -> Result<(), Failure> {
+    let mut args = args.to_vec();
+    let precision = take_precision(&mut args)?;
+    let mut config = take_measured_colors(&mut args)?;
+    let max_degree: usize = take_value(&mut args, "--max-degree", "a maximum degree")?.unwrap_or(5);
+    let rounds: usize = take_value(&mut args, "--rounds", "a number of rounds")?.unwrap_or(20);
+    let report_dir: Option<String> = take_value(&mut args, "--report", "a directory")?;
+    let max_blocks = take_max_blocks(&mut args)?;
+    take_halfedge_range(&mut args, &mut config)?;
+    let selected = take_reductions(&mut args, MaxDegree(max_degree))?;
+    let reductions: Vec<&dyn Reduction> = selected.iter().map(|r| r.as_ref()).collect();
+    let guard = take_memory_guard(&mut args)?;
+    let progress_interval: Option<u64> =
+        take_value(&mut args, "--progress-interval", "a number of milliseconds")?;
+    let best_weights: Option<String> = take_value(&mut args, "--best-weights", "a file")?;
+    let seed: u64 = take_value(&mut args, "--seed", "a seed")?.unwrap_or(0);
+    let restarts: usize = take_value(&mut args, "--restarts", "a number of restarts")?.unwrap_or(1);
+    let optimize = take_flag(&mut args, "--optimize");
+    reject_unknown_flags(&args)?;
+    let weights = match args.as_slice() {
+        [] => NodeFeatures::from_array([1.0; 9]),
+        [weights] => parse_weights(Some(weights))?,
+        _ => return Err(USAGE.to_string().into()),
+    };
+    if max_degree < 3 {
+        return Err(format!(
+            "no stars of degree at least 3 in graphs of maximum degree {max_degree}"
+        )
+        .into());
+    }
+    if restarts == 0 {
+        return Err("--restarts must be at least 1".to_string().into());
+    }
+    config.max_degree = Some(max_degree);
+    // The stars of the largest degree have the most halfedges.
+    config.validate_for_degree(max_degree)?;
+    let config = AnalysisConfig {
+        enumeration: config,
+        max_blocks,
+        reductions: &reductions,
+        ..AnalysisConfig::default()
+    };
+    let degrees = 3..=max_degree;
+    // The reports of all degrees, and the failures of the checks before them, are kept until
+    // the end.
+    let estimate = degrees.clone().try_fold(0u128, |total, d| {
+        let report = estimate_report_memory(d, &config)?;
+        let failures = estimate_failures_memory(d, &config)?;
+        total
+            .checked_add(report)
+            .and_then(|t| t.checked_add(failures))
+            .ok_or(CountError::Overflow)
+    });
+    check_memory(
+        guard,
+        estimate,
+        &format!("the analyses of the stars of degrees 3 to {max_degree}"),
+        "analyze fewer stars with --colors, a smaller --max-degree or --max-blocks",
+        out,
+    )?;
+    let num = |x: f64| format_float(x, precision);
+    let stage = |i: usize, name: &str, detail: &str| eprintln!("[{i}/6] {name}: {detail}");
+    let violated = |message: String| {
+        println!(
+            "{}",
+            out.status(
+                "analyze-all",
+                "violated",
+                &format!("max_degree={max_degree}")
+            )
+        );
+        Failure {
+            code: EXIT_VIOLATED,
+            message,
+        }
+    };
+
+    let counts: Vec<usize> = degrees
+        .clone()
+        .map(|d| fold_stars_with_config(d, &config.enumeration, 0, |n, _| n + 1))
+        .collect();
+    let stars: usize = counts.iter().sum();
+    stage(
+        1,
+        "enumerate",
+        &format!(
+            "{stars} stars ({})",
+            degrees
+                .clone()
+                .zip(counts.iter())
+                .map(|(d, n)| format!("degree {d}: {n}"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    );
+
+    let violations: Vec<_> = degrees
+        .clone()
+        .flat_map(|d| verify_all_invariants_with_config(d, &config))
+        .collect();
+    for v in violations.iter() {
+        println!("{}", out.paint(RED, &v.to_string()));
+    }
+    stage(
+        2,
+        "reduce",
+        &format!("{} invariant violations", violations.len()),
+    );
+    if !violations.is_empty() {
+        return Err(violated(format!(
+            "{} invariant violations",
+            violations.len()
+        )));
+    }
+
+    // The worst star of every degree under the starting weights.
+    let mut initial = Vec::new();
+    if optimize {
+        for d in degrees.clone() {
+            let worst = most_violated_star(weights, 1.0, d, &config)
+                .map_err(|e| format!("invalid weights: {e}"))?;
+            initial.extend(worst.map(|(star, _)| StarConstraints::new(&star, &config)));
+        }
+        stage(
+            3,
+            "constraints",
+            &format!("{} stars precomputed", initial.len()),
+        );
+    } else {
+        stage(3, "constraints", "skipped");
+    }
+
+    let (weights, bound, binding, features) = if optimize {
+        let interval = std::time::Duration::from_millis(progress_interval.unwrap_or(0));
+        let mut saved: Option<f64> = None;
+        let mut save_error = None;
+        let mut rng = SplitMix64(seed);
+        // The converged restart of the smallest bound, or the last one if none converged.
+        let mut best: Option<Optimization> = None;
+        for restart in 1..=restarts {
+            // The first restart starts from the given weights, the others from random ones.
+            let start = if restart == 1 {
+                Weights(weights)
+            } else {
+                random_start(&mut rng)
+            };
+            let label = if restarts > 1 {
+                format!("restart {restart}/{restarts}: ")
+            } else {
+                String::new()
+            };
+            let mut printed: Option<std::time::Duration> = None;
+            let optimization = optimize_weights_with_oracle(
+                degrees.clone(),
+                &config,
+                start,
+                initial.clone(),
+                rounds,
+                |p| {
+                    if printed.is_none_or(|t| p.elapsed >= t + interval) {
+                        printed = Some(p.elapsed);
+                        let above = p
+                            .worst_star
+                            .as_ref()
+                            .map(|(star, bn)| format!(", {} at {}", star_label(star), num(*bn)))
+                            .unwrap_or_default();
+                        stage(
+                            4,
+                            "optimize",
+                            &format!(
+                                "{label}round {} after {:.1}s: worst {} over {} stars{above}; best bound {}",
+                                p.round,
+                                p.elapsed.as_secs_f64(),
+                                num(p.worst),
+                                p.stars,
+                                num(p.best_bound)
+                            ),
+                        );
+                    }
+                    if let Some(path) = best_weights
+                        .as_ref()
+                        .filter(|_| saved.is_none_or(|b| p.best_bound < b))
+                    {
+                        saved = Some(p.best_bound);
+                        let line = p.best_weights.to_array().map(|w| format_float(w, None));
+                        if let Err(e) = write_atomically(path, &format!("{}\n", line.join(","))) {
+                            save_error.get_or_insert(e);
+                        }
+                    }
+                },
+            )
+            .map_err(|e| format!("invalid weights: {e}"))?;
+            if let Some(e) = save_error {
+                return Err(e.into());
+            }
+            if !optimization.converged {
+                stage(
+                    4,
+                    "optimize",
+                    &format!(
+                        "{label}stopped after {rounds} rounds; the weights may not be optimal"
+                    ),
+                );
+            } else if restarts > 1 {
+                stage(
+                    4,
+                    "optimize",
+                    &format!("{label}bound {}", num(optimization.worst)),
+                );
+            }
+            let better =
+                best.as_ref()
+                    .is_none_or(|b| match (b.converged, optimization.converged) {
+                        (true, true) => optimization.worst < b.worst,
+                        (converged, _) => !converged,
+                    });
+            if better {
+                best = Some(optimization);
+            }
+        }
+        let optimization = best.expect("there is at least one restart");
+        if restarts > 1 {
+            let bound = if optimization.converged {
+                num(optimization.worst)
+            } else {
+                "none (no restart converged)".to_string()
+            };
+            stage(
+                4,
+                "optimize",
+                &format!("best bound over {restarts} restarts: {bound}"),
+            );
+        }
+        // The stars that bind at the optimum, in place of the dual values of an LP.
+        stage(
+            4,
+            "optimize",
+            &format!(
+                "{} stars bind at the final weights (within {BINDING_TOLERANCE:e})",
+                optimization.binding.len()
+            ),
+        );
+        let binding: Vec<String> = optimization
+            .binding
+            .iter()
+            .map(|b| {
+                format!(
+                    "{} on {} at {}",
+                    star_label(&b.star),
+                    fmt_partition(&b.partition.unpack()),
+                    num(b.branching_number)
+                )
+            })
+            .collect();
+        for line in binding.iter() {
+            eprintln!("  {line}");
+        }
+        // Which features the bound needs most, to guide changes to the measure.
+        let ranking = rank_features(&optimization.constraints, &optimization.weights, 200);
+        stage(
+            4,
+            "optimize",
+            &format!(
+                "features by the bound over the {} stars without them",
+                optimization.constraints.len()
+            ),
+        );
+        let features: Vec<String> = ranking
+            .iter()
+            .map(|r| {
+                format!(
+                    "{} {} (+{})",
+                    NodeFeatures::NAMES[r.feature],
+                    num(r.bound),
+                    num(r.worsening)
+                )
+            })
+            .collect();
+        for line in features.iter() {
+            eprintln!("  {line}");
+        }
+        let bound = optimization.converged.then_some(optimization.worst);
+        (optimization.weights.0, bound, binding, features)
+    } else {
+        stage(4, "optimize", "skipped");
+        (weights, None, Vec::new(), Vec::new())
+    };
+
+    let increases: Vec<_> = degrees
+        .clone()
+        .flat_map(|d| measure_increases_with_config(d, &config, weights))
+        .collect();
+    for increase in increases.iter() {
+        println!(
+            "{}",
+            out.paint(RED, &format!("measure increase: {increase}"))
+        );
+    }
+    if !increases.is_empty() {
+        return Err(violated(format!(
+            "{} reduction steps increase the measure",
+            increases.len()
+        )));
+    }
+
+    let mut reports = Vec::new();
+    let mut worst = 1.0f64;
+    for d in degrees.clone() {
+        let report = analysis_report(d, weights, &config);
+        let json = report.to_json_string();
+        let read_back = report_from_json_string(&json).map_err(|e| format!("degree {d}: {e}"))?;
+        if !diff_reports(&read_back, &report, 0.0).is_empty() {
+            return Err(violated(format!(
+                "degree {d}: the JSON artifact does not read back to the analysis"
+            )));
+        }
+        worst = worst.max(report.worst_branching_number());
+        reports.push((d, report, json));
+    }
+    // The oracle searched all stars, so only rounding separates the bounds.
+    if let Some(bound) = bound.filter(|b| worst > b + 1e-9) {
+        return Err(violated(format!(
+            "the analysis reaches {}, above the optimized bound {}",
+            num(worst),
+            num(bound)
+        )));
+    }
+    // The comparisons above are in floating point; check the claimed bound with the drops
+    // of every case, rounding against it, up to the same tolerance.
+    let certified = bound.unwrap_or(worst) + 1e-9;
+    let uncertified: usize = reports
+        .iter()
+        .map(|(_, report, _)| report.uncertified_cases(certified).len())
+        .sum();
+    if uncertified > 0 {
+        return Err(violated(format!(
+            "{uncertified} cases are not certified to have branching number at most {}",
+            num(certified)
+        )));
+    }
+    // The cost of the limit: how much better the same weights do with all partitions.
+    let unlimited = reports
+        .iter()
+        .filter_map(|(_, report, _)| report.unlimited_worst_branching_number())
+        .reduce(f64::max);
+    let cost = unlimited
+        .map(|u| format!(" ({} without the limit on the blocks)", num(u)))
+        .unwrap_or_default();
+    stage(
+        5,
+        "verify",
+        &format!(
+            "worst branching number {}{cost}, certified at most {}",
+            num(worst),
+            num(certified)
+        ),
+    );
+
+    let weights_line = weights.to_array().map(|w| format_float(w, None)).join(",");
+    match report_dir.as_ref() {
+        Some(dir) => {
+            let write = |name: String, contents: &str| {
+                let path = std::path::Path::new(dir).join(name);
+                std::fs::write(&path, contents).map_err(|e| format!("{}: {e}", path.display()))
+            };
+            std::fs::create_dir_all(dir).map_err(|e| format!("{dir}: {e}"))?;
+            for (d, report, json) in reports.iter() {
+                write(format!("degree-{d}.json"), json)?;
+                write(
+                    format!("degree-{d}.md"),
+                    &report_to_markdown(report, precision),
+                )?;
+                write(
+                    format!("degree-{d}.tex"),
+                    &report_to_latex(report, precision),
+                )?;
+            }
+            write("weights.txt".to_string(), &format!("{weights_line}\n"))?;
+            let mut files = 3 * reports.len() + 1;
+            if optimize {
+                let lines: String = binding.iter().map(|line| format!("{line}\n")).collect();
+                write("binding.txt".to_string(), &lines)?;
+                let lines: String = features.iter().map(|line| format!("{line}\n")).collect();
+                write("features.txt".to_string(), &lines)?;
+                files += 2;
+            }
+            stage(6, "report", &format!("{files} files written to {dir}"));
+        }
+        None => stage(6, "report", "skipped"),
+    }
+
+    let details = format!(
+        "max_degree={max_degree} stars={stars} worst={} certified={} weights={weights_line}",
+        num(worst),
+        num(certified)
+    );
+    println!("{}", out.status("analyze-all", "verified", &details));
+    Ok(())
+}
+
+fn case_set_fingerprint(args: &[String]) -> Result<(), String> {
+    let mut args = args.to_vec();
+    let mut config = take_colors(&mut args)?;
+    config.max_degree = take_value(&mut args, "--max-degree", "a maximum degree")?;
+    config.halfedge_bounds = take_halfedge_bounds(&mut args)?;
+    let manifest = take_flag(&mut args, "--manifest");
+    reject_unknown_flags(&args)?;
+    let [degree] = args.as_slice() else {
+        return Err(USAGE.to_string());
+    };
+    let degree = parse_degree(Some(degree))?;
+    if degree < 2 {
+        return Err(format!("no stars of degree {degree}"));
+    }
+    config.validate_for_degree(degree)?;
+    let set = CaseSet::new(degree, &config);
+    if manifest {
+        print!("{}", set.to_text());
+    } else {
+        println!(
+            "case-set-fingerprint: fingerprint={:016x} degree={degree} cases={} version={}",
+            set.fingerprint(),
+            set.cases.len(),
+            set.version
+        );
+    }
+    Ok(())
+}
+
+fn bench_features(args: &[String], out: Output) -> Result<(), String> {
+    let mut args = args.to_vec();
+    let rounds: usize = take_value(&mut args, "--rounds", "a number of rounds")?.unwrap_or(100);
+    let guard = take_memory_guard(&mut args)?;
+    reject_unknown_flags(&args)?;
+    let [degree] = args.as_slice() else {
+        return Err(USAGE.to_string());
+    };
+    let degree = parse_degree(Some(degree))?;
+    if degree < 2 {
+        return Err(format!("no stars of degree {degree}"));
+    }
+    let stars = collect_stars(degree, &EnumerationConfig::default(), guard, out)?;
+    let start = std::time::Instant::now();
+    let mut total = 0.0;
+    for _ in 0..rounds {
+        for star in &stars {
+            total += std::hint::black_box(star_list_degree_counts(std::hint::black_box(star))).n2_3;
+        }
+    }
+    let elapsed = start.elapsed();
+    let ns_per_star = elapsed.as_nanos() as f64 / (rounds * stars.len()).max(1) as f64;
+    println!(
+        "bench-features: degree={degree} stars={} rounds={rounds} ns_per_star={ns_per_star:.1} checksum={total}",
+        stars.len()
+    );
+    Ok(())
+}
+
+fn case_set_diff(args: &[String], out: Output) -> Result<(), Failure> {
+    let [before, after] = args else {
+        return Err(USAGE.to_string().into());
+    };
+    let read = |path: &String| {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+        case_set_from_text(&text).map_err(|e| Failure {
+            code: EXIT_MALFORMED,
+            message: format!("{path}: {e}"),
+        })
+    };
+    let (before, after) = (read(before)?, read(after)?);
+    if (before.degree, &before.config) != (after.degree, &after.config) {
+        eprintln!(
+            "{}",
+            out.warning("the case sets were enumerated with different parameters")
+        );
+    }
+    let diff = diff_case_sets(&before, &after);
+    for star in diff.removed.iter() {
+        println!("{}", out.paint(RED, &format!("removed {star}")));
+    }
+    for star in diff.added.iter() {
+        println!("{}", out.paint(GREEN, &format!("added {star}")));
+    }
+    let status = if diff.is_empty() { "same" } else { "changed" };
+    let details = format!(
+        "before={} ({:016x}) after={} ({:016x}) added={} removed={}",
+        before.version,
+        before.fingerprint(),
+        after.version,
+        after.fingerprint(),
+        diff.added.len(),
+        diff.removed.len()
+    );
+    println!("{}", out.status("case-set-diff", status, &details));
+    Ok(())
+}
+
+fn replay(args: &[String], out: Output) -> Result<(), Failure> {
+    let mut args = args.to_vec();
+    let allow_unproved = take_allow_experimental(&mut args);
+    reject_unknown_flags(&args)?;
+    let [path] = args.as_slice() else {
+        return Err(USAGE.to_string().into());
+    };
+    let bytes = std::fs::read(path).map_err(|e| format!("{path}: {e}"))?;
+    let trace = decode_trace(&bytes).ok_or_else(|| Failure {
+        code: EXIT_MALFORMED,
+        message: format!("{path}: not a trace"),
+    })?;
+    let reductions = trace.reductions(allow_unproved).map_err(|e| Failure {
+        code: EXIT_VIOLATED,
+        message: format!("{path}: {e} (use --allow-experimental to replay the trace anyway)"),
+    })?;
+    let reductions: Vec<&dyn Reduction> = reductions.iter().map(|r| r.as_ref()).collect();
+    match replay_trace(&trace, &reductions) {
+        Ok(events) => {
+            println!(
+                "{}",
+                out.status("replay", "identical", &format!("events={events}"))
+            );
+            Ok(())
+        }
+        Err(divergence) => {
+            println!("{}", out.paint(RED, &divergence.to_string()));
+            let details = format!("event={}", divergence.index);
+            println!("{}", out.status("replay", "diverged", &details));
+            Err(Failure {
+                code: EXIT_VIOLATED,
+                message: format!("{path}: the replay diverges from the trace"),
+            })
+        }
+    }
+}
+
+fn solve(args: &[String]) -> Result<(), String> {
+    let mut args = args.to_vec();
+    let precision = take_precision(&mut args)?;
+    let poly = take_flag(&mut args, "--poly");
+    let vector: Option<String> = take_value(&mut args, "--vector", "comma-separated drops")?;
+    reject_unknown_flags(&args)?;
+    let Some(vector) = vector.filter(|_| args.is_empty()) else {
+        return Err(USAGE.to_string());
+    };
+    let drops: Vec<f64> = vector
+        .split(',')
+        .map(|x| x.trim().parse::<f64>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("invalid branching vector: {vector}"))?;
+
+    println!("{}", format_float(branching_number(&drops), precision));
+    if poly {
+        let Some(coeffs) = characteristic_polynomial(&drops) else {
+            return Err("the characteristic polynomial needs positive integer drops".to_string());
+        };
+        println!("{}", polynomial_to_string(&coeffs));
+    }
+    Ok(())
+}
+
+fn compare(args: &[String], out: Output) -> Result<(), String> {
+    let mut args = args.to_vec();
+    let precision = take_precision(&mut args)?;
+    let path = match args.as_slice() {
+        [flag, path] if flag == "--file" => path,
+        _ => return Err(USAGE.to_string()),
+    };
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+    let JsonValue::Object(entries) = parse_json(&text).map_err(|e| format!("{path}: {e}"))? else {
+        return Err(format!(
+            "{path}: expected an object mapping names to vectors"
+        ));
+    };
+
+    let mut rows: Vec<(String, f64)> = Vec::new();
+    for (name, vector) in entries.iter() {
+        let drops: Option<Vec<f64>> = vector
+            .as_array()
+            .map(|xs| xs.iter().map(|x| x.as_f64()).collect())
+            .unwrap_or(None);
+        let Some(drops) = drops else {
+            return Err(format!("{path}: {name} is not an array of numbers"));
+        };
+        rows.push((name.clone(), branching_number(&drops)));
+    }
+    let Some(&(_, baseline)) = rows.first() else {
+        return Err(format!("{path}: no recurrences"));
+    };
+
+    let cells: Vec<[String; 3]> = rows
+        .iter()
+        .map(|(name, bn)| {
+            let diff = 100.0 * (bn - baseline) / baseline;
+            [
+                name.clone(),
+                format_float(*bn, precision),
+                format!("{:+.2}%", diff),
+            ]
+        })
+        .collect();
+    let header = [
+        "name".to_string(),
+        "branching_number".to_string(),
+        "diff".to_string(),
+    ];
+    if !out.human {
+        for row in std::iter::once(&header).chain(cells.iter()) {
+            println!("{}", row.join("\t"));
+        }
+        return Ok(());
+    }
+    let widths: Vec<usize> = (0..3)
+        .map(|i| {
+            std::iter::once(&header)
+                .chain(cells.iter())
+                .map(|row| row[i].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    for (i, row) in std::iter::once(&header).chain(cells.iter()).enumerate() {
+        let line = format!(
+            "{:<w0$}  {:>w1$}  ",
+            row[0],
+            row[1],
+            w0 = widths[0],
+            w1 = widths[1],
+        );
+        let diff = format!("{:>w2$}", row[2], w2 = widths[2]);
+        if i == 0 {
+            println!("{}", out.paint(BOLD, &format!("{line}{diff}")));
+        } else {
+            // Smaller branching numbers are better.
+            let code = match row[2].chars().next() {
+                Some('-') => GREEN,
+                Some('+') if row[2] != "+0.00%" => RED,
+                _ => "",
+            };
+            println!("{line}{}", out.paint(code, &diff));
+        }
+    }
+    Ok(())
+}
+
+/// Parses a star given as a v1/v2 string or an NDJSON object.
+fn parse_star_line(line: &str) -> Result<Star, StarParseError> {
+    if line.starts_with('{') {
+        star_from_json_string(line)
+    } else {
+        star_from_string(line)
+    }
+}
+
+/// Reads stars from stdin, one per line as accepted by [`parse_star_line`].
+fn read_stars_from_stdin() -> Result<Vec<Star>, String> {
+    let mut stars = Vec::new();
+    for (i, line) in std::io::stdin().lock().lines().enumerate() {
+        let line = line.map_err(|e| e.to_string())?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        stars.push(parse_star_line(line.trim()).map_err(|e| format!("line {}: {e}", i + 1))?);
+    }
+    Ok(stars)
+}
+
+fn convert(args: &[String]) -> Result<(), String> {
+    let mut args = args.to_vec();
+    let from = take_value::<String>(&mut args, "--from", "an input format")?;
+    let to = match args.as_slice() {
+        [flag, to] if flag == "--to" => to.as_str(),
+        _ => return Err(USAGE.to_string()),
+    };
+    let encode: fn(&Star, &mut Vec<u8>) -> Result<(), StarError> = match to {
+        "v1" => |star, out| write_line(out, star_to_string(star)),
+        "v2" => |star, out| write_line(out, star_to_string_v2(star)),
+        "ndjson" => |star, out| write_line(out, Ok(star_to_json_string(star))),
+        "binary" => encode_star,
+        _ => return Err(format!("unknown format: {to}")),
+    };
+    // Every star with where it came from, for the errors. The binary format is one star file,
+    // so its stars are only known once it is read.
+    type Labeled = Result<(String, Star), String>;
+    let stars: Box<dyn Iterator<Item = Labeled>> = match from.as_deref() {
+        None | Some("text") => Box::new(std::io::stdin().lock().lines().enumerate().filter_map(
+            |(i, line)| {
+                match line {
+                    Err(e) => Some(Err(e.to_string())),
+                    Ok(line) if line.trim().is_empty() => None,
+                    Ok(line) => Some(
+                        parse_star_line(line.trim())
+                            .map(|star| (format!("line {}", i + 1), star))
+                            .map_err(|e| format!("line {}: {e}", i + 1)),
+                    ),
+                }
+            },
+        )),
+        Some("binary") => {
+            let mut bytes = Vec::new();
+            std::io::stdin()
+                .lock()
+                .read_to_end(&mut bytes)
+                .map_err(|e| e.to_string())?;
+            let stars = decode_stars(&bytes).ok_or("stdin is not a binary star file")?;
+            Box::new(
+                stars
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, star)| Ok((format!("star {}", i + 1), star))),
+            )
+        }
+        Some(from) => return Err(format!("unknown input format: {from}")),
+    };
+
+    let stdout = std::io::stdout();
+    let mut out = std::io::BufWriter::new(stdout.lock());
+    if to == "binary" {
+        out.write_all(STARS_MAGIC).map_err(|e| e.to_string())?;
+        out.write_all(&[STARS_VERSION]).map_err(|e| e.to_string())?;
+    }
+    let mut buffer = Vec::new();
+    for star in stars {
+        let (label, star) = star?;
+        buffer.clear();
+        encode(&star, &mut buffer).map_err(|e| format!("{label}: cannot encode as {to}: {e}"))?;
+        out.write_all(&buffer).map_err(|e| e.to_string())?;
+    }
+    out.flush().map_err(|e| e.to_string())
+}
+
+/// Appends `line` and a newline to `out`, if the star could be encoded as a line.
+fn write_line(out: &mut Vec<u8>, line: Result<String, StarError>) -> Result<(), StarError> {
+    out.extend_from_slice(line?.as_bytes());
+    out.push(b'\n');
+    Ok(())
+}
+
+fn join_features_command(args: &[String]) -> Result<(), String> {
+    let path = match args {
+        [flag, path] if flag == "--table" => path,
+        _ => return Err(USAGE.to_string()),
+    };
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{path}: {e}"))?;
+    let table = parse_feature_table(&text).map_err(|e| format!("{path}: {e}"))?;
+
+    let stdout = std::io::stdout();
+    let mut out = std::io::BufWriter::new(stdout.lock());
+    let (mut stars, mut matched) = (0, 0);
+    for (i, line) in std::io::stdin().lock().lines().enumerate() {
+        let line = line.map_err(|e| e.to_string())?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let star = parse_star_line(line).map_err(|e| format!("line {}: {e}", i + 1))?;
+        stars += 1;
+        matched += usize::from(table.get(&star).is_some());
+        writeln!(out, "{}", join_features(&star, &table)).map_err(|e| e.to_string())?;
+    }
+    out.flush().map_err(|e| e.to_string())?;
+    eprintln!(
+        "join-features: stars={stars} matched={matched} rows={}",
+        table.rows.len()
+    );
+    Ok(())
+}
+
+fn run(args: &[String], out: Output) -> Result<(), Failure> {
+    let result = match args.first().map(|s| s.as_str()) {
+        Some("verify") => return verify(&args[1..], out),
+        Some("check-proof") => return check_proof(&args[1..], out),
+        Some("audit-cases") => return audit_cases(&args[1..], out),
+        Some("stress") => return stress(&args[1..], out),
+        Some("analyze-all") => return analyze_all(&args[1..], out),
+        Some("case-set-diff") => return case_set_diff(&args[1..], out),
+        Some("replay") => return replay(&args[1..], out),
+        Some("check-bound") => return check_bound(&args[1..], out),
+        Some("reduction-closure") => return reduction_closure_command(&args[1..], out),
+        Some("case-set-fingerprint") => case_set_fingerprint(&args[1..]),
+        Some("bench-features") => bench_features(&args[1..], out),
+        Some("explain") => explain(&args[1..]),
+        Some("proof") => proof(&args[1..], out),
+        Some("proof-diff") => proof_diff(&args[1..], out),
+        Some("repl") => repl(&args[1..], out),
+        Some("compare-rules") => compare_rules(&args[1..], out),
+        Some("heuristic-gap") => heuristic_gap(&args[1..], out),
+        Some("reduction-order") => reduction_order_dot(&args[1..], out),
+        Some("microstructure") => microstructure(&args[1..]),
+        Some("worker") => match &args[1..] {
+            [] => serve(
+                std::io::stdin().lock(),
+                std::io::stdout().lock(),
+                &AnalysisConfig::default(),
+            )
+            .map_err(|e| e.to_string()),
+            [flag, d] if flag == "--max-degree" => {
+                let d = parse_degree(Some(d))?;
+                if d < 3 {
+                    return Err(format!("invalid maximum degree: {d}").into());
+                }
+                serve(
+                    std::io::stdin().lock(),
+                    std::io::stdout().lock(),
+                    &AnalysisConfig::for_max_degree(d),
+                )
+                .map_err(|e| e.to_string())
+            }
+            _ => Err(USAGE.to_string()),
+        },
+        Some("solve") => solve(&args[1..]),
+        Some("compare") => compare(&args[1..], out),
+        Some("convert") => convert(&args[1..]),
+        Some("join-features") => join_features_command(&args[1..]),
+        Some(cmd) => Err(format!("unknown command: {cmd}\n\n{USAGE}")),
+        None => Err(USAGE.to_string()),
+    };
+    Ok(result?)
+}
+
+fn main() -> std::process::ExitCode {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let out = Output::from_args(&mut args);
+    // A panic is a bug, not a verification result; the default hook has printed it already.
+    let result = std::panic::catch_unwind(|| run(&args, out)).unwrap_or_else(|_| {
+        Err(Failure {
+            code: EXIT_INTERNAL,
+            message: "internal error".to_string(),
+        })
+    });
+    match result {
+        Ok(()) => std::process::ExitCode::SUCCESS,
+        Err(failure) => {
+            eprintln!("{}", failure.message);
+            std::process::ExitCode::from(failure.code)
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::analysis::AnalysisConfig;
+use crate::list_coloring_utils::NodeFeatures;
+use crate::report::analysis_report;
+use crate::star_utils::EnumerationConfig;
+
+/// Hashes the parts of a configuration into a cache key.
+///
+/// The crate version is hashed in as well, so artifacts of other releases are not reused.
+pub fn cache_key(parts: &[&str]) -> u64 {
+    const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let mut hash = FNV_OFFSET;
+    for part in [env!("CARGO_PKG_VERSION")]
+        .into_iter()
+        .chain(parts.iter().copied())
+    {
+        // The terminating 0 keeps ("ab", "c") and ("a", "bc") apart.
+        for &byte in part.as_bytes().iter().chain(&[0]) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+    hash
+}
+
+/// The cache key of the report of the stars of `degree` under `weights` and `config`.
+///
+/// Hashes all of `config`, so options added to it later are part of the key, and the JSON
+/// report of the stars of degree 2 under its reductions and degree buckets as a fingerprint of
+/// the code: changing a reduction, the measure or the report format changes the key, so
+/// reports computed by other code are not reused.
+pub fn report_cache_key(degree: usize, weights: NodeFeatures, config: &AnalysisConfig) -> u64 {
+    let probe = AnalysisConfig {
+        enumeration: EnumerationConfig {
+            max_degree: config.enumeration.max_degree,
+            ..EnumerationConfig::default()
+        },
+        max_blocks: None,
+        reductions: config.reductions,
+        pareto: config.pareto,
+        exclusions: config.exclusions,
+    };
+    let fingerprint = analysis_report(2, weights, &probe).to_json_string();
+    cache_key(&[
+        "report",
+        &degree.to_string(),
+        &weights.to_json_string(),
+        &format!("{config:?}"),
+        &fingerprint,
+    ])
+}
+
+/// A directory of expensive artifacts (analyses, reports, ...), one file per artifact, named
+/// by its kind and the [`cache_key`] of the configuration it was computed from.
+#[derive(Clone, Debug)]
+pub struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Cache { dir: dir.into() }
+    }
+
+    /// The file holding the artifact of the given `kind` and `key`.
+    pub fn path(&self, kind: &str, key: u64) -> PathBuf {
+        self.dir.join(format!("{kind}-{key:016x}"))
+    }
+
+    /// Returns the cached artifact, or `None` if there is none (or it cannot be read).
+    pub fn get(&self, kind: &str, key: u64) -> Option<String> {
+        fs::read_to_string(self.path(kind, key)).ok()
+    }
+
+    /// Stores an artifact, creating the directory if needed.
+    ///
+    /// The contents are written to a temporary file first and then renamed, so concurrent
+    /// readers never see a partial artifact.
+    pub fn put(&self, kind: &str, key: u64, contents: &str) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.path(kind, key);
+        let tmp = path.with_extension(format!("tmp{}", std::process::id()));
+        fs::write(&tmp, contents)?;
+        fs::rename(&tmp, &path)
+    }
+
+    /// Returns the cached artifact, computing and storing it with `compute` on a miss.
+    pub fn get_or_insert_with(
+        &self,
+        kind: &str,
+        key: u64,
+        compute: impl FnOnce() -> String,
+    ) -> io::Result<String> {
+        if let Some(contents) = self.get(kind, key) {
+            return Ok(contents);
+        }
+        let contents = compute();
+        self.put(kind, key, &contents)?;
+        Ok(contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::list_coloring_utils::MaxDegree;
+    use crate::reductions::{CappedDuplicateTwoLists, DominatedRootColors, Reduction, SafetyLevel};
+    use crate::star_utils::Star;
+
+    #[test]
+    fn cache_round_trip() {
+        let dir = std::env::temp_dir().join(format!("recurrences-cache-{}", std::process::id()));
+        let cache = Cache::new(&dir);
+        let key = cache_key(&["report", "2"]);
+        assert_ne!(key, cache_key(&["report2", ""]));
+        assert_eq!(cache.get("report", key), None);
+
+        let mut calls = 0;
+        for _ in 0..2 {
+            let contents = cache
+                .get_or_insert_with("report", key, || {
+                    calls += 1;
+                    "{}".to_string()
+                })
+                .unwrap();
+            assert_eq!(contents, "{}");
+        }
+        assert_eq!(calls, 1);
+        assert_eq!(cache.get("report", key).as_deref(), Some("{}"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn report_keys_cover_the_whole_configuration() {
+        let weights = NodeFeatures::from_array([1.0, 1.0, 1.0, 0.7, 0.7, 0.7, 0.4, 0.4, 0.4]);
+        let config = AnalysisConfig::default();
+        let key = report_cache_key(3, weights, &config);
+        assert_eq!(
+            key,
+            report_cache_key(3, weights, &AnalysisConfig::default())
+        );
+        assert_ne!(key, report_cache_key(4, weights, &config));
+
+        let pendant = AnalysisConfig {
+            enumeration: EnumerationConfig {
+                min_halfedges: 0,
+                ..EnumerationConfig::default()
+            },
+            ..AnalysisConfig::default()
+        };
+        assert_ne!(key, report_cache_key(3, weights, &pendant));
+
+        // A rule with the name of a default one but other behavior changes the fingerprint.
+        let renamed: &[&dyn Reduction] = &[
+            &DominatedRootColors,
+            &Renamed(CappedDuplicateTwoLists(MaxDegree::default())),
+        ];
+        let changed = AnalysisConfig {
+            reductions: renamed,
+            ..AnalysisConfig::default()
+        };
+        assert_eq!(changed, config);
+        assert_ne!(key, report_cache_key(3, weights, &changed));
+    }
+
+    struct Renamed<R>(R);
+
+    impl<R: Reduction> Reduction for Renamed<R> {
+        fn name(&self) -> &'static str {
+            "duplicate-2lists"
+        }
+        fn precondition(&self) -> &'static str {
+            self.0.precondition()
+        }
+        fn safety(&self) -> SafetyLevel {
+            self.0.safety()
+        }
+        fn apply(&self, star: &Star) -> Option<Star> {
+            self.0.apply(star)
+        }
+    }
+}
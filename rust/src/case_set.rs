@@ -0,0 +1,305 @@
+use alloc::collections::BTreeSet;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+
+use crate::star_utils::{
+    EnumerationConfig, NeighborMultiset, Star, fold_stars_with_config, star_from_string,
+    star_label, star_type_key,
+};
+
+/// The representative of the isomorphism class of `star` under reordering the neighbors and
+/// renaming the colors: the star of [`star_type_key`], with the neighbors in the order of the
+/// key. With fewer than 4 colors, it may use colors the enumeration does not.
+pub fn canonical_star(star: &Star) -> Star {
+    let (root_colors, NeighborMultiset { entries }) = star_type_key(star);
+    let (neighbor_colors, neighbor_halfedges) = entries
+        .iter()
+        .flat_map(|&(t, count)| core::iter::repeat_n(t, count))
+        .unzip();
+    Star {
+        root_colors,
+        neighbor_colors,
+        neighbor_halfedges,
+        neighbor_adjacency: None,
+    }
+}
+
+/// The canonical ID of `star`: the [`star_label`] of its [`canonical_star`], equal for all
+/// stars of an isomorphism class. Stars without a star string get their JSON object, so
+/// distinct classes never share an ID.
+pub fn canonical_id(star: &Star) -> String {
+    star_label(&canonical_star(star))
+}
+
+/// The canonical case set of an enumeration: one [`canonical_star`] per isomorphism class of
+/// the stars of [`generate_stars_with_config`](crate::star_utils::generate_stars_with_config),
+/// as a manifest that records which version of the crate produced it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CaseSet {
+    /// The crate version that computed the set.
+    pub version: String,
+    pub degree: usize,
+    pub config: EnumerationConfig,
+    /// The canonical IDs of the stars, sorted.
+    pub cases: Vec<String>,
+}
+
+impl CaseSet {
+    /// Enumerates the stars of the given `degree` with `config` and reduces them to their
+    /// canonical stars.
+    pub fn new(degree: usize, config: &EnumerationConfig) -> Self {
+        let cases: BTreeSet<String> =
+            fold_stars_with_config(degree, config, BTreeSet::new(), |mut set, star| {
+                set.insert(canonical_id(&star));
+                set
+            });
+        CaseSet {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            degree,
+            config: config.clone(),
+            cases: cases.into_iter().collect(),
+        }
+    }
+
+    /// The `key=value` lines of the manifest for the enumeration parameters. The halfedge
+    /// range only has a line if it differs from the default, so that the fingerprints of the
+    /// default enumeration stay the same.
+    fn parameter_lines(&self) -> Vec<String> {
+        let halfedges: Vec<String> = self
+            .config
+            .halfedge_bounds
+            .iter()
+            .map(|b| b.to_string())
+            .collect();
+        let max_degree = self
+            .config
+            .max_degree
+            .map_or("none".to_string(), |d| d.to_string());
+        let mut lines = vec![
+            format!("degree={}", self.degree),
+            format!("colors={}", self.config.colors),
+            format!("max_degree={max_degree}"),
+            format!("halfedges={}", halfedges.join(",")),
+        ];
+        let default = EnumerationConfig::default();
+        if (self.config.min_halfedges, self.config.max_halfedges)
+            != (default.min_halfedges, default.max_halfedges)
+        {
+            let max = self
+                .config
+                .max_halfedges
+                .map_or(String::new(), |h| h.to_string());
+            lines.push(format!(
+                "halfedge_range={}-{max}",
+                self.config.min_halfedges
+            ));
+        }
+        lines
+    }
+
+    /// FNV-1a hash of the enumeration parameters and the sorted canonical stars. It does not
+    /// depend on the version, so equal fingerprints of two versions mean equal case sets.
+    pub fn fingerprint(&self) -> u64 {
+        const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+        const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+        self.parameter_lines()
+            .iter()
+            .chain(self.cases.iter())
+            .flat_map(|line| line.bytes().chain(core::iter::once(b'\n')))
+            .fold(FNV_OFFSET, |hash, b| {
+                (hash ^ b as u64).wrapping_mul(FNV_PRIME)
+            })
+    }
+
+    /// The manifest: `key=value` lines for the version, the enumeration parameters, the
+    /// fingerprint and the number of cases, followed by one canonical star per line.
+    pub fn to_text(&self) -> String {
+        let mut out = format!("version={}\n", self.version);
+        for line in self.parameter_lines() {
+            out.push_str(&line);
+            out.push('\n');
+        }
+        out.push_str(&format!(
+            "fingerprint={:016x}\ncases={}\n",
+            self.fingerprint(),
+            self.cases.len()
+        ));
+        for case in self.cases.iter() {
+            out.push_str(case);
+            out.push('\n');
+        }
+        out
+    }
+}
+
+/// Reads a manifest written by [`CaseSet::to_text`], checking that the stars parse and that
+/// the fingerprint and the number of cases match them.
+pub fn case_set_from_text(text: &str) -> Result<CaseSet, String> {
+    let mut fields: Vec<(&str, &str)> = Vec::new();
+    let mut cases: Vec<String> = Vec::new();
+    for (i, line) in text
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty())
+    {
+        let line = line.trim();
+        match line.split_once('=') {
+            Some(field) if cases.is_empty() => fields.push(field),
+            _ => {
+                star_from_string(line).map_err(|e| format!("line {}: {e}", i + 1))?;
+                cases.push(line.to_string());
+            }
+        }
+    }
+    let field = |key: &str| {
+        fields
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| *v)
+            .ok_or_else(|| format!("missing field \"{key}\""))
+    };
+    let invalid = |key: &str| format!("invalid field \"{key}\"");
+    let max_degree = match field("max_degree")? {
+        "none" => None,
+        d => Some(d.parse().map_err(|_| invalid("max_degree"))?),
+    };
+    let halfedge_bounds = match field("halfedges")? {
+        "" => Vec::new(),
+        bounds => bounds
+            .split(',')
+            .map(str::parse)
+            .collect::<Result<_, _>>()?,
+    };
+    let default = EnumerationConfig::default();
+    let (min_halfedges, max_halfedges) = match field("halfedge_range") {
+        Err(_) => (default.min_halfedges, default.max_halfedges),
+        Ok(range) => {
+            let (min, max) = range
+                .split_once('-')
+                .ok_or_else(|| invalid("halfedge_range"))?;
+            let min = min.parse().map_err(|_| invalid("halfedge_range"))?;
+            let max = match max {
+                "" => None,
+                max => Some(max.parse().map_err(|_| invalid("halfedge_range"))?),
+            };
+            (min, max)
+        }
+    };
+    let set = CaseSet {
+        version: field("version")?.to_string(),
+        degree: field("degree")?.parse().map_err(|_| invalid("degree"))?,
+        config: EnumerationConfig {
+            colors: field("colors")?.parse().map_err(|_| invalid("colors"))?,
+            max_degree,
+            halfedge_bounds,
+            min_halfedges,
+            max_halfedges,
+            // The canonical stars are the same with or without it.
+            orbit_representatives: false,
+        },
+        cases,
+    };
+    if field("cases")? != set.cases.len().to_string() {
+        return Err(format!(
+            "the manifest lists {} cases but declares {}",
+            set.cases.len(),
+            field("cases")?
+        ));
+    }
+    if field("fingerprint")? != format!("{:016x}", set.fingerprint()) {
+        return Err("the fingerprint does not match the cases".to_string());
+    }
+    Ok(set)
+}
+
+/// The canonical stars that one case set has and another lacks, see [`diff_case_sets`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CaseSetDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl CaseSetDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Lists the canonical stars added from `before` to `after` and the ones removed, sorted.
+pub fn diff_case_sets(before: &CaseSet, after: &CaseSet) -> CaseSetDiff {
+    let old: BTreeSet<&String> = before.cases.iter().collect();
+    let new: BTreeSet<&String> = after.cases.iter().collect();
+    CaseSetDiff {
+        added: new.difference(&old).map(|s| s.to_string()).collect(),
+        removed: old.difference(&new).map(|s| s.to_string()).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::star_utils::{ColorPermutation, HalfedgeBound, generate_stars};
+
+    #[test]
+    fn case_sets_round_trip_and_diff() {
+        let star = generate_stars(3)[5].clone();
+        let renamed = star.permute_colors(&ColorPermutation::new(&[2, 0, 3, 1]));
+        assert_eq!(canonical_star(&star), canonical_star(&renamed));
+
+        let full = CaseSet::new(3, &EnumerationConfig::default());
+        assert!(full.cases.len() < generate_stars(3).len());
+        let text = full.to_text();
+        assert_eq!(case_set_from_text(&text), Ok(full.clone()));
+        let tampered = text.replacen("version=", "version=0.0.0-", 1);
+        assert_eq!(
+            case_set_from_text(&tampered).unwrap().fingerprint(),
+            full.fingerprint()
+        );
+        let last = full.cases.last().unwrap();
+        assert!(case_set_from_text(&text.replace(&format!("{last}\n"), "")).is_err());
+
+        let bounded = CaseSet::new(
+            3,
+            &EnumerationConfig {
+                halfedge_bounds: vec![HalfedgeBound {
+                    min: 3,
+                    max: u8::MAX,
+                    max_count: 0,
+                }],
+                ..EnumerationConfig::default()
+            },
+        );
+        assert_ne!(bounded.fingerprint(), full.fingerprint());
+        assert_eq!(case_set_from_text(&bounded.to_text()), Ok(bounded.clone()));
+        let diff = diff_case_sets(&full, &bounded);
+        assert!(diff.added.is_empty());
+        assert_eq!(diff.removed.len(), full.cases.len() - bounded.cases.len());
+        assert!(diff_case_sets(&full, &full).is_empty());
+
+        // Stars without a star string still get distinct IDs that read back.
+        let wide = |h| Star {
+            root_colors: 0b0011,
+            neighbor_colors: vec![0b0011],
+            neighbor_halfedges: vec![h],
+            neighbor_adjacency: None,
+        };
+        assert_ne!(canonical_id(&wide(16)), canonical_id(&wide(17)));
+        assert_eq!(
+            star_from_string(&canonical_id(&wide(16))),
+            Ok(canonical_star(&wide(16)))
+        );
+
+        let pendant = CaseSet::new(
+            3,
+            &EnumerationConfig {
+                min_halfedges: 0,
+                ..EnumerationConfig::default()
+            },
+        );
+        assert!(pendant.to_text().contains("halfedge_range=0-\n"));
+        assert_eq!(case_set_from_text(&pendant.to_text()), Ok(pendant.clone()));
+        assert!(diff_case_sets(&full, &pendant).removed.is_empty());
+    }
+}
@@ -0,0 +1,141 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::case_set::{CaseSet, canonical_id};
+use crate::list_coloring_utils::{Branch, apply_list_coloring_partition, partitions_of_colors};
+use crate::reductions::{DEFAULT_REDUCTIONS, reduce_to_fixpoint};
+use crate::star_utils::{EnumerationConfig, Star};
+
+/// The stars reachable from a set of stars by the analysis, see [`reduction_closure`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReductionClosure {
+    /// One star per isomorphism class, in the order they were reached: first the given
+    /// stars, then breadth-first.
+    pub stars: Vec<Star>,
+    /// The number of branches that were solved or infeasible, which end the recursion.
+    pub terminal_branches: usize,
+}
+
+impl ReductionClosure {
+    /// The stars of the closure that are not isomorphic to a star of
+    /// [`generate_stars_with_config`](crate::star_utils::generate_stars_with_config) with
+    /// `config` and the same number of neighbors, in closure order.
+    pub fn outside_universe(&self, config: &EnumerationConfig) -> Vec<&Star> {
+        let mut universes: BTreeMap<usize, CaseSet> = BTreeMap::new();
+        self.stars
+            .iter()
+            .filter(|star| {
+                let degree = star.neighbor_colors.len();
+                let universe = universes
+                    .entry(degree)
+                    .or_insert_with(|| CaseSet::new(degree, config));
+                universe.cases.binary_search(&canonical_id(star)).is_err()
+            })
+            .collect()
+    }
+}
+
+/// Applies the [`DEFAULT_REDUCTIONS`] and every branching rule recursively to `stars`: the
+/// closure contains the given stars, their reduced forms, and the ordinary branches of every
+/// partition of the root list into at least 2 blocks of every star in the closure.
+///
+/// Stars are identified up to isomorphism. Branches and reduced stars that are solved or
+/// infeasible, or whose root list has a single color, end the recursion and are only counted:
+/// the root is colored, so the analysis has no case for them. The recursion ends since
+/// branching and reductions only shrink lists.
+pub fn reduction_closure(stars: &[Star]) -> ReductionClosure {
+    let mut closure = ReductionClosure::default();
+    let mut seen: BTreeSet<String> = BTreeSet::new();
+    let mut add = |branch: Branch, closure: &mut ReductionClosure| match branch {
+        Branch::Star(star) if star.root_colors.count_ones() >= 2 => {
+            if seen.insert(canonical_id(&star)) {
+                closure.stars.push(star);
+            }
+        }
+        _ => closure.terminal_branches += 1,
+    };
+    for star in stars {
+        add(Branch::Star(star.clone()), &mut closure);
+    }
+    let mut next = 0;
+    while next < closure.stars.len() {
+        let star = closure.stars[next].clone();
+        next += 1;
+        let Branch::Star(reduced) =
+            Branch::from_star(reduce_to_fixpoint(&star, DEFAULT_REDUCTIONS))
+        else {
+            closure.terminal_branches += 1;
+            continue;
+        };
+        for partition in partitions_of_colors(reduced.root_colors) {
+            if partition.len() < 2 {
+                continue;
+            }
+            for branch in apply_list_coloring_partition(&reduced, &partition) {
+                add(branch, &mut closure);
+            }
+        }
+        add(Branch::Star(reduced), &mut closure);
+    }
+    closure
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::star_utils::{generate_stars, star_from_string};
+    use alloc::vec;
+
+    #[test]
+    fn closure_contains_the_branches_and_leaves_the_enumeration() {
+        let star = Star {
+            root_colors: 0b0111,
+            neighbor_colors: vec![0b0011, 0b0110],
+            neighbor_halfedges: vec![2, 2],
+            neighbor_adjacency: None,
+        };
+        let closure = reduction_closure(core::slice::from_ref(&star));
+        assert_eq!(closure.stars[0], star);
+        assert!(closure.terminal_branches > 0);
+        // Branching on {0} | {1,2} leaves the root list {1,2} and the neighbor lists intact.
+        let branch = Star {
+            root_colors: 0b0110,
+            ..star.clone()
+        };
+        let ids: Vec<_> = closure.stars.iter().map(canonical_id).collect();
+        assert!(ids.contains(&canonical_id(&branch)));
+        assert_eq!(
+            reduction_closure(&closure.stars).stars.len(),
+            closure.stars.len()
+        );
+
+        // Branches with singleton lists are not in the enumeration of generate_stars.
+        let config = EnumerationConfig::default();
+        let outside = closure.outside_universe(&config);
+        assert!(!outside.contains(&&star));
+        assert!(outside.iter().all(|s| {
+            !generate_stars(2)
+                .iter()
+                .any(|t| t.is_isomorphic(s).is_some())
+        }));
+    }
+
+    #[test]
+    fn colored_roots_end_the_closure() {
+        let star = star_from_string("star_3_ffff_0222").unwrap();
+        let closure = reduction_closure(&[star]);
+        assert!(closure.terminal_branches > 0);
+        assert!(
+            closure
+                .stars
+                .iter()
+                .all(|s| s.root_colors.count_ones() >= 2)
+        );
+        let config = EnumerationConfig {
+            max_degree: Some(4),
+            ..EnumerationConfig::default()
+        };
+        assert!(closure.outside_universe(&config).is_empty());
+    }
+}
@@ -0,0 +1,161 @@
+use alloc::vec::Vec;
+
+use crate::analysis::{AnalysisConfig, best_partition, branches_recurrence};
+use crate::colors::colors;
+use crate::list_coloring_utils::{Branch, NodeFeatures};
+use crate::recurrence::Recurrence;
+use crate::star_utils::Star;
+
+// Branching on color classes, as in Kempe-chain arguments: for one color `c` of the root
+// list, either the root joins the color class of `c`, or it is excluded from it. This is the
+// partition `{c} | L \ {c}` of the root list `L`, but the rule family only has one rule per
+// color, so its worst case can be compared with the one of partition branching.
+
+/// The two branches of branching on a color class.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColorClassBranches {
+    pub color: u8,
+    /// The root is colored `color`, which is removed from the neighbor lists.
+    pub join: Branch,
+    /// `color` is removed from the root list.
+    pub exclude: Branch,
+}
+
+/// Branches `star` on the color class of `color`, reducing both branches with the reductions
+/// of `config` as in [`AnalysisConfig::branches`].
+///
+/// Returns `None` if `color` is not in the root list or is its only color, since the rule
+/// does not branch then.
+pub fn color_class_branches(
+    star: &Star,
+    color: u8,
+    config: &AnalysisConfig,
+) -> Option<ColorClassBranches> {
+    let join = 1u8 << color;
+    let exclude = star.root_colors & !join;
+    if star.root_colors & join == 0 || exclude == 0 {
+        return None;
+    }
+    let [join_branch, exclude_branch]: [Branch; 2] = config
+        .branches(star, &[join, exclude])
+        .try_into()
+        .expect("one branch per block");
+    Some(ColorClassBranches {
+        color,
+        join: join_branch,
+        exclude: exclude_branch,
+    })
+}
+
+/// Returns the recurrence of branching `star` on the color class of `color`, with the drops
+/// of the join branch and the exclude branch in this order, see [`color_class_branches`].
+/// Stars are measured with the degree buckets of `config`.
+pub fn color_class_recurrence(
+    star: &Star,
+    color: u8,
+    weights: NodeFeatures,
+    config: &AnalysisConfig,
+) -> Option<Recurrence> {
+    let branches = color_class_branches(star, color, config)?;
+    Some(branches_recurrence(
+        star,
+        &[branches.join, branches.exclude],
+        weights,
+        config,
+    ))
+}
+
+/// Finds the color of the root list whose color class gives the smallest branching number.
+/// Ties are broken by the smaller color.
+///
+/// Returns `None` if the root list has fewer than 2 colors.
+pub fn best_color_class(
+    star: &Star,
+    weights: NodeFeatures,
+    config: &AnalysisConfig,
+) -> Option<(u8, f64)> {
+    let mut best: Option<(u8, f64)> = None;
+    for color in colors(star.root_colors) {
+        let Some(recurrence) = color_class_recurrence(star, color, weights, config) else {
+            continue;
+        };
+        let bn = recurrence.branching_number();
+        if best.is_none_or(|(_, best_bn)| bn < best_bn) {
+            best = Some((color, bn));
+        }
+    }
+    best
+}
+
+/// The best rule of both rule families for one star.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RuleComparison {
+    pub star: Star,
+    /// The best partition and its branching number, see [`best_partition`].
+    pub partition: (Vec<u8>, f64),
+    /// The best color and its branching number, see [`best_color_class`].
+    pub color_class: (u8, f64),
+}
+
+/// Compares partition branching with color class branching on every star of `stars` whose
+/// root list can be split, in the order of `stars`.
+pub fn compare_rule_families(
+    stars: &[Star],
+    weights: NodeFeatures,
+    config: &AnalysisConfig,
+) -> Vec<RuleComparison> {
+    stars
+        .iter()
+        .filter_map(|star| {
+            Some(RuleComparison {
+                star: star.clone(),
+                partition: best_partition(star, weights, config)?,
+                color_class: best_color_class(star, weights, config)?,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::partition_recurrence;
+    use crate::list_coloring_utils::apply_list_coloring_partition;
+    use crate::star_utils::generate_stars;
+    use alloc::vec;
+
+    #[test]
+    fn color_classes_are_binary_partitions() {
+        let weights = NodeFeatures::from_array([1.0, 1.0, 1.0, 0.7, 0.7, 0.7, 0.4, 0.4, 0.4]);
+        let star = Star {
+            root_colors: 0b0111,
+            neighbor_colors: vec![0b0011, 0b0110],
+            neighbor_halfedges: vec![2, 2],
+            neighbor_adjacency: None,
+        };
+        let config = AnalysisConfig::default();
+        assert_eq!(color_class_branches(&star, 3, &config), None);
+        let branches = color_class_branches(&star, 0, &config).unwrap();
+        assert_eq!(
+            [branches.join, branches.exclude].to_vec(),
+            apply_list_coloring_partition(&star, &[0b0001, 0b0110])
+        );
+        assert_eq!(
+            color_class_recurrence(&star, 0, weights, &AnalysisConfig::default()),
+            Some(partition_recurrence(
+                &star,
+                &[0b0001, 0b0110],
+                weights,
+                &AnalysisConfig::default()
+            ))
+        );
+
+        // Every color class is a partition, so partition branching is never worse.
+        let stars = generate_stars(2);
+        let comparisons = compare_rule_families(&stars, weights, &AnalysisConfig::default());
+        assert!(!comparisons.is_empty());
+        for c in comparisons.iter() {
+            assert!(c.partition.1 <= c.color_class.1);
+        }
+    }
+}
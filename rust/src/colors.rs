@@ -0,0 +1,347 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+/// Number of colors in the universe by default, and the most colors the measure has weights
+/// for.
+pub const NUM_COLORS: u8 = 4;
+
+/// The color list containing every color, as a bitmask over `0..NUM_COLORS`.
+pub const ALL_COLORS: u8 = (1 << NUM_COLORS) - 1;
+
+/// The most colors of stars, trees, partitions and reductions: the colors a `u8` list holds,
+/// see [`ColorSet`].
+pub const MAX_COLORS: u8 = <u8 as ColorSet>::CAPACITY;
+
+/// Whether the color lists `a` and `b` share a color.
+pub fn intersects(a: u8, b: u8) -> bool {
+    (a & b) != 0
+}
+
+/// Whether every color of `a` is in `b`.
+pub fn is_subset(a: u8, b: u8) -> bool {
+    (a & !b) == 0
+}
+
+/// The colors of `universe` that are not in `set`.
+pub fn complement(set: u8, universe: u8) -> u8 {
+    universe & !set
+}
+
+/// The colors in `set`, in increasing order.
+pub fn colors(set: u8) -> impl DoubleEndedIterator<Item = u8> {
+    (0..8u8).filter(move |&i| (set >> i) & 1 == 1)
+}
+
+/// The singleton lists `1 << i` for the colors `i` in `set`, in increasing order.
+pub fn singletons(set: u8) -> impl DoubleEndedIterator<Item = u8> {
+    colors(set).map(|i| 1u8 << i)
+}
+
+/// All subsets of `set`, including `set` itself and the empty set, in decreasing order of
+/// their bitmasks.
+pub fn subsets(set: u8) -> impl Iterator<Item = u8> {
+    core::iter::successors(Some(set), move |&sub| (sub != 0).then(|| (sub - 1) & set))
+}
+
+/// All supersets of `set` within `universe`, in decreasing order of their bitmasks.
+pub fn supersets(set: u8, universe: u8) -> impl Iterator<Item = u8> {
+    subsets(complement(set, universe)).map(move |extra| set | extra)
+}
+
+/// The subsets of `set` with exactly `size` colors, in lexicographic order of their sorted
+/// colors, e.g. `{0,1}, {0,2}, {1,2}`.
+pub fn subsets_of_size(set: u8, size: u32) -> Vec<u8> {
+    let mut out: Vec<u8> = subsets(set).filter(|s| s.count_ones() == size).collect();
+    out.sort_by_key(|&s| colors(s).collect::<Vec<_>>());
+    out
+}
+
+/// The subsets of `set` with at least `min_size` colors, grouped by size from largest to
+/// smallest and ordered as in [`subsets_of_size`] within a group.
+pub fn subsets_by_size(set: u8, min_size: u32) -> Vec<u8> {
+    (min_size..=set.count_ones())
+        .rev()
+        .flat_map(|size| subsets_of_size(set, size))
+        .collect()
+}
+
+/// Formats a color list as a set, e.g. `{0,2,3}`.
+pub fn fmt_colors(set: u8) -> String {
+    fmt_color_set(set)
+}
+
+/// Formats a partition of a color list as its blocks separated by `|`, e.g. `{0,1} | {2}`.
+pub fn fmt_partition(partition: &[u8]) -> String {
+    partition
+        .iter()
+        .map(|&block| fmt_colors(block))
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+/// A set of colors as a bitmask, where bit `i` is set if color `i` is in the set.
+///
+/// The lists of stars, trees and partitions are `u8` sets, so they, the enumeration and the
+/// reductions work for k-list-coloring with up to [`MAX_COLORS`] colors, with `k` chosen at
+/// run time as [`EnumerationConfig::colors`](crate::star_utils::EnumerationConfig::colors).
+/// [`fmt_color_set`] and [`partitions_of_set`] also take `u16` and `u32` sets of up to 16 and
+/// 32 colors. The measure has weights only for lists of 2 to [`NUM_COLORS`] colors, so the
+/// analysis checks `k` with
+/// [`AnalysisConfig::validate`](crate::analysis::AnalysisConfig::validate), and the star
+/// strings have one hex digit per list, so stars with more colors are written as JSON, see
+/// [`star_label`](crate::star_utils::star_label).
+pub trait ColorSet: Copy + Ord + core::fmt::Debug + core::hash::Hash {
+    /// The number of colors the type can hold.
+    const CAPACITY: u8;
+    const EMPTY: Self;
+
+    /// The set `{0, ..., k - 1}`. Panics if `k` exceeds [`ColorSet::CAPACITY`].
+    fn first(k: u8) -> Self;
+    fn singleton(color: u8) -> Self;
+    fn union(self, other: Self) -> Self;
+    fn intersection(self, other: Self) -> Self;
+    /// The colors of `self` that are not in `other`.
+    fn difference(self, other: Self) -> Self;
+    fn len(self) -> u32;
+
+    fn is_empty(self) -> bool {
+        self == Self::EMPTY
+    }
+
+    fn contains(self, color: u8) -> bool {
+        color < Self::CAPACITY && self.intersection(Self::singleton(color)) != Self::EMPTY
+    }
+
+    fn is_subset(self, other: Self) -> bool {
+        self.difference(other).is_empty()
+    }
+
+    /// The colors in the set, in increasing order.
+    fn colors(self) -> impl DoubleEndedIterator<Item = u8> {
+        (0..Self::CAPACITY).filter(move |&c| self.contains(c))
+    }
+}
+
+macro_rules! impl_color_set {
+    ($($t:ty),*) => {$(
+        impl ColorSet for $t {
+            const CAPACITY: u8 = <$t>::BITS as u8;
+            const EMPTY: Self = 0;
+
+            fn first(k: u8) -> Self {
+                assert!(k <= Self::CAPACITY, "{k} colors do not fit into {}", stringify!($t));
+                if k == 0 {
+                    0
+                } else {
+                    <$t>::MAX >> (Self::CAPACITY - k)
+                }
+            }
+
+            fn singleton(color: u8) -> Self {
+                1 << color
+            }
+
+            fn union(self, other: Self) -> Self {
+                self | other
+            }
+
+            fn intersection(self, other: Self) -> Self {
+                self & other
+            }
+
+            fn difference(self, other: Self) -> Self {
+                self & !other
+            }
+
+            fn len(self) -> u32 {
+                self.count_ones()
+            }
+        }
+    )*};
+}
+
+impl_color_set!(u8, u16, u32);
+
+/// Formats a set of any [`ColorSet`] type like [`fmt_colors`].
+pub fn fmt_color_set<C: ColorSet>(set: C) -> String {
+    let elems: Vec<String> = set.colors().map(|i| i.to_string()).collect();
+    format!("{{{}}}", elems.join(","))
+}
+
+/// All set partitions of `set` into nonempty blocks, for any [`ColorSet`] type, in the order
+/// of [`partitions_of_colors`](crate::list_coloring_utils::partitions_of_colors): blocks in
+/// decreasing order, partitions by number of blocks and then lexicographically. The empty set
+/// has the single empty partition.
+pub fn partitions_of_set<C: ColorSet>(set: C) -> Vec<Vec<C>> {
+    fn backtrack<C: ColorSet>(rest: &[u8], blocks: &mut Vec<C>, out: &mut Vec<Vec<C>>) {
+        let Some((&color, rest)) = rest.split_first() else {
+            let mut partition = blocks.clone();
+            partition.sort_by(|a, b| b.cmp(a));
+            out.push(partition);
+            return;
+        };
+        let bit = C::singleton(color);
+        for i in 0..blocks.len() {
+            let block = blocks[i];
+            blocks[i] = block.union(bit);
+            backtrack(rest, blocks, out);
+            blocks[i] = block;
+        }
+        blocks.push(bit);
+        backtrack(rest, blocks, out);
+        blocks.pop();
+    }
+
+    // Adding the colors in decreasing order keeps the output stable and human-friendly.
+    let colors: Vec<u8> = set.colors().rev().collect();
+    let mut out = Vec::new();
+    backtrack(&colors, &mut Vec::new(), &mut out);
+    out.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
+    out
+}
+
+/// A partition of a set of at most 8 colors into at most 8 blocks, packed into a `u32`: bits
+/// `3c..3c + 3` hold the index of the block of color `c`, and bit `24 + c` is set if color `c`
+/// is in the partitioned set.
+///
+/// Tables of millions of partitions, such as the precomputed drops of
+/// [`StarConstraints`](crate::optimize::StarConstraints), store these instead of a `Vec<u8>`
+/// per partition. Unpacking keeps the order of the blocks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PackedPartition(u32);
+
+impl PackedPartition {
+    /// Packs the blocks of `partition`, in order; `None` if a block is empty, two blocks
+    /// share a color, or there are more than 8 blocks.
+    pub fn pack(partition: &[u8]) -> Option<Self> {
+        if partition.len() > 8 {
+            return None;
+        }
+        let mut packed = 0u32;
+        for (index, &block) in partition.iter().enumerate() {
+            if block == 0 || (packed >> 24) as u8 & block != 0 {
+                return None;
+            }
+            packed |= (block as u32) << 24;
+            for c in colors(block) {
+                packed |= (index as u32) << (3 * c);
+            }
+        }
+        Some(PackedPartition(packed))
+    }
+
+    /// The blocks, in the order they were packed.
+    pub fn unpack(self) -> Vec<u8> {
+        let mut blocks: Vec<u8> = Vec::new();
+        for c in colors(self.colors()) {
+            let index = self.block_index(c) as usize;
+            if blocks.len() <= index {
+                blocks.resize(index + 1, 0);
+            }
+            blocks[index] |= 1 << c;
+        }
+        blocks
+    }
+
+    /// The partitioned set of colors.
+    pub fn colors(self) -> u8 {
+        (self.0 >> 24) as u8
+    }
+
+    pub fn num_blocks(self) -> usize {
+        match self.colors() {
+            0 => 0,
+            set => colors(set).map(|c| self.block_index(c)).max().unwrap_or(0) as usize + 1,
+        }
+    }
+
+    /// The index of the block that contains `color`, if the partitioned set contains it.
+    pub fn block_of(self, color: u8) -> Option<u8> {
+        (color < 8 && (self.colors() >> color) & 1 == 1).then(|| self.block_index(color))
+    }
+
+    fn block_index(self, color: u8) -> u8 {
+        ((self.0 >> (3 * color)) & 0b111) as u8
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::star_utils::{COLOR_SUBSETS_GE2, ROOT_COLOR_SUBSETS};
+    use alloc::vec;
+
+    #[test]
+    fn subset_lattice_iteration() {
+        assert_eq!(
+            subsets(0b0101).collect::<Vec<_>>(),
+            vec![0b0101, 0b0100, 0b0001, 0]
+        );
+        assert_eq!(
+            supersets(0b0101, ALL_COLORS).collect::<Vec<_>>(),
+            vec![0b1111, 0b1101, 0b0111, 0b0101]
+        );
+        assert!(subsets(0b1011).all(|s| is_subset(s, 0b1011)));
+        assert_eq!(complement(0b0101, ALL_COLORS), 0b1010);
+        assert_eq!(singletons(0b1010).collect::<Vec<_>>(), vec![0b0010, 0b1000]);
+        assert_eq!(fmt_colors(0b1101), "{0,2,3}");
+        assert_eq!(fmt_colors(0), "{}");
+        assert_eq!(fmt_partition(&[0b0011, 0b0100]), "{0,1} | {2}");
+    }
+
+    #[test]
+    fn color_sets_beyond_four_colors() {
+        assert_eq!(<u8 as ColorSet>::first(8), 0xff);
+        assert_eq!(<u16 as ColorSet>::first(10), 0x3ff);
+        assert_eq!(<u32 as ColorSet>::first(0), 0);
+        let set: u16 = 0b1_1000_0001;
+        assert_eq!(set.colors().collect::<Vec<_>>(), vec![0, 7, 8]);
+        assert_eq!(fmt_color_set(set), "{0,7,8}");
+        assert!(!set.contains(20));
+
+        // The Bell number B(5), and the same order for shifted colors.
+        assert_eq!(
+            partitions_of_set(<u16 as ColorSet>::first(10) & !0b11111).len(),
+            52
+        );
+        let shifted: Vec<Vec<u16>> = partitions_of_set(0b0111u8)
+            .iter()
+            .map(|p| p.iter().map(|&b| (b as u16) << 8).collect())
+            .collect();
+        assert_eq!(partitions_of_set(0b0111u16 << 8), shifted);
+    }
+
+    #[test]
+    fn packed_partitions_round_trip() {
+        for set in subsets(0xff).step_by(7) {
+            for partition in partitions_of_set(set).into_iter().step_by(11) {
+                let packed = PackedPartition::pack(&partition).unwrap();
+                assert_eq!(packed.unpack(), partition);
+                assert_eq!(packed.colors(), set);
+                assert_eq!(packed.num_blocks(), partition.len());
+            }
+        }
+        let packed = PackedPartition::pack(&[0b0100, 0b0011]).unwrap();
+        assert_eq!(packed.block_of(0), Some(1));
+        assert_eq!(packed.block_of(2), Some(0));
+        assert_eq!(packed.block_of(3), None);
+        assert_eq!(PackedPartition::pack(&[0b0011, 0b0110]), None);
+        assert_eq!(PackedPartition::pack(&[0b0011, 0]), None);
+        assert_eq!(
+            PackedPartition::pack(&[]).map(PackedPartition::unpack),
+            Some(vec![])
+        );
+    }
+
+    #[test]
+    fn popcount_groups_match_enumerated_lists() {
+        assert_eq!(subsets_by_size(ALL_COLORS, 2), COLOR_SUBSETS_GE2.to_vec());
+        assert_eq!(subsets_of_size(ALL_COLORS, 4).len(), 1);
+        assert!(
+            ROOT_COLOR_SUBSETS
+                .iter()
+                .all(|&s| subsets_by_size(ALL_COLORS, 2).contains(&s))
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::star_utils::{Star, generate_stars, star_label};
+
+/// Index of a star within a [`Corpus`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StarId(pub usize);
+
+/// A deduplicated collection of stars together with their string encodings.
+///
+/// Stars are deduplicated by value, so every distinct star gets its own ID even when it has no
+/// v1 string. Each new star is encoded once on insertion; the encoding is interned and shared
+/// between the forward table (`StarId` to encoding) and the reverse index (encoding to `StarId`),
+/// so lookups in either direction neither re-encode nor re-parse stars.
+#[derive(Clone, Debug, Default)]
+pub struct Corpus {
+    stars: Vec<Star>,
+    encodings: Vec<Arc<str>>,
+    ids: HashMap<Arc<str>, StarId>,
+    by_star: HashMap<Star, StarId>,
+}
+
+impl Corpus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a corpus of all stars of the given `degree`, in enumeration order.
+    pub fn from_degree(degree: usize) -> Self {
+        let mut corpus = Self::new();
+        for star in generate_stars(degree) {
+            corpus.insert(star);
+        }
+        corpus
+    }
+
+    /// Inserts a star and returns its ID. Inserting a star that is already present returns the
+    /// existing ID.
+    pub fn insert(&mut self, star: Star) -> StarId {
+        if let Some(&id) = self.by_star.get(&star) {
+            return id;
+        }
+        let encoding: Arc<str> = star_label(&star).into();
+        let id = StarId(self.stars.len());
+        self.by_star.insert(star.clone(), id);
+        self.stars.push(star);
+        self.encodings.push(Arc::clone(&encoding));
+        self.ids.insert(encoding, id);
+        id
+    }
+
+    pub fn len(&self) -> usize {
+        self.stars.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stars.is_empty()
+    }
+
+    pub fn get(&self, id: StarId) -> Option<&Star> {
+        self.stars.get(id.0)
+    }
+
+    /// Returns the string encoding of the star with the given ID.
+    pub fn encoding(&self, id: StarId) -> Option<&str> {
+        self.encodings.get(id.0).map(|s| &**s)
+    }
+
+    /// Returns the ID of the given star.
+    pub fn id(&self, star: &Star) -> Option<StarId> {
+        self.by_star.get(star).copied()
+    }
+
+    /// Returns the ID of the star with the given string encoding.
+    pub fn lookup(&self, encoding: &str) -> Option<StarId> {
+        self.ids.get(encoding).copied()
+    }
+
+    /// Iterates over `(id, star, encoding)` in insertion order.
+    pub fn iter(&self) -> impl Iterator<Item = (StarId, &Star, &str)> {
+        self.stars
+            .iter()
+            .zip(self.encodings.iter())
+            .enumerate()
+            .map(|(i, (star, encoding))| (StarId(i), star, &**encoding))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::star_utils::star_to_string;
+
+    #[test]
+    fn corpus_deduplicates_and_indexes_by_encoding() {
+        let mut corpus = Corpus::new();
+        let star = Star {
+            root_colors: 0b0011,
+            neighbor_colors: vec![0b0011, 0b0110],
+            neighbor_halfedges: vec![2, 3],
+            neighbor_adjacency: None,
+        };
+        let id = corpus.insert(star.clone());
+        assert_eq!(corpus.insert(star.clone()), id);
+        assert_eq!(corpus.len(), 1);
+
+        let encoding = corpus.encoding(id).unwrap().to_string();
+        assert_eq!(encoding, star_to_string(&star).unwrap());
+        assert_eq!(corpus.lookup(&encoding), Some(id));
+        assert_eq!(corpus.get(id), Some(&star));
+        assert_eq!(corpus.id(&star), Some(id));
+        assert_eq!(corpus.lookup("star_2_3_023"), None);
+    }
+
+    #[test]
+    fn corpus_keeps_wide_stars_apart() {
+        let wide = |halfedges| Star {
+            root_colors: 0b0011,
+            neighbor_colors: vec![0b0011],
+            neighbor_halfedges: vec![halfedges],
+            neighbor_adjacency: None,
+        };
+        let mut corpus = Corpus::new();
+        let a = corpus.insert(wide(16));
+        let b = corpus.insert(wide(17));
+        assert_ne!(a, b);
+        assert_eq!(corpus.len(), 2);
+        assert_eq!(corpus.lookup(corpus.encoding(b).unwrap()), Some(b));
+    }
+
+    #[test]
+    fn corpus_from_degree_keeps_enumeration_order() {
+        let stars = generate_stars(3);
+        let corpus = Corpus::from_degree(3);
+        assert_eq!(corpus.len(), stars.len());
+        for ((id, star, encoding), expected) in corpus.iter().zip(stars.iter()) {
+            assert_eq!(star, expected);
+            assert_eq!(corpus.lookup(encoding), Some(id));
+        }
+    }
+}
@@ -0,0 +1,212 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::star_utils::{NeighborMultiset, Star, star_from_string, star_label, star_type_key};
+
+/// One row of a case list: a named case of a prior analysis and a star it treats.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ListedCase {
+    /// Case name, e.g. `C5.3`.
+    pub name: String,
+    pub star: Star,
+}
+
+/// Splits a CSV line into fields. Fields may be quoted with `"`, and `""` inside a quoted
+/// field is a literal quote.
+pub(crate) fn csv_fields(line: &str) -> Result<Vec<String>, String> {
+    let mut fields: Vec<String> = Vec::new();
+    let mut field = String::new();
+    let mut chars = line.chars().peekable();
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (true, c) => field.push(c),
+            (false, '"') if field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+            }
+            (false, ',') => fields.push(core::mem::take(&mut field).trim().to_string()),
+            (false, c) => field.push(c),
+        }
+    }
+    if quoted {
+        return Err("unterminated quoted field".to_string());
+    }
+    fields.push(field.trim().to_string());
+    Ok(fields)
+}
+
+/// Reads a case list in CSV format.
+///
+/// The first line is a header naming the columns; the columns `case` (the case name) and
+/// `star` (a v1 or v2 star string) are required, others such as a reference are ignored.
+/// Empty lines and lines starting with `#` are skipped. A case that treats several stars
+/// has one row per star:
+///
+/// ```text
+/// case,star,reference
+/// C5.3,star_3_ffff_0222,Lemma 12
+/// "C5.4, tight",star_3_f73c_0222,
+/// ```
+pub fn parse_case_list(csv: &str) -> Result<Vec<ListedCase>, String> {
+    let mut lines = csv
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty() && !l.trim_start().starts_with('#'));
+    let Some((i, header)) = lines.next() else {
+        return Err("missing header line".to_string());
+    };
+    let header = csv_fields(header).map_err(|e| format!("line {}: {e}", i + 1))?;
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|h| h == name)
+            .ok_or_else(|| format!("missing column \"{name}\""))
+    };
+    let (name_col, star_col) = (column("case")?, column("star")?);
+
+    let mut cases = Vec::new();
+    for (i, line) in lines {
+        let err = |e: String| format!("line {}: {e}", i + 1);
+        let fields = csv_fields(line).map_err(err)?;
+        let field = |col: usize| {
+            fields
+                .get(col)
+                .filter(|f| !f.is_empty())
+                .ok_or_else(|| err(format!("missing \"{}\"", header[col])))
+        };
+        cases.push(ListedCase {
+            name: field(name_col)?.clone(),
+            star: star_from_string(field(star_col)?).map_err(|e| err(e.to_string()))?,
+        });
+    }
+    Ok(cases)
+}
+
+/// The result of [`audit_coverage`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CoverageAudit {
+    /// Every enumerated star up to isomorphism, in enumeration order, with the names of the
+    /// listed cases that treat it; stars with no names are uncovered.
+    pub stars: Vec<(Star, Vec<String>)>,
+    /// The listed cases that match no enumerated star, in list order.
+    pub unmatched: Vec<ListedCase>,
+}
+
+impl CoverageAudit {
+    pub fn uncovered(&self) -> impl Iterator<Item = &Star> {
+        self.stars
+            .iter()
+            .filter(|(_, names)| names.is_empty())
+            .map(|(star, _)| star)
+    }
+
+    /// Whether every enumerated star is covered and every listed case matched.
+    pub fn is_complete(&self) -> bool {
+        self.uncovered().next().is_none() && self.unmatched.is_empty()
+    }
+
+    /// One line per star, `<star>\t<case names>` or `<star>\tUNCOVERED`, followed by one line
+    /// `UNMATCHED\t<case name>\t<star>` per unmatched listed case.
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for (star, names) in self.stars.iter() {
+            let names = if names.is_empty() {
+                "UNCOVERED".to_string()
+            } else {
+                names.join(", ")
+            };
+            out.push_str(&format!("{}\t{names}\n", star_label(star)));
+        }
+        for case in self.unmatched.iter() {
+            out.push_str(&format!(
+                "UNMATCHED\t{}\t{}\n",
+                case.name,
+                star_label(&case.star)
+            ));
+        }
+        out
+    }
+}
+
+/// Maps the enumerated `stars` to the listed `cases` that treat them.
+///
+/// Stars are compared up to reordering the neighbors and renaming the colors (see
+/// [`star_type_key`]), so the enumeration is reduced to one canonical star per isomorphism
+/// class, and a case list may use any representative.
+pub fn audit_coverage(stars: &[Star], cases: &[ListedCase]) -> CoverageAudit {
+    let mut index: BTreeMap<(u8, NeighborMultiset), usize> = BTreeMap::new();
+    let mut audit = CoverageAudit::default();
+    for star in stars {
+        let next = audit.stars.len();
+        if *index.entry(star_type_key(star)).or_insert(next) == next {
+            audit.stars.push((star.clone(), Vec::new()));
+        }
+    }
+    for case in cases {
+        match index.get(&star_type_key(&case.star)) {
+            Some(&i) => {
+                let names = &mut audit.stars[i].1;
+                if !names.contains(&case.name) {
+                    names.push(case.name.clone());
+                }
+            }
+            None => audit.unmatched.push(case.clone()),
+        }
+    }
+    audit
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::star_utils::{ColorPermutation, generate_stars, star_to_string};
+    use alloc::vec;
+
+    #[test]
+    fn audit_maps_stars_to_listed_cases() {
+        let stars = generate_stars(2);
+        let first = stars[0].clone();
+        let renamed = first.permute_colors(&ColorPermutation::new(&[1, 0, 3, 2]));
+        let foreign = Star {
+            root_colors: 0b1111,
+            neighbor_colors: vec![0b0011; 3],
+            neighbor_halfedges: vec![2; 3],
+            neighbor_adjacency: None,
+        };
+        let csv = format!(
+            "# prior work\ncase,star,reference\nC1,{},Lemma 1\n\"C1, again\",{},\nC9,{},\n",
+            star_to_string(&first).unwrap(),
+            star_to_string(&renamed).unwrap(),
+            star_to_string(&foreign).unwrap()
+        );
+        let cases = parse_case_list(&csv).unwrap();
+        assert_eq!(cases.len(), 3);
+        assert_eq!(cases[1].name, "C1, again");
+
+        let audit = audit_coverage(&stars, &cases);
+        assert!(audit.stars.len() < stars.len());
+        assert_eq!(
+            audit.stars[0],
+            (first, vec!["C1".into(), "C1, again".into()])
+        );
+        assert_eq!(audit.uncovered().count(), audit.stars.len() - 1);
+        assert_eq!(audit.unmatched, vec![cases[2].clone()]);
+        assert!(!audit.is_complete());
+        assert_eq!(
+            audit.to_text().lines().count(),
+            audit.stars.len() + audit.unmatched.len()
+        );
+
+        assert!(parse_case_list("name,star\nC1,star_2_333_022\n").is_err());
+        assert!(parse_case_list("case,star\nC1,nonsense\n").is_err());
+        assert!(parse_case_list("case,star\n\"C1,star_2_333_022\n").is_err());
+    }
+}
@@ -0,0 +1,157 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+use crate::colors::singletons;
+use crate::list_coloring_utils::{apply_list_coloring_partition_propagated, is_infeasible};
+use crate::star_utils::{NeighborAdjacency, Star, generate_stars};
+
+/// The number of proper colorings of the root and its neighbors from their lists, by brute
+/// force, respecting the edges among the neighbors. The halfedges are ignored.
+pub fn count_colorings(star: &Star) -> usize {
+    fn extend(star: &Star, chosen: &mut Vec<u8>, root: u8) -> usize {
+        let i = chosen.len();
+        if i == star.neighbor_colors.len() {
+            return 1;
+        }
+        let adjacent = |j: usize| {
+            star.neighbor_adjacency
+                .as_ref()
+                .is_some_and(|adjacency| adjacency.is_adjacent(i, j))
+        };
+        let mut total = 0;
+        for c in singletons(star.neighbor_colors[i] & !root) {
+            if (0..i).all(|j| !adjacent(j) || chosen[j] != c) {
+                chosen.push(c);
+                total += extend(star, chosen, root);
+                chosen.pop();
+            }
+        }
+        total
+    }
+    singletons(star.root_colors)
+        .map(|root| extend(star, &mut Vec::new(), root))
+        .sum()
+}
+
+/// Applies a partition of the root list with [`apply_list_coloring_partition_propagated`] and
+/// drops the [infeasible](is_infeasible) branches.
+///
+/// Unlike [`apply_list_coloring_partition`](crate::list_coloring_utils::apply_list_coloring_partition),
+/// no reductions are applied and neighbors are never removed, since a neighbor with a
+/// singleton list still constrains its adjacent neighbors.
+pub fn apply_partition_cyclic(star: &Star, partition: &[u8]) -> Vec<Star> {
+    apply_list_coloring_partition_propagated(star, partition)
+        .into_iter()
+        .filter(|branch| !is_infeasible(branch))
+        .collect()
+}
+
+/// Generates the stars of the given `degree` (see [`generate_stars`]) with every adjacency of
+/// at most `max_edges` edges among the neighbors, see [`NeighborAdjacency::all_consistent`].
+/// The stars without edges have no adjacency.
+///
+/// The neighbor edges are halfedges of both endpoints, so `neighbor_halfedges` still counts
+/// all edges of a neighbor other than the one to the root. Every neighbor edge closes a
+/// triangle with the root, so the treewidth is 2 for a matching among the neighbors and 3 for
+/// the root plus a triangle.
+///
+/// Meant for small sizes, such as degree 3 with `max_edges = 3` for the triangle.
+pub fn generate_cyclic_stars(degree: usize, max_edges: usize) -> Vec<Star> {
+    generate_stars(degree)
+        .into_iter()
+        .flat_map(|star| {
+            NeighborAdjacency::all_consistent(&star, max_edges)
+                .into_iter()
+                .map(move |adjacency| Star {
+                    neighbor_adjacency: (adjacency.edge_count() > 0).then(|| Box::new(adjacency)),
+                    ..star.clone()
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::list_coloring_utils::partitions_of_colors;
+    use alloc::vec;
+
+    fn triangle() -> Star {
+        Star {
+            root_colors: 0b0111,
+            neighbor_colors: vec![0b0011, 0b0110, 0b0111],
+            neighbor_halfedges: vec![2, 2, 2],
+            neighbor_adjacency: Some(Box::new(NeighborAdjacency::from_edges(
+                3,
+                &[(0, 1), (0, 2), (1, 2)],
+            ))),
+        }
+    }
+
+    #[test]
+    fn partitions_split_the_colorings_of_a_triangle() {
+        let star = triangle();
+        assert_eq!(star.validate(), Ok(()));
+        // Root := 2 fixes neighbor 1 to {1}, which fixes neighbor 0 to {0}, which leaves
+        // neighbor 2 without a color.
+        assert_eq!(apply_partition_cyclic(&star, &[0b0011, 0b0100]).len(), 1);
+        for partition in partitions_of_colors(star.root_colors) {
+            let branches = apply_partition_cyclic(&star, &partition);
+            let total: usize = branches.iter().map(count_colorings).sum();
+            assert_eq!(total, count_colorings(&star), "{partition:?}");
+        }
+        let acyclic = Star {
+            neighbor_adjacency: None,
+            ..star.clone()
+        };
+        assert!(count_colorings(&acyclic) > count_colorings(&star));
+    }
+
+    #[test]
+    fn propagation_shrinks_the_root_of_a_branch() {
+        // In the branch root := {0, 1}, neighbor 0 fixed to {1} removes 1 from the root, which
+        // is then fixed to {0} and fixes the other neighbors to {2}. In the branch root := {2},
+        // neighbor 1 has no color left.
+        let star = Star {
+            root_colors: 0b0111,
+            neighbor_colors: vec![0b0010, 0b0110, 0b0101],
+            neighbor_halfedges: vec![2, 2, 2],
+            neighbor_adjacency: Some(Box::new(NeighborAdjacency::from_edges(3, &[(0, 1)]))),
+        };
+        let branches = apply_partition_cyclic(&star, &[0b0011, 0b0100]);
+        assert_eq!(branches.len(), 1);
+        assert_eq!(branches[0].root_colors, 0b0001);
+        assert_eq!(branches[0].neighbor_colors, vec![0b0010, 0b0100, 0b0100]);
+    }
+
+    #[test]
+    fn generate_cyclic_stars_finds_triangles_once() {
+        let all = generate_cyclic_stars(3, 3);
+        assert!(all.iter().all(|star| star.validate().is_ok()));
+        let acyclic = all
+            .iter()
+            .filter(|star| star.neighbor_adjacency.is_none())
+            .count();
+        assert_eq!(acyclic, generate_stars(3).len());
+
+        let full = Star {
+            root_colors: 0b1111,
+            neighbor_colors: vec![0b1111; 3],
+            neighbor_halfedges: vec![2; 3],
+            neighbor_adjacency: None,
+        };
+        let edge_counts: Vec<usize> = all
+            .iter()
+            .filter(|star| star.neighbor_colors == full.neighbor_colors)
+            .filter(|star| star.root_colors == full.root_colors)
+            .filter(|star| star.neighbor_halfedges == full.neighbor_halfedges)
+            .map(|star| {
+                star.neighbor_adjacency
+                    .as_ref()
+                    .map_or(0, |adjacency| adjacency.edge_count())
+            })
+            .collect();
+        // No edges, one edge, a path, and the triangle.
+        assert_eq!(edge_counts, vec![0, 1, 2, 3]);
+    }
+}
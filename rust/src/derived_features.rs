@@ -0,0 +1,271 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::list_coloring_utils::{NodeFeatures, star_list_degree_counts};
+use crate::number_format::format_json_float;
+use crate::star_utils::Star;
+
+/// A scalar feature of a star beyond the per-vertex counts of
+/// [`crate::list_coloring_utils::NodeFeatures`].
+pub trait DerivedFeature: Sync {
+    /// Name used as the JSON key.
+    fn name(&self) -> String;
+
+    fn compute(&self, star: &Star) -> f64;
+}
+
+/// Number of unordered pairs of neighbors whose color lists intersect.
+pub struct IntersectingNeighborPairs;
+
+impl DerivedFeature for IntersectingNeighborPairs {
+    fn name(&self) -> String {
+        "intersecting_neighbor_pairs".to_string()
+    }
+
+    fn compute(&self, star: &Star) -> f64 {
+        let colors = &star.neighbor_colors;
+        let mut count = 0;
+        for i in 0..colors.len() {
+            for j in (i + 1)..colors.len() {
+                if (colors[i] & colors[j]) != 0 {
+                    count += 1;
+                }
+            }
+        }
+        count as f64
+    }
+}
+
+/// Number of unordered pairs of neighbors with identical color lists.
+pub struct EqualListNeighborPairs;
+
+impl DerivedFeature for EqualListNeighborPairs {
+    fn name(&self) -> String {
+        "equal_list_neighbor_pairs".to_string()
+    }
+
+    fn compute(&self, star: &Star) -> f64 {
+        let colors = &star.neighbor_colors;
+        let mut count = 0;
+        for i in 0..colors.len() {
+            for j in (i + 1)..colors.len() {
+                if colors[i] == colors[j] {
+                    count += 1;
+                }
+            }
+        }
+        count as f64
+    }
+}
+
+/// Number of root colors that appear in at least `k` neighbor lists.
+pub struct RootColorsInAtLeast(pub usize);
+
+impl DerivedFeature for RootColorsInAtLeast {
+    fn name(&self) -> String {
+        format!("root_colors_in_ge{}", self.0)
+    }
+
+    fn compute(&self, star: &Star) -> f64 {
+        let mut count = 0;
+        for bit_idx in 0..8u8 {
+            let bit = 1u8 << bit_idx;
+            if (star.root_colors & bit) == 0 {
+                continue;
+            }
+            let occurrences = star
+                .neighbor_colors
+                .iter()
+                .filter(|&&c| (c & bit) != 0)
+                .count();
+            if occurrences >= self.0 {
+                count += 1;
+            }
+        }
+        count as f64
+    }
+}
+
+/// The derived features computed by default.
+pub static DEFAULT_DERIVED_FEATURES: &[&dyn DerivedFeature] = &[
+    &IntersectingNeighborPairs,
+    &EqualListNeighborPairs,
+    &RootColorsInAtLeast(2),
+    &RootColorsInAtLeast(3),
+];
+
+/// Values of a list of derived features for one star.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DerivedFeatures {
+    pub names: Vec<String>,
+    pub values: Vec<f64>,
+}
+
+impl DerivedFeatures {
+    /// Returns the value of the feature with the given name.
+    pub fn get(&self, name: &str) -> Option<f64> {
+        let idx = self.names.iter().position(|n| n == name)?;
+        Some(self.values[idx])
+    }
+
+    /// Returns a compact one-line JSON object string with no whitespace.
+    pub fn to_json_string(&self) -> String {
+        self.to_json_string_with_precision(None)
+    }
+
+    /// Like [`DerivedFeatures::to_json_string`], with numbers formatted by [`format_json_float`].
+    pub fn to_json_string_with_precision(&self, precision: Option<usize>) -> String {
+        let fields: Vec<String> = self
+            .names
+            .iter()
+            .zip(self.values.iter())
+            .map(|(name, &value)| format!("\"{name}\":{}", format_json_float(value, precision)))
+            .collect();
+        format!("{{{}}}", fields.join(","))
+    }
+}
+
+/// Computes `features` for the given star.
+pub fn derived_features(star: &Star, features: &[&dyn DerivedFeature]) -> DerivedFeatures {
+    DerivedFeatures {
+        names: features.iter().map(|f| f.name()).collect(),
+        values: features.iter().map(|f| f.compute(star)).collect(),
+    }
+}
+
+/// Features of the edges among the neighbors of `star`: `neighbor_edges`, `triangles` (all
+/// triangles on the root and its neighbors; every neighbor edge closes one with the root) and
+/// `adjacent_intersecting_pairs` (adjacent neighbors whose lists intersect, so they constrain
+/// each other). All zero without [`Star::neighbor_adjacency`].
+pub fn adjacency_features(star: &Star) -> DerivedFeatures {
+    let (edges, triangles, intersecting) = match &star.neighbor_adjacency {
+        Some(adjacency) => (
+            adjacency.edge_count(),
+            adjacency.edge_count() + adjacency.neighbor_triangles(),
+            adjacency
+                .edges()
+                .filter(|&(i, j)| (star.neighbor_colors[i] & star.neighbor_colors[j]) != 0)
+                .count(),
+        ),
+        None => (0, 0, 0),
+    };
+    DerivedFeatures {
+        names: vec![
+            "neighbor_edges".to_string(),
+            "triangles".to_string(),
+            "adjacent_intersecting_pairs".to_string(),
+        ],
+        values: vec![edges as f64, triangles as f64, intersecting as f64],
+    }
+}
+
+/// Maps named feature values into a vector of fixed length `dims` (the "hashing trick"), so
+/// models can consume feature sets that grow over time.
+///
+/// The hash is FNV-1a (64 bit) of the UTF-8 name: the value is added to index
+/// `hash % dims`, negated if bit 63 of the hash is set, so that collisions cancel out in
+/// expectation. Returns an empty vector if `dims == 0`.
+pub fn hash_features<'a>(
+    features: impl IntoIterator<Item = (&'a str, f64)>,
+    dims: usize,
+) -> Vec<f64> {
+    const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let mut out = vec![0.0; dims];
+    if dims == 0 {
+        return out;
+    }
+    for (name, value) in features {
+        let hash = name
+            .bytes()
+            .fold(FNV_OFFSET, |h, b| (h ^ b as u64).wrapping_mul(FNV_PRIME));
+        let sign = if hash >> 63 == 1 { -1.0 } else { 1.0 };
+        out[(hash % dims as u64) as usize] += sign * value;
+    }
+    out
+}
+
+/// Hashes the list/degree counts of `star` together with the derived `features` into a vector
+/// of length `dims`, see [`hash_features`].
+pub fn hashed_star_features(
+    star: &Star,
+    features: &[&dyn DerivedFeature],
+    dims: usize,
+) -> Vec<f64> {
+    let counts = star_list_degree_counts(star).to_array();
+    let derived = derived_features(star, features);
+    let named = NodeFeatures::NAMES.iter().copied().zip(counts).chain(
+        derived
+            .names
+            .iter()
+            .map(|n| n.as_str())
+            .zip(derived.values.iter().copied()),
+    );
+    hash_features(named, dims)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::star_utils::NeighborAdjacency;
+    use alloc::boxed::Box;
+
+    #[test]
+    fn default_derived_features() {
+        let star = Star {
+            root_colors: 0b0111,
+            neighbor_colors: vec![0b0011, 0b0011, 0b0110, 0b1000],
+            neighbor_halfedges: vec![2, 2, 2, 2],
+            neighbor_adjacency: None,
+        };
+        let f = derived_features(&star, DEFAULT_DERIVED_FEATURES);
+        assert_eq!(f.get("intersecting_neighbor_pairs"), Some(3.0));
+        assert_eq!(f.get("equal_list_neighbor_pairs"), Some(1.0));
+        // Color 0 is in 2 lists, color 1 in 3 lists, color 2 in 1 list.
+        assert_eq!(f.get("root_colors_in_ge2"), Some(2.0));
+        assert_eq!(f.get("root_colors_in_ge3"), Some(1.0));
+        assert_eq!(
+            f.to_json_string(),
+            "{\"intersecting_neighbor_pairs\":3,\"equal_list_neighbor_pairs\":1,\"root_colors_in_ge2\":2,\"root_colors_in_ge3\":1}"
+        );
+
+        // A triangle on neighbors 0, 1, 2 and the edge 2-3.
+        assert_eq!(adjacency_features(&star).values, vec![0.0; 3]);
+        let star = Star {
+            neighbor_adjacency: Some(Box::new(NeighborAdjacency::from_edges(
+                4,
+                &[(0, 1), (0, 2), (1, 2), (2, 3)],
+            ))),
+            ..star
+        };
+        assert_eq!(adjacency_features(&star).values, vec![4.0, 5.0, 3.0]);
+    }
+
+    #[test]
+    fn hashed_features_are_stable() {
+        // FNV-1a("a") = 0xaf63dc4c8601ec8c, FNV-1a("b") = 0xaf63df4c8601f1a5.
+        assert_eq!(
+            hash_features([("a", 1.0), ("b", 2.0)], 4),
+            vec![-1.0, -2.0, 0.0, 0.0]
+        );
+        assert!(hash_features([("a", 1.0)], 0).is_empty());
+
+        let star = Star {
+            root_colors: 0b0111,
+            neighbor_colors: vec![0b0011, 0b0110],
+            neighbor_halfedges: vec![2, 3],
+            neighbor_adjacency: None,
+        };
+        let hashed = hashed_star_features(&star, DEFAULT_DERIVED_FEATURES, 64);
+        assert_eq!(hashed.len(), 64);
+        let total: f64 = hashed.iter().map(|x| x.abs()).sum();
+        assert!(total > 0.0);
+        assert_eq!(
+            hashed,
+            hashed_star_features(&star, DEFAULT_DERIVED_FEATURES, 64)
+        );
+    }
+}
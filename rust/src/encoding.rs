@@ -0,0 +1,406 @@
+// Versioned encodings of the crate's artifacts. The functions of a version module are frozen:
+// they keep reading and writing exactly the format they were introduced with, so artifacts
+// archived with an earlier draft stay readable. A format change gets a new version module,
+// and only formats that changed appear in it. The tests below pin every format to literal
+// strings; they must never be edited, only extended. The frozen functions do not call the
+// live encoders of `star_utils` and `colors`, which may change with the crate.
+
+/// The original formats: the unversioned star strings of
+/// [`star_to_string`](crate::star_utils::star_to_string), the tree JSON of the
+/// `enumerate-trees` binary, and partitions as JSON arrays of block bitmasks, as in the
+/// `partition` field of the artifacts of `proof --json`.
+pub mod v1 {
+    use alloc::format;
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
+
+    use crate::json::{JsonValue, parse_json};
+    use crate::star_utils::{Star, StarError, StarParseError};
+    use crate::tree_utils::Node;
+
+    /// Encodes a star as `star_<degree>_<colors>_0<halfedges>`, with one hex digit per list
+    /// (the root first) and per halfedge count. Stars with a list or a halfedge count that does
+    /// not fit in one hex digit cannot be encoded.
+    pub fn encode_star(star: &Star) -> Result<String, StarError> {
+        if star.neighbor_adjacency.is_some() {
+            return Err(StarError::NeighborEdgesNotEncodable);
+        }
+        let degree = star.neighbor_colors.len();
+        if degree != star.neighbor_halfedges.len() {
+            return Err(StarError::LengthMismatch {
+                colors: degree,
+                halfedges: star.neighbor_halfedges.len(),
+            });
+        }
+        if star.root_colors > 0xf {
+            return Err(StarError::ColorsOutOfRange {
+                vertex: None,
+                colors: star.root_colors,
+            });
+        }
+        if let Some((i, &colors)) = star
+            .neighbor_colors
+            .iter()
+            .enumerate()
+            .find(|&(_, &c)| c > 0xf)
+        {
+            return Err(StarError::ColorsOutOfRange {
+                vertex: Some(i),
+                colors,
+            });
+        }
+        if let Some((neighbor, &halfedges)) = star
+            .neighbor_halfedges
+            .iter()
+            .enumerate()
+            .find(|&(_, &h)| h > 0xf)
+        {
+            return Err(StarError::TooManyHalfedges {
+                neighbor,
+                halfedges,
+                max: 0xf,
+            });
+        }
+        let mut s = format!("star_{degree}_{:x}", star.root_colors);
+        for c in &star.neighbor_colors {
+            s.push_str(&format!("{c:x}"));
+        }
+        s.push_str("_0");
+        for h in &star.neighbor_halfedges {
+            s.push_str(&format!("{h:x}"));
+        }
+        Ok(s)
+    }
+
+    /// Decodes a star written by [`encode_star`]; versioned strings are rejected. The degree
+    /// is written in decimal without leading zeros.
+    pub fn decode_star(s: &str) -> Result<Star, StarParseError> {
+        let s = s.trim();
+        let malformed = || StarParseError::Malformed(s.to_string());
+        let nibbles = |digits: &str| -> Result<Vec<u8>, StarParseError> {
+            digits
+                .chars()
+                .map(|c| c.to_digit(16).map(|d| d as u8).ok_or_else(malformed))
+                .collect()
+        };
+
+        let mut parts = s.split('_');
+        if parts.next() != Some("star") {
+            return Err(malformed());
+        }
+        let degree: usize = parts
+            .next()
+            .filter(|d| d.bytes().all(|b| b.is_ascii_digit()) && (*d == "0" || !d.starts_with('0')))
+            .and_then(|d| d.parse().ok())
+            .ok_or_else(malformed)?;
+        let colors = nibbles(parts.next().ok_or_else(malformed)?)?;
+        let halfedges = nibbles(parts.next().ok_or_else(malformed)?)?;
+        if parts.next().is_some()
+            || colors.len() != degree + 1
+            || halfedges.len() != degree + 1
+            || halfedges[0] != 0
+        {
+            return Err(malformed());
+        }
+        Ok(Star {
+            root_colors: colors[0],
+            neighbor_colors: colors[1..].to_vec(),
+            neighbor_halfedges: halfedges[1..].to_vec(),
+            neighbor_adjacency: None,
+        })
+    }
+
+    /// Encodes a tree as nested JSON objects
+    /// `{"colors":<list>,"halfedges":<count>,"children":[...]}`, without whitespace.
+    pub fn encode_tree(node: &Node) -> String {
+        let children: Vec<String> = node.children.iter().map(encode_tree).collect();
+        format!(
+            "{{\"colors\":{},\"halfedges\":{},\"children\":[{}]}}",
+            node.colors,
+            node.halfedges,
+            children.join(",")
+        )
+    }
+
+    /// Decodes a tree written by [`encode_tree`]. Whitespace and the order of the keys do not
+    /// matter; other keys are ignored.
+    pub fn decode_tree(s: &str) -> Result<Node, String> {
+        fn node(v: &JsonValue) -> Result<Node, String> {
+            let byte = |key: &str| {
+                v.get(key)
+                    .and_then(JsonValue::as_f64)
+                    .filter(|&x| (0.0..=255.0).contains(&x) && x as u8 as f64 == x)
+                    .map(|x| x as u8)
+                    .ok_or_else(|| format!("field \"{key}\" is not an integer in 0..=255"))
+            };
+            let children = v
+                .get("children")
+                .and_then(JsonValue::as_array)
+                .ok_or("field \"children\" is not an array")?;
+            Ok(Node {
+                colors: byte("colors")?,
+                halfedges: byte("halfedges")?,
+                children: children.iter().map(node).collect::<Result<_, _>>()?,
+            })
+        }
+        node(&parse_json(s)?)
+    }
+
+    /// Encodes a partition as a JSON array of the blocks as bitmasks, e.g. `[3,4]`.
+    pub fn encode_partition(partition: &[u8]) -> String {
+        let blocks: Vec<String> = partition.iter().map(|b| b.to_string()).collect();
+        format!("[{}]", blocks.join(","))
+    }
+
+    /// Decodes a partition written by [`encode_partition`]. The blocks are not checked to be
+    /// disjoint.
+    pub fn decode_partition(s: &str) -> Result<Vec<u8>, String> {
+        let inner = s
+            .trim()
+            .strip_prefix('[')
+            .and_then(|rest| rest.strip_suffix(']'))
+            .ok_or_else(|| format!("malformed partition: {s}"))?;
+        if inner.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        inner
+            .split(',')
+            .map(|b| b.trim().parse().map_err(|_| format!("invalid block: {b}")))
+            .collect()
+    }
+}
+
+/// The formats that changed since [`v1`]: star strings with a version prefix and a checksum,
+/// and partitions written as sets of colors, as in the Markdown and LaTeX reports.
+pub mod v2 {
+    use alloc::format;
+    use alloc::string::{String, ToString};
+    use alloc::vec::Vec;
+
+    use super::v1;
+    use crate::star_utils::{Star, StarError, StarParseError};
+
+    const PREFIX: &str = "v2:";
+
+    /// FNV-1a over `bytes`, xor-folded to 16 bits.
+    fn checksum(bytes: &[u8]) -> u16 {
+        let mut hash: u32 = 0x811c9dc5;
+        for &b in bytes {
+            hash ^= b as u32;
+            hash = hash.wrapping_mul(0x01000193);
+        }
+        ((hash >> 16) ^ (hash & 0xffff)) as u16
+    }
+
+    /// Encodes a star as `v2:<v1 string>#<checksum>`, where the checksum is four hex digits
+    /// computed from the v1 string. Stars that [`v1::encode_star`] cannot encode are rejected.
+    pub fn encode_star(star: &Star) -> Result<String, StarError> {
+        let body = v1::encode_star(star)?;
+        Ok(format!("{PREFIX}{body}#{:04x}", checksum(body.as_bytes())))
+    }
+
+    /// Decodes a star written by [`encode_star`], checking the checksum; strings without the
+    /// version prefix are rejected.
+    pub fn decode_star(s: &str) -> Result<Star, StarParseError> {
+        let s = s.trim();
+        let Some(rest) = s.strip_prefix(PREFIX) else {
+            return Err(StarParseError::Malformed(s.to_string()));
+        };
+        let Some((body, found)) = rest.rsplit_once('#') else {
+            return Err(StarParseError::ChecksumMismatch {
+                expected: format!("{:04x}", checksum(rest.as_bytes())),
+                found: String::new(),
+            });
+        };
+        let expected = format!("{:04x}", checksum(body.as_bytes()));
+        if expected != found {
+            return Err(StarParseError::ChecksumMismatch {
+                expected,
+                found: found.to_string(),
+            });
+        }
+        v1::decode_star(body)
+    }
+
+    /// Encodes a partition as its blocks separated by ` | `, e.g. `{0,1} | {2}`.
+    pub fn encode_partition(partition: &[u8]) -> String {
+        let blocks: Vec<String> = partition
+            .iter()
+            .map(|&block| {
+                let colors: Vec<String> = (0..8)
+                    .filter(|c| block & 1 << c != 0)
+                    .map(|c| c.to_string())
+                    .collect();
+                format!("{{{}}}", colors.join(","))
+            })
+            .collect();
+        blocks.join(" | ")
+    }
+
+    /// Decodes a partition written by [`encode_partition`]. Whitespace does not matter; the
+    /// blocks are not checked to be disjoint.
+    pub fn decode_partition(s: &str) -> Result<Vec<u8>, String> {
+        s.split('|')
+            .map(|block| {
+                let inner = block
+                    .trim()
+                    .strip_prefix('{')
+                    .and_then(|rest| rest.strip_suffix('}'))
+                    .ok_or_else(|| format!("malformed block: {}", block.trim()))?;
+                inner
+                    .split(',')
+                    .filter(|c| !c.trim().is_empty())
+                    .try_fold(0u8, |set, c| match c.trim().parse::<u8>() {
+                        Ok(c) if c < 8 => Ok(set | 1 << c),
+                        _ => Err(format!("invalid color: {}", c.trim())),
+                    })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::star_utils::Star;
+    use crate::tree_utils::Node;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    fn star() -> Star {
+        Star {
+            root_colors: 0b0111,
+            neighbor_colors: vec![0b0011, 0b0110],
+            neighbor_halfedges: vec![2, 3],
+            neighbor_adjacency: None,
+        }
+    }
+
+    #[test]
+    fn archived_encodings_stay_readable() {
+        assert_eq!(super::v1::encode_star(&star()).unwrap(), "star_2_736_023");
+        assert_eq!(super::v1::decode_star("star_2_736_023"), Ok(star()));
+        assert!(super::v1::decode_star(&super::v2::encode_star(&star()).unwrap()).is_err());
+        assert_eq!(
+            super::v2::encode_star(&star()).unwrap(),
+            "v2:star_2_736_023#02e7"
+        );
+        assert_eq!(super::v2::decode_star("v2:star_2_736_023#02e7"), Ok(star()));
+        assert!(super::v2::decode_star("star_2_736_023").is_err());
+
+        let tree = Node {
+            colors: 3,
+            halfedges: 0,
+            children: vec![Node {
+                colors: 6,
+                halfedges: 2,
+                children: Vec::new(),
+            }],
+        };
+        let json =
+            r#"{"colors":3,"halfedges":0,"children":[{"colors":6,"halfedges":2,"children":[]}]}"#;
+        assert_eq!(super::v1::encode_tree(&tree), json);
+        assert_eq!(super::v1::decode_tree(json), Ok(tree.clone()));
+        let spaced = r#"{ "children": [ {"children":[], "halfedges":2, "colors":6} ],
+            "halfedges": 0, "colors": 3 }"#;
+        assert_eq!(super::v1::decode_tree(spaced), Ok(tree));
+        assert!(super::v1::decode_tree(r#"{"colors":300,"halfedges":0,"children":[]}"#).is_err());
+
+        let partition = [0b0011, 0b0100];
+        assert_eq!(super::v1::encode_partition(&partition), "[3,4]");
+        assert_eq!(
+            super::v1::decode_partition(" [3, 4] "),
+            Ok(partition.to_vec())
+        );
+        assert_eq!(super::v2::encode_partition(&partition), "{0,1} | {2}");
+        assert_eq!(
+            super::v2::decode_partition("{0,1}|{2}"),
+            Ok(partition.to_vec())
+        );
+        assert!(super::v2::decode_partition("{0,1} | {9}").is_err());
+    }
+
+    #[test]
+    fn frozen_encoders_match_golden_strings() {
+        use super::{v1, v2};
+        use crate::star_utils::{StarError, StarParseError};
+
+        let theta = Star {
+            root_colors: 0b0111,
+            neighbor_colors: vec![0b0111; 3],
+            neighbor_halfedges: vec![2; 3],
+            neighbor_adjacency: None,
+        };
+        assert_eq!(v1::encode_star(&theta).unwrap(), "star_3_7777_0222");
+        assert_eq!(v2::encode_star(&theta).unwrap(), "v2:star_3_7777_0222#62ba");
+        let wide = Star {
+            root_colors: 0b1111,
+            neighbor_colors: vec![0b1001; 10],
+            neighbor_halfedges: vec![15; 10],
+            neighbor_adjacency: None,
+        };
+        assert_eq!(
+            v1::encode_star(&wide).unwrap(),
+            "star_10_f9999999999_0ffffffffff"
+        );
+        assert_eq!(v1::decode_star("star_10_f9999999999_0ffffffffff"), Ok(wide));
+        let empty = Star {
+            root_colors: 0b0001,
+            neighbor_colors: Vec::new(),
+            neighbor_halfedges: Vec::new(),
+            neighbor_adjacency: None,
+        };
+        assert_eq!(v1::encode_star(&empty).unwrap(), "star_0_1_0");
+        assert_eq!(v2::encode_star(&empty).unwrap(), "v2:star_0_1_0#d969");
+
+        let mut too_wide = star();
+        too_wide.neighbor_halfedges[1] = 16;
+        assert_eq!(
+            v1::encode_star(&too_wide),
+            Err(StarError::TooManyHalfedges {
+                neighbor: 1,
+                halfedges: 16,
+                max: 15
+            })
+        );
+        too_wide.root_colors = 0x10;
+        assert_eq!(
+            v2::encode_star(&too_wide),
+            Err(StarError::ColorsOutOfRange {
+                vertex: None,
+                colors: 0x10
+            })
+        );
+        for s in [
+            "star_02_736_023",
+            "star_2_736_123",
+            "star_2_736_02",
+            "v1:star_0_1_0",
+        ] {
+            assert!(v1::decode_star(s).is_err(), "{s}");
+        }
+        assert_eq!(
+            v2::decode_star("v2:star_2_736_023#02e8"),
+            Err(StarParseError::ChecksumMismatch {
+                expected: "02e7".into(),
+                found: "02e8".into()
+            })
+        );
+
+        assert_eq!(v1::encode_partition(&[]), "[]");
+        assert_eq!(v1::decode_partition("[]"), Ok(Vec::new()));
+        assert_eq!(v1::encode_partition(&[0b1000, 0b0101, 0b0010]), "[8,5,2]");
+        assert_eq!(
+            v2::encode_partition(&[0b1000, 0b0101, 0b0010]),
+            "{3} | {0,2} | {1}"
+        );
+        assert_eq!(v2::encode_partition(&[0b1111]), "{0,1,2,3}");
+        assert_eq!(
+            v1::encode_tree(&Node {
+                colors: 15,
+                halfedges: 1,
+                children: Vec::new()
+            }),
+            r#"{"colors":15,"halfedges":1,"children":[]}"#
+        );
+    }
+}
@@ -0,0 +1,133 @@
+use alloc::vec::Vec;
+
+use crate::colors::subsets;
+use crate::list_coloring_utils::partitions_of_colors;
+use crate::star_utils::{Star, nondecreasing_sequences};
+
+// Small-parameter exhaustive enumerations for tests. Unlike `generate_stars`, which only
+// produces the stars of an analysis up to renaming colors, these cover every star and every
+// partition within the bounds, including the degenerate ones that branching and reductions
+// produce. Their size grows exponentially, so keep the bounds small.
+
+/// Every star with at most `max_degree` neighbors and lists within the first `colors` colors:
+/// every nonempty root list, and neighbors with every list (including empty and singleton
+/// lists) and every halfedge count in `1..=max_degree`, up to reordering the neighbors.
+/// Ordered by degree, then by root list.
+///
+/// For example, there are 20475 stars for `max_degree = 3` and `colors = 3`.
+pub fn all_stars_up_to(max_degree: usize, colors: u8) -> impl Iterator<Item = Star> {
+    let universe = (1u8 << colors) - 1;
+    let types: Vec<(u8, u8)> = subsets(universe)
+        .flat_map(|list| (1..=max_degree as u8).map(move |h| (list, h)))
+        .collect();
+    (0..=max_degree).flat_map(move |degree| {
+        let types = types.clone();
+        subsets(universe)
+            .filter(|&root| root != 0)
+            .flat_map(move |root_colors| {
+                let types = types.clone();
+                let choices = match types.len() {
+                    0 if degree > 0 => Vec::new(),
+                    0 => alloc::vec![Vec::new()],
+                    n => nondecreasing_sequences(n - 1, degree),
+                };
+                choices.into_iter().map(move |choice| {
+                    let (neighbor_colors, neighbor_halfedges) =
+                        choice.iter().map(|&idx| types[idx]).unzip();
+                    Star {
+                        root_colors,
+                        neighbor_colors,
+                        neighbor_halfedges,
+                        neighbor_adjacency: None,
+                    }
+                })
+            })
+    })
+}
+
+/// Every color list within the first `colors` colors, including the empty one, together with
+/// every partition of it into nonempty blocks as in [`partitions_of_colors`].
+pub fn all_partitions_up_to(colors: u8) -> impl Iterator<Item = (u8, Vec<u8>)> {
+    subsets((1u8 << colors) - 1).flat_map(|list| {
+        partitions_of_colors(list)
+            .into_iter()
+            .map(move |partition| (list, partition))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::colors::colors;
+    use crate::list_coloring_utils::{
+        Branch, MaxDegree, apply_list_coloring_partition, apply_list_coloring_partition_unreduced,
+        is_infeasible,
+    };
+    use crate::reductions::{
+        CappedDuplicateTwoLists, DEFAULT_REDUCTIONS, Reduction, verify_reduction,
+    };
+    use crate::star_utils::StarError;
+
+    /// The number of list colorings of `star`.
+    fn colorings(star: &Star) -> usize {
+        colors(star.root_colors)
+            .map(|c| {
+                star.neighbor_colors
+                    .iter()
+                    .map(|&list| (list & !(1 << c)).count_ones() as usize)
+                    .product::<usize>()
+            })
+            .sum()
+    }
+
+    #[test]
+    fn partitions_cover_their_lists_exactly() {
+        let mut count = 0;
+        for (list, partition) in all_partitions_up_to(4) {
+            assert!(partition.iter().all(|&block| block != 0));
+            assert_eq!(partition.iter().fold(0, |union, &b| union | b), list);
+            let sizes: u32 = partition.iter().map(|b| b.count_ones()).sum();
+            assert_eq!(sizes, list.count_ones());
+            count += 1;
+        }
+        // The Bell numbers B(0), ..., B(4) times the number of lists of each size.
+        assert_eq!(count, 1 + 4 + 6 * 2 + 4 * 5 + 15);
+    }
+
+    #[test]
+    fn branching_and_reductions_are_correct_on_all_small_stars() {
+        let capped = CappedDuplicateTwoLists(MaxDegree::default());
+        let mut reductions: Vec<&dyn Reduction> = DEFAULT_REDUCTIONS.to_vec();
+        reductions.push(&capped);
+        let mut stars = 0;
+        for star in all_stars_up_to(3, 3) {
+            stars += 1;
+            for reduction in reductions.iter() {
+                assert_ne!(verify_reduction(*reduction, &star), Some(false), "{star:?}");
+                if let Some(reduced) = reduction.apply(&star) {
+                    // Infeasible stars keep their empty lists.
+                    match reduced.validate() {
+                        Err(StarError::EmptyList { .. }) if is_infeasible(&star) => {}
+                        result => assert_eq!(result, Ok(()), "{star:?}"),
+                    }
+                }
+            }
+            for partition in partitions_of_colors(star.root_colors) {
+                // The blocks split the colorings of the star by the color of the root.
+                let branches = apply_list_coloring_partition_unreduced(&star, &partition);
+                assert_eq!(
+                    branches.iter().map(colorings).sum::<usize>(),
+                    colorings(&star),
+                    "{star:?} {partition:?}"
+                );
+                let reduced = apply_list_coloring_partition(&star, &partition);
+                for (branch, outcome) in branches.iter().zip(reduced) {
+                    if outcome == Branch::Infeasible {
+                        assert_eq!(colorings(branch), 0, "{star:?} {partition:?}");
+                    }
+                }
+            }
+        }
+        assert_eq!(stars, 20475);
+    }
+}
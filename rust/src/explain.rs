@@ -0,0 +1,238 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::analysis::{AnalysisConfig, best_partition, star_measure};
+use crate::colors::{fmt_colors, fmt_partition};
+use crate::list_coloring_utils::{
+    FeatureSchema, NodeFeatures, apply_list_coloring_partition_unreduced, is_infeasible,
+    star_features,
+};
+use crate::number_format::format_float;
+use crate::reductions::reduce_to_fixpoint_with_trace;
+use crate::star_utils::{Star, star_label};
+
+/// The recursion tree obtained by repeatedly branching on the best partition of the root list.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BranchTree {
+    pub star: Star,
+    pub features: NodeFeatures,
+    pub measure: f64,
+    /// The best partition and its branching number; `None` once the root list is a singleton
+    /// or some list is empty.
+    pub partition: Option<(Vec<u8>, f64)>,
+    pub children: Vec<BranchTreeEdge>,
+}
+
+/// One branch of a [`BranchTree`] node.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BranchTreeEdge {
+    /// The block of the partition that became the root list.
+    pub root_block: u8,
+    /// Names of the reductions applied to the branch, in order.
+    pub reductions: Vec<&'static str>,
+    /// Features of the parent minus features of the branch.
+    pub delta: NodeFeatures,
+    /// Drop in the measure, i.e. `weights * delta`.
+    pub drop: f64,
+    pub subtree: BranchTree,
+}
+
+/// Builds the full branch tree of `star` under `weights`, measuring stars with the degree
+/// buckets of `config` and reducing branches with its reductions.
+///
+/// Every node branches on its [`best_partition`] under `config`. Since each branch strictly shrinks the root
+/// list, the depth is less than the size of the root list.
+pub fn branch_tree(star: &Star, weights: NodeFeatures, config: &AnalysisConfig) -> BranchTree {
+    let max_degree = config.max_degree();
+    let features = star_features(star, FeatureSchema::Counts, max_degree);
+    let partition = if is_infeasible(star) {
+        None
+    } else {
+        best_partition(star, weights, config)
+    };
+
+    let mut children: Vec<BranchTreeEdge> = Vec::new();
+    if let Some((blocks, _)) = partition.as_ref() {
+        for branch in apply_list_coloring_partition_unreduced(star, blocks) {
+            let (reduced, reductions) = if is_infeasible(&branch) {
+                (branch.clone(), Vec::new())
+            } else {
+                reduce_to_fixpoint_with_trace(&branch, config.reductions)
+            };
+            let subtree = branch_tree(&reduced, weights, config);
+            let delta = features - subtree.features;
+            children.push(BranchTreeEdge {
+                root_block: branch.root_colors,
+                reductions,
+                delta,
+                drop: weights * delta,
+                subtree,
+            });
+        }
+    }
+
+    BranchTree {
+        star: star.clone(),
+        features,
+        measure: star_measure(star, weights, max_degree),
+        partition,
+        children,
+    }
+}
+
+fn node_summary(tree: &BranchTree, precision: Option<usize>) -> String {
+    let mut s = format!(
+        "{} measure={}",
+        star_label(&tree.star),
+        format_float(tree.measure, precision)
+    );
+    if let Some((partition, bn)) = tree.partition.as_ref() {
+        s.push_str(&format!(
+            " partition={} branching_number={}",
+            fmt_partition(partition),
+            format_float(*bn, precision)
+        ));
+    }
+    s
+}
+
+fn edge_summary(edge: &BranchTreeEdge, precision: Option<usize>) -> String {
+    let reductions = if edge.reductions.is_empty() {
+        "none".to_string()
+    } else {
+        edge.reductions.join(", ")
+    };
+    format!(
+        "root:={} drop={} reductions: {}",
+        fmt_colors(edge.root_block),
+        format_float(edge.drop, precision),
+        reductions
+    )
+}
+
+/// Renders the tree as indented plain text, one line per node and per branch.
+///
+/// Numbers are formatted by [`format_float`] with the given `precision`.
+pub fn branch_tree_to_text(tree: &BranchTree, precision: Option<usize>) -> String {
+    fn go(tree: &BranchTree, precision: Option<usize>, depth: usize, out: &mut String) {
+        out.push_str(&"  ".repeat(2 * depth));
+        out.push_str(&node_summary(tree, precision));
+        out.push('\n');
+        for edge in tree.children.iter() {
+            out.push_str(&"  ".repeat(2 * depth + 1));
+            out.push_str(&edge_summary(edge, precision));
+            out.push_str(" delta=");
+            out.push_str(&edge.delta.to_json_string_with_precision(precision));
+            out.push('\n');
+            go(&edge.subtree, precision, depth + 1, out);
+        }
+    }
+
+    let mut out = String::new();
+    go(tree, precision, 0, &mut out);
+    out
+}
+
+/// Renders the tree as a self-contained HTML page with collapsible nodes.
+///
+/// Numbers are formatted by [`format_float`] with the given `precision`.
+pub fn branch_tree_to_html(tree: &BranchTree, precision: Option<usize>) -> String {
+    fn escape(s: &str) -> String {
+        s.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    }
+
+    fn go(tree: &BranchTree, precision: Option<usize>, out: &mut String) {
+        out.push_str("<details open><summary><code>");
+        out.push_str(&escape(&node_summary(tree, precision)));
+        out.push_str("</code></summary>\n<div class=\"features\">features: <code>");
+        out.push_str(&escape(
+            &tree.features.to_json_string_with_precision(precision),
+        ));
+        out.push_str("</code></div>\n");
+        if !tree.children.is_empty() {
+            out.push_str("<ul>\n");
+            for edge in tree.children.iter() {
+                out.push_str("<li><div class=\"edge\">");
+                out.push_str(&escape(&edge_summary(edge, precision)));
+                out.push_str("<br>delta: <code>");
+                out.push_str(&escape(
+                    &edge.delta.to_json_string_with_precision(precision),
+                ));
+                out.push_str("</code></div>\n");
+                go(&edge.subtree, precision, out);
+                out.push_str("</li>\n");
+            }
+            out.push_str("</ul>\n");
+        }
+        out.push_str("</details>\n");
+    }
+
+    let title = escape(&star_label(&tree.star));
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str(&format!("<title>Branch tree of {title}</title>\n"));
+    out.push_str(
+        "<style>\
+         body{font-family:sans-serif}\
+         ul{list-style:none;border-left:1px solid #ccc;margin-left:0.5em;padding-left:1em}\
+         .edge{color:#555;margin-top:0.5em}\
+         .features{font-size:small;color:#777}\
+         </style>\n",
+    );
+    out.push_str("</head>\n<body>\n");
+    out.push_str(&format!("<h1>Branch tree of <code>{title}</code></h1>\n"));
+    go(tree, precision, &mut out);
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn count_nodes(tree: &BranchTree) -> usize {
+        1 + tree
+            .children
+            .iter()
+            .map(|e| count_nodes(&e.subtree))
+            .sum::<usize>()
+    }
+
+    #[test]
+    fn branch_tree_records_reductions_and_deltas() {
+        let weights = NodeFeatures::from_array([1.0, 1.0, 1.0, 0.7, 0.7, 0.7, 0.4, 0.4, 0.4]);
+        let star = Star {
+            root_colors: 0b0111,
+            neighbor_colors: vec![0b0011, 0b0011, 0b0110],
+            neighbor_halfedges: vec![2, 2, 2],
+            neighbor_adjacency: None,
+        };
+        let tree = branch_tree(&star, weights, &AnalysisConfig::default());
+        let (partition, _) = tree.partition.clone().expect("root has 3 colors");
+        assert_eq!(tree.children.len(), partition.len());
+
+        for edge in tree.children.iter() {
+            assert_eq!(edge.delta, tree.features - edge.subtree.features);
+            assert!((edge.drop - (tree.measure - edge.subtree.measure)).abs() < 1e-12);
+            assert!(edge.subtree.star.root_colors.count_ones() < 3);
+        }
+
+        let html = branch_tree_to_html(&tree, None);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert_eq!(html.matches("<details").count(), count_nodes(&tree));
+        assert_eq!(
+            branch_tree_to_text(&tree, None).lines().count(),
+            2 * count_nodes(&tree) - 1
+        );
+
+        // With Δ = 3, the neighbors of degree 3 are in the top bucket.
+        let cubic = branch_tree(&star, weights, &AnalysisConfig::for_max_degree(3));
+        assert_ne!(cubic.features, tree.features);
+        assert_eq!(cubic.features.n2_ge5, 3.0);
+    }
+}
@@ -0,0 +1,243 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::case_set::canonical_id;
+use crate::coverage::csv_fields;
+use crate::star_utils::{Star, star_from_string, star_to_json_string};
+
+/// Per-star data computed outside the crate, keyed by [`canonical_id`], so that a row matches
+/// every star of its isomorphism class regardless of how the table wrote the star.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FeatureTable {
+    /// The names of the data columns, in table order, without the `star` column.
+    pub columns: Vec<String>,
+    /// The values of the data columns by canonical ID; empty fields are empty strings.
+    pub rows: BTreeMap<String, Vec<String>>,
+}
+
+impl FeatureTable {
+    /// The values of the row of `star`, in the order of [`FeatureTable::columns`].
+    pub fn get(&self, star: &Star) -> Option<&[String]> {
+        self.rows.get(&canonical_id(star)).map(Vec::as_slice)
+    }
+}
+
+/// Reads a feature table in CSV format.
+///
+/// The first line is a header naming the columns; the column `star` (a v1 or v2 star string)
+/// is required, all others are data columns. Empty lines and lines starting with `#` are
+/// skipped, and fields may be quoted as in [`parse_case_list`](crate::coverage::parse_case_list).
+/// Two rows for the same star up to isomorphism are an error, since the join could not tell
+/// which one is meant:
+///
+/// ```text
+/// star,tree_count,source
+/// star_3_ffff_0222,17,sage
+/// ```
+pub fn parse_feature_table(csv: &str) -> Result<FeatureTable, String> {
+    let mut lines = csv
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty() && !l.trim_start().starts_with('#'));
+    let Some((i, header)) = lines.next() else {
+        return Err("missing header line".to_string());
+    };
+    let header = csv_fields(header).map_err(|e| format!("line {}: {e}", i + 1))?;
+    let star_col = header
+        .iter()
+        .position(|h| h == "star")
+        .ok_or("missing column \"star\"")?;
+
+    let mut table = FeatureTable {
+        columns: header
+            .iter()
+            .enumerate()
+            .filter(|&(col, _)| col != star_col)
+            .map(|(_, h)| h.clone())
+            .collect(),
+        rows: BTreeMap::new(),
+    };
+    let mut first_line: BTreeMap<String, usize> = BTreeMap::new();
+    for (i, line) in lines {
+        let err = |e: String| format!("line {}: {e}", i + 1);
+        let mut fields = csv_fields(line).map_err(err)?;
+        if fields.len() != header.len() {
+            return Err(err(format!(
+                "{} fields, but the header has {}",
+                fields.len(),
+                header.len()
+            )));
+        }
+        let star = star_from_string(&fields.remove(star_col)).map_err(|e| err(e.to_string()))?;
+        let id = canonical_id(&star);
+        if let Some(first) = first_line.insert(id.clone(), i + 1) {
+            return Err(err(format!(
+                "same star as line {first} (canonical ID {id})"
+            )));
+        }
+        table.rows.insert(id, fields);
+    }
+    Ok(table)
+}
+
+/// Whether `s` is a number in JSON syntax, e.g. `-0.5e3` but not `.5`, `+1` or `01`.
+fn is_json_number(s: &str) -> bool {
+    fn digits(s: &str) -> (&str, &str) {
+        s.split_at(s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len()))
+    }
+    let (int, rest) = digits(s.strip_prefix('-').unwrap_or(s));
+    if int.is_empty() || (int.len() > 1 && int.starts_with('0')) {
+        return false;
+    }
+    let rest = match rest.strip_prefix('.') {
+        Some(frac) => match digits(frac) {
+            ("", _) => return false,
+            (_, rest) => rest,
+        },
+        None => rest,
+    };
+    match rest.strip_prefix(['e', 'E']) {
+        Some(exp) => {
+            let exp = exp.strip_prefix(['+', '-']).unwrap_or(exp);
+            matches!(digits(exp), (d, "") if !d.is_empty())
+        }
+        None => rest.is_empty(),
+    }
+}
+
+/// A JSON value for a table field: a number if it is written as a JSON number, `null` if it is
+/// empty, and a string otherwise.
+fn json_value(field: &str) -> String {
+    if field.is_empty() {
+        return "null".to_string();
+    }
+    if is_json_number(field) {
+        return field.to_string();
+    }
+    json_string(field)
+}
+
+/// `s` as a JSON string, with quotes, backslashes and control characters escaped.
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// One NDJSON record for `star`: the object of
+/// [`star_to_json_string`](crate::star_utils::star_to_json_string) with its canonical ID
+/// under `"id"` and the row of `table` under `"features"`, or `null` if the table has none.
+pub fn join_features(star: &Star, table: &FeatureTable) -> String {
+    let json = star_to_json_string(star);
+    let features = match table.get(star) {
+        Some(values) => {
+            let fields: Vec<String> = table
+                .columns
+                .iter()
+                .zip(values)
+                .map(|(column, value)| format!("{}:{}", json_string(column), json_value(value)))
+                .collect();
+            format!("{{{}}}", fields.join(","))
+        }
+        None => "null".to_string(),
+    };
+    format!(
+        "{{\"id\":{},{},\"features\":{features}}}",
+        json_string(&canonical_id(star)),
+        &json[1..json.len() - 1]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::parse_json;
+    use crate::star_utils::{ColorPermutation, star_to_string};
+    use alloc::vec;
+
+    #[test]
+    fn rows_join_every_star_of_their_class() {
+        let star = Star {
+            root_colors: 0b0111,
+            neighbor_colors: vec![0b0011, 0b0110],
+            neighbor_halfedges: vec![2, 3],
+            neighbor_adjacency: None,
+        };
+        let renamed = star.permute_colors(&ColorPermutation::new(&[1, 0, 3, 2]));
+        let csv = format!(
+            "# computed elsewhere\nstar,count,\"source, tool\"\n{},17,\"sage \"\"9\"\"\"\n",
+            star_to_string(&renamed).unwrap()
+        );
+        let table = parse_feature_table(&csv).unwrap();
+        assert_eq!(table.columns, ["count", "source, tool"]);
+
+        let record = parse_json(&join_features(&star, &table)).unwrap();
+        assert_eq!(
+            record.get("id").and_then(|v| v.as_str()),
+            Some(canonical_id(&star).as_str())
+        );
+        assert_eq!(
+            record.get("root_colors").and_then(|v| v.as_f64()),
+            Some(7.0)
+        );
+        let features = record.get("features").unwrap();
+        assert_eq!(features.get("count").and_then(|v| v.as_f64()), Some(17.0));
+        assert_eq!(
+            features.get("source, tool").and_then(|v| v.as_str()),
+            Some("sage \"9\"")
+        );
+
+        let other = Star {
+            root_colors: 0b0011,
+            ..star.clone()
+        };
+        assert!(join_features(&other, &table).ends_with("\"features\":null}"));
+
+        for (field, number) in [
+            ("-0.5e3", true),
+            (".5", false),
+            ("+1", false),
+            ("01", false),
+        ] {
+            assert_eq!(is_json_number(field), number, "{field}");
+        }
+
+        // Keys are strings even if they look like numbers or are empty.
+        let odd = parse_feature_table(&format!(
+            "star,2019,\n{},1,2\n",
+            star_to_string(&star).unwrap()
+        ))
+        .unwrap();
+        let features = parse_json(&join_features(&star, &odd)).unwrap();
+        let features = features.get("features").unwrap();
+        assert_eq!(features.get("2019").and_then(|v| v.as_f64()), Some(1.0));
+        assert_eq!(features.get("").and_then(|v| v.as_f64()), Some(2.0));
+        // Stars without a star string have their JSON object as ID, escaped as a string.
+        let wide = Star {
+            neighbor_halfedges: vec![2, 20],
+            ..star.clone()
+        };
+        let record = parse_json(&join_features(&wide, &odd)).unwrap();
+        assert_eq!(
+            record.get("id").and_then(|v| v.as_str()),
+            Some(canonical_id(&wide).as_str())
+        );
+
+        let duplicate = format!("{csv}{},18,\n", star_to_string(&star).unwrap());
+        assert!(
+            parse_feature_table(&duplicate)
+                .unwrap_err()
+                .contains("same star as line 3")
+        );
+    }
+}
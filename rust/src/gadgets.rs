@@ -0,0 +1,111 @@
+use alloc::vec;
+
+use crate::colors::ALL_COLORS;
+use crate::star_utils::Star;
+
+// Named stars that recur in the analysis and its write-up, so that tests, the REPL and the
+// paper can refer to them by name instead of by star string.
+
+/// The root and `degree` neighbors all have the full list of 4 colors, and every neighbor has
+/// 2 halfedges. For degree 3 this is `star_3_ffff_0222`, the case C5.3 of the examples.
+pub fn full_lists(degree: usize) -> Star {
+    Star {
+        root_colors: ALL_COLORS,
+        neighbor_colors: vec![ALL_COLORS; degree],
+        neighbor_halfedges: vec![2; degree],
+        neighbor_adjacency: None,
+    }
+}
+
+/// A root with all 4 colors and three neighbors with the 2-lists `{0,1}`, `{2,3}` and `{0,2}`,
+/// each with 2 halfedges: every root color is shared with a neighbor, so no root color is
+/// dominated.
+pub fn k4_root_with_three_2list_neighbors() -> Star {
+    Star {
+        root_colors: ALL_COLORS,
+        neighbor_colors: vec![0b0011, 0b1100, 0b0101],
+        neighbor_halfedges: vec![2, 2, 2],
+        neighbor_adjacency: None,
+    }
+}
+
+/// The star at a branch vertex of the theta graph with three paths of length 2 in a graph of
+/// maximum degree 3: the root and its three neighbors have the list `{0,1,2}`, and every
+/// neighbor has 2 halfedges, one to the other branch vertex and one further edge.
+pub fn theta_configuration() -> Star {
+    Star {
+        root_colors: 0b0111,
+        neighbor_colors: vec![0b0111; 3],
+        neighbor_halfedges: vec![2; 3],
+        neighbor_adjacency: None,
+    }
+}
+
+/// A root with the 2-list `{0,1}` and two neighbors with the same list, which the root forces
+/// to the same color; the smallest star that
+/// [`DuplicateTwoLists`](crate::reductions::DuplicateTwoLists) reduces.
+pub fn duplicate_2lists() -> Star {
+    Star {
+        root_colors: 0b0011,
+        neighbor_colors: vec![0b0011, 0b0011],
+        neighbor_halfedges: vec![2, 2],
+        neighbor_adjacency: None,
+    }
+}
+
+/// A star of the library under a name for the command-line tools.
+pub struct Gadget {
+    pub name: &'static str,
+    pub star: fn() -> Star,
+}
+
+/// All stars of this module, by name.
+pub static GADGETS: &[Gadget] = &[
+    Gadget {
+        name: "full-lists-3",
+        star: || full_lists(3),
+    },
+    Gadget {
+        name: "k4-root-with-three-2list-neighbors",
+        star: k4_root_with_three_2list_neighbors,
+    },
+    Gadget {
+        name: "theta",
+        star: theta_configuration,
+    },
+    Gadget {
+        name: "duplicate-2lists",
+        star: duplicate_2lists,
+    },
+];
+
+/// The star of the gadget called `name`, if there is one.
+pub fn gadget(name: &str) -> Option<Star> {
+    GADGETS.iter().find(|g| g.name == name).map(|g| (g.star)())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reductions::{DominatedRootColors, DuplicateTwoLists, Reduction};
+    use crate::star_utils::star_to_string;
+
+    #[test]
+    fn gadgets_are_the_documented_stars() {
+        for g in GADGETS {
+            assert_eq!((g.star)().validate(), Ok(()), "{}", g.name);
+            assert_eq!(GADGETS.iter().filter(|h| h.name == g.name).count(), 1);
+        }
+        assert_eq!(
+            star_to_string(&gadget("full-lists-3").unwrap()),
+            Ok("star_3_ffff_0222".into())
+        );
+        assert!(DuplicateTwoLists.apply(&duplicate_2lists()).is_some());
+        assert!(
+            DominatedRootColors
+                .apply(&k4_root_with_three_2list_neighbors())
+                .is_none()
+        );
+        assert_eq!(gadget("unknown"), None);
+    }
+}
@@ -0,0 +1,193 @@
+use alloc::vec::Vec;
+
+use crate::analysis::{AnalysisConfig, best_partition, partition_recurrence};
+use crate::colors::colors;
+use crate::list_coloring_utils::{NodeFeatures, NonFiniteFeature};
+use crate::star_utils::{Star, fold_stars_with_config};
+
+// The analysis branches every star on its best partition, which an algorithm can only do by
+// evaluating all partitions at run time. A selector is a rule an implementation can afford
+// instead, and the gap report measures what it costs compared with the analyzed optimum.
+
+/// A rule that chooses the partition of the root list to branch on from the star alone,
+/// without evaluating branching numbers.
+pub trait PartitionSelector: Sync {
+    /// A short identifier, e.g. for command-line flags.
+    fn name(&self) -> &'static str;
+
+    /// The partition to branch on, with at least 2 blocks, or `None` if the root list has
+    /// fewer than 2 colors.
+    fn select(&self, star: &Star) -> Option<Vec<u8>>;
+}
+
+/// Branches on the color class of the root color that occurs in the most neighbor lists, so
+/// the branch coloring the root shrinks as many lists as possible. Ties are broken by the
+/// smaller color.
+pub struct MostSharedColor;
+
+impl PartitionSelector for MostSharedColor {
+    fn name(&self) -> &'static str {
+        "most-shared-color"
+    }
+
+    fn select(&self, star: &Star) -> Option<Vec<u8>> {
+        if star.root_colors.count_ones() < 2 {
+            return None;
+        }
+        let shared = |c: u8| {
+            star.neighbor_colors
+                .iter()
+                .filter(|&&list| list & (1 << c) != 0)
+                .count()
+        };
+        let color = colors(star.root_colors).rev().max_by_key(|&c| shared(c))?;
+        Some(alloc::vec![1 << color, star.root_colors & !(1 << color)])
+    }
+}
+
+/// Branches on every color of the root list, i.e. the partition into singletons.
+pub struct AllSingletons;
+
+impl PartitionSelector for AllSingletons {
+    fn name(&self) -> &'static str {
+        "all-singletons"
+    }
+
+    fn select(&self, star: &Star) -> Option<Vec<u8>> {
+        (star.root_colors.count_ones() >= 2)
+            .then(|| colors(star.root_colors).map(|c| 1 << c).collect())
+    }
+}
+
+/// The selectors known to the command-line tools.
+pub static SELECTORS: &[&dyn PartitionSelector] = &[&MostSharedColor, &AllSingletons];
+
+/// One star of a [`GapReport`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct GapCase {
+    pub star: Star,
+    /// The partition of the selector and its branching number.
+    pub heuristic: (Vec<u8>, f64),
+    /// The best partition and its branching number.
+    pub optimum: (Vec<u8>, f64),
+}
+
+impl GapCase {
+    /// How much worse the selector's branching number is than the optimum; 0 if both are
+    /// infinite.
+    pub fn gap(&self) -> f64 {
+        if self.heuristic.1 == self.optimum.1 {
+            0.0
+        } else {
+            self.heuristic.1 - self.optimum.1
+        }
+    }
+}
+
+/// The gaps between a selector and the best partitions on all stars of an enumeration.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GapReport {
+    pub selector: &'static str,
+    /// One case per star whose root list can be split, in enumeration order.
+    pub cases: Vec<GapCase>,
+}
+
+impl GapReport {
+    /// The number of stars on which the selector is optimal up to `1e-12`.
+    pub fn optimal(&self) -> usize {
+        self.cases.iter().filter(|c| c.gap() <= 1e-12).count()
+    }
+
+    /// The `q`-quantile of the gaps for `q` in `[0, 1]` (the smallest gap at least a `q`
+    /// fraction of the stars reach), or 0 without cases.
+    pub fn quantile(&self, q: f64) -> f64 {
+        let mut gaps: Vec<f64> = self.cases.iter().map(GapCase::gap).collect();
+        gaps.sort_by(f64::total_cmp);
+        let i = ((q * gaps.len() as f64) as usize).min(gaps.len().saturating_sub(1));
+        gaps.get(i).copied().unwrap_or(0.0)
+    }
+
+    /// The `n` cases with the largest gaps, largest first; ties keep the enumeration order.
+    pub fn largest(&self, n: usize) -> Vec<&GapCase> {
+        let mut sorted: Vec<&GapCase> = self.cases.iter().collect();
+        sorted.sort_by(|a, b| b.gap().total_cmp(&a.gap()));
+        sorted.truncate(n);
+        sorted
+    }
+
+    /// The worst branching numbers of the selector and of the best partitions, or 1 without
+    /// cases. The selector matches the analyzed bound if they are equal.
+    pub fn worst(&self) -> (f64, f64) {
+        self.cases.iter().fold((1.0, 1.0), |(h, o), c| {
+            (h.max(c.heuristic.1), o.max(c.optimum.1))
+        })
+    }
+}
+
+/// Runs `selector` on every star of `config` and compares its branching numbers with the ones
+/// of the best partitions under `config`. Fails if some weight is not finite.
+pub fn gap_report(
+    selector: &dyn PartitionSelector,
+    weights: NodeFeatures,
+    degree: usize,
+    config: &AnalysisConfig,
+) -> Result<GapReport, NonFiniteFeature> {
+    weights.validate()?;
+    let cases = fold_stars_with_config(
+        degree,
+        &config.enumeration,
+        Vec::new(),
+        |mut cases, star| {
+            let (Some(partition), Some(optimum)) = (
+                selector.select(&star),
+                best_partition(&star, weights, config),
+            ) else {
+                return cases;
+            };
+            let bn = partition_recurrence(&star, &partition, weights, config).branching_number();
+            cases.push(GapCase {
+                star,
+                heuristic: (partition, bn),
+                optimum,
+            });
+            cases
+        },
+    );
+    Ok(GapReport {
+        selector: selector.name(),
+        cases,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn selectors_are_never_better_than_the_optimum() {
+        let weights = NodeFeatures::from_array([1.0, 1.0, 1.0, 0.7, 0.7, 0.7, 0.4, 0.4, 0.4]);
+        let star = Star {
+            root_colors: 0b0111,
+            neighbor_colors: vec![0b0110, 0b0101, 0b0100],
+            neighbor_halfedges: vec![2, 2, 2],
+            neighbor_adjacency: None,
+        };
+        assert_eq!(MostSharedColor.select(&star), Some(vec![0b0100, 0b0011]));
+        assert_eq!(
+            AllSingletons.select(&star),
+            Some(vec![0b0001, 0b0010, 0b0100])
+        );
+
+        for selector in SELECTORS {
+            let report = gap_report(*selector, weights, 2, &AnalysisConfig::default()).unwrap();
+            assert!(!report.cases.is_empty());
+            assert!(report.cases.iter().all(|c| c.gap() >= -1e-12));
+            let largest = report.largest(3);
+            assert_eq!(largest[0].gap(), report.quantile(1.0));
+            assert!(report.quantile(0.5) <= largest[0].gap());
+            let (heuristic, optimum) = report.worst();
+            assert!(heuristic >= optimum);
+        }
+    }
+}
@@ -0,0 +1,408 @@
+use alloc::vec::Vec;
+
+use crate::analysis::{AnalysisConfig, best_partition};
+use crate::list_coloring_utils::NodeFeatures;
+use crate::star_utils::{MAX_NEIGHBORS, Star, StarError};
+
+// Binary batch protocol for driving workers over pipes.
+//
+// Every message is a frame: a little-endian `u32` payload length followed by the payload.
+// All numbers are little-endian.
+//
+// Request payload:
+// - the 9 weights as `f64`, in the order of `NodeFeatures::to_array`,
+// - `u32` number of stars, then per star: `u8` root colors, `u8` number of neighbors `n`,
+//   `n` bytes of neighbor colors, `n` bytes of neighbor halfedges.
+//
+// Response payload:
+// - `u8` batch status: `STATUS_OK`, or `STATUS_MALFORMED` (and nothing else follows),
+// - `u32` number of results, then per star: `u8` result status, and for `STATUS_OK` the
+//   branching number as `f64`, `u8` number of blocks, and the blocks of the best partition.
+//
+// Star files, as `recurrences convert --to binary` writes them, are not framed: the magic
+// bytes `RSTR` and the format version `STARS_VERSION`, then the stars one after another as
+// in a request payload, up to the end of the file.
+
+/// The request was decoded, or the star was analyzed.
+pub const STATUS_OK: u8 = 0;
+/// The request could not be decoded, or its frame is longer than [`MAX_FRAME_LEN`].
+pub const STATUS_MALFORMED: u8 = 1;
+/// The root list of the star cannot be split.
+pub const STATUS_UNSPLITTABLE: u8 = 2;
+/// The star fails [`Star::validate`].
+pub const STATUS_INVALID: u8 = 3;
+
+/// The answer for one star of a batch.
+#[derive(Clone, Debug, PartialEq)]
+pub enum WorkerResult {
+    Analyzed {
+        partition: Vec<u8>,
+        branching_number: f64,
+    },
+    Unsplittable,
+    Invalid,
+}
+
+/// The longest request payload [`serve`] reads, 64 MiB; longer ones are skipped unread and
+/// answered with [`STATUS_MALFORMED`].
+pub const MAX_FRAME_LEN: usize = 64 << 20;
+
+/// The magic bytes at the start of a star file.
+pub const STARS_MAGIC: &[u8; 4] = b"RSTR";
+/// The version of the star file format, after [`STARS_MAGIC`].
+pub const STARS_VERSION: u8 = 1;
+
+/// Prepends the little-endian length of `payload`.
+pub fn frame(payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(payload.len() + 4);
+    out.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    out.extend_from_slice(payload);
+    out
+}
+
+/// Encodes a request payload (without the frame length). Fails if a star has more than
+/// [`MAX_NEIGHBORS`] neighbors or not one halfedge count per neighbor, since the format could
+/// not represent it.
+pub fn encode_request(weights: NodeFeatures, stars: &[Star]) -> Result<Vec<u8>, StarError> {
+    let mut out: Vec<u8> = Vec::new();
+    for w in weights.to_array() {
+        out.extend_from_slice(&w.to_le_bytes());
+    }
+    out.extend_from_slice(&(stars.len() as u32).to_le_bytes());
+    for star in stars {
+        encode_star(star, &mut out)?;
+    }
+    Ok(out)
+}
+
+/// Appends `u8` root colors, `u8` number of neighbors `n`, `n` bytes of neighbor colors and `n`
+/// bytes of neighbor halfedges. Fails for stars with edges among the neighbors.
+pub fn encode_star(star: &Star, out: &mut Vec<u8>) -> Result<(), StarError> {
+    if star.neighbor_adjacency.is_some() {
+        return Err(StarError::NeighborEdgesNotEncodable);
+    }
+    let n = star.neighbor_colors.len();
+    if n != star.neighbor_halfedges.len() {
+        return Err(StarError::LengthMismatch {
+            colors: n,
+            halfedges: star.neighbor_halfedges.len(),
+        });
+    }
+    let count = u8::try_from(n).map_err(|_| StarError::TooManyNeighbors {
+        neighbors: n,
+        max: MAX_NEIGHBORS,
+    })?;
+    out.push(star.root_colors);
+    out.push(count);
+    out.extend_from_slice(&star.neighbor_colors);
+    out.extend_from_slice(&star.neighbor_halfedges);
+    Ok(())
+}
+
+/// Reads values from a payload; every read returns `None` once the payload is exhausted.
+pub(crate) struct Reader<'a> {
+    pub(crate) bytes: &'a [u8],
+}
+
+impl Reader<'_> {
+    pub(crate) fn take(&mut self, n: usize) -> Option<&[u8]> {
+        if self.bytes.len() < n {
+            return None;
+        }
+        let (head, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Some(head)
+    }
+
+    pub(crate) fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|b| b[0])
+    }
+
+    pub(crate) fn u32(&mut self) -> Option<u32> {
+        self.take(4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()))
+    }
+
+    pub(crate) fn f64(&mut self) -> Option<f64> {
+        self.take(8)
+            .map(|b| f64::from_le_bytes(b.try_into().unwrap()))
+    }
+}
+
+/// Decodes a request payload. Returns `None` if it is truncated, has trailing bytes, or some
+/// weight is not finite.
+pub fn decode_request(payload: &[u8]) -> Option<(NodeFeatures, Vec<Star>)> {
+    let mut r = Reader { bytes: payload };
+    let mut weights = [0.0; 9];
+    for w in weights.iter_mut() {
+        *w = r.f64()?;
+    }
+    NodeFeatures::from_array(weights).validate().ok()?;
+    let count = r.u32()? as usize;
+    // Every star takes at least 2 bytes, which bounds the allocation below.
+    if count > r.bytes.len() / 2 {
+        return None;
+    }
+    let mut stars = Vec::with_capacity(count);
+    for _ in 0..count {
+        stars.push(decode_star(&mut r)?);
+    }
+    r.bytes
+        .is_empty()
+        .then_some((NodeFeatures::from_array(weights), stars))
+}
+
+/// Reads a star written by [`encode_star`].
+pub(crate) fn decode_star(r: &mut Reader) -> Option<Star> {
+    let root_colors = r.u8()?;
+    let n = r.u8()? as usize;
+    Some(Star {
+        root_colors,
+        neighbor_colors: r.take(n)?.to_vec(),
+        neighbor_halfedges: r.take(n)?.to_vec(),
+        neighbor_adjacency: None,
+    })
+}
+
+/// Encodes a star file: [`STARS_MAGIC`], [`STARS_VERSION`] and every star as by
+/// [`encode_star`], which fails for the same stars.
+pub fn encode_stars(stars: &[Star]) -> Result<Vec<u8>, StarError> {
+    let mut out: Vec<u8> = STARS_MAGIC.to_vec();
+    out.push(STARS_VERSION);
+    for star in stars {
+        encode_star(star, &mut out)?;
+    }
+    Ok(out)
+}
+
+/// Decodes a star file written by [`encode_stars`]. Returns `None` if the magic bytes or the
+/// version do not match or the last star is truncated.
+pub fn decode_stars(bytes: &[u8]) -> Option<Vec<Star>> {
+    let mut r = Reader { bytes };
+    if r.take(4)? != STARS_MAGIC || r.u8()? != STARS_VERSION {
+        return None;
+    }
+    let mut stars = Vec::new();
+    while !r.bytes.is_empty() {
+        stars.push(decode_star(&mut r)?);
+    }
+    Some(stars)
+}
+
+/// Analyzes every star of a request by its [`best_partition`] under `config`.
+pub fn analyze_batch(
+    weights: NodeFeatures,
+    stars: &[Star],
+    config: &AnalysisConfig,
+) -> Vec<WorkerResult> {
+    stars
+        .iter()
+        .map(|star| {
+            if star
+                .validate_with_colors(config.enumeration.colors)
+                .is_err()
+            {
+                return WorkerResult::Invalid;
+            }
+            match best_partition(star, weights, config) {
+                Some((partition, branching_number)) => WorkerResult::Analyzed {
+                    partition,
+                    branching_number,
+                },
+                None => WorkerResult::Unsplittable,
+            }
+        })
+        .collect()
+}
+
+/// Encodes a response payload; `None` encodes a malformed request.
+pub fn encode_response(results: Option<&[WorkerResult]>) -> Vec<u8> {
+    let Some(results) = results else {
+        return alloc::vec![STATUS_MALFORMED];
+    };
+    let mut out = alloc::vec![STATUS_OK];
+    out.extend_from_slice(&(results.len() as u32).to_le_bytes());
+    for result in results {
+        match result {
+            WorkerResult::Analyzed {
+                partition,
+                branching_number,
+            } => {
+                out.push(STATUS_OK);
+                out.extend_from_slice(&branching_number.to_le_bytes());
+                out.push(partition.len() as u8);
+                out.extend_from_slice(partition);
+            }
+            WorkerResult::Unsplittable => out.push(STATUS_UNSPLITTABLE),
+            WorkerResult::Invalid => out.push(STATUS_INVALID),
+        }
+    }
+    out
+}
+
+/// Decodes a response payload: `Some(None)` for a malformed request, `None` if the payload
+/// itself cannot be decoded.
+pub fn decode_response(payload: &[u8]) -> Option<Option<Vec<WorkerResult>>> {
+    let mut r = Reader { bytes: payload };
+    if r.u8()? == STATUS_MALFORMED {
+        return r.bytes.is_empty().then_some(None);
+    }
+    let count = r.u32()? as usize;
+    if count > r.bytes.len() {
+        return None;
+    }
+    let mut results = Vec::with_capacity(count);
+    for _ in 0..count {
+        results.push(match r.u8()? {
+            STATUS_OK => {
+                let branching_number = r.f64()?;
+                let n = r.u8()? as usize;
+                WorkerResult::Analyzed {
+                    partition: r.take(n)?.to_vec(),
+                    branching_number,
+                }
+            }
+            STATUS_UNSPLITTABLE => WorkerResult::Unsplittable,
+            STATUS_INVALID => WorkerResult::Invalid,
+            _ => return None,
+        });
+    }
+    r.bytes.is_empty().then_some(Some(results))
+}
+
+/// Answers framed requests from `input` on `output` until `input` ends between two frames,
+/// analyzing the stars under `config`.
+#[cfg(feature = "std")]
+pub fn serve(
+    mut input: impl std::io::Read,
+    mut output: impl std::io::Write,
+    config: &AnalysisConfig,
+) -> std::io::Result<()> {
+    loop {
+        let mut len = [0u8; 4];
+        match input.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e),
+        }
+        let len = u32::from_le_bytes(len) as usize;
+        let results = if len > MAX_FRAME_LEN {
+            // Skip the payload without allocating it, to stay in sync with the next frame.
+            let skipped = std::io::copy(
+                &mut std::io::Read::take(&mut input, len as u64),
+                &mut std::io::sink(),
+            )?;
+            if skipped < len as u64 {
+                return Err(std::io::ErrorKind::UnexpectedEof.into());
+            }
+            None
+        } else {
+            let mut payload = alloc::vec![0u8; len];
+            input.read_exact(&mut payload)?;
+            decode_request(&payload).map(|(weights, stars)| analyze_batch(weights, &stars, config))
+        };
+        output.write_all(&frame(&encode_response(results.as_deref())))?;
+        output.flush()?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::star_utils::{NeighborAdjacency, generate_stars};
+    use alloc::boxed::Box;
+    use alloc::vec;
+
+    #[test]
+    fn serve_answers_framed_batches() {
+        let weights = NodeFeatures::from_array([1.0, 1.0, 1.0, 0.7, 0.7, 0.7, 0.4, 0.4, 0.4]);
+        let mut stars: Vec<Star> = generate_stars(2).into_iter().take(3).collect();
+        stars.push(Star {
+            root_colors: 0b0001,
+            neighbor_colors: vec![0b0011],
+            neighbor_halfedges: vec![2],
+            neighbor_adjacency: None,
+        });
+        stars.push(Star {
+            root_colors: 0b1_0011,
+            neighbor_colors: vec![0b0011],
+            neighbor_halfedges: vec![2],
+            neighbor_adjacency: None,
+        });
+        let request = encode_request(weights, &stars).unwrap();
+        assert_eq!(decode_request(&request), Some((weights, stars.clone())));
+
+        #[cfg(feature = "std")]
+        serve_skips_bad_frames(weights, &stars, &request);
+
+        let nan = NodeFeatures::from_array([f64::NAN; 9]);
+        assert_eq!(decode_request(&encode_request(nan, &stars).unwrap()), None);
+        let wide = Star {
+            root_colors: 0b0011,
+            neighbor_colors: vec![0b0011; MAX_NEIGHBORS + 1],
+            neighbor_halfedges: vec![2; MAX_NEIGHBORS + 1],
+            neighbor_adjacency: None,
+        };
+        assert_eq!(
+            encode_request(weights, &[wide]),
+            Err(StarError::TooManyNeighbors {
+                neighbors: MAX_NEIGHBORS + 1,
+                max: MAX_NEIGHBORS
+            })
+        );
+    }
+
+    /// Answers a valid frame, a truncated one, one above the size limit and a valid one again.
+    #[cfg(feature = "std")]
+    fn serve_skips_bad_frames(weights: NodeFeatures, stars: &[Star], request: &[u8]) {
+        let mut input = frame(request);
+        input.extend(frame(&request[..request.len() - 1]));
+        // A frame above the limit is skipped, and the next one is still answered.
+        input.extend(((MAX_FRAME_LEN + 1) as u32).to_le_bytes());
+        input.extend(alloc::vec![0u8; MAX_FRAME_LEN + 1]);
+        input.extend(frame(request));
+        let mut output: Vec<u8> = Vec::new();
+        serve(&input[..], &mut output, &AnalysisConfig::default()).unwrap();
+
+        let first_len = u32::from_le_bytes(output[..4].try_into().unwrap()) as usize;
+        let results = decode_response(&output[4..4 + first_len]).unwrap().unwrap();
+        assert_eq!(
+            results,
+            analyze_batch(weights, stars, &AnalysisConfig::default())
+        );
+        assert!(matches!(results[0], WorkerResult::Analyzed { .. }));
+        assert_eq!(results[3], WorkerResult::Unsplittable);
+        assert_eq!(results[4], WorkerResult::Invalid);
+        let mut responses = &output[4 + first_len..];
+        let mut next = || {
+            let len = u32::from_le_bytes(responses[..4].try_into().unwrap()) as usize;
+            let response = decode_response(&responses[4..4 + len]).unwrap();
+            responses = &responses[4 + len..];
+            response
+        };
+        assert_eq!(next(), None);
+        assert_eq!(next(), None);
+        assert_eq!(next(), Some(results));
+    }
+
+    #[test]
+    fn star_files_round_trip() {
+        let stars: Vec<Star> = generate_stars(3).into_iter().step_by(97).collect();
+        let bytes = encode_stars(&stars).unwrap();
+        assert!(bytes.starts_with(STARS_MAGIC));
+        assert_eq!(decode_stars(&bytes), Some(stars.clone()));
+        assert_eq!(decode_stars(&encode_stars(&[]).unwrap()), Some(Vec::new()));
+        // A truncated star, another version and stray bytes are rejected.
+        assert_eq!(decode_stars(&bytes[..bytes.len() - 1]), None);
+        let mut other = bytes.clone();
+        other[4] += 1;
+        assert_eq!(decode_stars(&other), None);
+        assert_eq!(decode_stars(b"RSTR"), None);
+        let mut adjacent = stars[0].clone();
+        adjacent.neighbor_adjacency = Some(Box::new(NeighborAdjacency::from_edges(3, &[(0, 1)])));
+        assert_eq!(
+            encode_stars(&[adjacent]),
+            Err(StarError::NeighborEdgesNotEncodable)
+        );
+    }
+}
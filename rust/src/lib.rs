@@ -1,2 +1,45 @@
-pub mod star_utils;
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(not(any(feature = "std", feature = "libm")))]
+compile_error!("without the `std` feature, enable `libm` for the floating-point functions");
+
+pub mod analysis;
+pub mod annotated_star_utils;
+pub mod annotations;
+#[cfg(feature = "cli")]
+pub mod cache;
+pub mod case_set;
+pub mod closure;
+pub mod color_classes;
+pub mod colors;
+#[cfg(feature = "std")]
+pub mod corpus;
+pub mod coverage;
+pub mod cyclic_star_utils;
+pub mod derived_features;
+pub mod encoding;
+pub mod exhaustive;
+pub mod explain;
+pub mod feature_join;
+pub mod gadgets;
+pub mod heuristic;
+pub mod ipc;
+pub mod json;
 pub mod list_coloring_utils;
+pub mod memory;
+pub mod microstructure;
+pub mod number_format;
+pub mod optimize;
+pub mod path2_utils;
+pub mod recurrence;
+pub mod reduction_order;
+pub mod reductions;
+pub mod report;
+pub mod solver;
+pub mod star2_utils;
+pub mod star_utils;
+pub mod trace;
+pub mod tree_utils;
+pub mod weights;
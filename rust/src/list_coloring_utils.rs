@@ -1,4 +1,12 @@
-use crate::star_utils::Star;
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use alloc::{format, vec};
+
+use crate::colors::{fmt_colors, fmt_partition, partitions_of_set, singletons};
+use crate::number_format::format_json_float;
+use crate::reductions::{DEFAULT_REDUCTIONS, Reduction, reduce_owned_to_fixpoint};
+use crate::star_utils::{MAX_HALFEDGES, NeighborMultiset, Star, StarError, star_type_key};
 
 /// Returns whether node 1 has higher priority than node 2.
 ///
@@ -15,6 +23,7 @@ pub fn has_higher_priority(
 }
 
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NodeFeatures {
     pub n4_ge5: f64,
     pub n4_4: f64,
@@ -28,17 +37,73 @@ pub struct NodeFeatures {
 }
 
 impl NodeFeatures {
+    /// The field names, in the order of [`NodeFeatures::to_array`].
+    pub const NAMES: [&'static str; 9] = [
+        "n4_ge5", "n4_4", "n4_3", "n3_ge5", "n3_4", "n3_3", "n2_ge5", "n2_4", "n2_3",
+    ];
+
+    /// Returns the counts as an array, in the order of the fields.
+    pub fn to_array(&self) -> [f64; 9] {
+        [
+            self.n4_ge5,
+            self.n4_4,
+            self.n4_3,
+            self.n3_ge5,
+            self.n3_4,
+            self.n3_3,
+            self.n2_ge5,
+            self.n2_4,
+            self.n2_3,
+        ]
+    }
+
+    /// Like [`NodeFeatures::from_array`], but fails if some value is NaN or infinite, and
+    /// turns `-0.0` into `0.0` so that equal weights print and hash the same.
+    pub fn try_from_array(a: [f64; 9]) -> Result<Self, NonFiniteFeature> {
+        let features = NodeFeatures::from_array(a.map(|x| x + 0.0));
+        features.validate()?;
+        Ok(features)
+    }
+
+    /// Whether every value is finite.
+    pub fn is_finite(&self) -> bool {
+        self.validate().is_ok()
+    }
+
+    /// Fails with the first value that is NaN or infinite.
+    pub fn validate(&self) -> Result<(), NonFiniteFeature> {
+        match self.to_array().iter().position(|x| !x.is_finite()) {
+            Some(index) => Err(NonFiniteFeature {
+                index,
+                value: self.to_array()[index],
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Inverse of [`NodeFeatures::to_array`].
+    pub fn from_array(a: [f64; 9]) -> Self {
+        NodeFeatures {
+            n4_ge5: a[0],
+            n4_4: a[1],
+            n4_3: a[2],
+            n3_ge5: a[3],
+            n3_4: a[4],
+            n3_3: a[5],
+            n2_ge5: a[6],
+            n2_4: a[7],
+            n2_3: a[8],
+        }
+    }
+
     /// Returns a compact one-line JSON object string with no whitespace.
     pub fn to_json_string(&self) -> String {
-        fn fmt_num(x: f64) -> String {
-            if x == 0.0 {
-                // Normalizes both 0.0 and -0.0.
-                "0".to_string()
-            } else {
-                x.to_string()
-            }
-        }
+        self.to_json_string_with_precision(None)
+    }
 
+    /// Like [`NodeFeatures::to_json_string`], with numbers formatted by [`format_json_float`].
+    pub fn to_json_string_with_precision(&self, precision: Option<usize>) -> String {
+        let fmt_num = |x: f64| format_json_float(x, precision);
         format!(
             "{{\"n4_ge5\":{},\"n4_4\":{},\"n4_3\":{},\"n3_ge5\":{},\"n3_4\":{},\"n3_3\":{},\"n2_ge5\":{},\"n2_4\":{},\"n2_3\":{}}}",
             fmt_num(self.n4_ge5),
@@ -54,7 +119,29 @@ impl NodeFeatures {
     }
 }
 
-impl std::ops::Sub for NodeFeatures {
+/// A [`NodeFeatures`] value that is NaN or infinite, which would turn every measure and
+/// branching number computed from it into NaN or infinity.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NonFiniteFeature {
+    /// Index into [`NodeFeatures::to_array`].
+    pub index: usize,
+    pub value: f64,
+}
+
+impl core::fmt::Display for NonFiniteFeature {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{} is {}, expected a finite number",
+            NodeFeatures::NAMES[self.index],
+            self.value
+        )
+    }
+}
+
+impl core::error::Error for NonFiniteFeature {}
+
+impl core::ops::Sub for NodeFeatures {
     type Output = NodeFeatures;
 
     fn sub(self, rhs: NodeFeatures) -> Self::Output {
@@ -72,7 +159,7 @@ impl std::ops::Sub for NodeFeatures {
     }
 }
 
-impl std::ops::Mul for NodeFeatures {
+impl core::ops::Mul for NodeFeatures {
     type Output = f64;
 
     fn mul(self, rhs: NodeFeatures) -> Self::Output {
@@ -88,28 +175,172 @@ impl std::ops::Mul for NodeFeatures {
     }
 }
 
-fn bump_count(counts: &mut NodeFeatures, list_size: u32, degree: usize) {
-    let degree_bucket = if degree >= 5 {
-        5
-    } else if degree == 4 {
-        4
-    } else if degree == 3 {
-        3
-    } else {
-        0
+/// The maximum degree Δ of the graphs an analysis is for.
+///
+/// [`NodeFeatures`] bucket the vertex degrees into `>= Δ`, `Δ - 1` and `Δ - 2`. The field
+/// names spell out the buckets of the default Δ = 5; for other values, e.g. `n4_ge5` counts
+/// the vertices with 4 colors and degree at least Δ. Stars for Δ have at most Δ neighbors,
+/// each with at most `Δ - 1` halfedges (see
+/// [`EnumerationConfig::max_degree`](crate::star_utils::EnumerationConfig::max_degree)).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MaxDegree(pub usize);
+
+impl Default for MaxDegree {
+    fn default() -> Self {
+        MaxDegree(5)
+    }
+}
+
+impl MaxDegree {
+    /// The degree bucket of `degree`: 0 for `>= Δ`, 1 for `Δ - 1`, 2 for `Δ - 2`, and `None`
+    /// for smaller degrees, which do not count.
+    pub fn bucket(self, degree: usize) -> Option<usize> {
+        let below = self.0.saturating_sub(degree);
+        (below <= 2 && degree > 0).then_some(below)
+    }
+
+    /// The largest degree in the bucket of `degree`, or `None` if the bucket `>= Δ` has no
+    /// largest degree. The degrees below the buckets form one group up to `Δ - 3`.
+    pub fn bucket_top(self, degree: usize) -> Option<usize> {
+        match self.bucket(degree) {
+            Some(0) => None,
+            Some(_) => Some(degree),
+            None => Some(self.0.saturating_sub(3)),
+        }
+    }
+
+    /// The feature names with the buckets of this Δ, in the order of
+    /// [`NodeFeatures::to_array`], e.g. `n4_ge6`, `n4_5`, `n4_4`, ... for Δ = 6.
+    pub fn feature_names(self) -> [String; 9] {
+        let buckets = [
+            format!("ge{}", self.0),
+            format!("{}", self.0.saturating_sub(1)),
+            format!("{}", self.0.saturating_sub(2)),
+        ];
+        core::array::from_fn(|i| format!("n{}_{}", 4 - i / 3, buckets[i % 3]))
+    }
+}
+
+pub(crate) fn bump_count(counts: &mut NodeFeatures, list_size: u32, degree: usize) {
+    bump_count_by(counts, list_size, degree, 1.0, MaxDegree::default());
+}
+
+fn bump_count_by(
+    counts: &mut NodeFeatures,
+    list_size: u32,
+    degree: usize,
+    amount: f64,
+    max_degree: MaxDegree,
+) {
+    let (Some(bucket), 2..=4) = (max_degree.bucket(degree), list_size) else {
+        return;
     };
+    // `to_array` lists the buckets from large to small lists, each from large to small degrees.
+    let mut array = counts.to_array();
+    array[3 * (4 - list_size as usize) + bucket] += amount;
+    *counts = NodeFeatures::from_array(array);
+}
+
+/// How much each vertex of a star contributes to its [`NodeFeatures`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FeatureSchema {
+    /// Every vertex counts once.
+    #[default]
+    Counts,
+    /// Every neighbor counts with its number of halfedges, since vertices with more external
+    /// connections have more potential for future branching. The root has no halfedges and
+    /// counts once.
+    HalfedgeWeighted,
+}
+
+/// How [`NodeFeatures::normalized`] scales the features of a star, so that stars of different
+/// degrees become comparable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FeatureNormalization {
+    /// Divide by the degree of the root, the number of neighbors.
+    ByDegree(usize),
+    /// Divide by the number of vertices of the star, the root and its neighbors.
+    ByVertices(usize),
+}
+
+impl FeatureNormalization {
+    /// [`FeatureNormalization::ByDegree`] with the degree of `star`.
+    pub fn by_degree(star: &Star) -> Self {
+        FeatureNormalization::ByDegree(star.neighbor_colors.len())
+    }
+
+    /// [`FeatureNormalization::ByVertices`] with the number of vertices of `star`.
+    pub fn by_vertices(star: &Star) -> Self {
+        FeatureNormalization::ByVertices(star.neighbor_colors.len() + 1)
+    }
+}
+
+impl NodeFeatures {
+    /// Scales the features as given by `normalization`. Scaling by 0 leaves them unchanged.
+    pub fn normalized(&self, normalization: FeatureNormalization) -> NodeFeatures {
+        let (FeatureNormalization::ByDegree(n) | FeatureNormalization::ByVertices(n)) =
+            normalization;
+        if n == 0 {
+            return *self;
+        }
+        NodeFeatures::from_array(self.to_array().map(|x| x / n as f64))
+    }
+}
 
-    match (list_size, degree_bucket) {
-        (4, 5) => counts.n4_ge5 += 1.0,
-        (4, 4) => counts.n4_4 += 1.0,
-        (4, 3) => counts.n4_3 += 1.0,
-        (3, 5) => counts.n3_ge5 += 1.0,
-        (3, 4) => counts.n3_4 += 1.0,
-        (3, 3) => counts.n3_3 += 1.0,
-        (2, 5) => counts.n2_ge5 += 1.0,
-        (2, 4) => counts.n2_4 += 1.0,
-        (2, 3) => counts.n2_3 += 1.0,
-        _ => {}
+fn sqrt(x: f64) -> f64 {
+    #[cfg(feature = "std")]
+    return x.sqrt();
+    #[cfg(not(feature = "std"))]
+    return libm::sqrt(x);
+}
+
+/// Per-feature mean and standard deviation over a corpus, for standardizing features to mean 0
+/// and standard deviation 1.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FeatureStandardization {
+    pub mean: NodeFeatures,
+    /// The population standard deviation.
+    pub std: NodeFeatures,
+}
+
+impl FeatureStandardization {
+    /// Computes the mean and standard deviation of every feature over `corpus`; all zero for
+    /// an empty corpus.
+    pub fn fit(corpus: &[NodeFeatures]) -> Self {
+        if corpus.is_empty() {
+            return FeatureStandardization::default();
+        }
+        let n = corpus.len() as f64;
+        let mut mean = [0.0; 9];
+        for f in corpus {
+            for (m, x) in mean.iter_mut().zip(f.to_array()) {
+                *m += x / n;
+            }
+        }
+        let mut variance = [0.0; 9];
+        for f in corpus {
+            for ((v, x), m) in variance.iter_mut().zip(f.to_array()).zip(mean) {
+                *v += (x - m) * (x - m) / n;
+            }
+        }
+        FeatureStandardization {
+            mean: NodeFeatures::from_array(mean),
+            std: NodeFeatures::from_array(variance.map(sqrt)),
+        }
+    }
+
+    /// Returns `(features - mean) / std` per feature. Features that are constant over the
+    /// corpus have standard deviation 0 and become 0.
+    pub fn apply(&self, features: NodeFeatures) -> NodeFeatures {
+        let centered = (features - self.mean).to_array();
+        let std = self.std.to_array();
+        NodeFeatures::from_array(core::array::from_fn(|i| {
+            if std[i] == 0.0 {
+                0.0
+            } else {
+                centered[i] / std[i]
+            }
+        }))
     }
 }
 
@@ -126,27 +357,69 @@ fn bump_count(counts: &mut NodeFeatures, list_size: u32, degree: usize) {
 /// - Root degree is the number of neighbors.
 /// - Neighbor degree is `halfedges + 1`.
 pub fn star_list_degree_counts(star: &Star) -> NodeFeatures {
+    star_features(star, FeatureSchema::Counts, MaxDegree::default())
+}
+
+/// Computes the features of the given star under `schema`, with the conventions of
+/// [`star_list_degree_counts`] and the degree buckets of `max_degree`.
+pub fn star_features(star: &Star, schema: FeatureSchema, max_degree: MaxDegree) -> NodeFeatures {
     debug_assert_eq!(star.neighbor_colors.len(), star.neighbor_halfedges.len());
 
-    let mut counts = NodeFeatures::default();
+    let slots = neighbor_feature_slots(
+        &star.neighbor_colors,
+        &star.neighbor_halfedges,
+        schema,
+        max_degree,
+    );
+    let mut counts = NodeFeatures::from_array(core::array::from_fn(|i| slots[i] as f64));
 
     // Root
     let root_list_size = star.root_colors.count_ones();
     let root_degree = star.neighbor_colors.len();
-    bump_count(&mut counts, root_list_size, root_degree);
+    bump_count_by(&mut counts, root_list_size, root_degree, 1.0, max_degree);
 
-    // Neighbors
-    for (&colors, &halfedges) in star
-        .neighbor_colors
-        .iter()
-        .zip(star.neighbor_halfedges.iter())
-    {
-        let list_size = colors.count_ones();
-        let degree = (halfedges as usize) + 1;
-        bump_count(&mut counts, list_size, degree);
+    counts
+}
+
+/// The index into [`NodeFeatures::to_array`] of a vertex by list size and degree bucket (3
+/// for the degrees below the buckets), or [`DROPPED_SLOT`] if the vertex does not count.
+const FEATURE_SLOTS: [[u8; 4]; 9] = {
+    let mut slots = [[DROPPED_SLOT as u8; 4]; 9];
+    let mut list_size = 2;
+    while list_size <= 4 {
+        let mut bucket = 0;
+        while bucket < 3 {
+            slots[list_size][bucket] = (3 * (4 - list_size) + bucket) as u8;
+            bucket += 1;
+        }
+        list_size += 1;
     }
+    slots
+};
 
-    counts
+/// The slot after the 9 features, which collects the vertices that do not count.
+const DROPPED_SLOT: usize = 9;
+
+/// The feature counts of the neighbors with the given lists and halfedges, plus the dropped
+/// vertices in the last slot. The counts are `u64`, so halfedge weights cannot overflow.
+///
+/// Every neighbor adds to the slot of [`FEATURE_SLOTS`] for its list size and degree bucket,
+/// so the loop has no branches. Neighbors have degree at least 1, so the bucket is just
+/// `Δ - degree`, clamped to 3.
+fn neighbor_feature_slots(
+    colors: &[u8],
+    halfedges: &[u8],
+    schema: FeatureSchema,
+    max_degree: MaxDegree,
+) -> [u64; DROPPED_SLOT + 1] {
+    let mut slots = [0u64; DROPPED_SLOT + 1];
+    let weighted = matches!(schema, FeatureSchema::HalfedgeWeighted);
+    for (&colors, &halfedges) in colors.iter().zip(halfedges) {
+        let bucket = max_degree.0.saturating_sub(halfedges as usize + 1).min(3);
+        let slot = FEATURE_SLOTS[colors.count_ones() as usize][bucket];
+        slots[slot as usize] += if weighted { halfedges as u64 } else { 1 };
+    }
+    slots
 }
 
 /// Produces all set partitions of the set represented by `colors`.
@@ -159,49 +432,77 @@ pub fn star_list_degree_counts(star: &Star) -> NodeFeatures {
 /// - Output is deterministic: blocks inside a partition are sorted descending by bitmask,
 ///   and the list of partitions is sorted by (number of blocks, lexicographic).
 pub fn partitions_of_colors(colors: u8) -> Vec<Vec<u8>> {
-    if colors == 0 {
-        return vec![Vec::new()];
-    }
+    partitions_of_set(colors)
+}
 
-    // Collect element bits (descending) to get stable, human-friendly partitions.
-    let mut elems: Vec<u8> = Vec::new();
-    for bit_idx in (0..8u8).rev() {
-        let bit = 1u8 << bit_idx;
-        if (colors & bit) != 0 {
-            elems.push(bit);
+/// Outcome of one branch of a branching rule.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Branch {
+    /// Every vertex of the star has a singleton list, so the star is completely colored.
+    ///
+    /// The branch still continues on the rest of the graph; its measure is zero, so it drops
+    /// the full measure of the star it came from.
+    Solved,
+    /// The root or some neighbor has an empty list. The branch terminates immediately and
+    /// does not contribute to the recurrence.
+    Infeasible,
+    /// An ordinary branch.
+    Star(Star),
+}
+
+impl Branch {
+    /// Classifies a star produced by a branching rule.
+    pub fn from_star(star: Star) -> Branch {
+        if is_infeasible(&star) {
+            Branch::Infeasible
+        } else if star.root_colors.count_ones() == 1
+            && star.neighbor_colors.iter().all(|c| c.count_ones() == 1)
+        {
+            Branch::Solved
+        } else {
+            Branch::Star(star)
         }
     }
 
-    fn backtrack(idx: usize, elems: &[u8], blocks: &mut Vec<u8>, out: &mut Vec<Vec<u8>>) {
-        if idx == elems.len() {
-            let mut part = blocks.clone();
-            part.sort_by(|a, b| b.cmp(a));
-            out.push(part);
-            return;
+    /// Returns the star of an ordinary branch.
+    pub fn as_star(&self) -> Option<&Star> {
+        match self {
+            Branch::Star(star) => Some(star),
+            _ => None,
         }
+    }
+}
 
-        let bit = elems[idx];
-
-        // Add to an existing block.
-        for i in 0..blocks.len() {
-            blocks[i] |= bit;
-            backtrack(idx + 1, elems, blocks, out);
-            blocks[i] &= !bit;
+/// Groups branches of the same type, keeping the order of first occurrence.
+///
+/// Two ordinary branches have the same type if they are equal up to reordering the neighbors
+/// and renaming the colors (see [`star_type_key`]); all solved and all infeasible branches
+/// form one type each. Branches of the same type have the same measure, so a branching vector
+/// only needs one entry per type, with multiplicity.
+pub fn group_branches(branches: Vec<Branch>) -> Vec<(Branch, usize)> {
+    let mut out: Vec<(Branch, usize)> = Vec::new();
+    let mut keys: Vec<Option<(u8, NeighborMultiset)>> = Vec::new();
+    for branch in branches {
+        let key = branch.as_star().map(star_type_key);
+        let existing = out.iter().zip(keys.iter()).position(|((b, _), k)| {
+            *k == key && core::mem::discriminant(b) == core::mem::discriminant(&branch)
+        });
+        match existing {
+            Some(idx) => out[idx].1 += 1,
+            None => {
+                out.push((branch, 1));
+                keys.push(key);
+            }
         }
-
-        // Start a new block.
-        blocks.push(bit);
-        backtrack(idx + 1, elems, blocks, out);
-        blocks.pop();
     }
-
-    let mut out: Vec<Vec<u8>> = Vec::new();
-    backtrack(0, &elems, &mut Vec::new(), &mut out);
-
-    out.sort_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)));
     out
 }
 
+/// Returns whether the root or some neighbor of the star has an empty list.
+pub fn is_infeasible(star: &Star) -> bool {
+    star.root_colors == 0 || star.neighbor_colors.contains(&0)
+}
+
 /// Applies a list-coloring branching rule to a star.
 ///
 /// The `partition` represents a partition of `star.root_colors` into disjoint non-empty blocks.
@@ -209,116 +510,405 @@ pub fn partitions_of_colors(colors: u8) -> Vec<Vec<u8>> {
 /// - The root list becomes `b`.
 /// - If `b` is a singleton color, that color is removed from every neighbor list.
 /// - Neighbors are not dropped; they are kept with their updated color lists.
-pub fn apply_list_coloring_partition(star: &Star, partition: &Vec<u8>) -> Vec<Star> {
-    debug_assert_eq!(star.neighbor_colors.len(), star.neighbor_halfedges.len());
+/// - Branches in which a list became empty are [`Branch::Infeasible`].
+/// - Other branches are simplified with the [`DEFAULT_REDUCTIONS`] and then classified with
+///   [`Branch::from_star`].
+pub fn apply_list_coloring_partition(star: &Star, partition: &[u8]) -> Vec<Branch> {
+    apply_list_coloring_partition_with_reductions(star, partition, DEFAULT_REDUCTIONS)
+}
+
+/// Like [`apply_list_coloring_partition`], but simplifies the branches with `reductions`.
+pub fn apply_list_coloring_partition_with_reductions(
+    star: &Star,
+    partition: &[u8],
+    reductions: &[&dyn Reduction],
+) -> Vec<Branch> {
+    partition_branch_lists(star, partition)
+        .map(|(new_root, neighbor_colors)| {
+            // Infeasible branches are decided on the borrowed lists, without building a star.
+            if neighbor_colors.contains(&0) {
+                return Branch::Infeasible;
+            }
+            let branch = Star {
+                root_colors: new_root,
+                neighbor_colors: neighbor_colors.into_owned(),
+                neighbor_halfedges: star.neighbor_halfedges.clone(),
+                neighbor_adjacency: None,
+            };
+            Branch::from_star(reduce_owned_to_fixpoint(branch, reductions))
+        })
+        .collect()
+}
 
-    let mut out: Vec<Star> = Vec::with_capacity(partition.len());
+/// Simplifies one unreduced branch, e.g. of [`apply_all_partitions`], with `reductions` and
+/// classifies it, like [`apply_list_coloring_partition_with_reductions`] does for each of its
+/// branches. The reductions ignore the edges among the neighbors, so the branch drops them.
+pub fn reduce_branch(mut branch: Star, reductions: &[&dyn Reduction]) -> Branch {
+    if is_infeasible(&branch) {
+        return Branch::Infeasible;
+    }
+    branch.neighbor_adjacency = None;
+    Branch::from_star(reduce_owned_to_fixpoint(branch, reductions))
+}
 
-    for &root_block in partition.iter() {
-        if root_block == 0 {
-            continue;
+/// Why [`try_apply_list_coloring_partition`] refused to branch.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PartitionError {
+    /// The star fails [`Star::validate`].
+    InvalidStar(StarError),
+    /// The blocks are not nonempty, disjoint, and covering the root list exactly.
+    NotAPartition { root_colors: u8, partition: Vec<u8> },
+}
+
+impl core::fmt::Display for PartitionError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            PartitionError::InvalidStar(e) => write!(f, "invalid star: {e}"),
+            PartitionError::NotAPartition {
+                root_colors,
+                partition,
+            } => write!(
+                f,
+                "{} is not a partition of the root list {}",
+                fmt_partition(partition),
+                fmt_colors(*root_colors)
+            ),
         }
+    }
+}
+
+impl core::error::Error for PartitionError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            PartitionError::InvalidStar(e) => Some(e),
+            PartitionError::NotAPartition { .. } => None,
+        }
+    }
+}
+
+/// Like [`apply_list_coloring_partition`], but checks its inputs in release builds too.
+///
+/// The unchecked rule only catches a broken star or partition with `debug_assert!`, and
+/// otherwise ignores the colors of blocks outside the root list. Use this function for stars
+/// and partitions that come from outside the crate, such as files or the command line.
+pub fn try_apply_list_coloring_partition(
+    star: &Star,
+    partition: &[u8],
+) -> Result<Vec<Branch>, PartitionError> {
+    star.validate().map_err(PartitionError::InvalidStar)?;
+    let mut covered = 0u8;
+    let disjoint = partition.iter().all(|&block| {
+        let fresh = block != 0 && (block & covered) == 0;
+        covered |= block;
+        fresh
+    });
+    if !disjoint || covered != star.root_colors {
+        return Err(PartitionError::NotAPartition {
+            root_colors: star.root_colors,
+            partition: partition.to_vec(),
+        });
+    }
+    Ok(apply_list_coloring_partition(star, partition))
+}
+
+/// Like [`apply_list_coloring_partition`], but without simplifying the branches. The branches
+/// keep the edges among the neighbors of `star`.
+pub fn apply_list_coloring_partition_unreduced(star: &Star, partition: &[u8]) -> Vec<Star> {
+    partition_branch_lists(star, partition)
+        .map(|(new_root, neighbor_colors)| Star {
+            root_colors: new_root,
+            neighbor_colors: neighbor_colors.into_owned(),
+            neighbor_halfedges: star.neighbor_halfedges.clone(),
+            neighbor_adjacency: star.neighbor_adjacency.clone(),
+        })
+        .collect()
+}
 
+/// Like [`apply_list_coloring_partition_unreduced`], also propagating singleton lists with
+/// [`Star::propagate`] along the edges at the root and among the neighbors. A branch in which
+/// two adjacent vertices are fixed to the same color ends up with an empty list.
+pub fn apply_list_coloring_partition_propagated(star: &Star, partition: &[u8]) -> Vec<Star> {
+    apply_list_coloring_partition_unreduced(star, partition)
+        .iter()
+        .map(Star::propagate)
+        .collect()
+}
+
+/// The root list and the neighbor lists of every branch of a partition. Blocks with several
+/// colors leave the neighbor lists unchanged, so they borrow those of `star`; only singleton
+/// blocks remove their color from copies of the lists.
+fn partition_branch_lists<'a>(
+    star: &'a Star,
+    partition: &'a [u8],
+) -> impl Iterator<Item = (u8, Cow<'a, [u8]>)> {
+    debug_assert_eq!(star.neighbor_colors.len(), star.neighbor_halfedges.len());
+
+    partition.iter().filter_map(move |&root_block| {
         // If the caller gives a "partition" that doesn't fit the root, ignore the extra bits.
         // (In debug, try to catch it.)
         debug_assert_eq!(root_block & !star.root_colors, 0);
         let new_root = root_block & star.root_colors;
         if new_root == 0 {
+            return None;
+        }
+
+        // Propagate singleton root assignment by removing that color from neighbors.
+        let neighbor_colors = if new_root.count_ones() == 1 {
+            Cow::Owned(
+                star.neighbor_colors
+                    .iter()
+                    .map(|&nc| nc & !new_root)
+                    .collect(),
+            )
+        } else {
+            Cow::Borrowed(star.neighbor_colors.as_slice())
+        };
+        Some((new_root, neighbor_colors))
+    })
+}
+
+/// Applies every partition of [`partitions_of_colors`] of the root list in one pass, like
+/// [`apply_list_coloring_partition_unreduced`] for each.
+///
+/// The branch of a block does not depend on the rest of the partition: blocks of size at least
+/// 2 keep the neighbor lists, and the lists of each singleton block are computed once for all
+/// partitions that contain it, so every branch only clones one of these vectors.
+pub fn apply_all_partitions(star: &Star) -> Vec<(Vec<u8>, Vec<Star>)> {
+    let singleton_lists: Vec<(u8, Vec<u8>)> = singletons(star.root_colors)
+        .map(|c| (c, star.neighbor_colors.iter().map(|&nc| nc & !c).collect()))
+        .collect();
+    let branch = |block: u8| {
+        let neighbor_colors = match singleton_lists.iter().find(|(c, _)| *c == block) {
+            Some((_, lists)) => lists.clone(),
+            None => star.neighbor_colors.clone(),
+        };
+        Star {
+            root_colors: block,
+            neighbor_colors,
+            neighbor_halfedges: star.neighbor_halfedges.clone(),
+            neighbor_adjacency: star.neighbor_adjacency.clone(),
+        }
+    };
+    partitions_of_colors(star.root_colors)
+        .into_iter()
+        .map(|partition| {
+            let branches = partition.iter().map(|&block| branch(block)).collect();
+            (partition, branches)
+        })
+        .collect()
+}
+
+/// Returns whether neighbors `i` and `j` receive the same color in every proper list coloring
+/// of the star (ignoring the halfedges).
+///
+/// Neighbors are only adjacent to the root, so for a fixed root color `r` each neighbor
+/// independently picks a color from its list minus `r`. Root colors that leave some neighbor
+/// with an empty list admit no coloring and are skipped.
+pub fn neighbors_forced_equal(star: &Star, i: usize, j: usize) -> bool {
+    for r in singletons(star.root_colors) {
+        if star.neighbor_colors.iter().any(|&c| (c & !r) == 0) {
             continue;
         }
+        let ci = star.neighbor_colors[i] & !r;
+        let cj = star.neighbor_colors[j] & !r;
+        if ci != cj || ci.count_ones() != 1 {
+            return false;
+        }
+    }
+    true
+}
 
-        let mut new_neighbor_colors = star.neighbor_colors.clone();
-        let new_neighbor_halfedges = star.neighbor_halfedges.clone();
+/// Merges neighbors with identical color lists ("twins") into a single neighbor.
+///
+/// Scans the neighbors in order; for the first neighbor `i` that has later twins `j` with
+/// `can_merge(star, i, j)`, all such twins are merged into `i`. The merged neighbor keeps its
+/// color list, and its halfedges become the sum of the merged neighbors' halfedges.
+///
+/// `can_merge` is the safety predicate: merging is sound whenever the twins are colored equally
+/// in every coloring, see [`neighbors_forced_equal`].
+///
+/// Returns `None` if no twins can be merged. Twin classes whose halfedge sum exceeds
+/// [`MAX_HALFEDGES`] are skipped, since [`Star::validate`] rejects the merged neighbor.
+pub fn merge_twin_neighbors(
+    star: &Star,
+    can_merge: impl Fn(&Star, usize, usize) -> bool,
+) -> Option<Star> {
+    merge_twin_neighbors_with_map(star, can_merge).map(|(merged, _)| merged)
+}
 
-        // Propagate singleton root assignment by removing that color from neighbors.
-        if new_root.count_ones() == 1 {
-            for nc in new_neighbor_colors.iter_mut() {
-                *nc &= !new_root;
+/// Like [`merge_twin_neighbors`], but also returns for every neighbor of `star` the index of the
+/// neighbor of the merged star that it became.
+pub fn merge_twin_neighbors_with_map(
+    star: &Star,
+    can_merge: impl Fn(&Star, usize, usize) -> bool,
+) -> Option<(Star, Vec<usize>)> {
+    if star.neighbor_colors.len() != star.neighbor_halfedges.len() {
+        return None;
+    }
+
+    let degree = star.neighbor_colors.len();
+    for keep_idx in 0..degree {
+        let colors = star.neighbor_colors[keep_idx];
+        let mut merged: Vec<usize> = Vec::new();
+        let mut sum: Option<u8> = Some(star.neighbor_halfedges[keep_idx]);
+        for j in (keep_idx + 1)..degree {
+            if star.neighbor_colors[j] == colors && can_merge(star, keep_idx, j) {
+                merged.push(j);
+                sum = sum.and_then(|s| s.checked_add(star.neighbor_halfedges[j]));
             }
         }
 
-        let mut star = Star {
-            root_colors: new_root,
+        let Some(sum) = sum.filter(|&s| !merged.is_empty() && s as usize <= MAX_HALFEDGES) else {
+            continue;
+        };
+
+        let mut new_neighbor_colors: Vec<u8> = Vec::with_capacity(degree - merged.len());
+        let mut new_neighbor_halfedges: Vec<u8> = Vec::with_capacity(degree - merged.len());
+        let mut neighbor_map: Vec<usize> = vec![0; degree];
+        for i in 0..degree {
+            if merged.contains(&i) {
+                neighbor_map[i] = neighbor_map[keep_idx];
+                continue;
+            }
+            neighbor_map[i] = new_neighbor_colors.len();
+            if i == keep_idx {
+                new_neighbor_colors.push(colors);
+                new_neighbor_halfedges.push(sum);
+                continue;
+            }
+            new_neighbor_colors.push(star.neighbor_colors[i]);
+            new_neighbor_halfedges.push(star.neighbor_halfedges[i]);
+        }
+
+        let merged_star = Star {
+            root_colors: star.root_colors,
             neighbor_colors: new_neighbor_colors,
             neighbor_halfedges: new_neighbor_halfedges,
+            neighbor_adjacency: None,
         };
-        star = reduce_duplicate_2lists(&star).unwrap_or(star);
-        out.push(star);
+        return Some((merged_star, neighbor_map));
     }
-    out
+
+    None
 }
 
 /// If the root has exactly 2 colors and there are at least two neighbors with the exact
 /// same color list as the root, merges all such neighbors into a single neighbor.
 ///
 /// The merged neighbor keeps the same color list as the root, and its halfedges become the
-/// sum of the merged neighbors' halfedges.
+/// sum of the merged neighbors' halfedges. This is [`merge_twin_neighbors`] with the
+/// predicate "the twins' list equals the root's 2-list": whichever color the root takes, the
+/// twins are forced to the other one.
 ///
-/// Returns `None` if no reduction applies or if the halfedge sum overflows `u8`.
+/// Returns `None` if no reduction applies or if the halfedge sum exceeds [`MAX_HALFEDGES`].
 pub fn reduce_duplicate_2lists(star: &Star) -> Option<Star> {
+    reduce_duplicate_2lists_with_map(star).map(|(reduced, _)| reduced)
+}
+
+/// Like [`reduce_duplicate_2lists`], but also returns the neighbor map of
+/// [`merge_twin_neighbors_with_map`].
+pub fn reduce_duplicate_2lists_with_map(star: &Star) -> Option<(Star, Vec<usize>)> {
     if star.root_colors.count_ones() != 2 {
         return None;
     }
-    if star.neighbor_colors.len() != star.neighbor_halfedges.len() {
-        return None;
-    }
+    merge_twin_neighbors_with_map(star, |s, i, _| s.neighbor_colors[i] == s.root_colors)
+}
+
+/// Like [`reduce_duplicate_2lists`], but caps the halfedges of a merged neighbor so that its
+/// degree stays in the degree bucket of `max_degree` of the twin with the most halfedges
+/// (see [`MaxDegree::bucket_top`]).
+///
+/// The halfedge sum can move the merged neighbor into a bucket with a larger weight than all
+/// twins together, so [`reduce_duplicate_2lists`] can increase the measure. With the cap, the
+/// reduced star has the weight of that twin, and the measure never increases for nonnegative
+/// weights.
+pub fn reduce_duplicate_2lists_capped(star: &Star, max_degree: MaxDegree) -> Option<Star> {
+    reduce_duplicate_2lists_capped_with_map(star, max_degree).map(|(reduced, _)| reduced)
+}
 
-    // Find neighbors whose list equals the root list.
-    let mut matching_indices: Vec<usize> = Vec::new();
-    let mut sum: u16 = 0;
-    for (i, &c) in star.neighbor_colors.iter().enumerate() {
-        if c == star.root_colors {
-            matching_indices.push(i);
-            sum += star.neighbor_halfedges[i] as u16
+/// Like [`reduce_duplicate_2lists_capped`], but also returns the neighbor map of
+/// [`merge_twin_neighbors_with_map`].
+pub fn reduce_duplicate_2lists_capped_with_map(
+    star: &Star,
+    max_degree: MaxDegree,
+) -> Option<(Star, Vec<usize>)> {
+    let (mut reduced, neighbor_map) = reduce_duplicate_2lists_with_map(star)?;
+    for (k, halfedges) in reduced.neighbor_halfedges.iter_mut().enumerate() {
+        let twins: Vec<u8> = (0..neighbor_map.len())
+            .filter(|&i| neighbor_map[i] == k)
+            .map(|i| star.neighbor_halfedges[i])
+            .collect();
+        let Some(&largest) = twins.iter().max().filter(|_| twins.len() >= 2) else {
+            continue;
+        };
+        // A neighbor has degree `halfedges + 1`, and `top` is at least the degree of `largest`.
+        if let Some(top) = max_degree.bucket_top(largest as usize + 1) {
+            *halfedges = (*halfedges as usize).min(top - 1) as u8;
         }
     }
+    Some((reduced, neighbor_map))
+}
 
-    if matching_indices.len() < 2 {
+/// Removes root colors that are dominated by another root color.
+///
+/// Color `a` dominates color `b` at the root if every coloring that gives the root `b` stays
+/// proper after recoloring the root to `a`. The subset condition "every neighbor containing `a`
+/// also contains `b`" is not enough on its own, since the halfedges may force a shared neighbor
+/// to either color; it only suffices when no neighbor contains `a` at all. This gives two cases:
+/// - If some root color `a` appears in no neighbor list, it dominates every other root color
+///   and the root list becomes `{a}` (the smallest such color).
+/// - If a neighbor has the singleton list `{b}`, then `b` is never available to the root and is
+///   removed from the root list.
+///
+/// Returns `None` if the root list has fewer than 2 colors or no color is dominated.
+pub fn reduce_dominated_root_colors(star: &Star) -> Option<Star> {
+    if star.root_colors.count_ones() < 2 {
         return None;
     }
 
-    if sum > u8::MAX as u16 {
-        return None;
+    let mut used: u8 = 0;
+    let mut forced: u8 = 0;
+    for &c in star.neighbor_colors.iter() {
+        used |= c;
+        if c.count_ones() == 1 {
+            forced |= c;
+        }
     }
 
-    let merged_halfedges = sum as u8;
-
-    // Keep the first matching neighbor, drop the rest.
-    let keep_idx = matching_indices[0];
-    let mut new_neighbor_colors: Vec<u8> =
-        Vec::with_capacity(star.neighbor_colors.len() - matching_indices.len() + 1);
-    let mut new_neighbor_halfedges: Vec<u8> =
-        Vec::with_capacity(star.neighbor_halfedges.len() - matching_indices.len() + 1);
+    let private = star.root_colors & !used;
+    let new_root = if private != 0 {
+        // Lowest private color.
+        private & private.wrapping_neg()
+    } else {
+        star.root_colors & !forced
+    };
 
-    for i in 0..star.neighbor_colors.len() {
-        if i == keep_idx {
-            new_neighbor_colors.push(star.neighbor_colors[i]);
-            new_neighbor_halfedges.push(merged_halfedges);
-            continue;
-        }
-        if star.neighbor_colors[i] == star.root_colors {
-            continue;
-        }
-        new_neighbor_colors.push(star.neighbor_colors[i]);
-        new_neighbor_halfedges.push(star.neighbor_halfedges[i]);
+    if new_root == star.root_colors || new_root == 0 {
+        return None;
     }
 
     Some(Star {
-        root_colors: star.root_colors,
-        neighbor_colors: new_neighbor_colors,
-        neighbor_halfedges: new_neighbor_halfedges,
+        root_colors: new_root,
+        neighbor_colors: star.neighbor_colors.clone(),
+        neighbor_halfedges: star.neighbor_halfedges.clone(),
+        neighbor_adjacency: None,
     })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::star_utils::{
+        EnumerationConfig, NeighborAdjacency, generate_stars, generate_stars_with_config,
+    };
+    use alloc::boxed::Box;
+    use alloc::string::ToString;
 
     fn is_valid_partition(colors: u8, part: &[u8]) -> bool {
         if colors == 0 {
             return part.is_empty();
         }
-        if part.iter().any(|&b| b == 0) {
+        if part.contains(&0) {
             return false;
         }
         let mut union = 0u8;
@@ -360,17 +950,49 @@ mod tests {
         }
     }
 
+    #[test]
+    fn apply_all_partitions_matches_single_partitions() {
+        let mut stars = generate_stars(3);
+        stars.truncate(200);
+        stars.push(Star {
+            root_colors: 0b1011,
+            neighbor_colors: vec![0b0011, 0b1001, 0b0110],
+            neighbor_halfedges: vec![2, 3, 2],
+            neighbor_adjacency: Some(Box::new(NeighborAdjacency::from_edges(3, &[(0, 1)]))),
+        });
+        for star in stars {
+            let all = apply_all_partitions(&star);
+            let partitions = partitions_of_colors(star.root_colors);
+            assert_eq!(all.len(), partitions.len());
+            for ((partition, branches), expected) in all.into_iter().zip(partitions) {
+                assert_eq!(partition, expected);
+                assert_eq!(
+                    branches,
+                    apply_list_coloring_partition_unreduced(&star, &partition)
+                );
+                let reduced: Vec<Branch> = branches
+                    .into_iter()
+                    .map(|b| reduce_branch(b, DEFAULT_REDUCTIONS))
+                    .collect();
+                assert_eq!(reduced, apply_list_coloring_partition(&star, &partition));
+            }
+        }
+    }
+
     #[test]
     fn apply_list_coloring_partition_splits_into_branches() {
         let star = Star {
             root_colors: 0b0111,
             neighbor_colors: vec![0b0011, 0b0101, 0b0110],
             neighbor_halfedges: vec![2, 2, 2],
+            neighbor_adjacency: None,
         };
         let partition = vec![0b0001, 0b0110];
 
         let branches = apply_list_coloring_partition(&star, &partition);
         assert_eq!(branches.len(), 2);
+        let branches: Vec<&Star> = branches.iter().filter_map(Branch::as_star).collect();
+        assert_eq!(branches.len(), 2);
 
         // Root=1 is singleton: remove 1 from all neighbors, but keep all neighbors.
         assert!(branches.iter().any(|b| {
@@ -387,12 +1009,79 @@ mod tests {
         }));
     }
 
+    #[test]
+    fn apply_list_coloring_partition_marks_terminal_branches() {
+        let star = Star {
+            root_colors: 0b0011,
+            neighbor_colors: vec![0b0001, 0b0011],
+            neighbor_halfedges: vec![2, 3],
+            neighbor_adjacency: None,
+        };
+        let branches = apply_list_coloring_partition(&star, &[0b0010, 0b0001]);
+
+        // Root=1 forces both neighbors to color 0.
+        assert_eq!(branches[0], Branch::Solved);
+        // Root=0 empties the first neighbor's list.
+        assert_eq!(branches[1], Branch::Infeasible);
+
+        assert_eq!(
+            try_apply_list_coloring_partition(&star, &[0b0010, 0b0001]),
+            Ok(branches)
+        );
+        for partition in [
+            &[0b0011, 0b0001][..],
+            &[0b0010],
+            &[0b0110, 0b0001],
+            &[0b0011, 0],
+        ] {
+            assert_eq!(
+                try_apply_list_coloring_partition(&star, partition),
+                Err(PartitionError::NotAPartition {
+                    root_colors: 0b0011,
+                    partition: partition.to_vec()
+                })
+            );
+        }
+        let broken = Star {
+            neighbor_halfedges: vec![2],
+            ..star
+        };
+        let err = try_apply_list_coloring_partition(&broken, &[0b0011]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid star: 2 neighbor color lists but 1 halfedge counts"
+        );
+    }
+
+    #[test]
+    fn group_branches_merges_symmetric_branches() {
+        // The three singleton branches are the same up to renaming colors.
+        let star = Star {
+            root_colors: 0b0111,
+            neighbor_colors: vec![0b0111, 0b0111, 0b1111],
+            neighbor_halfedges: vec![2, 2, 3],
+            neighbor_adjacency: None,
+        };
+        let groups = group_branches(apply_list_coloring_partition(
+            &star,
+            &[0b0100, 0b0010, 0b0001],
+        ));
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].1, 3);
+
+        // {2} | {0,1} has two different branch types.
+        let groups = group_branches(apply_list_coloring_partition(&star, &[0b0100, 0b0011]));
+        assert_eq!(groups.len(), 2);
+        assert!(groups.iter().all(|(_, m)| *m == 1));
+    }
+
     #[test]
     fn reduce_duplicate_2lists_merges_root_list_neighbors() {
         let star = Star {
             root_colors: 0b0011,
             neighbor_colors: vec![0b0011, 0b0101, 0b0011, 0b0110],
             neighbor_halfedges: vec![2, 3, 5, 7],
+            neighbor_adjacency: None,
         };
 
         let reduced = reduce_duplicate_2lists(&star).expect("should reduce");
@@ -433,6 +1122,7 @@ mod tests {
             root_colors: 0b0111,
             neighbor_colors: vec![0b0111, 0b0111],
             neighbor_halfedges: vec![2, 2],
+            neighbor_adjacency: None,
         };
         assert!(reduce_duplicate_2lists(&star).is_none());
 
@@ -441,10 +1131,118 @@ mod tests {
             root_colors: 0b0011,
             neighbor_colors: vec![0b0011, 0b0101],
             neighbor_halfedges: vec![2, 2],
+            neighbor_adjacency: None,
         };
         assert!(reduce_duplicate_2lists(&star2).is_none());
     }
 
+    #[test]
+    fn merge_twin_neighbors_respects_predicate() {
+        let star = Star {
+            root_colors: 0b0111,
+            neighbor_colors: vec![0b0110, 0b0011, 0b0110, 0b0011],
+            neighbor_halfedges: vec![2, 3, 4, 5],
+            neighbor_adjacency: None,
+        };
+
+        assert!(merge_twin_neighbors(&star, |_, _, _| false).is_none());
+
+        // The first twin class is merged first.
+        let merged = merge_twin_neighbors(&star, |_, _, _| true).expect("should merge");
+        assert_eq!(merged.neighbor_colors, vec![0b0110, 0b0011, 0b0011]);
+        assert_eq!(merged.neighbor_halfedges, vec![6, 3, 5]);
+
+        // The predicate can restrict merging to a specific class.
+        let merged = merge_twin_neighbors(&star, |s, i, _| s.neighbor_colors[i] == 0b0011)
+            .expect("should merge");
+        assert_eq!(merged.neighbor_colors, vec![0b0110, 0b0011, 0b0110]);
+        assert_eq!(merged.neighbor_halfedges, vec![2, 8, 4]);
+
+        // Sums up to MAX_HALFEDGES are merged, larger ones skipped, even with many twins.
+        let heavy = Star {
+            root_colors: 0b0111,
+            neighbor_colors: vec![0b0011; 2],
+            neighbor_halfedges: vec![10, 5],
+            neighbor_adjacency: None,
+        };
+        let merged = merge_twin_neighbors(&heavy, |_, _, _| true).expect("should merge");
+        assert_eq!(merged.neighbor_halfedges, vec![MAX_HALFEDGES as u8]);
+        assert_eq!(merged.validate(), Ok(()));
+        let heavier = Star {
+            neighbor_halfedges: vec![10, 6],
+            ..heavy.clone()
+        };
+        assert!(merge_twin_neighbors(&heavier, |_, _, _| true).is_none());
+        let crowd = Star {
+            root_colors: 0b0111,
+            neighbor_colors: vec![0b0011; 300],
+            neighbor_halfedges: vec![255; 300],
+            neighbor_adjacency: None,
+        };
+        assert!(merge_twin_neighbors(&crowd, |_, _, _| true).is_none());
+    }
+
+    #[test]
+    fn duplicate_2lists_only_merges_forced_equal_twins() {
+        for star in generate_stars(3) {
+            let Some(reduced) = reduce_duplicate_2lists(&star) else {
+                continue;
+            };
+            assert!(reduced.neighbor_colors.len() < star.neighbor_colors.len());
+            for i in 0..star.neighbor_colors.len() {
+                for j in (i + 1)..star.neighbor_colors.len() {
+                    if star.neighbor_colors[i] == star.root_colors
+                        && star.neighbor_colors[j] == star.root_colors
+                    {
+                        assert!(neighbors_forced_equal(&star, i, j), "{star:?}");
+                    }
+                }
+            }
+        }
+
+        // A 3-list root leaves {0,1}-neighbors free to differ.
+        let star = Star {
+            root_colors: 0b0111,
+            neighbor_colors: vec![0b0011, 0b0011],
+            neighbor_halfedges: vec![2, 2],
+            neighbor_adjacency: None,
+        };
+        assert!(!neighbors_forced_equal(&star, 0, 1));
+    }
+
+    #[test]
+    fn reduce_dominated_root_colors_handles_private_and_forced_colors() {
+        // Color 3 appears in no neighbor list: the root can always take it.
+        let star = Star {
+            root_colors: 0b1011,
+            neighbor_colors: vec![0b0011, 0b0110],
+            neighbor_halfedges: vec![2, 2],
+            neighbor_adjacency: None,
+        };
+        let reduced = reduce_dominated_root_colors(&star).expect("should reduce");
+        assert_eq!(reduced.root_colors, 0b1000);
+        assert_eq!(reduced.neighbor_colors, star.neighbor_colors);
+
+        // Neighbor {1} always uses color 1, so the root can drop it.
+        let star = Star {
+            root_colors: 0b0111,
+            neighbor_colors: vec![0b0010, 0b0101, 0b0111],
+            neighbor_halfedges: vec![2, 3, 2],
+            neighbor_adjacency: None,
+        };
+        let reduced = reduce_dominated_root_colors(&star).expect("should reduce");
+        assert_eq!(reduced.root_colors, 0b0101);
+
+        // Overlapping neighbor sets alone do not dominate.
+        let star = Star {
+            root_colors: 0b0011,
+            neighbor_colors: vec![0b0011, 0b0110],
+            neighbor_halfedges: vec![2, 2],
+            neighbor_adjacency: None,
+        };
+        assert!(reduce_dominated_root_colors(&star).is_none());
+    }
+
     #[test]
     fn star_list_degree_counts_counts_root_and_neighbors() {
         // Degree(root)=4. Root list size=4.
@@ -453,6 +1251,7 @@ mod tests {
             root_colors: 0b1111,
             neighbor_colors: vec![0b1111, 0b0111, 0b0011, 0b0111],
             neighbor_halfedges: vec![4, 3, 2, 2],
+            neighbor_adjacency: None,
         };
 
         let c = star_list_degree_counts(&star);
@@ -461,6 +1260,42 @@ mod tests {
         assert_eq!(c.n3_4, 1.0); // neighbor (deg 4)
         assert_eq!(c.n3_3, 1.0); // neighbor (deg 3)
         assert_eq!(c.n2_3, 1.0); // neighbor (deg 3)
+
+        // The kernel agrees with bumping every vertex on its own.
+        for max_degree in [3, 5, 6] {
+            let max_degree = MaxDegree(max_degree);
+            let config = EnumerationConfig {
+                max_degree: Some(max_degree.0),
+                ..EnumerationConfig::default()
+            };
+            for star in generate_stars_with_config(3, &config) {
+                for schema in [FeatureSchema::Counts, FeatureSchema::HalfedgeWeighted] {
+                    let mut expected = NodeFeatures::default();
+                    let root_degree = star.neighbor_colors.len();
+                    bump_count_by(
+                        &mut expected,
+                        star.root_colors.count_ones(),
+                        root_degree,
+                        1.0,
+                        max_degree,
+                    );
+                    for (&colors, &h) in star.neighbor_colors.iter().zip(&star.neighbor_halfedges) {
+                        let amount = match schema {
+                            FeatureSchema::Counts => 1.0,
+                            FeatureSchema::HalfedgeWeighted => h as f64,
+                        };
+                        bump_count_by(
+                            &mut expected,
+                            colors.count_ones(),
+                            h as usize + 1,
+                            amount,
+                            max_degree,
+                        );
+                    }
+                    assert_eq!(star_features(&star, schema, max_degree), expected);
+                }
+            }
+        }
     }
 
     #[test]
@@ -491,6 +1326,99 @@ mod tests {
         assert_eq!(a * b, 10.0);
     }
 
+    #[test]
+    fn features_normalize_and_standardize() {
+        let star = Star {
+            root_colors: 0b0111,
+            neighbor_colors: vec![0b0011, 0b0110, 0b1111],
+            neighbor_halfedges: vec![2, 2, 4],
+            neighbor_adjacency: None,
+        };
+        let features = NodeFeatures::from_array([3.0, 0.0, 6.0, 0.0, 0.0, 0.0, 0.0, 0.0, 12.0]);
+        assert_eq!(
+            features.normalized(FeatureNormalization::by_degree(&star)),
+            NodeFeatures::from_array([1.0, 0.0, 2.0, 0.0, 0.0, 0.0, 0.0, 0.0, 4.0])
+        );
+        assert_eq!(
+            features.normalized(FeatureNormalization::by_vertices(&star)),
+            NodeFeatures::from_array([0.75, 0.0, 1.5, 0.0, 0.0, 0.0, 0.0, 0.0, 3.0])
+        );
+        assert_eq!(
+            features.normalized(FeatureNormalization::ByDegree(0)),
+            features
+        );
+
+        let corpus = [
+            NodeFeatures::from_array([1.0, 5.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]),
+            NodeFeatures::from_array([3.0, 5.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]),
+        ];
+        let standardization = FeatureStandardization::fit(&corpus);
+        assert_eq!(standardization.mean.n4_ge5, 2.0);
+        assert_eq!(standardization.std.n4_ge5, 1.0);
+        assert_eq!(standardization.std.n4_4, 0.0);
+        assert_eq!(
+            standardization.apply(corpus[0]).to_array(),
+            [-1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]
+        );
+        assert_eq!(
+            FeatureStandardization::fit(&[]),
+            FeatureStandardization::default()
+        );
+    }
+
+    #[test]
+    fn halfedge_weighted_features() {
+        let star = Star {
+            root_colors: 0b0111,
+            neighbor_colors: vec![0b0011, 0b0011, 0b1111],
+            neighbor_halfedges: vec![2, 4, 4],
+            neighbor_adjacency: None,
+        };
+        let counts = star_features(&star, FeatureSchema::Counts, MaxDegree::default());
+        assert_eq!(counts, star_list_degree_counts(&star));
+        assert_eq!((counts.n3_3, counts.n2_3, counts.n2_ge5), (1.0, 1.0, 1.0));
+
+        let weighted = star_features(&star, FeatureSchema::HalfedgeWeighted, MaxDegree::default());
+        assert_eq!(weighted.n3_3, 1.0);
+        assert_eq!(weighted.n2_3, 2.0);
+        assert_eq!(weighted.n2_ge5, 4.0);
+        assert_eq!(weighted.n4_ge5, 4.0);
+    }
+
+    #[test]
+    fn max_degree_shifts_the_degree_buckets() {
+        let star = Star {
+            root_colors: 0b0111,
+            neighbor_colors: vec![0b0011, 0b0011, 0b1111],
+            neighbor_halfedges: vec![2, 4, 5],
+            neighbor_adjacency: None,
+        };
+        let features = |delta| star_features(&star, FeatureSchema::Counts, delta);
+        assert_eq!(features(MaxDegree(5)), star_list_degree_counts(&star));
+        // Degrees 3 (root), 3, 5 and 6; for Δ = 6, degree 3 is below the smallest bucket.
+        let six = features(MaxDegree(6));
+        assert_eq!(
+            (six.n3_4, six.n2_4, six.n2_ge5, six.n4_ge5),
+            (0.0, 1.0, 0.0, 1.0)
+        );
+        assert_eq!(six.to_array().iter().sum::<f64>(), 2.0);
+        let four = features(MaxDegree(4));
+        assert_eq!(
+            (four.n3_4, four.n2_4, four.n2_ge5, four.n4_ge5),
+            (1.0, 1.0, 1.0, 1.0)
+        );
+
+        assert_eq!(
+            MaxDegree(5).feature_names(),
+            NodeFeatures::NAMES.map(String::from)
+        );
+        assert_eq!(
+            MaxDegree(6).feature_names()[..3],
+            ["n4_ge6", "n4_5", "n4_4"]
+        );
+        assert_eq!(MaxDegree(5).bucket(2), None);
+    }
+
     #[test]
     fn node_features_to_json_string_is_compact() {
         let f = NodeFeatures {
@@ -514,4 +1442,15 @@ mod tests {
         assert!(!s.contains('\n'));
         assert!(!s.contains('\t'));
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trips_node_features() {
+        let features = NodeFeatures::from_array([1.0, 0.0, 2.0, 0.5, 0.0, 0.0, 3.0, 0.0, 1.25]);
+        let json = serde_json::to_string(&features).unwrap();
+        assert_eq!(
+            serde_json::from_str::<NodeFeatures>(&json).unwrap(),
+            features
+        );
+    }
 }
@@ -0,0 +1,246 @@
+use alloc::format;
+use alloc::string::String;
+
+use crate::analysis::AnalysisConfig;
+use crate::colors::NUM_COLORS;
+use crate::reductions::{InvariantViolation, MeasureIncrease};
+use crate::report::{BranchReport, CaseReport};
+use crate::star_utils::{CountError, EnumerationConfig, Star, count_stars_with_config};
+use crate::tree_utils::{
+    EdgeConstraint, Node, count_colored_uniform_trees_with, uniform_tree_size,
+};
+
+// Estimates of the memory an enumeration takes when it is collected into a `Vec`, so that the
+// binaries can refuse to start a run that would exhaust the memory of the machine. The
+// estimates count the values and their heap allocations, but not the allocator's overhead.
+
+/// The memory limit of the binaries unless `--memory-limit` is given: 4 GiB.
+pub const DEFAULT_MEMORY_LIMIT: u64 = 4 << 30;
+
+/// The estimated size in bytes of `generate_stars(degree)`, see
+/// [`estimate_memory_with_config`].
+pub fn estimate_memory(degree: usize) -> Result<u128, CountError> {
+    estimate_memory_with_config(degree, &EnumerationConfig::default())
+}
+
+/// The estimated size in bytes of the stars of
+/// [`generate_stars_with_config`](crate::star_utils::generate_stars_with_config), from
+/// [`count_stars_with_config`]; an upper bound if `config` has halfedge bounds or orbits.
+pub fn estimate_memory_with_config(
+    degree: usize,
+    config: &EnumerationConfig,
+) -> Result<u128, CountError> {
+    let per_star = (size_of::<Star>() + star_heap(degree)) as u128;
+    count_stars_with_config(degree, config)?
+        .checked_mul(per_star)
+        .ok_or(CountError::Overflow)
+}
+
+/// The estimated size in bytes of the
+/// [`AnalysisReport`](crate::report::AnalysisReport) of the stars of `degree` under `config`:
+/// a [`CaseReport`] per star with a [`BranchReport`] for every block of its partition, each
+/// naming every reduction of `config` once. An upper bound on the cases, which leave out the
+/// stars that reduce.
+pub fn estimate_report_memory(degree: usize, config: &AnalysisConfig) -> Result<u128, CountError> {
+    let blocks = max_blocks(config);
+    let branch = size_of::<BranchReport>()
+        + star_heap(degree)
+        + config
+            .reductions
+            .iter()
+            .map(|r| size_of::<String>() + r.name().len() + size_of::<f64>())
+            .sum::<usize>();
+    per_star(
+        degree,
+        config,
+        size_of::<CaseReport>() + star_heap(degree) + blocks * (1 + branch),
+    )
+}
+
+/// The estimated size in bytes of the failures `verify` collects for the stars of `degree`
+/// under `config` if every star fails: an [`InvariantViolation`] and a [`MeasureIncrease`] per
+/// star. Stars with several failing steps are rare enough to be left out.
+pub fn estimate_failures_memory(
+    degree: usize,
+    config: &AnalysisConfig,
+) -> Result<u128, CountError> {
+    let violation = size_of::<InvariantViolation>() + 2 * star_heap(degree);
+    let increase = size_of::<MeasureIncrease>() + 3 * star_heap(degree) + max_blocks(config);
+    per_star(degree, config, violation + increase)
+}
+
+/// The most blocks of a partition under `config`: one per color, at most `max_blocks`.
+fn max_blocks(config: &AnalysisConfig) -> usize {
+    let colors = config.enumeration.colors as usize;
+    config.max_blocks.map_or(colors, |b| b.min(colors))
+}
+
+/// The heap size of a star of `degree`: its neighbor lists and halfedges.
+fn star_heap(degree: usize) -> usize {
+    2 * degree
+}
+
+/// `bytes` for every star of `degree` under `config`.
+fn per_star(degree: usize, config: &AnalysisConfig, bytes: usize) -> Result<u128, CountError> {
+    count_stars_with_config(degree, &config.enumeration)?
+        .checked_mul(bytes as u128)
+        .ok_or(CountError::Overflow)
+}
+
+/// The estimated size in bytes of
+/// [`generate_colored_uniform_trees`](crate::tree_utils::generate_colored_uniform_trees) for
+/// `depth` and `degree`.
+pub fn estimate_memory_trees(depth: usize, degree: usize) -> Result<u128, CountError> {
+    estimate_memory_trees_with(depth, degree, EdgeConstraint::default(), NUM_COLORS)
+}
+
+/// Like [`estimate_memory_trees`] for every parent/child edge satisfying `constraint` and the
+/// lists of `colors` colors.
+pub fn estimate_memory_trees_with(
+    depth: usize,
+    degree: usize,
+    constraint: EdgeConstraint,
+    colors: u8,
+) -> Result<u128, CountError> {
+    let per_tree = uniform_tree_size(depth, degree)?
+        .checked_mul(size_of::<Node>() as u128)
+        .ok_or(CountError::Overflow)?;
+    count_colored_uniform_trees_with(depth, degree, constraint, colors)?
+        .checked_mul(per_tree)
+        .ok_or(CountError::Overflow)
+}
+
+/// Parses a number of bytes with an optional binary unit `K`, `M`, `G` or `T`, e.g. `512M`.
+pub fn parse_memory_size(s: &str) -> Result<u64, String> {
+    let invalid = || format!("invalid memory size: {s}");
+    let (digits, shift) = match s.as_bytes().last() {
+        Some(b'K' | b'k') => (&s[..s.len() - 1], 10),
+        Some(b'M' | b'm') => (&s[..s.len() - 1], 20),
+        Some(b'G' | b'g') => (&s[..s.len() - 1], 30),
+        Some(b'T' | b't') => (&s[..s.len() - 1], 40),
+        _ => (s, 0),
+    };
+    let n: u64 = digits.parse().map_err(|_| invalid())?;
+    n.checked_mul(1 << shift).ok_or_else(invalid)
+}
+
+/// Formats a number of bytes with the largest binary unit that keeps it at least 1, e.g.
+/// `1.5 GiB`.
+pub fn fmt_memory_size(bytes: u128) -> String {
+    const UNITS: [&str; 7] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{bytes} B"),
+        _ => format!("{value:.1} {}", UNITS[unit]),
+    }
+}
+
+/// What a binary does with an enumeration whose estimated size exceeds `limit` bytes: refuse
+/// to run unless `force` is set, and warn otherwise.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemoryGuard {
+    pub limit: u64,
+    pub force: bool,
+}
+
+impl Default for MemoryGuard {
+    fn default() -> Self {
+        MemoryGuard {
+            limit: DEFAULT_MEMORY_LIMIT,
+            force: false,
+        }
+    }
+}
+
+impl MemoryGuard {
+    /// Checks the `estimate` of the memory `what` takes, e.g. "the stars of degree 6". `Ok`
+    /// with a warning to print if the estimate exceeds the limit but `force` is set, and
+    /// `Err` with the reason to refuse if it is not; `alternative` suggests how to run
+    /// without collecting everything.
+    pub fn check(
+        &self,
+        estimate: u128,
+        what: &str,
+        alternative: &str,
+    ) -> Result<Option<String>, String> {
+        if estimate <= self.limit as u128 {
+            return Ok(None);
+        }
+        let message = format!(
+            "{what} take an estimated {}, more than the limit of {}",
+            fmt_memory_size(estimate),
+            fmt_memory_size(self.limit as u128)
+        );
+        if self.force {
+            Ok(Some(format!("warning: {message}")))
+        } else {
+            Err(format!(
+                "{message}; {alternative}, or pass --force or a larger --memory-limit"
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::star_utils::generate_stars;
+
+    #[test]
+    fn estimates_and_limits() {
+        let stars = generate_stars(3).len() as u128;
+        assert_eq!(
+            estimate_memory(3),
+            Ok(stars * (core::mem::size_of::<Star>() as u128 + 6))
+        );
+        assert!(estimate_memory_trees(2, 3).unwrap() > estimate_memory_trees(1, 3).unwrap());
+        assert!(estimate_memory(12).unwrap() > estimate_memory(6).unwrap());
+        let config = AnalysisConfig::default();
+        let report = estimate_report_memory(3, &config).unwrap();
+        assert!(report > estimate_memory(3).unwrap());
+        let limited = AnalysisConfig {
+            max_blocks: Some(2),
+            ..AnalysisConfig::default()
+        };
+        assert!(estimate_report_memory(3, &limited).unwrap() < report);
+        assert!(estimate_failures_memory(3, &config).unwrap() < report);
+        assert_eq!(estimate_memory_trees(4, 255), Err(CountError::Overflow));
+
+        assert_eq!(parse_memory_size("512M"), Ok(512 << 20));
+        assert_eq!(parse_memory_size("1000"), Ok(1000));
+        assert!(parse_memory_size("4X").is_err());
+        assert!(parse_memory_size("99999999999T").is_err());
+        assert_eq!(fmt_memory_size(1536 << 20), "1.5 GiB");
+        assert_eq!(fmt_memory_size(100), "100 B");
+
+        let guard = MemoryGuard {
+            limit: 1 << 20,
+            force: false,
+        };
+        assert_eq!(guard.check(1 << 20, "the stars", "stream them"), Ok(None));
+        let refused = guard
+            .check(2 << 20, "the stars", "stream them")
+            .unwrap_err();
+        assert_eq!(
+            refused,
+            "the stars take an estimated 2.0 MiB, more than the limit of 1.0 MiB; stream them, \
+             or pass --force or a larger --memory-limit"
+        );
+        let forced = MemoryGuard {
+            force: true,
+            ..guard
+        };
+        assert!(
+            forced
+                .check(2 << 20, "the stars", "stream them")
+                .unwrap()
+                .unwrap()
+                .starts_with("warning: ")
+        );
+    }
+}
@@ -0,0 +1,133 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::colors::colors;
+use crate::star_utils::{Star, star_label};
+
+/// The microstructure of a star seen as a constraint satisfaction problem: one vertex per
+/// assignment of a color to a vertex of the star, and an edge between two assignments to
+/// different vertices that are compatible, i.e. unless the vertices are adjacent and get the
+/// same color. The list colorings of the star are exactly the cliques that contain one
+/// assignment per vertex.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Microstructure {
+    /// The assignments `(vertex, color)`, where the vertex is `None` for the root and
+    /// `Some(i)` for neighbor `i`. Ordered by vertex, the root first, then by color.
+    pub assignments: Vec<(Option<usize>, u8)>,
+    /// The compatible pairs `(a, b)` of indices into `assignments`, with `a < b`. Sorted.
+    pub edges: Vec<(usize, usize)>,
+}
+
+impl Microstructure {
+    pub fn from_star(star: &Star) -> Self {
+        let vertices = core::iter::once((None, star.root_colors)).chain(
+            star.neighbor_colors
+                .iter()
+                .enumerate()
+                .map(|(i, &list)| (Some(i), list)),
+        );
+        let assignments: Vec<(Option<usize>, u8)> = vertices
+            .flat_map(|(v, list)| colors(list).map(move |c| (v, c)))
+            .collect();
+        let mut edges = Vec::new();
+        for (a, &(u, c)) in assignments.iter().enumerate() {
+            for (b, &(v, d)) in assignments.iter().enumerate().skip(a + 1) {
+                // The star's only edges join the root to the neighbors.
+                let adjacent = u.is_none() != v.is_none();
+                if u != v && !(adjacent && c == d) {
+                    edges.push((a, b));
+                }
+            }
+        }
+        Microstructure { assignments, edges }
+    }
+
+    /// Whether `coloring`, given as the root color followed by the neighbor colors, is a
+    /// clique of assignments, i.e. a list coloring of the star.
+    pub fn is_clique(&self, coloring: &[u8]) -> bool {
+        let vertex = |i: usize| i.checked_sub(1);
+        let index = |i: usize| {
+            self.assignments
+                .iter()
+                .position(|&a| a == (vertex(i), coloring[i]))
+        };
+        let indices: Option<Vec<usize>> = (0..coloring.len()).map(index).collect();
+        let Some(indices) = indices else {
+            return false;
+        };
+        indices.iter().enumerate().all(|(i, &a)| {
+            indices[i + 1..]
+                .iter()
+                .all(|&b| self.edges.binary_search(&(a.min(b), a.max(b))).is_ok())
+        })
+    }
+
+    /// Renders the microstructure in Graphviz DOT format, with the assignments of each vertex
+    /// of `star` in a labelled cluster. Since neighbors are compatible with each other in every
+    /// color, only the edges at the root are drawn unless `all_edges` is set.
+    pub fn to_dot(&self, star: &Star, all_edges: bool) -> String {
+        let mut out = format!(
+            "graph microstructure {{\n  label=\"{}\";\n  node [shape=circle, fontname=monospace];\n",
+            star_label(star).replace('"', "\\\"")
+        );
+        let names = |v: Option<usize>| match v {
+            None => (String::from("r"), String::from("root")),
+            Some(i) => (
+                format!("n{i}"),
+                format!("neighbor {i} ({} halfedges)", star.neighbor_halfedges[i]),
+            ),
+        };
+        let mut vertices: Vec<Option<usize>> = self.assignments.iter().map(|&(v, _)| v).collect();
+        vertices.dedup();
+        for v in vertices {
+            let (id, label) = names(v);
+            out.push_str(&format!(
+                "  subgraph cluster_{id} {{\n    label=\"{label}\";\n"
+            ));
+            for &(_, c) in self.assignments.iter().filter(|&&(u, _)| u == v) {
+                out.push_str(&format!("    {id}_{c} [label=\"{c}\"];\n"));
+            }
+            out.push_str("  }\n");
+        }
+        for &(a, b) in self.edges.iter() {
+            let ((u, c), (v, d)) = (self.assignments[a], self.assignments[b]);
+            if all_edges || u.is_none() || v.is_none() {
+                out.push_str(&format!("  {}_{c} -- {}_{d};\n", names(u).0, names(v).0));
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn colorings_are_cliques_of_the_microstructure() {
+        let star = Star {
+            root_colors: 0b0011,
+            neighbor_colors: vec![0b0011, 0b0110],
+            neighbor_halfedges: vec![2, 3],
+            neighbor_adjacency: None,
+        };
+        let m = Microstructure::from_star(&star);
+        assert_eq!(m.assignments.len(), 6);
+        // The root and neighbor 0 share 2 colors, the root and neighbor 1 share 1, and the
+        // neighbors are compatible in all 4 pairs of colors.
+        assert_eq!(m.edges.len(), 2 + 3 + 4);
+        assert!(m.is_clique(&[0, 1, 2]));
+        assert!(m.is_clique(&[1, 0, 2]));
+        assert!(!m.is_clique(&[0, 0, 1]));
+        assert!(!m.is_clique(&[0, 1, 3]));
+
+        let dot = m.to_dot(&star, false);
+        assert!(dot.starts_with("graph microstructure {"));
+        assert!(dot.contains("r_0 -- n1_1;"));
+        assert!(!dot.contains("n0_0 -- n1_1;"));
+        assert!(m.to_dot(&star, true).contains("n0_0 -- n1_1;"));
+    }
+}
@@ -0,0 +1,54 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+
+/// Formats a float for output.
+///
+/// With `precision = None` this is the shortest decimal string that parses back to the same
+/// value; with `Some(p)` the value is rounded to `p` digits after the decimal point. The decimal
+/// separator is always `.`, regardless of the system locale, and zero (including `-0.0`, also
+/// after rounding) is always printed without a sign.
+pub fn format_float(x: f64, precision: Option<usize>) -> String {
+    let s = match precision {
+        None => x.to_string(),
+        Some(p) => format!("{x:.p$}"),
+    };
+    match s.strip_prefix('-') {
+        Some(rest) if rest.bytes().all(|b| b == b'0' || b == b'.') => rest.to_string(),
+        _ => s,
+    }
+}
+
+/// Formats a float as a JSON value: finite values as numbers by [`format_float`], and the
+/// values JSON numbers cannot express as the strings `"inf"`, `"-inf"` and `"NaN"`, which
+/// [`JsonValue::as_float`](crate::json::JsonValue::as_float) reads back.
+pub fn format_json_float(x: f64, precision: Option<usize>) -> String {
+    if x.is_finite() {
+        format_float(x, precision)
+    } else if x.is_nan() {
+        "\"NaN\"".to_string()
+    } else if x > 0.0 {
+        "\"inf\"".to_string()
+    } else {
+        "\"-inf\"".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_float_shortest_and_fixed() {
+        assert_eq!(format_float(0.1 + 0.2, None), "0.30000000000000004");
+        assert_eq!(format_float(0.7, None), "0.7");
+        assert_eq!(format_float(2.0, None), "2");
+        assert_eq!(format_float(-0.0, None), "0");
+        assert_eq!(format_float(1.23456, Some(3)), "1.235");
+        assert_eq!(format_float(-0.0001, Some(2)), "0.00");
+        assert_eq!(format_float(-1.5, Some(0)), "-2");
+        assert_eq!(format_float(f64::INFINITY, Some(2)), "inf");
+        assert_eq!(format_json_float(2.0, None), "2");
+        assert_eq!(format_json_float(f64::NEG_INFINITY, None), "\"-inf\"");
+        assert_eq!(format_json_float(f64::NAN, Some(3)), "\"NaN\"");
+    }
+}
@@ -0,0 +1,580 @@
+use alloc::vec::Vec;
+
+use crate::analysis::{AnalysisConfig, most_violated_star};
+use crate::colors::PackedPartition;
+use crate::list_coloring_utils::{
+    Branch, FeatureSchema, NodeFeatures, NonFiniteFeature, star_features,
+};
+use crate::recurrence::branching_number;
+use crate::solver::SplitMix64;
+use crate::star_utils::Star;
+use crate::weights::{WeightConstraint, WeightTerm, Weights, shape_constraints};
+
+// The drop of a branch is `weights * (features before - features after)`, which is linear in
+// the weights. Precomputing the feature differences once per star turns the evaluation of new
+// weights into inner products, without applying partitions and reductions again.
+
+/// The branches of one partition of a star as feature differences, whose inner products with
+/// the weights are the drops. Infeasible branches are left out, as in
+/// [`Recurrence`](crate::recurrence::Recurrence).
+#[derive(Clone, Debug, PartialEq)]
+pub struct PartitionConstraint {
+    pub partition: PackedPartition,
+    pub drops: Vec<NodeFeatures>,
+}
+
+/// The partitions of one star, with the drops of their branches as functions of the weights.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StarConstraints {
+    pub star: Star,
+    pub partitions: Vec<PartitionConstraint>,
+}
+
+impl StarConstraints {
+    /// Applies every partition of the root list that `config` allows with its reductions,
+    /// measuring stars with the degree buckets of `config`.
+    pub fn new(star: &Star, config: &AnalysisConfig) -> Self {
+        let max_degree = config.max_degree();
+        let features = |s: &Star| star_features(s, FeatureSchema::Counts, max_degree);
+        let before = features(star);
+        let partitions = config
+            .all_branches(star)
+            .map(|(partition, branches)| {
+                let drops = branches
+                    .into_iter()
+                    .filter_map(|branch| match branch {
+                        Branch::Solved => Some(before),
+                        Branch::Infeasible => None,
+                        Branch::Star(b) => Some(before - features(&b)),
+                    })
+                    .collect();
+                PartitionConstraint {
+                    partition: PackedPartition::pack(&partition)
+                        .expect("partitions_of_colors returns partitions"),
+                    drops,
+                }
+            })
+            .collect();
+        StarConstraints {
+            star: star.clone(),
+            partitions,
+        }
+    }
+
+    /// The best branching number of the star under `weights`, as
+    /// [`best_partition`](crate::analysis::best_partition) computes it up to rounding; `None`
+    /// if the root list cannot be split.
+    pub fn branching_number(&self, weights: &Weights) -> Option<f64> {
+        self.best_partition(weights).map(|(_, bn)| bn)
+    }
+
+    /// The partition of the [best branching number](Self::branching_number) under `weights`,
+    /// the first one on ties, with that branching number.
+    pub fn best_partition(&self, weights: &Weights) -> Option<(PackedPartition, f64)> {
+        self.partitions
+            .iter()
+            .map(|p| {
+                let drops: Vec<f64> = p.drops.iter().map(|&d| weights.measure(d)).collect();
+                (p.partition, branching_number(&drops))
+            })
+            .reduce(|best, p| if p.1 < best.1 { p } else { best })
+    }
+}
+
+/// A star of the working set whose best branching number is within [`BINDING_TOLERANCE`] of
+/// the worst one at the optimized weights, see [`Optimization::binding`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct BindingStar {
+    pub star: Star,
+    /// The best partition of the star under the optimized weights.
+    pub partition: PackedPartition,
+    pub branching_number: f64,
+}
+
+/// How close to the worst branching number a star must be to count as binding. The local
+/// search of [`optimize_weights`] stops once its step is below `1e-6`, so the constraints it
+/// balances agree only up to about that much.
+pub const BINDING_TOLERANCE: f64 = 1e-6;
+
+/// The stars of `constraints` whose best branching number under `weights` is within
+/// [`BINDING_TOLERANCE`] of `worst`, from the largest branching number down.
+pub fn binding_stars(
+    constraints: &[StarConstraints],
+    weights: &Weights,
+    worst: f64,
+) -> Vec<BindingStar> {
+    let mut binding: Vec<BindingStar> = constraints
+        .iter()
+        .filter_map(|c| {
+            let (partition, bn) = c.best_partition(weights)?;
+            (bn >= worst - BINDING_TOLERANCE).then(|| BindingStar {
+                star: c.star.clone(),
+                partition,
+                branching_number: bn,
+            })
+        })
+        .collect();
+    binding.sort_by(|a, b| b.branching_number.total_cmp(&a.branching_number));
+    binding
+}
+
+/// The largest best branching number of the stars of `constraints` under `weights`, or 1 if
+/// no star branches.
+pub fn worst_branching_number(constraints: &[StarConstraints], weights: &Weights) -> f64 {
+    constraints
+        .iter()
+        .filter_map(|c| c.branching_number(weights))
+        .fold(1.0, f64::max)
+}
+
+/// The [`shape_constraints`] and an upper bound of 1 on every weight. Scaling all weights up
+/// scales all drops up, so without a bound the branching numbers approach 1.
+pub fn normalized_weight_constraints() -> Vec<WeightConstraint> {
+    let mut out = shape_constraints();
+    out.extend((0..9).map(|i| WeightConstraint {
+        larger: WeightTerm::Constant(1.0),
+        smaller: WeightTerm::Weight(i),
+    }));
+    out
+}
+
+/// Random starting weights for a restart of the optimization: every weight uniform in
+/// `[0, 1)`, projected onto [`normalized_weight_constraints`]. The same state of `rng` gives
+/// the same weights on every machine.
+pub fn random_start(rng: &mut SplitMix64) -> Weights {
+    let w: [f64; 9] = core::array::from_fn(|_| rng.next_f64());
+    Weights::from_array(w).project_onto(&normalized_weight_constraints())
+}
+
+/// Minimizes [`worst_branching_number`] over the weights satisfying `feasible` by a pattern
+/// search from `start`: every weight is moved up and down by a step, projected back onto
+/// `feasible`, and kept if the worst branching number improves; the step is halved when no
+/// move improves. Stops after `iterations` rounds of moves or once the step is below `1e-6`.
+///
+/// Returns the best weights found and their worst branching number. The search is local, so
+/// the weights are not guaranteed to be optimal.
+pub fn optimize_weights(
+    constraints: &[StarConstraints],
+    start: Weights,
+    feasible: &[WeightConstraint],
+    iterations: usize,
+) -> (Weights, f64) {
+    let mut weights = start.project_onto(feasible);
+    let mut worst = worst_branching_number(constraints, &weights);
+    let mut step = 0.25;
+    for _ in 0..iterations {
+        if step < 1e-6 {
+            break;
+        }
+        let mut improved = false;
+        for i in 0..9 {
+            for sign in [1.0, -1.0] {
+                let mut w = weights.to_array();
+                w[i] += sign * step;
+                let candidate = Weights::from_array(w).project_onto(feasible);
+                let value = worst_branching_number(constraints, &candidate);
+                if value < worst {
+                    (weights, worst) = (candidate, value);
+                    improved = true;
+                }
+            }
+        }
+        if !improved {
+            step /= 2.0;
+        }
+    }
+    (weights, worst)
+}
+
+/// The bound reached without one feature of the measure, see [`rank_features`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct FeatureRanking {
+    /// The index of the feature in [`NodeFeatures::to_array`].
+    pub feature: usize,
+    /// The worst branching number [`optimize_weights`] reaches with the weight of the feature
+    /// fixed to 0.
+    pub bound: f64,
+    /// How much `bound` exceeds the bound with all features; infinite if some star no longer
+    /// branches with a finite branching number.
+    pub worsening: f64,
+}
+
+/// Ranks the features of the measure by how much the bound over `constraints` worsens
+/// without them: for every feature, its weight is fixed to 0 and the other weights are
+/// optimized again with [`optimize_weights`] from `weights`, for `iterations` rounds. The
+/// shape constraints then also fix the weights of the shorter lists of its degree bucket to
+/// 0. The search is local, so it may find a smaller bound without a feature than with all of
+/// them; those weights are feasible with all features too, so the bound with all features is
+/// the smallest one found. Returns the features from the largest worsening down, so the first
+/// ones carry the bound and the last ones could be dropped from the measure at the smallest
+/// cost.
+pub fn rank_features(
+    constraints: &[StarConstraints],
+    weights: &Weights,
+    iterations: usize,
+) -> Vec<FeatureRanking> {
+    let feasible = normalized_weight_constraints();
+    let (_, all) = optimize_weights(constraints, *weights, &feasible, iterations);
+    let bounds: Vec<f64> = (0..9)
+        .map(|feature| {
+            let mut without = feasible.clone();
+            without.push(WeightConstraint {
+                larger: WeightTerm::Constant(0.0),
+                smaller: WeightTerm::Weight(feature),
+            });
+            optimize_weights(constraints, *weights, &without, iterations).1
+        })
+        .collect();
+    let base = bounds.iter().copied().fold(all, f64::min);
+    let mut ranking: Vec<FeatureRanking> = bounds
+        .into_iter()
+        .enumerate()
+        .map(|(feature, bound)| FeatureRanking {
+            feature,
+            bound,
+            worsening: bound - base,
+        })
+        .collect();
+    ranking.sort_by(|a, b| b.worsening.total_cmp(&a.worsening));
+    ranking
+}
+
+/// What [`optimize_weights_with_oracle`] reports after every round, so that a caller can show
+/// or save the best weights found so far while the optimization runs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OptimizationProgress {
+    pub round: usize,
+    /// The weights of the round, and their worst branching number over the working set.
+    pub weights: Weights,
+    pub worst: f64,
+    /// The number of stars in the working set.
+    pub stars: usize,
+    /// The star of the largest branching number under `weights` among those the oracle found
+    /// above `worst`, with that branching number; `None` if it found none.
+    pub worst_star: Option<(Star, f64)>,
+    /// The weights of the smallest bound over all stars in the rounds so far, and that bound:
+    /// the branching number of the worst star of their round, or `worst` if there was none
+    /// (up to the tolerance).
+    pub best_weights: Weights,
+    pub best_bound: f64,
+    /// The time since the optimization started.
+    #[cfg(feature = "std")]
+    pub elapsed: std::time::Duration,
+}
+
+/// The result of [`optimize_weights_with_oracle`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Optimization {
+    pub weights: Weights,
+    /// The worst best branching number over all stars, if the oracle found no star above it,
+    /// and otherwise over the stars of `constraints`.
+    pub worst: f64,
+    /// The stars the weights were optimized for, in the order the oracle found them.
+    pub constraints: Vec<StarConstraints>,
+    /// Whether the oracle found no star above `worst` in the last round.
+    pub converged: bool,
+    /// The stars that bind at `weights` among those of the last round, before the oracle
+    /// added its stars, see [`binding_stars`]. They stand in for the dual values of an LP over
+    /// the constraints: moving the weights to lower the branching number of any other star
+    /// does not lower the optimum.
+    pub binding: Vec<BindingStar>,
+}
+
+/// Optimizes the weights over all stars of the given degrees with cutting planes: the weights
+/// are optimized with [`optimize_weights`] for a set of stars, then the separation oracle
+/// [`most_violated_star`] looks for a star of `config` of every degree whose branching number
+/// exceeds the one reached, and those stars are added to the set. Starts from `start` and the
+/// stars in `initial`, and stops when the oracle finds no star or after `rounds` rounds. Every
+/// round searches from both the previous weights and `start`. Stars only branch on the
+/// partitions `config` allows, so `initial` should be built by [`StarConstraints::new`] with
+/// the same `config`.
+///
+/// `progress` is called after the search of every round, see [`OptimizationProgress`].
+pub fn optimize_weights_with_oracle(
+    degrees: core::ops::RangeInclusive<usize>,
+    config: &AnalysisConfig,
+    start: Weights,
+    initial: Vec<StarConstraints>,
+    rounds: usize,
+    progress: impl FnMut(&OptimizationProgress),
+) -> Result<Optimization, NonFiniteFeature> {
+    cutting_planes(degrees, config, start, initial, rounds, 1e-9, progress)
+}
+
+/// Optimizes the weights for the stars of `degree` with cutting planes, as
+/// [`optimize_weights_with_oracle`] for the [`AnalysisConfig::default`] stars, starting from
+/// all weights 1 and no stars. The working set only grows by the most violated star of every
+/// round, which the oracle finds by streaming the enumeration, so the stars are never held at
+/// once. Stops once no star exceeds the worst branching number of the working set by more than
+/// `tolerance`; every round adds a star that is not in the set yet, so this happens after
+/// finitely many rounds.
+pub fn optimize_weights_cutting_plane(
+    degree: usize,
+    tolerance: f64,
+) -> Result<Optimization, NonFiniteFeature> {
+    cutting_planes(
+        degree..=degree,
+        &AnalysisConfig::default(),
+        Weights::from_array([1.0; 9]),
+        Vec::new(),
+        usize::MAX,
+        tolerance,
+        |_| {},
+    )
+}
+
+/// The loop of [`optimize_weights_with_oracle`], adding the stars that exceed the worst
+/// branching number of the working set by more than `tolerance`.
+fn cutting_planes(
+    degrees: core::ops::RangeInclusive<usize>,
+    config: &AnalysisConfig,
+    start: Weights,
+    initial: Vec<StarConstraints>,
+    rounds: usize,
+    tolerance: f64,
+    mut progress: impl FnMut(&OptimizationProgress),
+) -> Result<Optimization, NonFiniteFeature> {
+    #[cfg(feature = "std")]
+    let started = std::time::Instant::now();
+    let feasible = normalized_weight_constraints();
+    let mut constraints = initial;
+    let (mut weights, mut worst) = (start, worst_branching_number(&constraints, &start));
+    let mut best: Option<(Weights, f64)> = None;
+    let mut binding = Vec::new();
+    for round in 1..=rounds {
+        // The previous weights may have an infinite branching number on the new stars, where
+        // the local search cannot make progress, so it also restarts from `start`.
+        (weights, worst) = [weights, start]
+            .map(|w| optimize_weights(&constraints, w, &feasible, 200))
+            .into_iter()
+            .fold((weights, f64::INFINITY), |best, candidate| {
+                if candidate.1 < best.1 {
+                    candidate
+                } else {
+                    best
+                }
+            });
+        let mut violated: Vec<(Star, f64)> = Vec::new();
+        for degree in degrees.clone() {
+            // Stars within the tolerance of the bound are not worth another round.
+            violated.extend(most_violated_star(
+                weights.0,
+                worst + tolerance,
+                degree,
+                config,
+            )?);
+        }
+        let worst_star = violated.iter().max_by(|a, b| a.1.total_cmp(&b.1)).cloned();
+        let bound = worst_star.as_ref().map_or(worst, |&(_, bn)| bn.max(worst));
+        if best.is_none_or(|(_, best_bound)| bound < best_bound) {
+            best = Some((weights, bound));
+        }
+        let (best_weights, best_bound) = best.expect("the first round sets the best weights");
+        progress(&OptimizationProgress {
+            round,
+            weights,
+            worst,
+            stars: constraints.len(),
+            worst_star,
+            best_weights,
+            best_bound,
+            #[cfg(feature = "std")]
+            elapsed: started.elapsed(),
+        });
+        binding = binding_stars(&constraints, &weights, worst);
+        constraints.extend(
+            violated
+                .iter()
+                .map(|(star, _)| StarConstraints::new(star, config)),
+        );
+        if violated.is_empty() {
+            return Ok(Optimization {
+                binding,
+                weights,
+                worst,
+                constraints,
+                converged: true,
+            });
+        }
+    }
+    Ok(Optimization {
+        binding,
+        weights,
+        worst,
+        constraints,
+        converged: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::best_partition;
+    use crate::list_coloring_utils::partitions_of_colors;
+    use crate::star_utils::generate_stars;
+
+    #[test]
+    fn optimized_weights_do_not_get_worse() {
+        let start = Weights::from_array([1.0, 1.0, 1.0, 0.7, 0.7, 0.7, 0.4, 0.4, 0.4]);
+        let stars = generate_stars(3);
+        let constraints: Vec<StarConstraints> = stars
+            .iter()
+            .take(50)
+            .map(|s| StarConstraints::new(s, &AnalysisConfig::default()))
+            .collect();
+        for (c, star) in constraints.iter().zip(stars.iter()) {
+            let expected = best_partition(star, start.0, &AnalysisConfig::default());
+            // Equal up to rounding, since the drops are computed as `w * (a - b)`.
+            let (actual, expected) = (c.branching_number(&start), expected.map(|(_, bn)| bn));
+            assert_eq!(actual.is_some(), expected.is_some());
+            assert!((actual.unwrap_or(0.0) - expected.unwrap_or(0.0)).abs() < 1e-9);
+        }
+
+        let feasible = normalized_weight_constraints();
+        let before = worst_branching_number(&constraints, &start);
+        let (weights, after) = optimize_weights(&constraints, start, &feasible, 20);
+        assert!(after <= before);
+        // Up to the tolerance of the projection.
+        assert!(
+            weights
+                .to_array()
+                .iter()
+                .all(|w| (-1e-9..=1.0 + 1e-9).contains(w))
+        );
+
+        let mut reports: Vec<OptimizationProgress> = Vec::new();
+        let result = optimize_weights_with_oracle(
+            3..=3,
+            &AnalysisConfig::default(),
+            start,
+            Vec::new(),
+            3,
+            |p| reports.push(p.clone()),
+        )
+        .unwrap();
+        // The best bound never gets worse and bounds every star under the best weights.
+        assert!(!reports.is_empty());
+        assert!(
+            reports
+                .windows(2)
+                .all(|w| w[1].best_bound <= w[0].best_bound)
+        );
+        let last = reports.last().unwrap();
+        assert_eq!(last.worst_star.is_none(), result.converged);
+        for report in reports.iter() {
+            if let Some((star, bn)) = report.worst_star.as_ref() {
+                assert!(*bn > report.worst);
+                let (_, best) =
+                    best_partition(star, report.weights.0, &AnalysisConfig::default()).unwrap();
+                assert_eq!(*bn, best);
+            }
+        }
+        assert_eq!(
+            most_violated_star(
+                last.best_weights.0,
+                last.best_bound + 1e-9,
+                3,
+                &AnalysisConfig::default()
+            ),
+            Ok(None)
+        );
+        if result.converged {
+            assert_eq!(
+                most_violated_star(
+                    result.weights.0,
+                    result.worst + 1e-9,
+                    3,
+                    &AnalysisConfig::default()
+                ),
+                Ok(None)
+            );
+        }
+        assert!(!result.constraints.is_empty());
+    }
+
+    #[test]
+    fn restarts_with_the_same_seed_give_the_same_weights() {
+        let starts = |seed| {
+            let mut rng = SplitMix64(seed);
+            [random_start(&mut rng), random_start(&mut rng)]
+        };
+        assert_eq!(starts(7), starts(7));
+        assert_ne!(starts(7), starts(8));
+        // Up to the tolerance of the projection.
+        assert!(
+            starts(7)
+                .iter()
+                .flat_map(|w| w.to_array())
+                .all(|w| (-1e-9..=1.0 + 1e-9).contains(&w))
+        );
+        let optimize = |seed| {
+            let start = random_start(&mut SplitMix64(seed));
+            optimize_weights_with_oracle(
+                3..=3,
+                &AnalysisConfig::default(),
+                start,
+                Vec::new(),
+                2,
+                |_| {},
+            )
+            .unwrap()
+        };
+        assert_eq!(optimize(7), optimize(7));
+    }
+
+    #[test]
+    fn cutting_planes_leave_no_star_above_the_tolerance() {
+        let tolerance = 1e-3;
+        let result = optimize_weights_cutting_plane(3, tolerance).unwrap();
+        assert!(result.converged);
+        assert_eq!(
+            most_violated_star(
+                result.weights.0,
+                result.worst + tolerance,
+                3,
+                &AnalysisConfig::default()
+            ),
+            Ok(None)
+        );
+        // The working set is a small part of the stars of degree 3.
+        assert!(!result.constraints.is_empty());
+        // The worst star of the working set binds, and every binding star reaches its best
+        // branching number on the recorded partition.
+        assert!(!result.binding.is_empty());
+        assert_eq!(
+            result.binding[0].branching_number,
+            worst_branching_number(&result.constraints, &result.weights)
+        );
+        for b in result.binding.iter() {
+            assert!(b.branching_number >= result.worst - BINDING_TOLERANCE);
+            let (_, bn) = best_partition(&b.star, result.weights.0, &AnalysisConfig::default())
+                .expect("binding stars branch");
+            assert!((bn - b.branching_number).abs() < 1e-9);
+            assert!(partitions_of_colors(b.star.root_colors).contains(&b.partition.unpack()));
+        }
+        assert!(result.constraints.len() < generate_stars(3).len() / 10);
+    }
+
+    #[test]
+    fn ranking_features_reoptimizes_without_each_one() {
+        let result = optimize_weights_cutting_plane(3, 1e-3).unwrap();
+        let ranking = rank_features(&result.constraints, &result.weights, 200);
+        let mut features: Vec<usize> = ranking.iter().map(|r| r.feature).collect();
+        features.sort();
+        assert_eq!(features, (0..9).collect::<Vec<_>>());
+        assert!(ranking.windows(2).all(|w| w[0].worsening >= w[1].worsening));
+        for r in ranking.iter() {
+            let mut fixed = normalized_weight_constraints();
+            fixed.push(WeightConstraint {
+                larger: WeightTerm::Constant(0.0),
+                smaller: WeightTerm::Weight(r.feature),
+            });
+            let (_, bound) = optimize_weights(&result.constraints, result.weights, &fixed, 200);
+            assert_eq!(r.bound, bound);
+        }
+        // Without some feature a branch drops nothing, and no feature is worth less than
+        // nothing.
+        assert_eq!(ranking[0].worsening, f64::INFINITY);
+        assert!(ranking.iter().all(|r| r.worsening >= 0.0));
+    }
+}
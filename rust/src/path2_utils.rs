@@ -0,0 +1,234 @@
+use alloc::vec::Vec;
+
+use crate::list_coloring_utils::{NodeFeatures, bump_count, partitions_of_colors};
+
+/// Two adjacent constrained vertices `u` and `v` (an edge), each with its other neighbors.
+///
+/// This is the local structure for branching on an edge instead of a single root. Neighbors
+/// are leaves with dangling halfedges, exactly as in [`crate::star_utils::Star`]; the edge
+/// `uv` itself is not counted as a halfedge.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Path2 {
+    // Bitmasks over colors {0,1,2,3}, as in `Star`.
+    pub u_colors: u8,
+    pub v_colors: u8,
+
+    // Color lists and halfedge counts of the other neighbors of `u`.
+    pub u_neighbor_colors: Vec<u8>,
+    pub u_neighbor_halfedges: Vec<u8>,
+
+    // Color lists and halfedge counts of the other neighbors of `v`.
+    pub v_neighbor_colors: Vec<u8>,
+    pub v_neighbor_halfedges: Vec<u8>,
+}
+
+/// Computes the same counts as [`crate::list_coloring_utils::star_list_degree_counts`] for a
+/// `Path2`.
+///
+/// The degree of `u` (resp. `v`) is its number of other neighbors plus one for the edge `uv`.
+pub fn path2_list_degree_counts(path: &Path2) -> NodeFeatures {
+    debug_assert_eq!(
+        path.u_neighbor_colors.len(),
+        path.u_neighbor_halfedges.len()
+    );
+    debug_assert_eq!(
+        path.v_neighbor_colors.len(),
+        path.v_neighbor_halfedges.len()
+    );
+
+    let mut counts = NodeFeatures::default();
+
+    bump_count(
+        &mut counts,
+        path.u_colors.count_ones(),
+        path.u_neighbor_colors.len() + 1,
+    );
+    bump_count(
+        &mut counts,
+        path.v_colors.count_ones(),
+        path.v_neighbor_colors.len() + 1,
+    );
+
+    let neighbors = path
+        .u_neighbor_colors
+        .iter()
+        .zip(path.u_neighbor_halfedges.iter())
+        .chain(
+            path.v_neighbor_colors
+                .iter()
+                .zip(path.v_neighbor_halfedges.iter()),
+        );
+    for (&colors, &halfedges) in neighbors {
+        bump_count(&mut counts, colors.count_ones(), (halfedges as usize) + 1);
+    }
+
+    counts
+}
+
+/// Produces all pairs `(partition of u_colors, partition of v_colors)`, in the order of
+/// [`partitions_of_colors`] with the partition of `u` varying slowest.
+pub fn partition_pairs(path: &Path2) -> Vec<(Vec<u8>, Vec<u8>)> {
+    let v_partitions = partitions_of_colors(path.v_colors);
+    let mut out: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
+    for pu in partitions_of_colors(path.u_colors) {
+        for pv in v_partitions.iter() {
+            out.push((pu.clone(), pv.clone()));
+        }
+    }
+    out
+}
+
+/// Applies a pair of partitions to an edge.
+///
+/// This produces one branch per pair of blocks `(bu, bv)`:
+/// - The lists of `u` and `v` become `bu` and `bv`.
+/// - If one endpoint's list is a singleton, that color is removed from the other endpoint; this
+///   is repeated until neither list changes.
+/// - If an endpoint ends up with a singleton list, that color is removed from its neighbors.
+///
+/// Pairs of blocks in which an endpoint's list becomes empty (both endpoints forced to the same
+/// color) are contradictory and produce no branch.
+pub fn apply_partition_pair(path: &Path2, u_partition: &[u8], v_partition: &[u8]) -> Vec<Path2> {
+    let mut out: Vec<Path2> = Vec::with_capacity(u_partition.len() * v_partition.len());
+
+    for &u_block in u_partition.iter() {
+        debug_assert_eq!(u_block & !path.u_colors, 0);
+        for &v_block in v_partition.iter() {
+            debug_assert_eq!(v_block & !path.v_colors, 0);
+
+            let mut u_colors = u_block & path.u_colors;
+            let mut v_colors = v_block & path.v_colors;
+            loop {
+                let (prev_u, prev_v) = (u_colors, v_colors);
+                if v_colors.count_ones() == 1 {
+                    u_colors &= !v_colors;
+                }
+                if u_colors.count_ones() == 1 {
+                    v_colors &= !u_colors;
+                }
+                if (u_colors, v_colors) == (prev_u, prev_v) {
+                    break;
+                }
+            }
+            if u_colors == 0 || v_colors == 0 {
+                continue;
+            }
+
+            let mut u_neighbor_colors = path.u_neighbor_colors.clone();
+            if u_colors.count_ones() == 1 {
+                for nc in u_neighbor_colors.iter_mut() {
+                    *nc &= !u_colors;
+                }
+            }
+            let mut v_neighbor_colors = path.v_neighbor_colors.clone();
+            if v_colors.count_ones() == 1 {
+                for nc in v_neighbor_colors.iter_mut() {
+                    *nc &= !v_colors;
+                }
+            }
+
+            out.push(Path2 {
+                u_colors,
+                v_colors,
+                u_neighbor_colors,
+                u_neighbor_halfedges: path.u_neighbor_halfedges.clone(),
+                v_neighbor_colors,
+                v_neighbor_halfedges: path.v_neighbor_halfedges.clone(),
+            });
+        }
+    }
+
+    out
+}
+
+/// Returns the combined branching vector of a partition pair: for every branch, the drop in
+/// the measure `weights * counts` from `path` to the branch.
+pub fn branching_vector(
+    path: &Path2,
+    u_partition: &[u8],
+    v_partition: &[u8],
+    weights: NodeFeatures,
+) -> Vec<f64> {
+    let before = path2_list_degree_counts(path);
+    apply_partition_pair(path, u_partition, v_partition)
+        .iter()
+        .map(|branch| weights * (before - path2_list_degree_counts(branch)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn example() -> Path2 {
+        Path2 {
+            u_colors: 0b0011,
+            v_colors: 0b0111,
+            u_neighbor_colors: vec![0b0011, 0b0110],
+            u_neighbor_halfedges: vec![2, 3],
+            v_neighbor_colors: vec![0b0101],
+            v_neighbor_halfedges: vec![2],
+        }
+    }
+
+    #[test]
+    fn apply_partition_pair_propagates_along_the_edge() {
+        let path = example();
+        let branches = apply_partition_pair(&path, &[0b0001, 0b0010], &[0b0001, 0b0110]);
+
+        // (u=0, v=0) is contradictory; the other three pairs survive.
+        assert_eq!(branches.len(), 3);
+
+        // (u=0, v={1,2}): v keeps {1,2}, u's neighbors lose color 0.
+        assert!(branches.iter().any(|b| {
+            b.u_colors == 0b0001
+                && b.v_colors == 0b0110
+                && b.u_neighbor_colors == vec![0b0010, 0b0110]
+                && b.v_neighbor_colors == vec![0b0101]
+        }));
+
+        // (u=1, v=0): both singletons, each propagates to its own neighbors.
+        assert!(branches.iter().any(|b| {
+            b.u_colors == 0b0010
+                && b.v_colors == 0b0001
+                && b.u_neighbor_colors == vec![0b0001, 0b0100]
+                && b.v_neighbor_colors == vec![0b0100]
+        }));
+
+        // (u=1, v={1,2}): v loses color 1 and becomes the singleton {2}.
+        assert!(branches.iter().any(|b| {
+            b.u_colors == 0b0010 && b.v_colors == 0b0100 && b.v_neighbor_colors == vec![0b0001]
+        }));
+    }
+
+    #[test]
+    fn branching_vector_has_one_drop_per_branch() {
+        let path = example();
+        let pairs = partition_pairs(&path);
+        // Bell numbers B2 * B3 = 2 * 5.
+        assert_eq!(pairs.len(), 10);
+
+        let weights = NodeFeatures {
+            n4_ge5: 1.0,
+            n4_4: 1.0,
+            n4_3: 1.0,
+            n3_ge5: 0.8,
+            n3_4: 0.8,
+            n3_3: 0.8,
+            n2_ge5: 0.5,
+            n2_4: 0.5,
+            n2_3: 0.5,
+        };
+        for (pu, pv) in pairs.iter() {
+            let vector = branching_vector(&path, pu, pv, weights);
+            assert_eq!(vector.len(), apply_partition_pair(&path, pu, pv).len());
+        }
+
+        // Trivial partitions do not change anything.
+        assert_eq!(
+            branching_vector(&path, &[0b0011], &[0b0111], weights),
+            vec![0.0]
+        );
+    }
+}
@@ -0,0 +1,246 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+
+use crate::list_coloring_utils::Branch;
+
+fn powf(x: f64, y: f64) -> f64 {
+    #[cfg(feature = "std")]
+    return x.powf(y);
+    #[cfg(not(feature = "std"))]
+    return libm::pow(x, y);
+}
+
+/// Returns the branching number of a branching vector.
+///
+/// For drops `d_1, ..., d_k` in the measure, this is the unique `x >= 1` with
+/// `x^(-d_1) + ... + x^(-d_k) = 1`, so that the recursion tree has `O(x^mu)` leaves for an
+/// instance of measure `mu`.
+///
+/// Conventions:
+/// - An empty vector (no branches) has branching number `1`.
+/// - If some drop is not positive, the measure does not decrease in that branch and the
+///   branching number is `f64::INFINITY`.
+pub fn branching_number(drops: &[f64]) -> f64 {
+    if drops.is_empty() {
+        return 1.0;
+    }
+    if drops.iter().any(|&d| d.is_nan() || d <= 0.0) {
+        return f64::INFINITY;
+    }
+
+    let excess = |x: f64| drops.iter().map(|&d| powf(x, -d)).sum::<f64>() - 1.0;
+    if excess(1.0) <= 0.0 {
+        return 1.0;
+    }
+
+    // `excess` is decreasing in x; find an upper bound, then bisect.
+    let mut lo = 1.0;
+    let mut hi = 2.0;
+    while excess(hi) > 0.0 {
+        lo = hi;
+        hi *= 2.0;
+    }
+    for _ in 0..200 {
+        let mid = 0.5 * (lo + hi);
+        if mid <= lo || mid >= hi {
+            break;
+        }
+        if excess(mid) > 0.0 {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    hi
+}
+
+/// Decides whether the branching number of `drops` is at most `bound`, certified against
+/// rounding errors: `bound^(-d_1) + ... + bound^(-d_k)` is bounded from above with every power
+/// and every sum rounded up, and the bound holds if this is at most `1`.
+///
+/// The powers are assumed to be within one ulp of the exact value, as for the `pow` of common
+/// libms, and are rounded up by two ulps. A `false` only means that the bound could not be
+/// certified; bounds within a few ulps of the branching number cannot be.
+pub fn certify_branching_number_at_most(drops: &[f64], bound: f64) -> bool {
+    if bound == f64::INFINITY {
+        return true;
+    }
+    if bound.is_nan() || bound < 1.0 || drops.iter().any(|&d| d.is_nan() || d <= 0.0) {
+        return false;
+    }
+    let upper = drops.iter().fold(0.0f64, |sum, &d| {
+        let power = powf(bound, -d).next_up().next_up();
+        (sum + power).next_up()
+    });
+    upper <= 1.0
+}
+
+/// Like [`branching_number`], but for a branching vector given as `(drop, multiplicity)`
+/// pairs, i.e. the unique `x >= 1` with `m_1 x^(-d_1) + ... + m_k x^(-d_k) = 1`.
+pub fn branching_number_grouped(vector: &[(f64, usize)]) -> f64 {
+    let drops: Vec<f64> = vector
+        .iter()
+        .flat_map(|&(d, m)| core::iter::repeat_n(d, m))
+        .collect();
+    branching_number(&drops)
+}
+
+/// Returns the characteristic polynomial `x^D - x^(D - d_1) - ... - x^(D - d_k)` of a
+/// branching vector with `D = max d_i`, as coefficients indexed by the power of `x`. Its
+/// largest real root is the [`branching_number`].
+///
+/// Returns `None` unless all drops are positive integers.
+pub fn characteristic_polynomial(drops: &[f64]) -> Option<Vec<i64>> {
+    if drops
+        .iter()
+        .any(|&d| d <= 0.0 || d > u16::MAX as f64 || (d as u16) as f64 != d)
+    {
+        return None;
+    }
+    let degree = drops.iter().fold(0.0f64, |a, &b| a.max(b)) as usize;
+    let mut coeffs = vec![0i64; degree + 1];
+    coeffs[degree] = 1;
+    for &d in drops {
+        coeffs[degree - d as usize] -= 1;
+    }
+    Some(coeffs)
+}
+
+/// Renders polynomial coefficients (indexed by power) in the variable `x`, highest power
+/// first, e.g. `x^2 - x - 1`.
+pub fn polynomial_to_string(coeffs: &[i64]) -> String {
+    let mut out = String::new();
+    for (power, &c) in coeffs.iter().enumerate().rev() {
+        if c == 0 {
+            continue;
+        }
+        let sign = if c < 0 { "-" } else { "+" };
+        if out.is_empty() {
+            if c < 0 {
+                out.push('-');
+            }
+        } else {
+            out.push_str(&format!(" {sign} "));
+        }
+        let abs = c.unsigned_abs();
+        let var = match power {
+            0 => String::new(),
+            1 => "x".to_string(),
+            _ => format!("x^{power}"),
+        };
+        if abs != 1 || power == 0 {
+            out.push_str(&abs.to_string());
+        }
+        out.push_str(&var);
+    }
+    if out.is_empty() {
+        out.push('0');
+    }
+    out
+}
+
+/// The recurrence of one application of a branching rule.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Recurrence {
+    /// Measure drops of the branches that take part in the characteristic equation.
+    pub drops: Vec<f64>,
+    /// Number of branches that terminated immediately because they were infeasible. These cost
+    /// no recursive call and are excluded from the characteristic equation.
+    pub free_branches: usize,
+    /// Number of branches that completely colored the star. They are included in `drops`,
+    /// since the rest of the graph still has to be solved.
+    pub solved_branches: usize,
+}
+
+impl Recurrence {
+    /// The recurrence of branches with the given outcomes and drops in the measure, `None`
+    /// for an infeasible branch: infeasible branches are free, and solved ones are counted
+    /// and keep their drop.
+    pub fn from_branches<'a>(
+        branches: impl IntoIterator<Item = (&'a Branch, Option<f64>)>,
+    ) -> Self {
+        let mut recurrence = Recurrence::default();
+        for (branch, drop) in branches {
+            match branch {
+                Branch::Infeasible => recurrence.free_branches += 1,
+                Branch::Solved => recurrence.solved_branches += 1,
+                Branch::Star(_) => {}
+            }
+            recurrence.drops.extend(drop);
+        }
+        recurrence
+    }
+
+    /// Total number of branches, including free ones.
+    pub fn num_branches(&self) -> usize {
+        self.drops.len() + self.free_branches
+    }
+
+    pub fn branching_number(&self) -> f64 {
+        branching_number(&self.drops)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn branching_number_of_known_vectors() {
+        assert_eq!(branching_number(&[]), 1.0);
+        assert_eq!(branching_number(&[3.0]), 1.0);
+        assert!((branching_number(&[1.0, 1.0]) - 2.0).abs() < 1e-12);
+        assert!((branching_number(&[1.0, 1.0, 1.0]) - 3.0).abs() < 1e-12);
+
+        // Fibonacci: x^2 = x + 1.
+        let golden = (1.0 + 5f64.sqrt()) / 2.0;
+        assert!((branching_number(&[1.0, 2.0]) - golden).abs() < 1e-12);
+
+        assert_eq!(branching_number(&[1.0, 0.0]), f64::INFINITY);
+        assert_eq!(branching_number(&[1.0, -0.5]), f64::INFINITY);
+    }
+
+    #[test]
+    fn certified_bounds_leave_room_for_rounding() {
+        let golden = (1.0 + 5f64.sqrt()) / 2.0;
+        assert!(certify_branching_number_at_most(&[1.0, 2.0], golden + 1e-9));
+        assert!(!certify_branching_number_at_most(&[1.0, 2.0], golden));
+        assert!(!certify_branching_number_at_most(&[1.0, 2.0], 1.6));
+        assert!(certify_branching_number_at_most(&[], 1.0));
+        assert!(!certify_branching_number_at_most(&[1.0, 0.0], 1e9));
+        assert!(certify_branching_number_at_most(&[1.0, 0.0], f64::INFINITY));
+    }
+
+    #[test]
+    fn grouped_branching_number_expands_multiplicities() {
+        assert_eq!(
+            branching_number_grouped(&[(1.0, 2)]),
+            branching_number(&[1.0, 1.0])
+        );
+        assert_eq!(branching_number_grouped(&[(2.0, 0)]), 1.0);
+    }
+
+    #[test]
+    fn characteristic_polynomial_of_integer_vectors() {
+        let coeffs = characteristic_polynomial(&[1.0, 1.0, 2.0, 3.0]).unwrap();
+        assert_eq!(coeffs, vec![-1, -1, -2, 1]);
+        assert_eq!(polynomial_to_string(&coeffs), "x^3 - 2x^2 - x - 1");
+        assert_eq!(
+            polynomial_to_string(&characteristic_polynomial(&[1.0, 2.0]).unwrap()),
+            "x^2 - x - 1"
+        );
+        assert_eq!(characteristic_polynomial(&[1.5, 2.0]), None);
+    }
+
+    #[test]
+    fn free_branches_do_not_count() {
+        let r = Recurrence {
+            drops: vec![1.0, 1.0],
+            free_branches: 2,
+            solved_branches: 0,
+        };
+        assert_eq!(r.num_branches(), 4);
+        assert!((r.branching_number() - 2.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,208 @@
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::reductions::Reduction;
+use crate::star_utils::{NeighborMultiset, Star, star_label, star_type_key};
+
+/// The stars reachable from `star` by applying `reductions` one at a time, in any order,
+/// including `star` itself, in the order they are found.
+///
+/// Unlike [`reduce_to_fixpoint`](crate::reductions::reduce_to_fixpoint), every applicable
+/// reduction is followed in every step, not only the first one.
+pub fn reachable_by_reductions(star: &Star, reductions: &[&dyn Reduction]) -> Vec<Star> {
+    let mut seen: BTreeSet<Star> = BTreeSet::new();
+    seen.insert(star.clone());
+    let mut out: Vec<Star> = alloc::vec![star.clone()];
+    let mut stack: Vec<Star> = alloc::vec![star.clone()];
+    while let Some(current) = stack.pop() {
+        for reduction in reductions.iter() {
+            if let Some(next) = reduction.apply(&current)
+                && seen.insert(next.clone())
+            {
+                out.push(next.clone());
+                stack.push(next);
+            }
+        }
+    }
+    out
+}
+
+/// The preorder "star A reduces to star B" on a case set, as a Hasse diagram.
+///
+/// A star reduces to another one if some sequence of reductions turns it into a star
+/// isomorphic to the other one (see [`reachable_by_reductions`] and [`star_type_key`]).
+/// Isomorphic stars reduce to each other, so they are collected into one class; on the
+/// classes the preorder is a partial order. Stars that reduce to another case are handled by
+/// that case, so only the minimal classes need separate treatment.
+///
+/// Reductions shrink lists or remove neighbors, so the stars of one enumeration rarely reduce
+/// to each other. The order therefore also has a class for every star a case reduces to,
+/// after the classes of the case set.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReductionOrder {
+    /// The classes of isomorphic stars: first those of the case set, ordered by their first
+    /// star in the case set, then those only reached by reductions, in the order they were
+    /// reached.
+    pub classes: Vec<Vec<Star>>,
+    /// The number of classes of the case set, which come first in `classes`.
+    pub cases: usize,
+    /// The covering pairs `(a, b)` of classes: `a` reduces to `b`, and to no class that
+    /// reduces to `b`. Sorted.
+    pub edges: Vec<(usize, usize)>,
+}
+
+impl ReductionOrder {
+    /// The classes that reduce to no other class, in increasing order.
+    pub fn minimal(&self) -> Vec<usize> {
+        let reducible: BTreeSet<usize> = self.edges.iter().map(|&(a, _)| a).collect();
+        (0..self.classes.len())
+            .filter(|i| !reducible.contains(i))
+            .collect()
+    }
+
+    /// Renders the Hasse diagram in Graphviz DOT format, with an edge from every class to
+    /// the classes it covers. Minimal classes are drawn with a double border, and classes
+    /// outside the case set dashed.
+    pub fn to_dot(&self) -> String {
+        let minimal: BTreeSet<usize> = self.minimal().into_iter().collect();
+        let mut out =
+            String::from("digraph reductions {\n  node [shape=box, fontname=monospace];\n");
+        for (i, class) in self.classes.iter().enumerate() {
+            let label = class
+                .iter()
+                .map(|star| star_label(star).replace('"', "\\\""))
+                .collect::<Vec<_>>()
+                .join("\\n");
+            let peripheries = if minimal.contains(&i) { 2 } else { 1 };
+            let style = if i < self.cases { "" } else { ", style=dashed" };
+            out.push_str(&format!(
+                "  c{i} [label=\"{label}\", peripheries={peripheries}{style}];\n"
+            ));
+        }
+        for &(a, b) in self.edges.iter() {
+            out.push_str(&format!("  c{a} -> c{b};\n"));
+        }
+        out.push_str("}\n");
+        out
+    }
+}
+
+/// Computes the [`ReductionOrder`] of the stars in `cases` under `reductions`.
+pub fn reduction_order(cases: &[Star], reductions: &[&dyn Reduction]) -> ReductionOrder {
+    let mut class_of: BTreeMap<(u8, NeighborMultiset), usize> = BTreeMap::new();
+    let mut classes: Vec<Vec<Star>> = Vec::new();
+    let mut add = |star: Star, classes: &mut Vec<Vec<Star>>| {
+        let next = classes.len();
+        let class = *class_of.entry(star_type_key(&star)).or_insert(next);
+        if class == next {
+            classes.push(Vec::new());
+        }
+        class
+    };
+    for star in cases {
+        let class = add(star.clone(), &mut classes);
+        classes[class].push(star.clone());
+    }
+    let num_cases = classes.len();
+
+    // Reductions make stars strictly smaller, so `reaches` is acyclic and transitively closed.
+    // The classes reached from a class are reached from its first star as well, and a new
+    // class is appended before its own stars are followed.
+    let mut reaches: Vec<BTreeSet<usize>> = Vec::new();
+    let mut i = 0;
+    while i < classes.len() {
+        let mut reached = BTreeSet::new();
+        for star in reachable_by_reductions(&classes[i][0], reductions) {
+            let j = add(star.clone(), &mut classes);
+            if j >= num_cases && classes[j].is_empty() {
+                classes[j].push(star);
+            }
+            if j != i {
+                reached.insert(j);
+            }
+        }
+        reaches.push(reached);
+        i += 1;
+    }
+
+    let mut edges: Vec<(usize, usize)> = Vec::new();
+    for (a, reached) in reaches.iter().enumerate() {
+        for &b in reached.iter() {
+            let covered = reached.iter().any(|&c| reaches[c].contains(&b));
+            if !covered {
+                edges.push((a, b));
+            }
+        }
+    }
+    ReductionOrder {
+        classes,
+        cases: num_cases,
+        edges,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reductions::{DEFAULT_REDUCTIONS, reduce_to_fixpoint};
+    use crate::star_utils::generate_stars;
+
+    #[test]
+    fn reduction_order_is_a_hasse_diagram() {
+        // No enumerated star reduces to another one; the order adds the stars they reduce to.
+        let cases = generate_stars(2);
+        let order = reduction_order(&cases, DEFAULT_REDUCTIONS);
+        assert_eq!(
+            order.classes[..order.cases]
+                .iter()
+                .map(Vec::len)
+                .sum::<usize>(),
+            cases.len()
+        );
+        assert!(order.classes[order.cases..].iter().all(|c| c.len() == 1));
+        assert!(order.classes.len() > order.cases);
+        assert!(!order.edges.is_empty());
+        assert!(order.edges.iter().all(|&(a, b)| a != b && b >= order.cases));
+        assert!(order.minimal().len() < order.classes.len());
+
+        // A case reduces to its fixpoint, if that is a case, through the covering pairs.
+        for (i, class) in order.classes.iter().enumerate() {
+            let fixpoint = star_type_key(&reduce_to_fixpoint(&class[0], DEFAULT_REDUCTIONS));
+            let Some(j) = order
+                .classes
+                .iter()
+                .position(|c| star_type_key(&c[0]) == fixpoint)
+            else {
+                continue;
+            };
+            let mut frontier = alloc::vec![i];
+            let mut found = i == j;
+            while let Some(a) = frontier.pop() {
+                for &(_, b) in order.edges.iter().filter(|&&(x, _)| x == a) {
+                    found |= b == j;
+                    frontier.push(b);
+                }
+            }
+            assert!(found);
+            assert!(order.minimal().contains(&j));
+        }
+
+        let dot = order.to_dot();
+        assert!(dot.starts_with("digraph reductions {"));
+        assert_eq!(dot.matches(" -> ").count(), order.edges.len());
+        assert_eq!(dot.matches("peripheries=2").count(), order.minimal().len());
+        assert_eq!(
+            dot.matches("style=dashed").count(),
+            order.classes.len() - order.cases
+        );
+
+        // Closing the case set under reductions first gives the same order.
+        let closed: Vec<Star> = order.classes.iter().flatten().cloned().collect();
+        assert_eq!(
+            reduction_order(&closed, DEFAULT_REDUCTIONS).edges,
+            order.edges
+        );
+    }
+}
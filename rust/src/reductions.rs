@@ -0,0 +1,918 @@
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+
+use crate::analysis::{AnalysisConfig, star_measure};
+use crate::colors::{ALL_COLORS, ColorSet, is_subset, singletons, subsets};
+use crate::list_coloring_utils::{
+    MaxDegree, NodeFeatures, apply_list_coloring_partition_unreduced, is_infeasible,
+    partitions_of_colors, reduce_dominated_root_colors, reduce_duplicate_2lists,
+    reduce_duplicate_2lists_capped, reduce_duplicate_2lists_capped_with_map,
+    reduce_duplicate_2lists_with_map,
+};
+use crate::star_utils::{Star, StarError, fold_stars_with_config, generate_stars, star_label};
+
+/// How well a [`Reduction`] is established.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SafetyLevel {
+    /// Proved safe, so bounds relying on the rule can be published.
+    Proved,
+    /// Believed safe, e.g. checked by [`certify_reduction`] on small stars, but not proved.
+    Conjectured,
+    /// Under development.
+    Experimental,
+}
+
+impl SafetyLevel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SafetyLevel::Proved => "proved",
+            SafetyLevel::Conjectured => "conjectured",
+            SafetyLevel::Experimental => "experimental",
+        }
+    }
+}
+
+/// Fails if some of `reductions` is not [`SafetyLevel::Proved`], unless `allow_unproved` is
+/// set. The analysis pipeline checks its reductions with this before producing a proof.
+pub fn check_reduction_safety(
+    reductions: &[&dyn Reduction],
+    allow_unproved: bool,
+) -> Result<(), String> {
+    let unproved: Vec<String> = reductions
+        .iter()
+        .filter(|r| r.safety() != SafetyLevel::Proved)
+        .map(|r| format!("{} ({})", r.name(), r.safety().name()))
+        .collect();
+    if unproved.is_empty() || allow_unproved {
+        Ok(())
+    } else {
+        Err(format!("unproved reductions: {}", unproved.join(", ")))
+    }
+}
+
+/// A reduction rule for stars.
+///
+/// A reduction replaces a star by a simpler one such that, for every way the rest of the graph
+/// can constrain the neighbors through their halfedges, the reduced star is list-colorable if
+/// and only if the original one is.
+pub trait Reduction: Sync {
+    /// Short identifier used in logs and reports.
+    fn name(&self) -> &'static str;
+
+    /// Human-readable statement of when the rule applies, for certificates and reports.
+    fn precondition(&self) -> &'static str;
+
+    /// Whether the rule is proved safe; proofs refuse to rely on rules that are not.
+    fn safety(&self) -> SafetyLevel;
+
+    /// Returns the reduced star, or `None` if the rule does not apply.
+    fn apply(&self, star: &Star) -> Option<Star>;
+
+    /// Like [`Reduction::apply`], but also returns for every neighbor of `star` the index of the
+    /// neighbor of the reduced star that it became.
+    ///
+    /// The default implementation is for rules that keep all neighbors in place.
+    fn apply_with_neighbor_map(&self, star: &Star) -> Option<(Star, Vec<usize>)> {
+        let reduced = self.apply(star)?;
+        debug_assert_eq!(reduced.neighbor_colors.len(), star.neighbor_colors.len());
+        Some((reduced, (0..star.neighbor_colors.len()).collect()))
+    }
+}
+
+/// See [`reduce_dominated_root_colors`].
+pub struct DominatedRootColors;
+
+impl Reduction for DominatedRootColors {
+    fn name(&self) -> &'static str {
+        "dominated-root-colors"
+    }
+
+    fn precondition(&self) -> &'static str {
+        "the root list has at least 2 colors, and some root color appears in no neighbor list \
+         or forms the singleton list of a neighbor"
+    }
+
+    fn safety(&self) -> SafetyLevel {
+        SafetyLevel::Proved
+    }
+
+    fn apply(&self, star: &Star) -> Option<Star> {
+        reduce_dominated_root_colors(star)
+    }
+}
+
+/// See [`reduce_duplicate_2lists`].
+pub struct DuplicateTwoLists;
+
+impl Reduction for DuplicateTwoLists {
+    fn name(&self) -> &'static str {
+        "duplicate-2lists"
+    }
+
+    fn precondition(&self) -> &'static str {
+        "the root list has exactly 2 colors and at least 2 neighbors have the same list as the root"
+    }
+
+    fn safety(&self) -> SafetyLevel {
+        SafetyLevel::Proved
+    }
+
+    fn apply(&self, star: &Star) -> Option<Star> {
+        reduce_duplicate_2lists(star)
+    }
+
+    fn apply_with_neighbor_map(&self, star: &Star) -> Option<(Star, Vec<usize>)> {
+        reduce_duplicate_2lists_with_map(star)
+    }
+}
+
+/// See [`reduce_duplicate_2lists_capped`]: [`DuplicateTwoLists`] with the halfedges of the
+/// merged neighbor capped at the degree bucket of the maximum degree.
+///
+/// The star is reduced soundly, since halfedges only carry constraints from the rest of the
+/// graph and the twins are colored equally anyway. But in the graph, the merged vertex keeps
+/// all its edges, so its measure is only bounded by the one of the reduced star if the weights
+/// do not increase with the degree; hence the rule is not proved for all measures.
+pub struct CappedDuplicateTwoLists(pub MaxDegree);
+
+impl Reduction for CappedDuplicateTwoLists {
+    fn name(&self) -> &'static str {
+        "duplicate-2lists-capped"
+    }
+
+    fn precondition(&self) -> &'static str {
+        "the root list has exactly 2 colors and at least 2 neighbors have the same list as the \
+         root; the merged neighbor keeps the degree bucket of the twin with the most halfedges"
+    }
+
+    fn safety(&self) -> SafetyLevel {
+        SafetyLevel::Conjectured
+    }
+
+    fn apply(&self, star: &Star) -> Option<Star> {
+        reduce_duplicate_2lists_capped(star, self.0)
+    }
+
+    fn apply_with_neighbor_map(&self, star: &Star) -> Option<(Star, Vec<usize>)> {
+        reduce_duplicate_2lists_capped_with_map(star, self.0)
+    }
+}
+
+/// The reductions applied after every branching step, in order of priority.
+pub static DEFAULT_REDUCTIONS: &[&dyn Reduction] = &[&DominatedRootColors, &DuplicateTwoLists];
+
+/// The names of the reductions [`reduction_by_name`] knows.
+pub const REDUCTION_NAMES: &[&str] = &[
+    "dominated-root-colors",
+    "duplicate-2lists",
+    "duplicate-2lists-capped",
+];
+
+/// Returns the reduction called `name`, see [`Reduction::name`], or `None` if there is none.
+/// [`CappedDuplicateTwoLists`] caps at the degree buckets of `max_degree`.
+pub fn reduction_by_name(name: &str, max_degree: MaxDegree) -> Option<Box<dyn Reduction>> {
+    Some(match name {
+        "dominated-root-colors" => Box::new(DominatedRootColors),
+        "duplicate-2lists" => Box::new(DuplicateTwoLists),
+        "duplicate-2lists-capped" => Box::new(CappedDuplicateTwoLists(max_degree)),
+        _ => return None,
+    })
+}
+
+/// Returns the reductions called `names`, in that order of priority, as
+/// [`reduction_by_name`].
+///
+/// Fails on unknown names, and like [`check_reduction_safety`] if some of the reductions is
+/// not proved, unless `allow_unproved` is set.
+pub fn select_reductions(
+    names: &[&str],
+    max_degree: MaxDegree,
+    allow_unproved: bool,
+) -> Result<Vec<Box<dyn Reduction>>, String> {
+    let reductions = names
+        .iter()
+        .map(|&name| {
+            reduction_by_name(name, max_degree).ok_or_else(|| {
+                format!(
+                    "unknown reduction: {name} (expected one of {})",
+                    REDUCTION_NAMES.join(", ")
+                )
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let refs: Vec<&dyn Reduction> = reductions.iter().map(|r| r.as_ref()).collect();
+    check_reduction_safety(&refs, allow_unproved)?;
+    Ok(reductions)
+}
+
+/// Applies `reductions` to `star` until none of them applies anymore.
+///
+/// In each round the first applicable reduction (in slice order) is used, and the search
+/// restarts from the beginning of the slice.
+pub fn reduce_to_fixpoint(star: &Star, reductions: &[&dyn Reduction]) -> Star {
+    reduce_owned_to_fixpoint(star.clone(), reductions)
+}
+
+/// Like [`reduce_to_fixpoint`], but takes the star by value, so that a star none of the
+/// reductions applies to is returned without copying it.
+pub fn reduce_owned_to_fixpoint(star: Star, reductions: &[&dyn Reduction]) -> Star {
+    let mut current = star;
+    'outer: loop {
+        for reduction in reductions.iter() {
+            if let Some(next) = reduction.apply(&current) {
+                current = next;
+                continue 'outer;
+            }
+        }
+        return current;
+    }
+}
+
+/// Like [`reduce_to_fixpoint`], but also returns the names of the reductions that were applied,
+/// in order.
+pub fn reduce_to_fixpoint_with_trace(
+    star: &Star,
+    reductions: &[&dyn Reduction],
+) -> (Star, Vec<&'static str>) {
+    let (reduced, steps) = reduce_to_fixpoint_with_steps(star, reductions, |_| 0.0);
+    (reduced, steps.into_iter().map(|step| step.name).collect())
+}
+
+/// One application of a reduction in [`reduce_to_fixpoint_with_steps`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReductionStep {
+    pub name: &'static str,
+    /// The star after the step.
+    pub star: Star,
+    /// The measure before and after the step.
+    pub measure_before: f64,
+    pub measure_after: f64,
+}
+
+impl ReductionStep {
+    /// The drop in the measure, `measure_before - measure_after`.
+    pub fn drop(&self) -> f64 {
+        self.measure_before - self.measure_after
+    }
+}
+
+/// Like [`reduce_to_fixpoint`], but also returns every step with the change of `measure`, so
+/// that the total change can be justified one reduction at a time.
+pub fn reduce_to_fixpoint_with_steps(
+    star: &Star,
+    reductions: &[&dyn Reduction],
+    measure: impl Fn(&Star) -> f64,
+) -> (Star, Vec<ReductionStep>) {
+    let mut current = star.clone();
+    let mut current_measure = measure(&current);
+    let mut steps: Vec<ReductionStep> = Vec::new();
+    'outer: loop {
+        for reduction in reductions.iter() {
+            if let Some(next) = reduction.apply(&current) {
+                let next_measure = measure(&next);
+                steps.push(ReductionStep {
+                    name: reduction.name(),
+                    star: next.clone(),
+                    measure_before: current_measure,
+                    measure_after: next_measure,
+                });
+                current = next;
+                current_measure = next_measure;
+                continue 'outer;
+            }
+        }
+        return (current, steps);
+    }
+}
+
+/// Brute-force check that `reduction` is safe on `star`.
+///
+/// Returns `None` if the reduction does not apply. Otherwise returns whether, for every way of
+/// fixing the color of each neighbor from the outside (through its halfedges), the reduced star
+/// is colorable if and only if `star` is. Fixing single colors suffices, since colorability
+/// under an arbitrary outside constraint is a disjunction over such assignments.
+pub fn verify_reduction(reduction: &dyn Reduction, star: &Star) -> Option<bool> {
+    let (reduced, neighbor_map) = reduction.apply_with_neighbor_map(star)?;
+    let mut assignment = vec![0u8; star.neighbor_colors.len()];
+    Some(verify_assignments(
+        star,
+        &reduced,
+        &neighbor_map,
+        &mut assignment,
+        0,
+    ))
+}
+
+fn verify_assignments(
+    star: &Star,
+    reduced: &Star,
+    neighbor_map: &[usize],
+    assignment: &mut [u8],
+    idx: usize,
+) -> bool {
+    if idx == assignment.len() {
+        return colorable_under(star, assignment, None)
+            == colorable_under(reduced, assignment, Some(neighbor_map));
+    }
+    let colors = star.color_count().max(reduced.color_count());
+    for color in singletons(u8::first(colors)) {
+        assignment[idx] = color;
+        if !verify_assignments(star, reduced, neighbor_map, assignment, idx + 1) {
+            return false;
+        }
+    }
+    true
+}
+
+/// Whether `star` has a coloring in which neighbor `neighbor_map[i]` takes the single color
+/// `assignment[i]` for every `i`.
+fn colorable_under(star: &Star, assignment: &[u8], neighbor_map: Option<&[usize]>) -> bool {
+    let mut forced = vec![0u8; star.neighbor_colors.len()];
+    for (i, &color) in assignment.iter().enumerate() {
+        let k = neighbor_map.map_or(i, |map| map[i]);
+        forced[k] |= color;
+    }
+    let mut used = 0u8;
+    for (k, &colors) in forced.iter().enumerate() {
+        if colors.count_ones() > 1 || !is_subset(colors, star.neighbor_colors[k]) {
+            return false;
+        }
+        used |= colors;
+    }
+    (star.root_colors & !used) != 0
+}
+
+/// Record of a brute-force verification of one reduction.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReductionCertificate {
+    pub name: &'static str,
+    pub precondition: &'static str,
+    pub max_degree: usize,
+    /// Number of cases on which the reduction applied and was verified, per degree
+    /// `2..=max_degree`.
+    pub cases_verified: Vec<usize>,
+    /// Cases on which the reduction applied but the verification failed.
+    pub counterexamples: Vec<Star>,
+    /// FNV-1a hash over the encodings and outcomes of all verified cases, in order.
+    pub run_hash: u64,
+}
+
+impl ReductionCertificate {
+    /// Returns a compact one-line JSON object string with no whitespace.
+    pub fn to_json_string(&self) -> String {
+        fn escape(s: &str) -> String {
+            s.replace('\\', "\\\\").replace('"', "\\\"")
+        }
+
+        let cases_verified = self
+            .cases_verified
+            .iter()
+            .map(|n| n.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+        let counterexamples = self
+            .counterexamples
+            .iter()
+            .map(|s| format!("\"{}\"", escape(&star_label(s))))
+            .collect::<Vec<_>>()
+            .join(",");
+        format!(
+            "{{\"name\":\"{}\",\"precondition\":\"{}\",\"max_degree\":{},\"cases_verified\":[{}],\"counterexamples\":[{}],\"run_hash\":\"{:016x}\"}}",
+            escape(self.name),
+            escape(self.precondition),
+            self.max_degree,
+            cases_verified,
+            counterexamples,
+            self.run_hash,
+        )
+    }
+}
+
+/// The cases a reduction is certified on, for a given `degree`.
+///
+/// Besides the enumerated stars, this includes the situations reductions are meant for:
+/// - the root list restricted to each subset of at least 2 of its colors (non-singleton blocks
+///   of a partition), and
+/// - each color removed from all neighbor lists (a color taken elsewhere in the graph).
+pub fn certification_cases(degree: usize) -> Vec<Star> {
+    let mut out: Vec<Star> = Vec::new();
+    for star in generate_stars(degree) {
+        for sub in subsets(star.root_colors).filter(|s| s.count_ones() >= 2) {
+            let mut case = star.clone();
+            case.root_colors = sub;
+            out.push(case);
+        }
+        for color in singletons(ALL_COLORS) {
+            let mut case = star.clone();
+            for c in case.neighbor_colors.iter_mut() {
+                *c &= !color;
+            }
+            out.push(case);
+        }
+    }
+    out
+}
+
+/// Brute-force verifies `reduction` on the [`certification_cases`] of degrees
+/// `2..=max_degree`.
+pub fn certify_reduction(reduction: &dyn Reduction, max_degree: usize) -> ReductionCertificate {
+    const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let mut run_hash = FNV_OFFSET;
+    let mut cases_verified: Vec<usize> = Vec::new();
+    let mut counterexamples: Vec<Star> = Vec::new();
+
+    for degree in 2..=max_degree {
+        let mut count = 0;
+        for case in certification_cases(degree) {
+            let Some(ok) = verify_reduction(reduction, &case) else {
+                continue;
+            };
+            count += 1;
+            if !ok {
+                counterexamples.push(case.clone());
+            }
+            let line = format!("{}:{}\n", star_label(&case), ok);
+            for &byte in line.as_bytes() {
+                run_hash ^= byte as u64;
+                run_hash = run_hash.wrapping_mul(FNV_PRIME);
+            }
+        }
+        cases_verified.push(count);
+    }
+
+    ReductionCertificate {
+        name: reduction.name(),
+        precondition: reduction.precondition(),
+        max_degree,
+        cases_verified,
+        counterexamples,
+        run_hash,
+    }
+}
+
+/// A star that failed [`Star::validate`] during [`verify_invariants`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvariantViolation {
+    /// The star that was branched on or reduced.
+    pub input: Star,
+    /// `"input"`, `"partition"`, or the name of the reduction that produced `output`.
+    pub stage: &'static str,
+    pub output: Star,
+    pub error: StarError,
+}
+
+impl core::fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{} -> {} ({}): {}",
+            star_label(&self.input),
+            star_label(&self.output),
+            self.stage,
+            self.error
+        )
+    }
+}
+
+/// Applies `partition` to `star` like
+/// [`crate::list_coloring_utils::apply_list_coloring_partition`] and validates every
+/// star produced along the way: each branch, and the result of each reduction step.
+///
+/// Violations are collected instead of asserted, so a long verification run reports all of
+/// them at the end. Branches that fail validation are not reduced further. The lists of the
+/// branches and reduced stars must stay within the colors of `star`, see
+/// [`Star::color_count`].
+pub fn verify_invariants(
+    star: &Star,
+    partition: &[u8],
+    reductions: &[&dyn Reduction],
+) -> Vec<InvariantViolation> {
+    let violation = |input: &Star, stage, output: &Star, error| InvariantViolation {
+        input: input.clone(),
+        stage,
+        output: output.clone(),
+        error,
+    };
+    if let Err(error) = star.validate() {
+        return vec![violation(star, "input", star, error)];
+    }
+
+    let colors = star.color_count();
+    let mut out: Vec<InvariantViolation> = Vec::new();
+    'branches: for branch in apply_list_coloring_partition_unreduced(star, partition) {
+        match branch.validate_with_colors(colors) {
+            // An empty list ends the branch as infeasible.
+            Err(StarError::EmptyList { .. }) => continue,
+            Err(error) => {
+                out.push(violation(star, "partition", &branch, error));
+                continue;
+            }
+            Ok(()) => {}
+        }
+        let mut current = branch;
+        'outer: loop {
+            for reduction in reductions.iter() {
+                if let Some(next) = reduction.apply(&current) {
+                    if let Err(error) = next.validate_with_colors(colors) {
+                        out.push(violation(&current, reduction.name(), &next, error));
+                        continue 'branches;
+                    }
+                    current = next;
+                    continue 'outer;
+                }
+            }
+            break;
+        }
+    }
+    out
+}
+
+/// A reduction step that increases the measure, found by [`measure_increases`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct MeasureIncrease {
+    /// The star that was branched on.
+    pub star: Star,
+    pub partition: Vec<u8>,
+    /// The star the reduction was applied to.
+    pub input: Star,
+    pub step: ReductionStep,
+}
+
+impl core::fmt::Display for MeasureIncrease {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{} -> {} ({}): measure {} -> {}",
+            star_label(&self.input),
+            star_label(&self.step.star),
+            self.step.name,
+            self.step.measure_before,
+            self.step.measure_after
+        )
+    }
+}
+
+/// Applies `partition` to `star` and reduces every branch with `reductions` as in
+/// [`reduce_to_fixpoint_with_steps`], measuring stars with `weights` and the degree buckets of
+/// `max_degree`, and returns the steps that increase the measure.
+///
+/// The analysis credits every branch with the drop from `star` to the reduced branch, which is
+/// only sound if no reduction increases the measure of the graph.
+pub fn measure_increases(
+    star: &Star,
+    partition: &[u8],
+    reductions: &[&dyn Reduction],
+    weights: NodeFeatures,
+    max_degree: MaxDegree,
+) -> Vec<MeasureIncrease> {
+    let measure = |s: &Star| star_measure(s, weights, max_degree);
+    let mut out = Vec::new();
+    for branch in apply_list_coloring_partition_unreduced(star, partition) {
+        if is_infeasible(&branch) {
+            continue;
+        }
+        let (_, steps) = reduce_to_fixpoint_with_steps(&branch, reductions, measure);
+        let mut input = branch;
+        for step in steps {
+            // Measures are sums in different orders, so equal ones may differ by rounding.
+            if step.drop() < -1e-9 {
+                out.push(MeasureIncrease {
+                    star: star.clone(),
+                    partition: partition.to_vec(),
+                    input: input.clone(),
+                    step: step.clone(),
+                });
+            }
+            input = step.star;
+        }
+    }
+    out
+}
+
+/// Runs [`measure_increases`] with the reductions and degree buckets of `config` on every
+/// partition `config` allows of every star of the given `degree` that `config` enumerates,
+/// streaming the stars as [`fold_stars_with_config`].
+pub fn measure_increases_with_config(
+    degree: usize,
+    config: &AnalysisConfig,
+    weights: NodeFeatures,
+) -> Vec<MeasureIncrease> {
+    let max_degree = config.max_degree();
+    fold_stars_with_config(degree, &config.enumeration, Vec::new(), |mut out, star| {
+        for partition in partitions_of_colors(star.root_colors) {
+            if config.allows(&partition) {
+                out.extend(measure_increases(
+                    &star,
+                    &partition,
+                    config.reductions,
+                    weights,
+                    max_degree,
+                ));
+            }
+        }
+        out
+    })
+}
+
+/// Runs [`verify_invariants`] with the [`DEFAULT_REDUCTIONS`] on every partition of every star
+/// of the given `degree`.
+pub fn verify_all_invariants(degree: usize) -> Vec<InvariantViolation> {
+    verify_all_invariants_with_config(degree, &AnalysisConfig::default())
+}
+
+/// Like [`verify_all_invariants`], with the reductions of `config` on the partitions it
+/// allows of the stars `config` enumerates, streamed as [`fold_stars_with_config`].
+pub fn verify_all_invariants_with_config(
+    degree: usize,
+    config: &AnalysisConfig,
+) -> Vec<InvariantViolation> {
+    fold_stars_with_config(degree, &config.enumeration, Vec::new(), |mut out, star| {
+        for partition in partitions_of_colors(star.root_colors) {
+            if config.allows(&partition) {
+                out.extend(verify_invariants(&star, &partition, config.reductions));
+            }
+        }
+        out
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::star_utils::EnumerationConfig;
+
+    #[test]
+    fn capped_merge_keeps_the_measure_from_increasing() {
+        // Two 2-lists of degree 3 merge into one of degree 5, which weighs more than both.
+        let weights = NodeFeatures::from_array([1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 1.0, 0.5, 0.1]);
+        let star = Star {
+            root_colors: 0b0111,
+            neighbor_colors: vec![0b0011, 0b0011],
+            neighbor_halfedges: vec![2, 2],
+            neighbor_adjacency: None,
+        };
+        let partition = [0b0011, 0b0100];
+        let max_degree = MaxDegree::default();
+        let increases =
+            measure_increases(&star, &partition, DEFAULT_REDUCTIONS, weights, max_degree);
+        assert_eq!(increases.len(), 1);
+        assert_eq!(increases[0].step.name, "duplicate-2lists");
+        assert_eq!(increases[0].step.star.neighbor_halfedges, vec![4]);
+
+        let capped: &[&dyn Reduction] =
+            &[&DominatedRootColors, &CappedDuplicateTwoLists(max_degree)];
+        assert_eq!(
+            measure_increases(&star, &partition, capped, weights, max_degree),
+            vec![]
+        );
+        let branch = Star {
+            root_colors: 0b0011,
+            ..star.clone()
+        };
+        assert_eq!(
+            reduce_duplicate_2lists_capped(&branch, max_degree)
+                .unwrap()
+                .neighbor_halfedges,
+            vec![2]
+        );
+        assert_eq!(
+            verify_reduction(&CappedDuplicateTwoLists(max_degree), &branch),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn reductions_keep_the_invariants_with_eight_colors() {
+        let star = Star {
+            root_colors: 0b1110_0001,
+            neighbor_colors: vec![0b1100_0000, 0b1100_0000, 0b0110_0001, 0b0001_1000],
+            neighbor_halfedges: vec![2, 2, 3, 2],
+            neighbor_adjacency: None,
+        };
+        for partition in partitions_of_colors(star.root_colors) {
+            assert_eq!(
+                verify_invariants(&star, &partition, DEFAULT_REDUCTIONS),
+                Vec::new()
+            );
+            for branch in apply_list_coloring_partition_unreduced(&star, &partition) {
+                for &reduction in DEFAULT_REDUCTIONS {
+                    assert_ne!(verify_reduction(reduction, &branch), Some(false));
+                }
+            }
+        }
+        // The twins with the root list {6,7} merge.
+        let twins = Star {
+            root_colors: 0b1100_0000,
+            neighbor_colors: vec![0b1100_0000, 0b1100_0000, 0b1010_0000],
+            neighbor_halfedges: vec![2, 2, 2],
+            neighbor_adjacency: None,
+        };
+        assert_eq!(verify_reduction(&DuplicateTwoLists, &twins), Some(true));
+    }
+
+    #[test]
+    fn dominated_root_colors_is_safe_on_small_stars() {
+        let mut checked = 0;
+        for degree in 2..=3 {
+            for star in generate_stars(degree) {
+                // Also remove each color from all neighbor lists to produce private colors and
+                // singleton neighbor lists.
+                let mut cases = vec![star.clone()];
+                for bit_idx in 0..4 {
+                    let mut case = star.clone();
+                    for c in case.neighbor_colors.iter_mut() {
+                        *c &= !(1u8 << bit_idx);
+                    }
+                    cases.push(case);
+                }
+                for case in cases {
+                    if let Some(ok) = verify_reduction(&DominatedRootColors, &case) {
+                        assert!(ok, "{case:?}");
+                        checked += 1;
+                    }
+                }
+            }
+        }
+        assert!(checked > 0);
+    }
+
+    #[test]
+    fn verify_reduction_rejects_unsound_rules() {
+        // Dropping a color shared with a neighbor is unsound: the neighbor may be forced to
+        // the other color from the outside.
+        struct DropHighestRootColor;
+        impl Reduction for DropHighestRootColor {
+            fn name(&self) -> &'static str {
+                "drop-highest-root-color"
+            }
+            fn precondition(&self) -> &'static str {
+                "always"
+            }
+            fn safety(&self) -> SafetyLevel {
+                SafetyLevel::Experimental
+            }
+            fn apply(&self, star: &Star) -> Option<Star> {
+                let highest = 0x80u8 >> star.root_colors.leading_zeros();
+                let mut reduced = star.clone();
+                reduced.root_colors &= !highest;
+                Some(reduced)
+            }
+        }
+
+        let star = Star {
+            root_colors: 0b0011,
+            neighbor_colors: vec![0b0011],
+            neighbor_halfedges: vec![2],
+            neighbor_adjacency: None,
+        };
+        assert_eq!(verify_reduction(&DropHighestRootColor, &star), Some(false));
+        assert_eq!(verify_reduction(&DominatedRootColors, &star), None);
+
+        assert_eq!(check_reduction_safety(DEFAULT_REDUCTIONS, false), Ok(()));
+        let reductions: &[&dyn Reduction] = &[&DominatedRootColors, &DropHighestRootColor];
+        assert_eq!(
+            check_reduction_safety(reductions, false),
+            Err("unproved reductions: drop-highest-root-color (experimental)".to_string())
+        );
+        assert_eq!(check_reduction_safety(reductions, true), Ok(()));
+    }
+
+    #[test]
+    fn select_reductions_refuses_unproved_rules_unless_allowed() {
+        let max_degree = MaxDegree(4);
+        for &name in REDUCTION_NAMES {
+            assert_eq!(reduction_by_name(name, max_degree).unwrap().name(), name);
+        }
+        let names = ["dominated-root-colors", "duplicate-2lists-capped"];
+        assert_eq!(
+            select_reductions(&names, max_degree, false).err(),
+            Some("unproved reductions: duplicate-2lists-capped (conjectured)".to_string())
+        );
+        let selected = select_reductions(&names, max_degree, true).unwrap();
+        assert_eq!(selected.iter().map(|r| r.name()).collect::<Vec<_>>(), names);
+        let star = Star {
+            root_colors: 0b0011,
+            neighbor_colors: vec![0b0011, 0b0011],
+            neighbor_halfedges: vec![1, 1],
+            neighbor_adjacency: None,
+        };
+        // Twins of degree 2 keep that degree instead of merging into a vertex of degree 3.
+        assert_eq!(
+            selected[1].apply(&star).unwrap().neighbor_halfedges,
+            vec![1]
+        );
+
+        let defaults: Vec<&str> = DEFAULT_REDUCTIONS.iter().map(|r| r.name()).collect();
+        assert!(select_reductions(&defaults, max_degree, false).is_ok());
+        assert!(
+            select_reductions(&["no-such-rule"], max_degree, true)
+                .err()
+                .unwrap()
+                .starts_with("unknown reduction: no-such-rule")
+        );
+    }
+
+    #[test]
+    fn default_reductions_keep_degree_5_stars_valid() {
+        // Merging four or five twin 2-lists once gave neighbors with up to 20 halfedges, more
+        // than Star::validate allows. Three colors are enough for root lists that branch into
+        // 2-lists, and keep the check fast.
+        let config = AnalysisConfig {
+            enumeration: EnumerationConfig {
+                colors: 3,
+                ..EnumerationConfig::default()
+            },
+            ..AnalysisConfig::default()
+        };
+        assert_eq!(verify_all_invariants_with_config(5, &config), vec![]);
+    }
+
+    #[test]
+    fn verify_invariants_collects_violations() {
+        assert!(verify_all_invariants(2).is_empty());
+
+        // Adds a fifth color to the root list once, which breaks the color range.
+        struct AddFifthColor;
+        impl Reduction for AddFifthColor {
+            fn name(&self) -> &'static str {
+                "add-fifth-color"
+            }
+            fn precondition(&self) -> &'static str {
+                "the root list has no fifth color"
+            }
+            fn safety(&self) -> SafetyLevel {
+                SafetyLevel::Experimental
+            }
+            fn apply(&self, star: &Star) -> Option<Star> {
+                let mut reduced = star.clone();
+                reduced.root_colors |= 0b1_0000;
+                (reduced != *star).then_some(reduced)
+            }
+        }
+
+        let star = Star {
+            root_colors: 0b0111,
+            neighbor_colors: vec![0b0011, 0b0110],
+            neighbor_halfedges: vec![2, 2],
+            neighbor_adjacency: None,
+        };
+        let violations = verify_invariants(&star, &[0b0110, 0b0001], &[&AddFifthColor]);
+        assert_eq!(violations.len(), 2);
+        assert!(violations.iter().all(|v| v.stage == "add-fifth-color"
+            && v.error
+                == StarError::ColorsOutOfRange {
+                    vertex: None,
+                    colors: v.input.root_colors | 0b1_0000
+                }));
+
+        let mut broken = star.clone();
+        broken.neighbor_halfedges.pop();
+        let violations = verify_invariants(&broken, &[0b0110, 0b0001], DEFAULT_REDUCTIONS);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].stage, "input");
+    }
+
+    #[test]
+    fn default_reductions_certify_up_to_degree_3() {
+        for &reduction in DEFAULT_REDUCTIONS.iter() {
+            let cert = certify_reduction(reduction, 3);
+            assert!(cert.counterexamples.is_empty(), "{}", cert.name);
+            assert!(
+                cert.cases_verified.iter().sum::<usize>() > 0,
+                "{}",
+                cert.name
+            );
+            assert_eq!(cert, certify_reduction(reduction, 3));
+        }
+    }
+
+    #[test]
+    fn reduce_to_fixpoint_chains_reductions() {
+        // Neighbor {2} forces the root down to {0,1}, after which the two {0,1} neighbors merge.
+        let star = Star {
+            root_colors: 0b0111,
+            neighbor_colors: vec![0b0011, 0b0100, 0b0011],
+            neighbor_halfedges: vec![2, 3, 4],
+            neighbor_adjacency: None,
+        };
+        let (reduced, trace) = reduce_to_fixpoint_with_trace(&star, DEFAULT_REDUCTIONS);
+        assert_eq!(trace, vec!["dominated-root-colors", "duplicate-2lists"]);
+        assert_eq!(reduced.root_colors, 0b0011);
+        assert_eq!(reduced.neighbor_colors, vec![0b0011, 0b0100]);
+        assert_eq!(reduced.neighbor_halfedges, vec![6, 3]);
+
+        // The steps add up: every step starts at the measure where the previous one ended.
+        let measure = |s: &Star| s.root_colors.count_ones() as f64 + s.neighbor_colors.len() as f64;
+        let (stepped, steps) = reduce_to_fixpoint_with_steps(&star, DEFAULT_REDUCTIONS, measure);
+        assert_eq!(stepped, reduced);
+        assert_eq!(steps.iter().map(|s| s.name).collect::<Vec<_>>(), trace);
+        assert_eq!(steps[0].measure_before, measure(&star));
+        assert_eq!(steps[0].measure_after, steps[1].measure_before);
+        assert_eq!(steps[1].star, reduced);
+        assert_eq!(steps.iter().map(ReductionStep::drop).sum::<f64>(), 2.0);
+    }
+}
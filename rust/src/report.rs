@@ -0,0 +1,1719 @@
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+pub use crate::analysis::CaseFilter;
+#[cfg(feature = "std")]
+use crate::analysis::best_partition_with_budget;
+use crate::analysis::{
+    AnalysisConfig, ParetoPartition, best_partition, branch_drop, pareto_partitions, star_measure,
+};
+use crate::annotations::{Annotations, StarAnnotation};
+use crate::colors::{NUM_COLORS, fmt_colors, fmt_partition};
+use crate::json::{JsonValue, parse_json};
+use crate::list_coloring_utils::{
+    Branch, MaxDegree, NodeFeatures, apply_list_coloring_partition_unreduced, is_infeasible,
+};
+use crate::number_format::{format_float, format_json_float};
+use crate::recurrence::{Recurrence, certify_branching_number_at_most};
+use crate::reductions::{
+    DEFAULT_REDUCTIONS, Reduction, reduce_to_fixpoint_with_steps, reduction_by_name,
+    select_reductions,
+};
+#[cfg(feature = "parallel")]
+use crate::star_utils::fold_stars_par;
+use crate::star_utils::{
+    EnumerationConfig, HalfedgeBound, Star, fold_stars_with_config, star_from_json_value,
+    star_from_string, star_label, star_to_json_string, star_to_string,
+};
+#[cfg(feature = "parallel")]
+use crate::trace::TraceEvent;
+use crate::trace::{TraceSink, record_case};
+
+/// One branch of a [`CaseReport`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct BranchReport {
+    /// The block of the partition that became the root list.
+    pub root_block: u8,
+    /// Names of the reductions applied to the branch, in order.
+    pub reductions: Vec<String>,
+    /// The drop in the measure of each reduction in `reductions`. The drop of the branch is
+    /// the drop from the star to the unreduced branch plus these.
+    pub reduction_drops: Vec<f64>,
+    pub outcome: Branch,
+    /// Drop in the measure; `None` for infeasible branches, which do not count.
+    pub drop: Option<f64>,
+}
+
+/// The analysis of one star: its best partition and the resulting branches.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CaseReport {
+    pub star: Star,
+    pub partition: Vec<u8>,
+    pub branching_number: f64,
+    pub branches: Vec<BranchReport>,
+    /// Whether the search for the partition ran out of its time budget (see
+    /// [`analysis_report_with_budget`]), so a better partition may exist.
+    pub possibly_suboptimal: bool,
+    /// The partitions with fewer branches at a worse branching number, the Pareto front of
+    /// [`pareto_partitions`], if [`AnalysisConfig::pareto`] is set and the front has more than
+    /// one partition; empty otherwise.
+    pub pareto: Vec<ParetoPartition>,
+}
+
+impl CaseReport {
+    /// The recurrence of the branches, see [`Recurrence::from_branches`].
+    pub fn recurrence(&self) -> Recurrence {
+        Recurrence::from_branches(self.branches.iter().map(|b| (&b.outcome, b.drop)))
+    }
+}
+
+/// The complete case analysis for all stars of one degree under one weight vector.
+///
+/// This is the single source for generated proof documents, so they cannot drift from the
+/// code that computes the branching numbers.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnalysisReport {
+    pub degree: usize,
+    pub weights: NodeFeatures,
+    /// One case per star whose root list can be split, in enumeration order.
+    pub cases: Vec<CaseReport>,
+    /// The rules by which stars were left out of `cases`, see
+    /// [`AnalysisConfig::exclusions`].
+    pub exclusions: CaseFilter,
+    /// The maximum degree Δ the analysis is for, see [`EnumerationConfig::max_degree`];
+    /// `None` for the unrestricted stars of
+    /// [`generate_stars`](crate::star_utils::generate_stars) with the buckets of Δ = 5.
+    pub max_degree: Option<MaxDegree>,
+    /// The number of colors of the lists, see [`EnumerationConfig::colors`].
+    pub colors: u8,
+    /// The constraints on the halfedges of the enumerated stars, see
+    /// [`EnumerationConfig::halfedge_bounds`].
+    pub halfedge_bounds: Vec<HalfedgeBound>,
+    /// The fewest halfedges of a neighbor, see [`EnumerationConfig::min_halfedges`].
+    pub min_halfedges: u8,
+    /// The most halfedges of a neighbor, see [`EnumerationConfig::max_halfedges`].
+    pub max_halfedges: Option<u8>,
+    /// The largest number of blocks of the partitions, see [`AnalysisConfig::max_blocks`];
+    /// `None` for no limit.
+    pub max_blocks: Option<usize>,
+    /// The names of the reductions applied to the branches, in order of priority, see
+    /// [`AnalysisConfig::reductions`].
+    pub reductions: Vec<String>,
+    /// Whether the cases record their Pareto fronts, see [`AnalysisConfig::pareto`].
+    pub pareto: bool,
+}
+
+impl AnalysisReport {
+    /// Leaves out the cases excluded by `filter` and records its rules in the report.
+    pub fn filtered(mut self, filter: CaseFilter) -> Self {
+        self.cases.retain(|case| !filter.excludes(&case.star));
+        self.exclusions.root_list_exceeds_degree |= filter.root_list_exceeds_degree;
+        self.exclusions.neighbor_list_exceeds_degree |= filter.neighbor_list_exceeds_degree;
+        self
+    }
+
+    /// Returns the largest branching number over all cases, or 1 if there are none.
+    pub fn worst_branching_number(&self) -> f64 {
+        self.cases
+            .iter()
+            .map(|c| c.branching_number)
+            .fold(1.0, f64::max)
+    }
+
+    /// The cases whose branching number is not certified to be at most `bound` by
+    /// [`certify_branching_number_at_most`], evaluated on the drops of their branches.
+    pub fn uncertified_cases(&self, bound: f64) -> Vec<&CaseReport> {
+        self.cases
+            .iter()
+            .filter(|c| !certify_branching_number_at_most(&c.recurrence().drops, bound))
+            .collect()
+    }
+
+    /// The reductions the cases were analyzed with, by their names in `reductions`, see
+    /// [`select_reductions`]. Fails on unknown names, and on reductions that are not proved
+    /// unless `allow_unproved` is set, since the report then proves nothing.
+    pub fn reductions(&self, allow_unproved: bool) -> Result<Vec<Box<dyn Reduction>>, String> {
+        let names: Vec<&str> = self.reductions.iter().map(String::as_str).collect();
+        select_reductions(&names, self.max_degree.unwrap_or_default(), allow_unproved)
+    }
+
+    /// The configuration the cases were analyzed with, given the objects of
+    /// [`AnalysisReport::reductions`].
+    pub fn config<'a>(&self, reductions: &'a [&'a dyn Reduction]) -> AnalysisConfig<'a> {
+        AnalysisConfig {
+            enumeration: EnumerationConfig {
+                colors: self.colors,
+                max_degree: self.max_degree.map(|d| d.0),
+                halfedge_bounds: self.halfedge_bounds.clone(),
+                min_halfedges: self.min_halfedges,
+                max_halfedges: self.max_halfedges,
+                ..EnumerationConfig::default()
+            },
+            max_blocks: self.max_blocks,
+            reductions,
+            pareto: self.pareto,
+            exclusions: self.exclusions,
+        }
+    }
+
+    /// The worst branching number the cases would have without the limit on the number of
+    /// blocks, i.e. with their best partitions; `None` if the partitions are not limited.
+    /// Comparing it with [`AnalysisReport::worst_branching_number`] gives the cost of the limit.
+    pub fn unlimited_worst_branching_number(&self) -> Option<f64> {
+        self.max_blocks?;
+        // The report already depends on these reductions, proved or not.
+        let reductions = self.reductions(true).ok()?;
+        let reductions: Vec<&dyn Reduction> = reductions.iter().map(|r| r.as_ref()).collect();
+        let config = AnalysisConfig {
+            max_blocks: None,
+            ..self.config(&reductions)
+        };
+        Some(
+            self.cases
+                .iter()
+                .filter_map(|c| best_partition(&c.star, self.weights, &config))
+                .map(|(_, bn)| bn)
+                .fold(1.0, f64::max),
+        )
+    }
+
+    /// Returns the names of the reductions used by some branch, in the order of
+    /// `reductions`.
+    pub fn reductions_used(&self) -> Vec<&str> {
+        self.reductions
+            .iter()
+            .map(String::as_str)
+            .filter(|name| {
+                self.cases
+                    .iter()
+                    .flat_map(|c| c.branches.iter())
+                    .any(|b| b.reductions.iter().any(|r| r == name))
+            })
+            .collect()
+    }
+}
+
+/// Analyzes `star` by its [`best_partition`] under `config`, or returns `None` if the root
+/// list cannot be split. A star whose root list can be split but has no partition `config`
+/// allows, which takes `config.max_blocks < 2`, is a case with an empty partition, no
+/// branches and an infinite branching number, so that it violates every bound.
+pub fn case_report(
+    star: &Star,
+    weights: NodeFeatures,
+    config: &AnalysisConfig,
+) -> Option<CaseReport> {
+    match best_partition(star, weights, config) {
+        Some((partition, branching_number)) => Some(case_report_for_partition(
+            star,
+            weights,
+            config,
+            partition,
+            branching_number,
+        )),
+        None => unsplit_case(star),
+    }
+}
+
+/// The case of a star whose root list can be split, but not by an allowed partition; `None`
+/// if the root list cannot be split at all.
+fn unsplit_case(star: &Star) -> Option<CaseReport> {
+    (star.root_colors.count_ones() >= 2).then(|| CaseReport {
+        star: star.clone(),
+        partition: Vec::new(),
+        branching_number: f64::INFINITY,
+        branches: Vec::new(),
+        possibly_suboptimal: false,
+        pareto: Vec::new(),
+    })
+}
+
+/// The case of `star` branching by `partition`, whose branching number is known.
+fn case_report_for_partition(
+    star: &Star,
+    weights: NodeFeatures,
+    config: &AnalysisConfig,
+    partition: Vec<u8>,
+    branching_number: f64,
+) -> CaseReport {
+    let max_degree = config.max_degree();
+    let star_measure = |s: &Star, w| star_measure(s, w, max_degree);
+    let before = star_measure(star, weights);
+    let branches = apply_list_coloring_partition_unreduced(star, &partition)
+        .into_iter()
+        .map(|branch| {
+            let root_block = branch.root_colors;
+            if is_infeasible(&branch) {
+                return BranchReport {
+                    root_block,
+                    reductions: Vec::new(),
+                    reduction_drops: Vec::new(),
+                    outcome: Branch::Infeasible,
+                    drop: None,
+                };
+            }
+            let (reduced, steps) = reduce_to_fixpoint_with_steps(&branch, config.reductions, |s| {
+                star_measure(s, weights)
+            });
+            let outcome = Branch::from_star(reduced);
+            let drop = branch_drop(before, &outcome, weights, max_degree);
+            BranchReport {
+                root_block,
+                reductions: steps.iter().map(|step| String::from(step.name)).collect(),
+                reduction_drops: steps.iter().map(|step| step.drop()).collect(),
+                outcome,
+                drop,
+            }
+        })
+        .collect();
+    let pareto = if config.pareto {
+        pareto_partitions(star, weights, config)
+    } else {
+        Vec::new()
+    };
+    CaseReport {
+        star: star.clone(),
+        partition,
+        branching_number,
+        branches,
+        possibly_suboptimal: false,
+        pareto: if pareto.len() > 1 { pareto } else { Vec::new() },
+    }
+}
+
+impl AnalysisReport {
+    /// The report of the analysis of the stars of `degree` under `config`, with the given
+    /// cases and the exclusions of `config`.
+    fn new(
+        degree: usize,
+        weights: NodeFeatures,
+        config: &AnalysisConfig,
+        cases: Vec<CaseReport>,
+    ) -> Self {
+        AnalysisReport {
+            degree,
+            weights,
+            cases,
+            exclusions: config.exclusions,
+            max_degree: config.enumeration.max_degree.map(MaxDegree),
+            colors: config.enumeration.colors,
+            halfedge_bounds: config.enumeration.halfedge_bounds.clone(),
+            min_halfedges: config.enumeration.min_halfedges,
+            max_halfedges: config.enumeration.max_halfedges,
+            max_blocks: config.max_blocks,
+            reductions: config.reductions.iter().map(|r| r.name().into()).collect(),
+            pareto: config.pareto,
+        }
+    }
+
+    /// The reductions of `reductions_used`, with their safety levels and preconditions.
+    fn used_reductions(&self) -> Vec<Box<dyn Reduction>> {
+        let max_degree = self.max_degree.unwrap_or_default();
+        self.reductions_used()
+            .into_iter()
+            .filter_map(|name| reduction_by_name(name, max_degree))
+            .collect()
+    }
+}
+
+/// Analyzes the stars of the given `degree` under `config`, see [`case_report`].
+///
+/// Panics if `config.enumeration` fails [`EnumerationConfig::validate`].
+pub fn analysis_report(
+    degree: usize,
+    weights: NodeFeatures,
+    config: &AnalysisConfig,
+) -> AnalysisReport {
+    analysis_report_traced(degree, weights, config, &mut ())
+}
+
+/// Like [`analysis_report`], recording the decisions of every case in `sink` as it is
+/// analyzed, see [`record_case`].
+pub fn analysis_report_traced(
+    degree: usize,
+    weights: NodeFeatures,
+    config: &AnalysisConfig,
+    sink: &mut impl TraceSink,
+) -> AnalysisReport {
+    let cases = fold_stars_with_config(
+        degree,
+        &config.enumeration,
+        Vec::new(),
+        |mut cases, star| {
+            if config.exclusions.excludes(&star) {
+                return cases;
+            }
+            if let Some(case) = case_report(&star, weights, config) {
+                record_case(&case, sink);
+                cases.push(case);
+            }
+            cases
+        },
+    );
+    AnalysisReport::new(degree, weights, config, cases)
+}
+
+/// Like [`analysis_report`], calling `progress` after each star with its index in the
+/// enumeration order, the star, and its case if its root list can be split and it is not
+/// excluded.
+pub fn analysis_report_with_progress(
+    degree: usize,
+    weights: NodeFeatures,
+    config: &AnalysisConfig,
+    mut progress: impl FnMut(usize, &Star, Option<&CaseReport>),
+) -> AnalysisReport {
+    let (_, cases) = fold_stars_with_config(
+        degree,
+        &config.enumeration,
+        (0, Vec::new()),
+        |(i, mut cases), star| {
+            let case = (!config.exclusions.excludes(&star))
+                .then(|| case_report(&star, weights, config))
+                .flatten();
+            progress(i, &star, case.as_ref());
+            cases.extend(case);
+            (i + 1, cases)
+        },
+    );
+    AnalysisReport::new(degree, weights, config, cases)
+}
+
+/// Like [`analysis_report`], analyzing the stars on `threads` threads.
+///
+/// The stars are split into contiguous shards of the enumeration order, which the threads
+/// take as they become free, and the shards are merged in that order (see
+/// [`fold_stars_par`]), so the result is identical to the sequential one.
+#[cfg(feature = "parallel")]
+pub fn analysis_report_parallel(
+    degree: usize,
+    weights: NodeFeatures,
+    config: &AnalysisConfig,
+    threads: usize,
+) -> AnalysisReport {
+    analysis_report_parallel_traced(degree, weights, config, threads, &mut ())
+}
+
+/// Like [`analysis_report_parallel`], recording the decisions of every case in `sink` like
+/// [`analysis_report_traced`]. Every shard records its own events, which are merged in the
+/// order of the shards, so the trace is identical to the sequential one.
+#[cfg(feature = "parallel")]
+pub fn analysis_report_parallel_traced(
+    degree: usize,
+    weights: NodeFeatures,
+    config: &AnalysisConfig,
+    threads: usize,
+    sink: &mut impl TraceSink,
+) -> AnalysisReport {
+    let tracing = sink.is_enabled();
+    let (cases, events) = fold_stars_par(
+        degree,
+        &config.enumeration,
+        threads,
+        || (Vec::new(), Vec::new()),
+        |(mut cases, mut events): (Vec<CaseReport>, Vec<TraceEvent>), star| {
+            if config.exclusions.excludes(&star) {
+                return (cases, events);
+            }
+            if let Some(case) = case_report(&star, weights, config) {
+                if tracing {
+                    record_case(&case, &mut events);
+                }
+                cases.push(case);
+            }
+            (cases, events)
+        },
+        |(mut cases, mut events), (shard_cases, shard_events)| {
+            cases.extend(shard_cases);
+            events.extend(shard_events);
+            (cases, events)
+        },
+    );
+    for event in events {
+        sink.record(event);
+    }
+    AnalysisReport::new(degree, weights, config, cases)
+}
+
+/// Like [`analysis_report`], but gives up searching for the best partition of a star once
+/// `budget` has elapsed (see [`best_partition_with_budget`]), so the analysis finishes in
+/// predictable time. The cases of the stars that hit the budget are marked
+/// [`CaseReport::possibly_suboptimal`].
+#[cfg(feature = "std")]
+pub fn analysis_report_with_budget(
+    degree: usize,
+    weights: NodeFeatures,
+    config: &AnalysisConfig,
+    budget: std::time::Duration,
+) -> AnalysisReport {
+    let cases = fold_stars_with_config(
+        degree,
+        &config.enumeration,
+        Vec::new(),
+        |mut cases, star| {
+            if config.exclusions.excludes(&star) {
+                return cases;
+            }
+            let Some(best) = best_partition_with_budget(&star, weights, config, budget) else {
+                cases.extend(unsplit_case(&star));
+                return cases;
+            };
+            cases.push(CaseReport {
+                possibly_suboptimal: best.possibly_suboptimal,
+                ..case_report_for_partition(
+                    &star,
+                    weights,
+                    config,
+                    best.partition,
+                    best.branching_number,
+                )
+            });
+            cases
+        },
+    );
+    AnalysisReport::new(degree, weights, config, cases)
+}
+
+/// How long the analysis of one star took.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, PartialEq)]
+pub struct CaseTiming {
+    pub star: Star,
+    /// Number of partitions of the root list that were evaluated.
+    pub partitions: usize,
+    pub elapsed: std::time::Duration,
+}
+
+/// Like [`analysis_report`], also timing the analysis of every star, including stars whose
+/// root list cannot be split. Timings are in enumeration order.
+#[cfg(feature = "std")]
+pub fn analysis_report_profiled(
+    degree: usize,
+    weights: NodeFeatures,
+    config: &AnalysisConfig,
+) -> (AnalysisReport, Vec<CaseTiming>) {
+    let (cases, timings) = fold_stars_with_config(
+        degree,
+        &config.enumeration,
+        (Vec::new(), Vec::new()),
+        |(mut cases, mut timings), star| {
+            if config.exclusions.excludes(&star) {
+                return (cases, timings);
+            }
+            let start = std::time::Instant::now();
+            cases.extend(case_report(&star, weights, config));
+            timings.push(CaseTiming {
+                partitions: crate::list_coloring_utils::partitions_of_colors(star.root_colors)
+                    .iter()
+                    .filter(|p| config.allows(p))
+                    .count(),
+                elapsed: start.elapsed(),
+                star,
+            });
+            (cases, timings)
+        },
+    );
+    (AnalysisReport::new(degree, weights, config, cases), timings)
+}
+
+/// Returns the `n` slowest cases, slowest first.
+#[cfg(feature = "std")]
+pub fn slowest_cases(timings: &[CaseTiming], n: usize) -> Vec<&CaseTiming> {
+    let mut sorted: Vec<&CaseTiming> = timings.iter().collect();
+    sorted.sort_by_key(|t| core::cmp::Reverse(t.elapsed));
+    sorted.truncate(n);
+    sorted
+}
+
+impl AnalysisReport {
+    /// FNV-1a hash of [`AnalysisReport::to_json_string`], to compare runs bit for bit.
+    pub fn content_hash(&self) -> u64 {
+        const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+        const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+        self.to_json_string().bytes().fold(FNV_OFFSET, |hash, b| {
+            (hash ^ b as u64).wrapping_mul(FNV_PRIME)
+        })
+    }
+}
+
+fn outcome_to_string(outcome: &Branch) -> String {
+    match outcome {
+        Branch::Solved => "solved".into(),
+        Branch::Infeasible => "infeasible".into(),
+        Branch::Star(s) => star_label(s),
+    }
+}
+
+/// A star as a JSON value: its string encoding if it has one, otherwise the object of
+/// [`star_to_json_string`], so that stars with wide lists still read back.
+fn star_to_json_value(star: &Star) -> String {
+    match star_to_string(star) {
+        Ok(s) => format!("\"{s}\""),
+        Err(_) => star_to_json_string(star),
+    }
+}
+
+fn outcome_to_json_value(outcome: &Branch) -> String {
+    match outcome {
+        Branch::Star(s) => star_to_json_value(s),
+        _ => format!("\"{}\"", outcome_to_string(outcome)),
+    }
+}
+
+/// Groups case indices by root list, in order of first appearance.
+fn root_classes(report: &AnalysisReport) -> Vec<(u8, Vec<usize>)> {
+    let mut classes: Vec<(u8, Vec<usize>)> = Vec::new();
+    for (i, case) in report.cases.iter().enumerate() {
+        match classes
+            .iter_mut()
+            .find(|(root, _)| *root == case.star.root_colors)
+        {
+            Some((_, cases)) => cases.push(i),
+            None => classes.push((case.star.root_colors, alloc::vec![i])),
+        }
+    }
+    classes
+}
+
+/// States the exclusion rules of the report, with the rule names formatted by `code`.
+fn exclusions_sentence(report: &AnalysisReport, code: impl Fn(&str) -> String) -> Option<String> {
+    let rules = report.exclusions.rules();
+    if rules.is_empty() {
+        return None;
+    }
+    let names: Vec<String> = rules.iter().map(|r| code(r)).collect();
+    Some(format!(
+        "Excluded stars ({}): a vertex whose list is larger than its degree can be colored last.",
+        names.join(", ")
+    ))
+}
+
+/// States the maximum degree of the report and the degrees its weights apply to, with the
+/// weight names formatted by `code`.
+fn max_degree_sentence(report: &AnalysisReport, code: impl Fn(&str) -> String) -> Option<String> {
+    let delta = report.max_degree?.0;
+    Some(format!(
+        "Maximum degree: {delta}. The weights {}, {} and {} apply to the degrees at least {delta}, {} and {}.",
+        code("n*_ge5"),
+        code("n*_4"),
+        code("n*_3"),
+        delta.saturating_sub(1),
+        delta.saturating_sub(2)
+    ))
+}
+
+/// States the range of halfedges of the neighbors of the report if it is not the default one.
+fn halfedge_range_sentence(report: &AnalysisReport) -> Option<String> {
+    let default = EnumerationConfig::default();
+    if report.min_halfedges == default.min_halfedges && report.max_halfedges.is_none() {
+        return None;
+    }
+    let max = match report.max_halfedges {
+        Some(max) => max.to_string(),
+        None => "the degree of the star".to_string(),
+    };
+    Some(format!(
+        "Neighbors have from {} to {max} halfedges.",
+        report.min_halfedges
+    ))
+}
+
+/// Lists the halfedge bounds of the report, formatted by `code`.
+fn halfedge_bounds_sentence(
+    report: &AnalysisReport,
+    code: impl Fn(&str) -> String,
+) -> Option<String> {
+    if report.halfedge_bounds.is_empty() {
+        return None;
+    }
+    let bounds: Vec<String> = report
+        .halfedge_bounds
+        .iter()
+        .map(|b| code(&b.to_string()))
+        .collect();
+    Some(format!(
+        "Halfedge bounds: {}, each of the form {}, allowing at most {} neighbors with {} to {} halfedges.",
+        bounds.join(", "),
+        code("min-max:count"),
+        code("count"),
+        code("min"),
+        code("max")
+    ))
+}
+
+/// States the limit on the number of blocks of the report and the worst branching number
+/// without it, formatted by `num`.
+fn max_blocks_sentence(report: &AnalysisReport, num: &impl Fn(f64) -> String) -> Option<String> {
+    let max_blocks = report.max_blocks?;
+    let unlimited = report.unlimited_worst_branching_number()?;
+    Some(format!(
+        "Partitions have at most {max_blocks} blocks. Without this limit, the worst branching number would be {}.",
+        num(unlimited)
+    ))
+}
+
+/// The reductions of a branch with the drop of each step, e.g. `dominated-root-colors (drop
+/// 0.3), ...`, or `none`.
+fn reduction_steps(branch: &BranchReport, num: &impl Fn(f64) -> String) -> String {
+    if branch.reductions.is_empty() {
+        return "none".into();
+    }
+    branch
+        .reductions
+        .iter()
+        .enumerate()
+        .map(|(i, r)| match branch.reduction_drops.get(i) {
+            Some(&d) => format!("{r} (drop {})", num(d)),
+            None => r.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Lists the partitions of a case that use fewer branches at a worse branching number, as
+/// recorded in [`CaseReport::pareto`], or `None` if it records none.
+fn pareto_sentence(case: &CaseReport, num: &impl Fn(f64) -> String) -> Option<String> {
+    if case.pareto.is_empty() {
+        return None;
+    }
+    let points: Vec<String> = case
+        .pareto
+        .iter()
+        .map(|p| {
+            format!(
+                "{} with {} branches, branching number {}",
+                fmt_partition(&p.partition),
+                p.branches,
+                num(p.branching_number)
+            )
+        })
+        .collect();
+    Some(format!("Pareto front: {}.", points.join("; ")))
+}
+
+/// Marks a case whose partition search ran out of its time budget.
+const SUBOPTIMAL_SENTENCE: &str =
+    "The search for the partition ran out of time, so a better partition may exist.";
+
+/// The note and reference of an annotation as one sentence, e.g. `Tight. See Lemma 12.`
+fn annotation_note(annotation: &StarAnnotation) -> Option<String> {
+    match (&annotation.note, &annotation.reference) {
+        (None, None) => None,
+        (Some(note), None) => Some(note.clone()),
+        (None, Some(reference)) => Some(format!("See {reference}.")),
+        (Some(note), Some(reference)) => Some(format!("{note} See {reference}.")),
+    }
+}
+
+/// Renders the report as a Markdown case analysis: one section per root list, one table of
+/// branches per case, and an appendix of the reductions used. Numbers are formatted by
+/// [`format_float`].
+pub fn report_to_markdown(report: &AnalysisReport, precision: Option<usize>) -> String {
+    report_to_markdown_annotated(report, precision, &Annotations::default())
+}
+
+/// Like [`report_to_markdown`], titling each case with its annotated case name and adding its
+/// note and reference.
+pub fn report_to_markdown_annotated(
+    report: &AnalysisReport,
+    precision: Option<usize>,
+    annotations: &Annotations,
+) -> String {
+    let num = |x: f64| format_float(x, precision);
+    let mut out = format!("# Case analysis for degree {}\n\n", report.degree);
+    if report.colors != NUM_COLORS {
+        out.push_str(&format!("Colors: {}.\n\n", report.colors));
+    }
+    out.push_str(&format!(
+        "Weights: `{}`\n\nCases: {}. Worst branching number: {}.\n",
+        report.weights.to_json_string_with_precision(precision),
+        report.cases.len(),
+        num(report.worst_branching_number())
+    ));
+    if let Some(rules) = exclusions_sentence(report, |r| format!("`{r}`")) {
+        out.push_str(&format!("\n{rules}\n"));
+    }
+    if let Some(sentence) = max_degree_sentence(report, |r| format!("`{r}`")) {
+        out.push_str(&format!("\n{sentence}\n"));
+    }
+    if let Some(sentence) = halfedge_range_sentence(report) {
+        out.push_str(&format!("\n{sentence}\n"));
+    }
+    if let Some(sentence) = halfedge_bounds_sentence(report, |r| format!("`{r}`")) {
+        out.push_str(&format!("\n{sentence}\n"));
+    }
+    if let Some(sentence) = max_blocks_sentence(report, &num) {
+        out.push_str(&format!("\n{sentence}\n"));
+    }
+
+    for (root, cases) in root_classes(report) {
+        out.push_str(&format!("\n## Root list {}\n", fmt_colors(root)));
+        for &i in cases.iter() {
+            let case = &report.cases[i];
+            let annotation = annotations.get(&case.star);
+            let name = annotation
+                .and_then(|a| a.name.as_deref())
+                .map(|n| format!("{n}: "))
+                .unwrap_or_default();
+            out.push_str(&format!(
+                "\n### Case {name}`{}`\n\nPartition {}, branching number {}.\n\n",
+                star_label(&case.star),
+                fmt_partition(&case.partition),
+                num(case.branching_number)
+            ));
+            if let Some(note) = annotation.and_then(annotation_note) {
+                out.push_str(&format!("{note}\n\n"));
+            }
+            if case.possibly_suboptimal {
+                out.push_str(&format!("{SUBOPTIMAL_SENTENCE}\n\n"));
+            }
+            if let Some(front) = pareto_sentence(case, &num) {
+                out.push_str(&format!("{front}\n\n"));
+            }
+            out.push_str("| root | reductions | result | drop |\n");
+            out.push_str("|---|---|---|---|\n");
+            for b in case.branches.iter() {
+                out.push_str(&format!(
+                    "| {} | {} | `{}` | {} |\n",
+                    fmt_colors(b.root_block),
+                    reduction_steps(b, &num),
+                    outcome_to_string(&b.outcome),
+                    b.drop.map(num).unwrap_or_else(|| "-".into())
+                ));
+            }
+        }
+    }
+
+    out.push_str("\n## Appendix: reductions\n\n");
+    let used = report.used_reductions();
+    if used.is_empty() {
+        out.push_str("No reductions were used.\n");
+    }
+    for reduction in used.iter() {
+        out.push_str(&format!(
+            "- **{}** ({}): applies if {}.\n",
+            reduction.name(),
+            reduction.safety().name(),
+            reduction.precondition()
+        ));
+    }
+    out
+}
+
+/// Renders the report as a LaTeX fragment with the same structure as
+/// [`report_to_markdown`], for inclusion in a paper appendix.
+pub fn report_to_latex(report: &AnalysisReport, precision: Option<usize>) -> String {
+    report_to_latex_annotated(report, precision, &Annotations::default())
+}
+
+/// Like [`report_to_latex`], with the annotations of [`report_to_markdown_annotated`].
+pub fn report_to_latex_annotated(
+    report: &AnalysisReport,
+    precision: Option<usize>,
+    annotations: &Annotations,
+) -> String {
+    fn escape(s: &str) -> String {
+        let mut out = String::new();
+        for c in s.chars() {
+            match c {
+                '_' | '{' | '}' | '&' | '%' | '$' | '#' => {
+                    out.push('\\');
+                    out.push(c);
+                }
+                _ => out.push(c),
+            }
+        }
+        out
+    }
+
+    let num = |x: f64| format_float(x, precision);
+    let mut out = format!("\\section{{Case analysis for degree {}}}\n", report.degree);
+    if report.colors != NUM_COLORS {
+        out.push_str(&format!("Colors: ${}$.\n", report.colors));
+    }
+    out.push_str(&format!(
+        "Cases: {}. Worst branching number: ${}$.\n",
+        report.cases.len(),
+        num(report.worst_branching_number())
+    ));
+    if let Some(rules) = exclusions_sentence(report, |r| format!("\\texttt{{{}}}", escape(r))) {
+        out.push_str(&format!("{rules}\n"));
+    }
+    if let Some(sentence) = max_degree_sentence(report, |r| format!("\\texttt{{{}}}", escape(r))) {
+        out.push_str(&format!("{sentence}\n"));
+    }
+    if let Some(sentence) = halfedge_range_sentence(report) {
+        out.push_str(&format!("{sentence}\n"));
+    }
+    if let Some(sentence) =
+        halfedge_bounds_sentence(report, |r| format!("\\texttt{{{}}}", escape(r)))
+    {
+        out.push_str(&format!("{sentence}\n"));
+    }
+    if let Some(sentence) = max_blocks_sentence(report, &|x| format!("${}$", num(x))) {
+        out.push_str(&format!("{sentence}\n"));
+    }
+
+    for (root, cases) in root_classes(report) {
+        out.push_str(&format!(
+            "\n\\subsection{{Root list ${}$}}\n",
+            escape(&fmt_colors(root))
+        ));
+        for &i in cases.iter() {
+            let case = &report.cases[i];
+            let annotation = annotations.get(&case.star);
+            let name = annotation
+                .and_then(|a| a.name.as_deref())
+                .map(|n| format!("{}: ", escape(n)))
+                .unwrap_or_default();
+            out.push_str(&format!(
+                "\n\\paragraph{{{name}\\texttt{{{}}}}} Partition ${}$, branching number ${}$.\n",
+                escape(&star_label(&case.star)),
+                escape(&fmt_partition(&case.partition)),
+                num(case.branching_number)
+            ));
+            if let Some(note) = annotation.and_then(annotation_note) {
+                out.push_str(&format!("{}\n\n", escape(&note)));
+            }
+            if case.possibly_suboptimal {
+                out.push_str(&format!("{SUBOPTIMAL_SENTENCE}\n\n"));
+            }
+            if let Some(front) = pareto_sentence(case, &num) {
+                out.push_str(&format!("{}\n\n", escape(&front)));
+            }
+            out.push_str(
+                "\\begin{tabular}{llll}\nroot & reductions & result & drop \\\\\n\\hline\n",
+            );
+            for b in case.branches.iter() {
+                out.push_str(&format!(
+                    "${}$ & {} & \\texttt{{{}}} & {} \\\\\n",
+                    escape(&fmt_colors(b.root_block)),
+                    escape(&reduction_steps(b, &num)),
+                    escape(&outcome_to_string(&b.outcome)),
+                    b.drop.map(num).unwrap_or_else(|| "--".into())
+                ));
+            }
+            out.push_str("\\end{tabular}\n");
+        }
+    }
+
+    out.push_str("\n\\subsection*{Reductions}\n\\begin{description}\n");
+    for reduction in report.used_reductions().iter() {
+        out.push_str(&format!(
+            "\\item[{}] ({}) applies if {}.\n",
+            escape(reduction.name()),
+            reduction.safety().name(),
+            escape(reduction.precondition())
+        ));
+    }
+    out.push_str("\\end{description}\n");
+    out
+}
+
+impl AnalysisReport {
+    /// Returns a compact one-line JSON document with no whitespace, readable by
+    /// [`report_from_json_string`]. Stars are written as v1 star strings, or as the JSON objects
+    /// of [`star_to_json_string`] if they have none, and numbers in their shortest round-trip
+    /// form. Infinite and NaN values are written as the strings of [`format_json_float`], and
+    /// the drops of infeasible branches as `null`.
+    pub fn to_json_string(&self) -> String {
+        let num = |x: f64| format_json_float(x, None);
+
+        let cases = self
+            .cases
+            .iter()
+            .map(|case| {
+                let branches = case
+                    .branches
+                    .iter()
+                    .map(|b| {
+                        let reductions = b
+                            .reductions
+                            .iter()
+                            .map(|r| format!("\"{r}\""))
+                            .collect::<Vec<_>>()
+                            .join(",");
+                        // Written only if some reduction applied, as branches without
+                        // reductions have no steps.
+                        let reduction_drops = if b.reduction_drops.is_empty() {
+                            String::new()
+                        } else {
+                            let drops: Vec<String> =
+                                b.reduction_drops.iter().map(|&d| num(d)).collect();
+                            format!(",\"reduction_drops\":[{}]", drops.join(","))
+                        };
+                        format!(
+                            "{{\"root_block\":{},\"reductions\":[{}]{},\"outcome\":{},\"drop\":{}}}",
+                            b.root_block,
+                            reductions,
+                            reduction_drops,
+                            outcome_to_json_value(&b.outcome),
+                            b.drop.map(num).unwrap_or_else(|| "null".into())
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(",");
+                let partition = case
+                    .partition
+                    .iter()
+                    .map(|block| block.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",");
+                // Written only if set, so artifacts without a time budget are unchanged.
+                let suboptimal = if case.possibly_suboptimal {
+                    ",\"possibly_suboptimal\":true"
+                } else {
+                    ""
+                };
+                let pareto = if case.pareto.is_empty() {
+                    String::new()
+                } else {
+                    let points: Vec<String> = case
+                        .pareto
+                        .iter()
+                        .map(|p| {
+                            let blocks: Vec<String> =
+                                p.partition.iter().map(|b| b.to_string()).collect();
+                            format!(
+                                "{{\"partition\":[{}],\"branching_number\":{},\"branches\":{}}}",
+                                blocks.join(","),
+                                num(p.branching_number),
+                                p.branches
+                            )
+                        })
+                        .collect();
+                    format!(",\"pareto\":[{}]", points.join(","))
+                };
+                format!(
+                    "{{\"star\":{},\"partition\":[{}],\"branching_number\":{},\"branches\":[{}]{}{}}}",
+                    star_to_json_value(&case.star),
+                    partition,
+                    num(case.branching_number),
+                    branches,
+                    suboptimal,
+                    pareto
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        // The safety levels of the reductions the proof relies on; derived from the cases, so
+        // they are not read back.
+        let levels: Vec<String> = self
+            .used_reductions()
+            .iter()
+            .map(|r| format!("\"{}\":\"{}\"", r.name(), r.safety().name()))
+            .collect();
+        let reduction_levels = if levels.is_empty() {
+            String::new()
+        } else {
+            format!(",\"reduction_levels\":{{{}}}", levels.join(","))
+        };
+        // Written only if some rule is enabled, so artifacts without exclusions stay unchanged.
+        let rules = self.exclusions.rules();
+        let exclusions = if rules.is_empty() {
+            String::new()
+        } else {
+            let names: Vec<String> = rules.iter().map(|r| format!("\"{r}\"")).collect();
+            format!(",\"exclusions\":[{}]", names.join(","))
+        };
+        let max_degree = self
+            .max_degree
+            .map(|d| format!(",\"max_degree\":{}", d.0))
+            .unwrap_or_default();
+        let colors = if self.colors == NUM_COLORS {
+            String::new()
+        } else {
+            format!(",\"colors\":{}", self.colors)
+        };
+        let halfedge_bounds = if self.halfedge_bounds.is_empty() {
+            String::new()
+        } else {
+            let bounds: Vec<String> = self
+                .halfedge_bounds
+                .iter()
+                .map(|b| format!("\"{b}\""))
+                .collect();
+            format!(",\"halfedge_bounds\":[{}]", bounds.join(","))
+        };
+        let max_blocks = self
+            .max_blocks
+            .map(|b| format!(",\"max_blocks\":{b}"))
+            .unwrap_or_default();
+        let min_halfedges = if self.min_halfedges == EnumerationConfig::default().min_halfedges {
+            String::new()
+        } else {
+            format!(",\"min_halfedges\":{}", self.min_halfedges)
+        };
+        let max_halfedges = self
+            .max_halfedges
+            .map(|h| format!(",\"max_halfedges\":{h}"))
+            .unwrap_or_default();
+        // Written only for other reductions than the default ones, like the fields above.
+        let reductions = if self
+            .reductions
+            .iter()
+            .map(String::as_str)
+            .eq(default_reduction_names())
+        {
+            String::new()
+        } else {
+            let names: Vec<String> = self.reductions.iter().map(|r| format!("\"{r}\"")).collect();
+            format!(",\"reductions\":[{}]", names.join(","))
+        };
+        let pareto = if self.pareto { ",\"pareto\":true" } else { "" };
+        format!(
+            "{{\"degree\":{},\"weights\":{},\"cases\":[{}]{}{}{}{}{}{}{}{}{}{}}}",
+            self.degree,
+            self.weights.to_json_string(),
+            cases,
+            reduction_levels,
+            exclusions,
+            max_degree,
+            colors,
+            halfedge_bounds,
+            max_blocks,
+            reductions,
+            min_halfedges,
+            max_halfedges,
+            pareto
+        )
+    }
+}
+
+/// Decodes a report written by [`AnalysisReport::to_json_string`]. Fails on unknown
+/// reductions; whether a report may rely on unproved ones is up to the caller, see
+/// [`AnalysisReport::reductions`].
+pub fn report_from_json_string(s: &str) -> Result<AnalysisReport, String> {
+    fn field<'a>(v: &'a JsonValue, key: &str) -> Result<&'a JsonValue, String> {
+        v.get(key).ok_or_else(|| format!("missing field \"{key}\""))
+    }
+    fn number(v: &JsonValue, key: &str) -> Result<f64, String> {
+        field(v, key)?
+            .as_float()
+            .ok_or_else(|| format!("field \"{key}\" is not a number"))
+    }
+    fn array<'a>(v: &'a JsonValue, key: &str) -> Result<&'a [JsonValue], String> {
+        field(v, key)?
+            .as_array()
+            .ok_or_else(|| format!("field \"{key}\" is not an array"))
+    }
+    fn flag(v: &JsonValue, key: &str) -> Result<bool, String> {
+        match v.get(key) {
+            None => Ok(false),
+            Some(JsonValue::Bool(b)) => Ok(*b),
+            Some(_) => Err(format!("field \"{key}\" is not a boolean")),
+        }
+    }
+    fn byte(x: &JsonValue) -> Result<u8, String> {
+        x.as_f64()
+            .filter(|&x| (0.0..=255.0).contains(&x) && x as u8 as f64 == x)
+            .map(|x| x as u8)
+            .ok_or_else(|| "expected an integer in 0..=255".to_string())
+    }
+    // A star is a string encoding or, for stars without one, an object with the fields of
+    // `star_to_json_string`.
+    fn star(v: &JsonValue) -> Result<Star, String> {
+        if let Some(s) = v.as_str() {
+            return star_from_string(s).map_err(|e| e.to_string());
+        }
+        star_from_json_value(v)
+    }
+
+    let doc = parse_json(s)?;
+    let degree = number(&doc, "degree")?;
+    let weights_doc = field(&doc, "weights")?;
+    let mut weights = [0.0; 9];
+    for (w, name) in weights.iter_mut().zip(NodeFeatures::NAMES) {
+        *w = number(weights_doc, name)?;
+    }
+
+    let colors = match doc.get("colors") {
+        None => NUM_COLORS,
+        Some(_) => byte(field(&doc, "colors")?)?,
+    };
+    let max_degree = match doc.get("max_degree") {
+        None => None,
+        Some(_) => Some(MaxDegree(number(&doc, "max_degree")? as usize)),
+    };
+    let halfedge_bounds = match doc.get("halfedge_bounds") {
+        None => Vec::new(),
+        Some(_) => array(&doc, "halfedge_bounds")?
+            .iter()
+            .map(|b| b.as_str().ok_or("halfedge bounds must be strings")?.parse())
+            .collect::<Result<Vec<HalfedgeBound>, String>>()?,
+    };
+    let max_blocks = match doc.get("max_blocks") {
+        None => None,
+        Some(_) => Some(number(&doc, "max_blocks")? as usize),
+    };
+    let min_halfedges = match doc.get("min_halfedges") {
+        None => EnumerationConfig::default().min_halfedges,
+        Some(_) => byte(field(&doc, "min_halfedges")?)?,
+    };
+    let max_halfedges = match doc.get("max_halfedges") {
+        None => None,
+        Some(_) => Some(byte(field(&doc, "max_halfedges")?)?),
+    };
+    let reductions = match doc.get("reductions") {
+        None => default_reduction_names().map(String::from).collect(),
+        Some(_) => array(&doc, "reductions")?
+            .iter()
+            .map(|r| r.as_str().map(String::from))
+            .collect::<Option<Vec<_>>>()
+            .ok_or("reduction names must be strings")?,
+    };
+    let exclusions = match doc.get("exclusions") {
+        None => CaseFilter::default(),
+        Some(_) => CaseFilter::from_rules(
+            array(&doc, "exclusions")?
+                .iter()
+                .map(|r| r.as_str().ok_or("exclusion rules must be strings"))
+                .collect::<Result<Vec<_>, _>>()?,
+        )?,
+    };
+
+    let mut cases = Vec::new();
+    for case in array(&doc, "cases")? {
+        let mut branches = Vec::new();
+        for b in array(case, "branches")? {
+            let outcome = match field(b, "outcome")?.as_str() {
+                Some("solved") => Branch::Solved,
+                Some("infeasible") => Branch::Infeasible,
+                _ => Branch::Star(star(field(b, "outcome")?)?),
+            };
+            branches.push(BranchReport {
+                root_block: byte(field(b, "root_block")?)?,
+                reductions: array(b, "reductions")?
+                    .iter()
+                    .map(|r| r.as_str().map(String::from))
+                    .collect::<Option<_>>()
+                    .ok_or("reduction names must be strings")?,
+                reduction_drops: match b.get("reduction_drops") {
+                    None => Vec::new(),
+                    Some(_) => array(b, "reduction_drops")?
+                        .iter()
+                        .map(|d| d.as_float())
+                        .collect::<Option<_>>()
+                        .ok_or("reduction drops must be numbers")?,
+                },
+                outcome,
+                drop: field(b, "drop")?.as_float(),
+            });
+        }
+        cases.push(CaseReport {
+            star: star(field(case, "star")?)?,
+            partition: array(case, "partition")?
+                .iter()
+                .map(byte)
+                .collect::<Result<_, _>>()?,
+            // Older artifacts wrote infinite branching numbers as null.
+            branching_number: field(case, "branching_number")?
+                .as_float()
+                .unwrap_or(f64::INFINITY),
+            branches,
+            possibly_suboptimal: flag(case, "possibly_suboptimal")?,
+            pareto: match case.get("pareto") {
+                None => Vec::new(),
+                Some(_) => array(case, "pareto")?
+                    .iter()
+                    .map(|p| {
+                        Ok(ParetoPartition {
+                            partition: array(p, "partition")?
+                                .iter()
+                                .map(byte)
+                                .collect::<Result<_, _>>()?,
+                            branching_number: number(p, "branching_number")?,
+                            branches: number(p, "branches")? as usize,
+                        })
+                    })
+                    .collect::<Result<_, String>>()?,
+            },
+        });
+    }
+    let report = AnalysisReport {
+        degree: degree as usize,
+        weights: NodeFeatures::from_array(weights),
+        cases,
+        exclusions,
+        max_degree,
+        colors,
+        halfedge_bounds,
+        min_halfedges,
+        max_halfedges,
+        max_blocks,
+        reductions,
+        pareto: flag(&doc, "pareto")?,
+    };
+    report.reductions(true)?;
+    Ok(report)
+}
+
+/// The names of the [`DEFAULT_REDUCTIONS`], which artifacts leave out.
+fn default_reduction_names() -> impl Iterator<Item = &'static str> {
+    DEFAULT_REDUCTIONS.iter().map(|r| r.name())
+}
+
+/// The differences between two reports of the same case analysis, e.g. before and after
+/// changing a reduction or the weights. Cases are matched by their star.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReportDiff {
+    /// Stars analyzed only in the second report.
+    pub added: Vec<Star>,
+    /// Stars analyzed only in the first report.
+    pub removed: Vec<Star>,
+    /// Stars whose branching number changed by more than the tolerance, with the old and new
+    /// values.
+    pub branching_numbers: Vec<(Star, f64, f64)>,
+    /// Stars whose branches use a different sequence of reductions, with the old and new sets
+    /// of reductions used.
+    pub reductions: Vec<(Star, Vec<String>, Vec<String>)>,
+}
+
+impl ReportDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.branching_numbers.is_empty()
+            && self.reductions.is_empty()
+    }
+}
+
+/// Compares two reports. Branching numbers count as changed if they differ by more than
+/// `tolerance`; a finite and an infinite value always differ.
+pub fn diff_reports(before: &AnalysisReport, after: &AnalysisReport, tolerance: f64) -> ReportDiff {
+    fn reductions(case: &CaseReport) -> Vec<String> {
+        let mut names: Vec<String> = case
+            .branches
+            .iter()
+            .flat_map(|b| b.reductions.iter().cloned())
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    let new_cases: BTreeMap<&Star, &CaseReport> =
+        after.cases.iter().map(|c| (&c.star, c)).collect();
+    let old_stars: BTreeSet<&Star> = before.cases.iter().map(|c| &c.star).collect();
+    let mut diff = ReportDiff::default();
+    for old in before.cases.iter() {
+        let Some(&new) = new_cases.get(&old.star) else {
+            diff.removed.push(old.star.clone());
+            continue;
+        };
+        let (x, y) = (old.branching_number, new.branching_number);
+        let changed = if x.is_finite() && y.is_finite() {
+            (x - y).abs() > tolerance
+        } else {
+            x != y
+        };
+        if changed {
+            diff.branching_numbers.push((old.star.clone(), x, y));
+        }
+        let old_reductions: Vec<&Vec<String>> =
+            old.branches.iter().map(|b| &b.reductions).collect();
+        let new_reductions: Vec<&Vec<String>> =
+            new.branches.iter().map(|b| &b.reductions).collect();
+        if old_reductions != new_reductions {
+            diff.reductions
+                .push((old.star.clone(), reductions(old), reductions(new)));
+        }
+    }
+    for new in after.cases.iter() {
+        if !old_stars.contains(&new.star) {
+            diff.added.push(new.star.clone());
+        }
+    }
+    diff
+}
+
+/// Renders a diff as text, one line per change, grouped by kind.
+pub fn report_diff_to_text(diff: &ReportDiff, precision: Option<usize>) -> String {
+    let star = |s: &Star| star_label(s);
+    let mut out = String::new();
+    for s in diff.added.iter() {
+        out.push_str(&format!("added {}\n", star(s)));
+    }
+    for s in diff.removed.iter() {
+        out.push_str(&format!("removed {}\n", star(s)));
+    }
+    for (s, x, y) in diff.branching_numbers.iter() {
+        out.push_str(&format!(
+            "branching number {}: {} -> {}\n",
+            star(s),
+            format_float(*x, precision),
+            format_float(*y, precision)
+        ));
+    }
+    for (s, old, new) in diff.reductions.iter() {
+        out.push_str(&format!(
+            "reductions {}: [{}] -> [{}]\n",
+            star(s),
+            old.join(", "),
+            new.join(", ")
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::partition_recurrence;
+    use crate::reductions::{CappedDuplicateTwoLists, DominatedRootColors};
+    use crate::star_utils::generate_stars;
+    use alloc::vec;
+
+    fn weights() -> NodeFeatures {
+        NodeFeatures::from_array([1.0, 1.0, 1.0, 0.7, 0.7, 0.7, 0.4, 0.4, 0.4])
+    }
+
+    #[test]
+    fn report_matches_recurrences_and_renders() {
+        let report = analysis_report(2, weights(), &AnalysisConfig::default());
+        assert_eq!(report.cases.len(), generate_stars(2).len());
+        for case in report.cases.iter() {
+            assert_eq!(
+                case.recurrence(),
+                partition_recurrence(
+                    &case.star,
+                    &case.partition,
+                    weights(),
+                    &AnalysisConfig::default()
+                )
+            );
+        }
+
+        let md = report_to_markdown(&report, Some(3));
+        assert!(md.starts_with("# Case analysis for degree 2\n"));
+        assert_eq!(md.matches("\n### Case ").count(), report.cases.len());
+        assert_eq!(md.matches("\n## Root list ").count(), 3);
+        for name in report.reductions_used() {
+            assert!(md.contains(&format!("- **{name}**")));
+        }
+
+        let tex = report_to_latex(&report, Some(3));
+        assert_eq!(tex.matches("\\begin{tabular}").count(), report.cases.len());
+        assert!(!tex.contains("star_2"));
+
+        let star = &report.cases[1].star;
+        let mut annotations = Annotations::default();
+        annotations.insert(
+            star,
+            StarAnnotation {
+                name: Some("C5.3".to_string()),
+                note: Some("Tight.".to_string()),
+                reference: Some("Lemma 12".to_string()),
+            },
+        );
+        let md = report_to_markdown_annotated(&report, Some(3), &annotations);
+        let title = format!("\n### Case C5.3: `{}`\n", star_to_string(star).unwrap());
+        assert!(md.contains(&title));
+        assert!(md.contains("\nTight. See Lemma 12.\n"));
+        let tex = report_to_latex_annotated(&report, Some(3), &annotations);
+        assert!(tex.contains("\\paragraph{C5.3: \\texttt{"));
+    }
+
+    #[test]
+    fn reports_round_trip_and_diff() {
+        let before = analysis_report(2, weights(), &AnalysisConfig::default());
+        let parsed = report_from_json_string(&before.to_json_string()).unwrap();
+        assert_eq!(parsed, before);
+        assert!(
+            before
+                .to_json_string()
+                .contains("\"reduction_levels\":{\"dominated-root-colors\":\"proved\"")
+        );
+        assert!(diff_reports(&before, &parsed, 0.0).is_empty());
+
+        let mut after = analysis_report(
+            2,
+            NodeFeatures::from_array([1.0; 9]),
+            &AnalysisConfig::default(),
+        );
+        let removed = after.cases.remove(0).star;
+        let diff = diff_reports(&before, &after, 1e-9);
+        assert_eq!(diff.removed, vec![removed]);
+        assert!(diff.added.is_empty());
+        assert!(!diff.branching_numbers.is_empty());
+        assert_eq!(
+            report_diff_to_text(&diff, None).lines().count(),
+            1 + diff.branching_numbers.len() + diff.reductions.len()
+        );
+        assert!(report_from_json_string("{\"degree\":2}").is_err());
+
+        // Stars without a v1 string are written as objects and still read back.
+        let mut wide = before.clone();
+        wide.cases[0].star.neighbor_halfedges[0] = 20;
+        wide.cases[0].branches[0].outcome = Branch::Star(wide.cases[0].star.clone());
+        let json = wide.to_json_string();
+        assert!(json.contains("\"outcome\":{\"root_colors\""));
+        assert_eq!(report_from_json_string(&json), Ok(wide));
+
+        // Stars of degree 2 occur for Δ = 5 as they are, so only the recorded Δ differs.
+        let bounded = analysis_report(2, weights(), &AnalysisConfig::for_max_degree(5));
+        assert_eq!(bounded.cases, before.cases);
+        assert!(bounded.to_json_string().ends_with(",\"max_degree\":5}"));
+        assert_eq!(
+            report_from_json_string(&bounded.to_json_string()),
+            Ok(bounded)
+        );
+        let small = analysis_report(2, weights(), &AnalysisConfig::for_max_degree(3));
+        assert_ne!(small.cases, before.cases);
+        let config = AnalysisConfig {
+            enumeration: EnumerationConfig {
+                colors: 3,
+                ..EnumerationConfig::default()
+            },
+            ..AnalysisConfig::default()
+        };
+        let three = analysis_report(2, weights(), &config);
+        assert_eq!(three.config(DEFAULT_REDUCTIONS), config);
+        assert!(
+            three
+                .cases
+                .iter()
+                .all(|c| c.star.validate_with_colors(3).is_ok())
+        );
+        assert_eq!(report_from_json_string(&three.to_json_string()), Ok(three));
+        let config = AnalysisConfig {
+            enumeration: EnumerationConfig {
+                halfedge_bounds: vec!["2-:1".parse().unwrap()],
+                ..EnumerationConfig::default()
+            },
+            ..AnalysisConfig::default()
+        };
+        let bounded = analysis_report(2, weights(), &config);
+        assert!(bounded.cases.len() < before.cases.len());
+        assert!(
+            bounded
+                .to_json_string()
+                .ends_with(",\"halfedge_bounds\":[\"2-:1\"]}")
+        );
+        assert_eq!(
+            report_from_json_string(&bounded.to_json_string()),
+            Ok(bounded)
+        );
+        let capped: &[&dyn Reduction] =
+            &[&DominatedRootColors, &CappedDuplicateTwoLists(MaxDegree(4))];
+        let config = AnalysisConfig {
+            reductions: capped,
+            ..AnalysisConfig::for_max_degree(4)
+        };
+        let report = analysis_report(2, weights(), &config);
+        let json = report.to_json_string();
+        assert!(
+            json.ends_with(
+                ",\"reductions\":[\"dominated-root-colors\",\"duplicate-2lists-capped\"]}"
+            )
+        );
+        let read_back = report_from_json_string(&json).unwrap();
+        // The capped rule is only conjectured, so the report proves nothing without allowing it.
+        assert!(read_back.reductions(false).is_err());
+        let reductions = read_back.reductions(true).unwrap();
+        let reductions: Vec<&dyn Reduction> = reductions.iter().map(|r| r.as_ref()).collect();
+        assert_eq!(read_back.config(&reductions), config);
+        assert_eq!(read_back, report);
+        assert!(report_from_json_string(&json.replace("capped", "merged")).is_err());
+        let config = AnalysisConfig {
+            enumeration: EnumerationConfig {
+                min_halfedges: 0,
+                max_halfedges: Some(3),
+                ..EnumerationConfig::default()
+            },
+            ..AnalysisConfig::default()
+        };
+        let pendant = analysis_report(2, weights(), &config);
+        assert_eq!(pendant.config(DEFAULT_REDUCTIONS), config);
+        assert!(
+            pendant
+                .to_json_string()
+                .ends_with(",\"min_halfedges\":0,\"max_halfedges\":3}")
+        );
+        assert!(
+            report_to_markdown(&pendant, None).contains("Neighbors have from 0 to 3 halfedges.")
+        );
+        assert_eq!(
+            report_from_json_string(&pendant.to_json_string()),
+            Ok(pendant)
+        );
+
+        let binary = analysis_report(
+            2,
+            weights(),
+            &AnalysisConfig {
+                max_blocks: Some(2),
+                ..AnalysisConfig::default()
+            },
+        );
+        assert_eq!(binary.cases.len(), before.cases.len());
+        assert!(binary.cases.iter().all(|c| c.partition.len() == 2));
+        assert!(binary.worst_branching_number() >= before.worst_branching_number());
+        assert_eq!(
+            binary.unlimited_worst_branching_number(),
+            Some(before.worst_branching_number())
+        );
+        assert!(report_to_markdown(&binary, None).contains("at most 2 blocks"));
+        assert!(binary.to_json_string().ends_with(",\"max_blocks\":2}"));
+        assert_eq!(
+            report_from_json_string(&binary.to_json_string()),
+            Ok(binary)
+        );
+        // Without partitions, every star is a violation instead of being left out.
+        let none = analysis_report(
+            2,
+            weights(),
+            &AnalysisConfig {
+                max_blocks: Some(1),
+                ..AnalysisConfig::default()
+            },
+        );
+        assert_eq!(none.cases.len(), before.cases.len());
+        assert_eq!(none.worst_branching_number(), f64::INFINITY);
+        let json = none.to_json_string();
+        assert!(json.contains("\"branching_number\":\"inf\""));
+        assert_eq!(report_from_json_string(&json), Ok(none));
+    }
+
+    #[test]
+    fn exclusions_are_applied_and_recorded() {
+        let full = analysis_report(2, weights(), &AnalysisConfig::default());
+        let filter = CaseFilter::from_rules([CaseFilter::ROOT_RULE]).unwrap();
+        let filtered = full.clone().filtered(filter);
+        // With 2 neighbors, only roots with the list {0,1} remain.
+        assert!(filtered.cases.iter().all(|c| c.star.root_colors == 0b0011));
+        assert_eq!(
+            filtered.cases.len(),
+            full.cases
+                .iter()
+                .filter(|c| c.star.root_colors == 0b0011)
+                .count()
+        );
+        assert_eq!(filtered.exclusions.rules(), vec![CaseFilter::ROOT_RULE]);
+
+        let json = filtered.to_json_string();
+        assert!(json.ends_with(",\"exclusions\":[\"root-list-exceeds-degree\"]}"));
+        assert_eq!(report_from_json_string(&json), Ok(filtered.clone()));
+        assert!(!full.to_json_string().contains("exclusions"));
+        assert!(report_to_markdown(&filtered, None).contains("`root-list-exceeds-degree`"));
+        assert!(CaseFilter::from_rules(["large-lists"]).is_err());
+
+        // The analyses skip the excluded stars themselves.
+        let config = AnalysisConfig {
+            exclusions: filter,
+            ..AnalysisConfig::default()
+        };
+        assert_eq!(analysis_report(2, weights(), &config), filtered);
+        #[cfg(feature = "parallel")]
+        assert_eq!(analysis_report_parallel(2, weights(), &config, 3), filtered);
+        #[cfg(feature = "std")]
+        {
+            let (profiled, timings) = analysis_report_profiled(2, weights(), &config);
+            assert_eq!(profiled, filtered);
+            assert!(timings.iter().all(|t| !filter.excludes(&t.star)));
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn budgeted_reports_mark_and_record_suboptimal_cases() {
+        let config = AnalysisConfig::default();
+        let unlimited =
+            analysis_report_with_budget(2, weights(), &config, std::time::Duration::MAX);
+        assert_eq!(unlimited, analysis_report(2, weights(), &config));
+        assert!(!unlimited.to_json_string().contains("possibly_suboptimal"));
+
+        let rushed = analysis_report_with_budget(2, weights(), &config, Default::default());
+        let marked: Vec<&CaseReport> = rushed
+            .cases
+            .iter()
+            .filter(|c| c.possibly_suboptimal)
+            .collect();
+        assert!(!marked.is_empty());
+        // A 2-list has a single partition, so its search never runs out of time.
+        assert!(marked.iter().all(|c| c.star.root_colors.count_ones() > 2));
+        assert_eq!(
+            report_from_json_string(&rushed.to_json_string()),
+            Ok(rushed.clone())
+        );
+        assert!(report_to_markdown(&rushed, None).contains(SUBOPTIMAL_SENTENCE));
+    }
+
+    #[test]
+    fn pareto_fronts_are_recorded_only_on_request() {
+        let plain = analysis_report(3, weights(), &AnalysisConfig::default());
+        assert!(plain.cases.iter().all(|c| c.pareto.is_empty()));
+        assert!(!report_to_markdown(&plain, None).contains("Pareto front"));
+
+        let config = AnalysisConfig {
+            pareto: true,
+            ..AnalysisConfig::default()
+        };
+        let report = analysis_report(3, weights(), &config);
+        let case = report.cases.iter().find(|c| !c.pareto.is_empty()).unwrap();
+        assert_eq!(
+            case.pareto,
+            pareto_partitions(&case.star, weights(), &config)
+        );
+        assert_eq!(
+            case.pareto.last().unwrap().branching_number,
+            case.branching_number
+        );
+        assert!(report_to_markdown(&report, None).contains("Pareto front: "));
+        assert!(report.to_json_string().ends_with(",\"pareto\":true}"));
+        assert_eq!(
+            report_from_json_string(&report.to_json_string()),
+            Ok(report.clone())
+        );
+        assert_eq!(report.config(DEFAULT_REDUCTIONS), config);
+    }
+
+    #[test]
+    fn parallel_and_profiled_reports_are_identical() {
+        let sequential = analysis_report(2, weights(), &AnalysisConfig::default());
+        #[cfg(feature = "parallel")]
+        for threads in [1, 3, 1000] {
+            let parallel =
+                analysis_report_parallel(2, weights(), &AnalysisConfig::default(), threads);
+            assert_eq!(parallel, sequential);
+            assert_eq!(parallel.content_hash(), sequential.content_hash());
+        }
+        #[cfg(feature = "parallel")]
+        {
+            let config = AnalysisConfig {
+                max_blocks: Some(2),
+                ..AnalysisConfig::for_max_degree(3)
+            };
+            let parallel = analysis_report_parallel(3, weights(), &config, 3);
+            assert_eq!(parallel, analysis_report(3, weights(), &config));
+        }
+
+        let mut calls = 0;
+        let with_progress = analysis_report_with_progress(
+            2,
+            weights(),
+            &AnalysisConfig::default(),
+            |i, star, case| {
+                assert_eq!(i, calls);
+                assert_eq!(star, &generate_stars(2)[i]);
+                assert_eq!(case.map(|c| &c.star), Some(star));
+                calls += 1;
+            },
+        );
+        assert_eq!(with_progress, sequential);
+        assert_eq!(calls, generate_stars(2).len());
+
+        #[cfg(feature = "std")]
+        {
+            let (profiled, timings) =
+                analysis_report_profiled(2, weights(), &AnalysisConfig::default());
+            assert_eq!(profiled, sequential);
+            assert_eq!(timings.len(), generate_stars(2).len());
+            let slowest = slowest_cases(&timings, 5);
+            assert_eq!(slowest.len(), 5);
+            assert!(slowest.windows(2).all(|w| w[0].elapsed >= w[1].elapsed));
+        }
+    }
+}
@@ -0,0 +1,1323 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+use crate::colors::{ALL_COLORS, NUM_COLORS, colors};
+use crate::star_utils::CountError;
+
+/// A graph whose vertices have color lists, as bitmasks over `0..NUM_COLORS`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ListGraph {
+    pub lists: Vec<u8>,
+    /// Symmetric adjacency lists without loops.
+    pub neighbors: Vec<Vec<usize>>,
+}
+
+impl ListGraph {
+    /// The graph on `lists.len()` vertices with the given undirected edges.
+    pub fn new(lists: Vec<u8>, edges: &[(usize, usize)]) -> Self {
+        let mut neighbors = alloc::vec![Vec::new(); lists.len()];
+        for &(u, v) in edges {
+            if u != v && !neighbors[u].contains(&v) {
+                neighbors[u].push(v);
+                neighbors[v].push(u);
+            }
+        }
+        ListGraph { lists, neighbors }
+    }
+
+    /// Whether `coloring` gives every vertex a color of its list and adjacent vertices
+    /// different colors.
+    pub fn is_proper_coloring(&self, coloring: &[u8]) -> bool {
+        coloring.len() == self.lists.len()
+            && coloring.iter().enumerate().all(|(v, &c)| {
+                (self.lists[v] >> c) & 1 == 1 && self.neighbors[v].iter().all(|&u| coloring[u] != c)
+            })
+    }
+}
+
+/// A [`ListGraph`] reduced by [`ListGraph::preprocess`], with what is needed to extend its
+/// colorings to the original graph.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Preprocessed {
+    /// The graph induced by the remaining vertices, with their reduced lists.
+    pub graph: ListGraph,
+    /// The vertex of the original graph of every vertex of `graph`.
+    pub vertices: Vec<usize>,
+    /// The vertices whose list shrank to a single color, with that color, in the order they
+    /// were colored.
+    pub forced: Vec<(usize, u8)>,
+    /// The vertices with more colors than remaining neighbors, in the order they were
+    /// removed.
+    pub removed: Vec<usize>,
+}
+
+impl ListGraph {
+    /// Reduces the graph to the instance the branching rules see, with the reductions that
+    /// [`solve`] applies at every node, once up front and to a fixpoint:
+    ///
+    /// - a vertex with a single color is colored with it and collapsed, and the color is
+    ///   removed from the lists of its neighbors, which may force them in turn;
+    /// - a vertex with more colors than remaining neighbors is removed, since it can always
+    ///   be colored after them.
+    ///
+    /// Returns `None` if a list becomes empty, so that the graph has no list coloring.
+    pub fn preprocess(&self) -> Option<Preprocessed> {
+        let n = self.lists.len();
+        let mut lists = self.lists.clone();
+        let mut live = alloc::vec![true; n];
+        let mut forced = Vec::new();
+        let mut removed = Vec::new();
+        'reduce: loop {
+            for v in 0..n {
+                if !live[v] {
+                    continue;
+                }
+                let size = lists[v].count_ones();
+                if size == 0 {
+                    return None;
+                }
+                if size == 1 {
+                    let c = lists[v].trailing_zeros() as u8;
+                    live[v] = false;
+                    forced.push((v, c));
+                    for &u in self.neighbors[v].iter() {
+                        lists[u] &= !(1 << c);
+                    }
+                    continue 'reduce;
+                }
+                let degree = self.neighbors[v].iter().filter(|&&u| live[u]).count();
+                if size as usize > degree {
+                    live[v] = false;
+                    removed.push(v);
+                    continue 'reduce;
+                }
+            }
+            break;
+        }
+        let vertices: Vec<usize> = (0..n).filter(|&v| live[v]).collect();
+        let mut index = alloc::vec![usize::MAX; n];
+        for (i, &v) in vertices.iter().enumerate() {
+            index[v] = i;
+        }
+        let graph = ListGraph {
+            lists: vertices.iter().map(|&v| lists[v]).collect(),
+            neighbors: vertices
+                .iter()
+                .map(|&v| {
+                    self.neighbors[v]
+                        .iter()
+                        .filter(|&&u| live[u])
+                        .map(|&u| index[u])
+                        .collect()
+                })
+                .collect(),
+        };
+        Some(Preprocessed {
+            graph,
+            vertices,
+            forced,
+            removed,
+        })
+    }
+}
+
+impl Preprocessed {
+    /// Extends a list coloring of the reduced graph to one of `original`, the graph this was
+    /// preprocessed from: the forced vertices get their colors, and the removed vertices are
+    /// colored greedily in the reverse order of their removal.
+    pub fn extend(&self, original: &ListGraph, coloring: &[u8]) -> Vec<u8> {
+        let mut out: Vec<Option<u8>> = alloc::vec![None; original.lists.len()];
+        for (&v, &c) in self.vertices.iter().zip(coloring) {
+            out[v] = Some(c);
+        }
+        for &(v, c) in self.forced.iter() {
+            out[v] = Some(c);
+        }
+        // Every removed vertex had more colors than the neighbors that were still in the
+        // graph, and the colors of the neighbors forced before were already off its list.
+        for &v in self.removed.iter().rev() {
+            let used = original.neighbors[v]
+                .iter()
+                .filter_map(|&u| out[u])
+                .fold(0u8, |used, c| used | 1 << c);
+            let free = original.lists[v] & !used;
+            debug_assert_ne!(free, 0);
+            out[v] = Some(free.trailing_zeros() as u8);
+        }
+        out.into_iter()
+            .map(|c| c.expect("every vertex is kept, forced or removed"))
+            .collect()
+    }
+}
+
+/// [`solve`] on the graph reduced by [`ListGraph::preprocess`], with the coloring extended
+/// back to `graph`.
+pub fn solve_preprocessed(graph: &ListGraph) -> Option<Vec<u8>> {
+    let preprocessed = graph.preprocess()?;
+    let coloring = solve(&preprocessed.graph)?;
+    Some(preprocessed.extend(graph, &coloring))
+}
+
+/// Options of the searches of the solver.
+#[derive(Clone)]
+pub struct SolverConfig<'a> {
+    /// The most memory in bytes, as estimated by [`TranspositionTable`], that the table of
+    /// solved subproblems may take; 0 disables it.
+    pub memory_limit: usize,
+    /// The heuristics tried, in order, on every subproblem the reductions leave.
+    pub heuristics: &'a [&'a dyn PruningHeuristic],
+}
+
+/// The memory limit of [`SolverConfig::default`], 64 MiB.
+pub const DEFAULT_MEMORY_LIMIT: usize = 64 << 20;
+
+impl Default for SolverConfig<'_> {
+    fn default() -> Self {
+        SolverConfig {
+            memory_limit: DEFAULT_MEMORY_LIMIT,
+            heuristics: DEFAULT_HEURISTICS,
+        }
+    }
+}
+
+/// How a search of the solver went.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SolverStats {
+    /// The subproblems searched, including the ones answered by the table.
+    pub nodes: u64,
+    pub table: TableStats,
+    /// How often every heuristic of the [`SolverConfig`] ran and pruned, in their order.
+    pub pruning: Vec<PruningStats>,
+}
+
+/// How often a [`PruningHeuristic`] ran in a search and how often it pruned.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PruningStats {
+    pub name: &'static str,
+    pub calls: u64,
+    pub prunes: u64,
+}
+
+impl PruningStats {
+    /// The fraction of the calls that pruned, 0 without calls.
+    pub fn prune_rate(&self) -> f64 {
+        if self.calls == 0 {
+            0.0
+        } else {
+            self.prunes as f64 / self.calls as f64
+        }
+    }
+}
+
+/// A test that proves that a subproblem of the solver has no list coloring, so that the
+/// search can skip it without branching.
+pub trait PruningHeuristic: Sync {
+    /// Short identifier used in the stats.
+    fn name(&self) -> &'static str;
+
+    /// Whether the vertices `v` with `live[v]` have no coloring from their `lists` in which
+    /// adjacent vertices get different colors. Returning `true` for a subproblem with a
+    /// coloring makes the solver wrong.
+    fn prunes(&self, graph: &ListGraph, lists: &[u8], live: &[bool]) -> bool;
+}
+
+/// Prunes if a clique of live vertices violates Hall's condition: some of its vertices have
+/// fewer colors in their lists than there are vertices. The cliques are grown greedily from
+/// every live vertex through its neighbors in order.
+pub struct CliqueHall;
+
+impl PruningHeuristic for CliqueHall {
+    fn name(&self) -> &'static str {
+        "clique-hall"
+    }
+
+    fn prunes(&self, graph: &ListGraph, lists: &[u8], live: &[bool]) -> bool {
+        (0..lists.len()).filter(|&v| live[v]).any(|v| {
+            let mut clique = alloc::vec![v];
+            for &u in graph.neighbors[v].iter() {
+                if live[u] && clique.iter().all(|w| graph.neighbors[u].contains(w)) {
+                    clique.push(u);
+                }
+            }
+            let all = clique.iter().fold(0u8, |union, &u| union | lists[u]);
+            // With more vertices than colors the subsets need not be tried; otherwise there
+            // are fewer than 2^8 of them.
+            all.count_ones() < clique.len() as u32
+                || (1..1u32 << clique.len()).any(|subset| {
+                    let union = colors(subset as u8)
+                        .fold(0u8, |union, i| union | lists[clique[i as usize]]);
+                    union.count_ones() < subset.count_ones()
+                })
+        })
+    }
+}
+
+/// Prunes if the live vertices with the same list of two colors have an odd cycle, which
+/// cannot alternate between the two colors.
+pub struct TwoListOddCycle;
+
+impl PruningHeuristic for TwoListOddCycle {
+    fn name(&self) -> &'static str {
+        "two-list-odd-cycle"
+    }
+
+    fn prunes(&self, graph: &ListGraph, lists: &[u8], live: &[bool]) -> bool {
+        let mut side: Vec<Option<bool>> = alloc::vec![None; lists.len()];
+        for start in 0..lists.len() {
+            if !live[start] || lists[start].count_ones() != 2 || side[start].is_some() {
+                continue;
+            }
+            side[start] = Some(false);
+            let mut stack = alloc::vec![start];
+            while let Some(v) = stack.pop() {
+                for &u in graph.neighbors[v].iter() {
+                    if !live[u] || lists[u] != lists[v] {
+                        continue;
+                    }
+                    match side[u] {
+                        None => {
+                            side[u] = side[v].map(|s| !s);
+                            stack.push(u);
+                        }
+                        Some(s) if Some(s) == side[v] => return true,
+                        Some(_) => {}
+                    }
+                }
+            }
+        }
+        false
+    }
+}
+
+/// The heuristics of [`SolverConfig::default`].
+pub static DEFAULT_HEURISTICS: &[&dyn PruningHeuristic] = &[&CliqueHall, &TwoListOddCycle];
+
+/// How the [`TranspositionTable`] of a search was used.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TableStats {
+    pub lookups: u64,
+    pub hits: u64,
+    pub insertions: u64,
+    pub evictions: u64,
+    /// The most memory in bytes the table took at any time, as estimated by the table.
+    pub peak_bytes: usize,
+}
+
+impl TableStats {
+    /// The fraction of the lookups that found their subproblem, 0 without lookups.
+    pub fn hit_rate(&self) -> f64 {
+        if self.lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / self.lookups as f64
+        }
+    }
+}
+
+/// The solved subproblems of a search by canonical form, see [`canonical_key`]. An entry is
+/// estimated to take the bytes of its key and value plus a fixed overhead for the tree; when
+/// an insertion would exceed the memory limit, the entries with the smallest keys are evicted
+/// until it fits.
+pub struct TranspositionTable<V> {
+    entries: BTreeMap<Vec<u8>, V>,
+    bytes: usize,
+    limit: usize,
+    stats: TableStats,
+}
+
+/// The bytes every entry of a [`TranspositionTable`] is estimated to take besides its key and
+/// value, for the nodes of the tree.
+const ENTRY_OVERHEAD: usize = 32;
+
+impl<V: Clone> TranspositionTable<V> {
+    pub fn new(limit: usize) -> Self {
+        TranspositionTable {
+            entries: BTreeMap::new(),
+            bytes: 0,
+            limit,
+            stats: TableStats::default(),
+        }
+    }
+
+    pub fn stats(&self) -> &TableStats {
+        &self.stats
+    }
+
+    fn entry_bytes(key: &[u8]) -> usize {
+        key.len() + core::mem::size_of::<Vec<u8>>() + core::mem::size_of::<V>() + ENTRY_OVERHEAD
+    }
+
+    pub fn get(&mut self, key: &[u8]) -> Option<V> {
+        self.stats.lookups += 1;
+        let value = self.entries.get(key).cloned();
+        self.stats.hits += value.is_some() as u64;
+        value
+    }
+
+    /// Stores `value` for `key`, unless the entry alone exceeds the limit or `key` is stored
+    /// already.
+    pub fn insert(&mut self, key: Vec<u8>, value: V) {
+        let size = Self::entry_bytes(&key);
+        if size > self.limit || self.entries.contains_key(&key) {
+            return;
+        }
+        while self.bytes + size > self.limit {
+            let (evicted, _) = self
+                .entries
+                .pop_first()
+                .expect("the entries take the bytes");
+            self.bytes -= Self::entry_bytes(&evicted);
+            self.stats.evictions += 1;
+        }
+        self.entries.insert(key, value);
+        self.bytes += size;
+        self.stats.insertions += 1;
+        self.stats.peak_bytes = self.stats.peak_bytes.max(self.bytes);
+    }
+}
+
+/// A [`TranspositionTable`] behind shared access, so that searches on several threads can use
+/// one table.
+trait Memo<V> {
+    fn enabled(&self) -> bool;
+    fn get(&self, key: &[u8]) -> Option<V>;
+    fn insert(&self, key: Vec<u8>, value: V);
+}
+
+impl<V: Clone> Memo<V> for RefCell<TranspositionTable<V>> {
+    fn enabled(&self) -> bool {
+        self.borrow().limit > 0
+    }
+    fn get(&self, key: &[u8]) -> Option<V> {
+        self.borrow_mut().get(key)
+    }
+    fn insert(&self, key: Vec<u8>, value: V) {
+        self.borrow_mut().insert(key, value)
+    }
+}
+
+/// The canonical form of a subproblem: the lists of the live vertices in vertex order and 0
+/// for the others, with the colors renamed in the order of their first occurrence if
+/// `rename`. Renaming the colors maps the colorings of a subproblem one to one to the
+/// colorings of the renamed one, so subproblems with the same renamed form have the same
+/// decision and count, but not the same weighted count.
+pub fn canonical_key(lists: &[u8], live: impl Fn(usize) -> bool, rename: bool) -> Vec<u8> {
+    let mut names = [u8::MAX; 8];
+    let mut next = 0;
+    (0..lists.len())
+        .map(|v| match (live(v), rename) {
+            (false, _) => 0,
+            (true, false) => lists[v],
+            (true, true) => colors(lists[v]).fold(0u8, |renamed, c| {
+                if names[c as usize] == u8::MAX {
+                    names[c as usize] = next;
+                    next += 1;
+                }
+                renamed | 1 << names[c as usize]
+            }),
+        })
+        .collect()
+}
+
+/// The state a search carries through its subproblems.
+struct Searcher<'a, M> {
+    graph: &'a ListGraph,
+    memo: &'a M,
+    heuristics: &'a [&'a dyn PruningHeuristic],
+    nodes: u64,
+    pruning: Vec<PruningStats>,
+}
+
+impl<M> Searcher<'_, M> {
+    /// Whether a heuristic prunes the subproblem, counting the calls until the first that
+    /// does.
+    fn pruned(&mut self, lists: &[u8], live: &[bool]) -> bool {
+        for (heuristic, stats) in self.heuristics.iter().zip(self.pruning.iter_mut()) {
+            stats.calls += 1;
+            if heuristic.prunes(self.graph, lists, live) {
+                stats.prunes += 1;
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// The stats of the heuristics of `config` before a search.
+fn pruning_stats(config: &SolverConfig) -> Vec<PruningStats> {
+    config
+        .heuristics
+        .iter()
+        .map(|h| PruningStats {
+            name: h.name(),
+            calls: 0,
+            prunes: 0,
+        })
+        .collect()
+}
+
+/// Runs `search` with a fresh table under the limit of `config`.
+fn with_searcher<V: Clone, R>(
+    graph: &ListGraph,
+    config: &SolverConfig,
+    search: impl FnOnce(&mut Searcher<RefCell<TranspositionTable<V>>>) -> R,
+) -> (R, SolverStats) {
+    let memo = RefCell::new(TranspositionTable::new(config.memory_limit));
+    let mut searcher = Searcher {
+        graph,
+        memo: &memo,
+        heuristics: config.heuristics,
+        nodes: 0,
+        pruning: pruning_stats(config),
+    };
+    let result = search(&mut searcher);
+    let (nodes, pruning) = (searcher.nodes, searcher.pruning);
+    let stats = SolverStats {
+        nodes,
+        table: memo.into_inner().stats,
+        pruning,
+    };
+    (result, stats)
+}
+
+/// Finds a list coloring of `graph` by branch and reduce, or returns `None` if there is none.
+///
+/// The reductions are the graph versions of the star rules: a vertex with a single color is
+/// colored and the color is removed from its neighbors, and a vertex with more colors than
+/// uncolored neighbors is set aside and colored greedily at the end, as for
+/// [`CaseFilter`](crate::report::CaseFilter). Otherwise the search branches on the colors of a
+/// vertex with the shortest list.
+pub fn solve(graph: &ListGraph) -> Option<Vec<u8>> {
+    solve_with_config(graph, &SolverConfig::default()).0
+}
+
+/// [`solve`], remembering the subproblems without a coloring in a table under the limit of
+/// `config`.
+pub fn solve_with_config(
+    graph: &ListGraph,
+    config: &SolverConfig,
+) -> (Option<Vec<u8>>, SolverStats) {
+    let n = graph.lists.len();
+    with_searcher(graph, config, |searcher| {
+        searcher.search(graph.lists.clone(), alloc::vec![None; n], Vec::new())
+    })
+}
+
+impl<M: Memo<()>> Searcher<'_, M> {
+    fn search(
+        &mut self,
+        mut lists: Vec<u8>,
+        mut coloring: Vec<Option<u8>>,
+        mut deferred: Vec<usize>,
+    ) -> Option<Vec<u8>> {
+        let graph = self.graph;
+        self.nodes += 1;
+        let live = |v: usize, coloring: &[Option<u8>], deferred: &[usize]| {
+            coloring[v].is_none() && !deferred.contains(&v)
+        };
+        'reduce: loop {
+            for v in 0..lists.len() {
+                if !live(v, &coloring, &deferred) {
+                    continue;
+                }
+                let size = lists[v].count_ones();
+                if size == 0 {
+                    return None;
+                }
+                if size == 1 {
+                    let c = lists[v].trailing_zeros() as u8;
+                    coloring[v] = Some(c);
+                    for &u in graph.neighbors[v].iter() {
+                        lists[u] &= !(1 << c);
+                    }
+                    continue 'reduce;
+                }
+                let degree = graph.neighbors[v]
+                    .iter()
+                    .filter(|&&u| live(u, &coloring, &deferred))
+                    .count();
+                if size as usize > degree {
+                    deferred.push(v);
+                    continue 'reduce;
+                }
+            }
+            break;
+        }
+
+        let branch_vertex = (0..lists.len())
+            .filter(|&v| live(v, &coloring, &deferred))
+            .min_by_key(|&v| lists[v].count_ones());
+        let Some(v) = branch_vertex else {
+            // Every deferred vertex had more colors than neighbors that are colored after it.
+            for &v in deferred.iter().rev() {
+                let used = graph.neighbors[v]
+                    .iter()
+                    .filter_map(|&u| coloring[u])
+                    .fold(0u8, |used, c| used | 1 << c);
+                let free = lists[v] & !used;
+                debug_assert_ne!(free, 0);
+                coloring[v] = Some(free.trailing_zeros() as u8);
+            }
+            return coloring.into_iter().collect();
+        };
+        // The colored and deferred vertices always get a color, so a subproblem has a
+        // coloring if and only if its live vertices have one.
+        let key = self
+            .memo
+            .enabled()
+            .then(|| canonical_key(&lists, |u| live(u, &coloring, &deferred), true));
+        if let Some(key) = key.as_ref()
+            && self.memo.get(key).is_some()
+        {
+            return None;
+        }
+        let live: Vec<bool> = (0..lists.len())
+            .map(|u| live(u, &coloring, &deferred))
+            .collect();
+        let found = if self.pruned(&lists, &live) {
+            None
+        } else {
+            colors(lists[v]).find_map(|c| {
+                let mut branch = lists.clone();
+                branch[v] = 1 << c;
+                self.search(branch, coloring.clone(), deferred.clone())
+            })
+        };
+        if found.is_none()
+            && let Some(key) = key
+        {
+            self.memo.insert(key, ());
+        }
+        found
+    }
+}
+
+/// What [`count_colorings`] and [`weighted_count`] sum over the colorings: the product of
+/// the weights of the colors of the vertices.
+trait Semiring: Copy {
+    const ZERO: Self;
+    const ONE: Self;
+    fn add(self, other: Self) -> Result<Self, CountError>;
+    fn mul(self, other: Self) -> Result<Self, CountError>;
+}
+
+impl Semiring for u128 {
+    const ZERO: Self = 0;
+    const ONE: Self = 1;
+    fn add(self, other: Self) -> Result<Self, CountError> {
+        self.checked_add(other).ok_or(CountError::Overflow)
+    }
+    fn mul(self, other: Self) -> Result<Self, CountError> {
+        self.checked_mul(other).ok_or(CountError::Overflow)
+    }
+}
+
+impl Semiring for f64 {
+    const ZERO: Self = 0.0;
+    const ONE: Self = 1.0;
+    fn add(self, other: Self) -> Result<Self, CountError> {
+        Ok(self + other)
+    }
+    fn mul(self, other: Self) -> Result<Self, CountError> {
+        Ok(self * other)
+    }
+}
+
+/// The number of list colorings of `graph`, or [`CountError::Overflow`] if it does not fit
+/// into a `u128`.
+///
+/// The search branches as [`solve`] does, but a vertex without uncolored neighbors is not set
+/// aside: it multiplies the count by the size of its list.
+pub fn count_colorings(graph: &ListGraph) -> Result<u128, CountError> {
+    count_colorings_with_config(graph, &SolverConfig::default()).0
+}
+
+/// [`count_colorings`], remembering the counts of the subproblems in a table under the limit
+/// of `config`.
+pub fn count_colorings_with_config(
+    graph: &ListGraph,
+    config: &SolverConfig,
+) -> (Result<u128, CountError>, SolverStats) {
+    let n = graph.lists.len();
+    with_searcher(graph, config, |searcher| {
+        searcher.count(graph.lists.clone(), alloc::vec![true; n], &|_| 1, true)
+    })
+}
+
+/// The sum over the list colorings of `graph` of the product of `weights[c]` over the colors
+/// `c` of its vertices; all weights 1 give [`count_colorings`].
+pub fn weighted_count(graph: &ListGraph, weights: &[f64; NUM_COLORS as usize]) -> f64 {
+    weighted_count_with_config(graph, weights, &SolverConfig::default()).0
+}
+
+/// [`weighted_count`], remembering the sums of the subproblems in a table under the limit of
+/// `config`. The colors keep their weights, so the table does not rename them.
+pub fn weighted_count_with_config(
+    graph: &ListGraph,
+    weights: &[f64; NUM_COLORS as usize],
+    config: &SolverConfig,
+) -> (f64, SolverStats) {
+    let n = graph.lists.len();
+    let (sum, stats) = with_searcher(graph, config, |searcher| {
+        searcher.count(
+            graph.lists.clone(),
+            alloc::vec![true; n],
+            &|c| weights[c as usize],
+            false,
+        )
+    });
+    (sum.expect("floating-point sums do not overflow"), stats)
+}
+
+impl<M> Searcher<'_, M> {
+    fn count<T: Semiring>(
+        &mut self,
+        mut lists: Vec<u8>,
+        mut live: Vec<bool>,
+        weight: &impl Fn(u8) -> T,
+        rename: bool,
+    ) -> Result<T, CountError>
+    where
+        M: Memo<T>,
+    {
+        let graph = self.graph;
+        self.nodes += 1;
+        let mut factor = T::ONE;
+        'reduce: loop {
+            for v in 0..lists.len() {
+                if !live[v] {
+                    continue;
+                }
+                if lists[v] == 0 {
+                    return Ok(T::ZERO);
+                }
+                if lists[v].count_ones() == 1 {
+                    let c = lists[v].trailing_zeros() as u8;
+                    factor = factor.mul(weight(c))?;
+                    live[v] = false;
+                    for &u in graph.neighbors[v].iter() {
+                        lists[u] &= !(1 << c);
+                    }
+                    continue 'reduce;
+                }
+                if graph.neighbors[v].iter().all(|&u| !live[u]) {
+                    let sum = colors(lists[v]).try_fold(T::ZERO, |sum, c| sum.add(weight(c)))?;
+                    factor = factor.mul(sum)?;
+                    live[v] = false;
+                    continue 'reduce;
+                }
+            }
+            break;
+        }
+
+        let branch_vertex = (0..lists.len())
+            .filter(|&v| live[v])
+            .min_by_key(|&v| lists[v].count_ones());
+        let Some(v) = branch_vertex else {
+            return Ok(factor);
+        };
+        let key = self
+            .memo
+            .enabled()
+            .then(|| canonical_key(&lists, |u| live[u], rename));
+        if let Some(sum) = key.as_ref().and_then(|key| self.memo.get(key)) {
+            return factor.mul(sum);
+        }
+        let sum = if self.pruned(&lists, &live) {
+            T::ZERO
+        } else {
+            colors(lists[v]).try_fold(T::ZERO, |sum, c| {
+                let mut branch = lists.clone();
+                branch[v] = 1 << c;
+                sum.add(self.count(branch, live.clone(), weight, rename)?)
+            })?
+        };
+        if let Some(key) = key {
+            self.memo.insert(key, sum);
+        }
+        factor.mul(sum)
+    }
+}
+
+/// The number of subproblems the parallel searches split a graph into, independently of the
+/// number of threads, so that their results do not depend on it.
+#[cfg(feature = "parallel")]
+const PARALLEL_SUBPROBLEMS: usize = 64;
+
+#[cfg(feature = "parallel")]
+impl<V: Clone> Memo<V> for std::sync::Mutex<TranspositionTable<V>> {
+    fn enabled(&self) -> bool {
+        self.lock().expect("a solver thread panicked").limit > 0
+    }
+    fn get(&self, key: &[u8]) -> Option<V> {
+        self.lock().expect("a solver thread panicked").get(key)
+    }
+    fn insert(&self, key: Vec<u8>, value: V) {
+        self.lock()
+            .expect("a solver thread panicked")
+            .insert(key, value)
+    }
+}
+
+/// The subproblems of `graph` that the parallel searches share out: its lists with the colors
+/// of the vertices of the highest degrees fixed in every combination, in lexicographic order,
+/// until there are at least `target` of them or every vertex is fixed. Their colorings are the
+/// colorings of `graph`, each in exactly one of them.
+#[cfg(feature = "parallel")]
+fn split(graph: &ListGraph, target: usize) -> Vec<Vec<u8>> {
+    let mut order: Vec<usize> = (0..graph.lists.len()).collect();
+    order.sort_by_key(|&v| core::cmp::Reverse(graph.neighbors[v].len()));
+    let mut subproblems = alloc::vec![graph.lists.clone()];
+    for v in order {
+        if subproblems.len() >= target {
+            break;
+        }
+        subproblems = subproblems
+            .into_iter()
+            .flat_map(|lists| {
+                colors(lists[v]).map(move |c| {
+                    let mut fixed = lists.clone();
+                    fixed[v] = 1 << c;
+                    fixed
+                })
+            })
+            .collect();
+    }
+    subproblems
+}
+
+/// Runs `search` on the subproblems of [`split`] on `threads` threads, which take the next
+/// unclaimed subproblem until none are left and share one table under the limit of `config`.
+/// Once a result is `done`, the subproblems after it are skipped. Returns the results by
+/// subproblem in order, and the stats of all threads together.
+#[cfg(feature = "parallel")]
+fn search_parallel<V: Clone + Send, R: Send>(
+    graph: &ListGraph,
+    config: &SolverConfig,
+    threads: usize,
+    search: impl Fn(&mut Searcher<std::sync::Mutex<TranspositionTable<V>>>, Vec<u8>) -> R + Sync,
+    done: impl Fn(&R) -> bool + Sync,
+) -> (Vec<(usize, R)>, SolverStats) {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let subproblems = split(graph, PARALLEL_SUBPROBLEMS);
+    let memo = std::sync::Mutex::new(TranspositionTable::new(config.memory_limit));
+    let next = AtomicUsize::new(0);
+    let first_done = AtomicUsize::new(usize::MAX);
+    let (subproblems, memo, next, first_done) = (&subproblems, &memo, &next, &first_done);
+    let (search, done) = (&search, &done);
+    let per_thread: Vec<(Vec<(usize, R)>, SolverStats)> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads.clamp(1, subproblems.len().max(1)))
+            .map(|_| {
+                scope.spawn(move || {
+                    let mut searcher = Searcher {
+                        graph,
+                        memo,
+                        heuristics: config.heuristics,
+                        nodes: 0,
+                        pruning: pruning_stats(config),
+                    };
+                    let mut results = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(lists) = subproblems.get(i) else {
+                            let stats = SolverStats {
+                                nodes: searcher.nodes,
+                                pruning: searcher.pruning,
+                                ..SolverStats::default()
+                            };
+                            return (results, stats);
+                        };
+                        if i > first_done.load(Ordering::Relaxed) {
+                            continue;
+                        }
+                        let result = search(&mut searcher, lists.clone());
+                        if done(&result) {
+                            first_done.fetch_min(i, Ordering::Relaxed);
+                        }
+                        results.push((i, result));
+                    }
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().expect("solver thread panicked"))
+            .collect()
+    });
+    let mut stats = SolverStats {
+        pruning: pruning_stats(config),
+        ..SolverStats::default()
+    };
+    let mut results = Vec::new();
+    for (thread_results, thread_stats) in per_thread {
+        results.extend(thread_results);
+        stats.nodes += thread_stats.nodes;
+        for (total, thread) in stats.pruning.iter_mut().zip(thread_stats.pruning) {
+            total.calls += thread.calls;
+            total.prunes += thread.prunes;
+        }
+    }
+    results.sort_unstable_by_key(|&(i, _)| i);
+    stats.table = memo.lock().expect("a solver thread panicked").stats.clone();
+    (results, stats)
+}
+
+/// [`solve_with_config`] on `threads` threads, see [`search_parallel`]. Returns the coloring
+/// of the first subproblem of [`split`] that has one, so that the coloring is the same for
+/// every number of threads, though it may differ from the one of [`solve_with_config`]; the
+/// stats vary with the scheduling.
+#[cfg(feature = "parallel")]
+pub fn solve_parallel(
+    graph: &ListGraph,
+    config: &SolverConfig,
+    threads: usize,
+) -> (Option<Vec<u8>>, SolverStats) {
+    let n = graph.lists.len();
+    let (results, stats) = search_parallel(
+        graph,
+        config,
+        threads,
+        |searcher, lists| searcher.search(lists, alloc::vec![None; n], Vec::new()),
+        Option::is_some,
+    );
+    (results.into_iter().find_map(|(_, r)| r), stats)
+}
+
+/// [`count_colorings_with_config`] on `threads` threads, see [`search_parallel`]; the counts
+/// of the subproblems are added in order.
+#[cfg(feature = "parallel")]
+pub fn count_colorings_parallel(
+    graph: &ListGraph,
+    config: &SolverConfig,
+    threads: usize,
+) -> (Result<u128, CountError>, SolverStats) {
+    let n = graph.lists.len();
+    let (results, stats) = search_parallel(
+        graph,
+        config,
+        threads,
+        |searcher, lists| searcher.count(lists, alloc::vec![true; n], &|_| 1, true),
+        Result::is_err,
+    );
+    let count = results
+        .into_iter()
+        .try_fold(0u128, |total, (_, count)| total.add(count?));
+    (count, stats)
+}
+
+/// [`weighted_count_with_config`] on `threads` threads, see [`search_parallel`]; the sums of
+/// the subproblems are added in order, so that the sum is the same for every number of
+/// threads.
+#[cfg(feature = "parallel")]
+pub fn weighted_count_parallel(
+    graph: &ListGraph,
+    weights: &[f64; NUM_COLORS as usize],
+    config: &SolverConfig,
+    threads: usize,
+) -> (f64, SolverStats) {
+    let n = graph.lists.len();
+    let (results, stats) = search_parallel(
+        graph,
+        config,
+        threads,
+        |searcher, lists| {
+            searcher
+                .count(lists, alloc::vec![true; n], &|c| weights[c as usize], false)
+                .expect("floating-point sums do not overflow")
+        },
+        |_| false,
+    );
+    (results.into_iter().map(|(_, sum)| sum).sum(), stats)
+}
+
+/// Decides list colorability of `graph` by trying every assignment of list colors; the
+/// reference for [`solve`].
+pub fn is_colorable_exhaustive(graph: &ListGraph) -> bool {
+    fn extend(graph: &ListGraph, coloring: &mut Vec<u8>) -> bool {
+        let v = coloring.len();
+        if v == graph.lists.len() {
+            return true;
+        }
+        for c in colors(graph.lists[v]) {
+            if graph.neighbors[v]
+                .iter()
+                .all(|&u| u >= v || coloring[u] != c)
+            {
+                coloring.push(c);
+                if extend(graph, coloring) {
+                    return true;
+                }
+                coloring.pop();
+            }
+        }
+        false
+    }
+    extend(graph, &mut Vec::new())
+}
+
+/// Counts the list colorings of `graph` by trying every assignment of list colors; the
+/// reference for [`count_colorings`].
+pub fn count_colorings_exhaustive(graph: &ListGraph) -> u128 {
+    fn extend(graph: &ListGraph, coloring: &mut Vec<u8>) -> u128 {
+        let v = coloring.len();
+        if v == graph.lists.len() {
+            return 1;
+        }
+        let mut count = 0;
+        for c in colors(graph.lists[v]) {
+            if graph.neighbors[v]
+                .iter()
+                .all(|&u| u >= v || coloring[u] != c)
+            {
+                coloring.push(c);
+                count += extend(graph, coloring);
+                coloring.pop();
+            }
+        }
+        count
+    }
+    extend(graph, &mut Vec::new())
+}
+
+/// The SplitMix64 generator, so stress tests and optimization restarts are reproducible from
+/// a seed without dependencies.
+#[derive(Clone, Debug)]
+pub struct SplitMix64(pub u64);
+
+impl SplitMix64 {
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A uniform number in `0..n`, up to a negligible bias.
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    /// A uniform number in `[0, 1)`, from the top 53 bits of [`next_u64`](Self::next_u64).
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// A random graph on 1 to `max_vertices` vertices, with every edge present with probability
+/// 1/2 and every list a uniform nonempty subset of the colors.
+pub fn random_list_graph(seed: u64, max_vertices: usize) -> ListGraph {
+    let mut rng = SplitMix64(seed);
+    let n = 1 + rng.below(max_vertices as u64) as usize;
+    let lists = (0..n)
+        .map(|_| 1 + rng.below(ALL_COLORS as u64) as u8)
+        .collect();
+    let mut edges = Vec::new();
+    for u in 0..n {
+        for v in (u + 1)..n {
+            if rng.below(2) == 1 {
+                edges.push((u, v));
+            }
+        }
+    }
+    ListGraph::new(lists, &edges)
+}
+
+/// A seed on which [`solve`] or [`solve_preprocessed`] disagrees with
+/// [`is_colorable_exhaustive`], or returned a coloring that is not proper.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StressFailure {
+    pub seed: u64,
+    pub graph: ListGraph,
+    pub solver: Option<Vec<u8>>,
+    pub preprocessed: Option<Vec<u8>>,
+    pub reference: bool,
+}
+
+/// The most vertices [`stress_test`] accepts. The random graphs have a quadratic number of
+/// edges, and the exhaustive search can take exponential time in the number of vertices.
+pub const MAX_STRESS_VERTICES: usize = 64;
+
+/// Compares [`solve`], without and with [`ListGraph::preprocess`], with
+/// [`is_colorable_exhaustive`] on the [`random_list_graph`] of every seed in `seeds`, and
+/// returns the seeds on which they disagree.
+///
+/// Panics if `max_vertices` exceeds [`MAX_STRESS_VERTICES`].
+pub fn stress_test(seeds: core::ops::Range<u64>, max_vertices: usize) -> Vec<StressFailure> {
+    assert!(
+        max_vertices <= MAX_STRESS_VERTICES,
+        "{max_vertices} vertices exceed the limit of {MAX_STRESS_VERTICES}"
+    );
+    seeds
+        .filter_map(|seed| {
+            let graph = random_list_graph(seed, max_vertices);
+            let solver = solve(&graph);
+            let preprocessed = solve_preprocessed(&graph);
+            let reference = is_colorable_exhaustive(&graph);
+            let agrees = |found: &Option<Vec<u8>>| match found {
+                Some(coloring) => reference && graph.is_proper_coloring(coloring),
+                None => !reference,
+            };
+            (!agrees(&solver) || !agrees(&preprocessed)).then_some(StressFailure {
+                seed,
+                graph,
+                solver,
+                preprocessed,
+                reference,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn solver_agrees_with_exhaustive_search() {
+        // A triangle with lists {0,1} is not colorable; adding color 2 to one list fixes it.
+        let triangle = [(0, 1), (1, 2), (0, 2)];
+        let graph = ListGraph::new(alloc::vec![0b011; 3], &triangle);
+        assert_eq!(solve(&graph), None);
+        assert!(!is_colorable_exhaustive(&graph));
+        let graph = ListGraph::new(alloc::vec![0b011, 0b011, 0b111], &triangle);
+        assert!(graph.is_proper_coloring(&solve(&graph).unwrap()));
+
+        assert_eq!(random_list_graph(7, 6), random_list_graph(7, 6));
+        assert_eq!(stress_test(0..500, 7), Vec::new());
+    }
+
+    #[test]
+    fn preprocessing_keeps_the_answer_of_the_solver() {
+        // Vertex 0 forces color 0 on the path, which forces color 1 on vertex 1, and vertex 2
+        // keeps two colors for no remaining neighbor.
+        let graph = ListGraph::new(alloc::vec![0b001, 0b011, 0b111], &[(0, 1), (1, 2)]);
+        let preprocessed = graph.preprocess().unwrap();
+        assert!(preprocessed.vertices.is_empty());
+        assert_eq!(preprocessed.forced, [(0, 0), (1, 1)]);
+        assert_eq!(preprocessed.removed, [2]);
+        assert_eq!(solve_preprocessed(&graph), Some(alloc::vec![0, 1, 0]));
+        // Forcing one end of an edge empties the list of the other end.
+        let graph = ListGraph::new(alloc::vec![0b001, 0b001], &[(0, 1)]);
+        assert_eq!(graph.preprocess(), None);
+
+        let mut shrunk = 0;
+        for seed in 0..1000 {
+            let graph = random_list_graph(seed, 9);
+            let (solver, preprocessed) = (solve(&graph), solve_preprocessed(&graph));
+            assert_eq!(solver.is_some(), preprocessed.is_some(), "seed {seed}");
+            if let Some(coloring) = preprocessed {
+                assert!(graph.is_proper_coloring(&coloring), "seed {seed}");
+            }
+            if let Some(p) = graph.preprocess() {
+                assert!(p.vertices.len() <= graph.lists.len());
+                shrunk += usize::from(p.vertices.len() < graph.lists.len());
+            }
+        }
+        assert!(shrunk > 0);
+    }
+
+    #[test]
+    fn counts_agree_with_exhaustive_counting() {
+        for seed in 0..300 {
+            let graph = random_list_graph(seed, 7);
+            let count = count_colorings_exhaustive(&graph);
+            assert_eq!(count_colorings(&graph), Ok(count), "seed {seed}");
+            assert_eq!(
+                weighted_count(&graph, &[1.0; 4]),
+                count as f64,
+                "seed {seed}"
+            );
+        }
+    }
+
+    #[test]
+    fn counts_follow_the_cycle_formula() {
+        // The cycle on n vertices has (k-1)^n + (-1)^n (k-1) proper colorings with k colors.
+        for n in 3..12usize {
+            let edges: Vec<_> = (0..n).map(|v| (v, (v + 1) % n)).collect();
+            for k in 2..=NUM_COLORS {
+                let graph = ListGraph::new(alloc::vec![(1 << k) - 1; n], &edges);
+                let k = k as i128;
+                let formula = (k - 1).pow(n as u32) + (-1i128).pow(n as u32) * (k - 1);
+                assert_eq!(count_colorings(&graph), Ok(formula as u128), "n={n} k={k}");
+            }
+        }
+        // Weighted by color, the triangle with lists {0,1,2} sums 3! colorings of weight
+        // w0 w1 w2 each.
+        let graph = ListGraph::new(alloc::vec![0b111; 3], &[(0, 1), (1, 2), (0, 2)]);
+        assert_eq!(weighted_count(&graph, &[2.0, 3.0, 5.0, 7.0]), 6.0 * 30.0);
+        // Without edges, the sum factors into the sums of the lists.
+        let graph = ListGraph::new(alloc::vec![0b0011, 0b1100], &[]);
+        assert_eq!(weighted_count(&graph, &[2.0, 3.0, 5.0, 7.0]), 5.0 * 12.0);
+    }
+
+    fn uncached_config() -> SolverConfig<'static> {
+        SolverConfig {
+            memory_limit: 0,
+            ..SolverConfig::default()
+        }
+    }
+
+    #[test]
+    fn the_table_answers_repeated_subproblems_within_its_limit() {
+        // Coloring a vertex of a cycle with any of its colors leaves the same path up to
+        // renaming the colors, so the table answers all but the first branch.
+        let edges: Vec<_> = (0..12).map(|v| (v, (v + 1) % 12)).collect();
+        let cycle = ListGraph::new(alloc::vec![0b111; 12], &edges);
+        let (count, stats) = count_colorings_with_config(&cycle, &SolverConfig::default());
+        assert_eq!(count, Ok(2u128.pow(12) + 2));
+        assert!(stats.table.hits > 0 && stats.table.hit_rate() > 0.0);
+        let (uncached, uncached_stats) = count_colorings_with_config(&cycle, &uncached_config());
+        assert_eq!(uncached, count);
+        assert_eq!(uncached_stats.table, TableStats::default());
+        assert!(uncached_stats.nodes > stats.nodes);
+
+        let tiny = SolverConfig {
+            memory_limit: 200,
+            ..SolverConfig::default()
+        };
+        for seed in 0..200 {
+            let graph = random_list_graph(seed, 9);
+            let (count, stats) = count_colorings_with_config(&graph, &tiny);
+            assert_eq!(count, Ok(count_colorings_exhaustive(&graph)), "seed {seed}");
+            assert!(stats.table.peak_bytes <= 200);
+            let (coloring, stats) = solve_with_config(&graph, &tiny);
+            assert_eq!(
+                coloring.is_some(),
+                is_colorable_exhaustive(&graph),
+                "seed {seed}"
+            );
+            assert!(stats.table.peak_bytes <= 200);
+            let weights = [0.5, 2.0, 3.0, 0.25];
+            assert_eq!(
+                weighted_count_with_config(&graph, &weights, &tiny).0,
+                weighted_count_with_config(&graph, &weights, &uncached_config()).0,
+                "seed {seed}"
+            );
+        }
+        let mut table = TranspositionTable::new(200);
+        for key in 0..10u8 {
+            table.insert(alloc::vec![key; 8], 0u128);
+        }
+        assert!(table.stats().evictions > 0);
+        assert_eq!(table.get(&[9; 8]), Some(0));
+        assert_eq!(table.get(&[0; 8]), None);
+    }
+
+    #[test]
+    fn heuristics_prune_only_subproblems_without_colorings() {
+        for heuristic in DEFAULT_HEURISTICS.iter() {
+            let config = SolverConfig {
+                memory_limit: 0,
+                heuristics: core::slice::from_ref(heuristic),
+            };
+            let mut prunes = 0;
+            for seed in 0..300 {
+                let graph = random_list_graph(seed, 8);
+                let (coloring, stats) = solve_with_config(&graph, &config);
+                assert_eq!(
+                    coloring.is_some(),
+                    is_colorable_exhaustive(&graph),
+                    "seed {seed}"
+                );
+                let (count, _) = count_colorings_with_config(&graph, &config);
+                assert_eq!(count, Ok(count_colorings_exhaustive(&graph)), "seed {seed}");
+                assert_eq!(stats.pruning[0].name, heuristic.name());
+                prunes += stats.pruning[0].prunes;
+            }
+            assert!(prunes > 0, "{} never pruned", heuristic.name());
+        }
+
+        // Five vertices with four colors each form a clique that needs five colors.
+        let edges: Vec<_> = (0..5)
+            .flat_map(|u| ((u + 1)..5).map(move |v| (u, v)))
+            .collect();
+        let clique = ListGraph::new(alloc::vec![ALL_COLORS; 5], &edges);
+        let (coloring, stats) = solve_with_config(&clique, &SolverConfig::default());
+        assert_eq!(coloring, None);
+        assert_eq!(stats.nodes, 1);
+        assert_eq!(stats.pruning[0].prunes, 1);
+        assert_eq!(stats.pruning[0].prune_rate(), 1.0);
+        // A pentagon with the lists {0,1} alternates between the colors only on even cycles.
+        let edges: Vec<_> = (0..5).map(|v| (v, (v + 1) % 5)).collect();
+        let pentagon = ListGraph::new(alloc::vec![0b0011; 5], &edges);
+        let (count, stats) = count_colorings_with_config(&pentagon, &SolverConfig::default());
+        assert_eq!(count, Ok(0));
+        assert_eq!(stats.nodes, 1);
+        assert_eq!(
+            stats
+                .pruning
+                .iter()
+                .map(|p| (p.name, p.prunes))
+                .collect::<Vec<_>>(),
+            [("clique-hall", 0), ("two-list-odd-cycle", 1)]
+        );
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_searches_agree_with_the_serial_ones_for_every_number_of_threads() {
+        let config = SolverConfig::default();
+        let weights = [0.5, 2.0, 3.0, 0.25];
+        for seed in 0..100 {
+            let graph = random_list_graph(seed, 12);
+            let count = count_colorings_with_config(&graph, &config).0;
+            let sum = weighted_count_parallel(&graph, &weights, &config, 1).0;
+            let coloring = solve_parallel(&graph, &config, 1).0;
+            assert_eq!(coloring.is_some(), solve(&graph).is_some(), "seed {seed}");
+            if let Some(coloring) = coloring.as_ref() {
+                assert!(graph.is_proper_coloring(coloring), "seed {seed}");
+            }
+            for threads in [2, 4] {
+                assert_eq!(
+                    count_colorings_parallel(&graph, &config, threads).0,
+                    count,
+                    "seed {seed}"
+                );
+                assert_eq!(
+                    weighted_count_parallel(&graph, &weights, &config, threads).0,
+                    sum,
+                    "seed {seed}"
+                );
+                assert_eq!(
+                    solve_parallel(&graph, &config, threads).0,
+                    coloring,
+                    "seed {seed}"
+                );
+            }
+            let serial = weighted_count_with_config(&graph, &weights, &config).0;
+            assert!((sum - serial).abs() <= 1e-9 * serial.abs(), "seed {seed}");
+        }
+
+        // The threads share one table.
+        let edges: Vec<_> = (0..16).map(|v| (v, (v + 1) % 16)).collect();
+        let cycle = ListGraph::new(alloc::vec![0b111; 16], &edges);
+        let (count, stats) = count_colorings_parallel(&cycle, &config, 4);
+        assert_eq!(count, Ok(2u128.pow(16) + 2));
+        assert!(stats.table.hits > 0);
+    }
+
+    #[test]
+    fn canonical_keys_rename_the_colors_of_the_live_vertices() {
+        assert_eq!(
+            canonical_key(&[0b1100, 0b0001, 0b0110], |v| v != 1, true),
+            alloc::vec![0b0011, 0, 0b0101]
+        );
+        assert_eq!(
+            canonical_key(&[0b1100, 0b0001, 0b0110], |v| v != 1, false),
+            alloc::vec![0b1100, 0, 0b0110]
+        );
+    }
+
+    #[test]
+    fn counts_that_exceed_u128_overflow() {
+        // 64 isolated vertices with 4 colors have 4^64 = 2^128 colorings.
+        let graph = ListGraph::new(alloc::vec![ALL_COLORS; 64], &[]);
+        assert_eq!(count_colorings(&graph), Err(CountError::Overflow));
+        let graph = ListGraph::new(alloc::vec![ALL_COLORS; 63], &[]);
+        assert_eq!(count_colorings(&graph), Ok(1 << 126));
+    }
+}
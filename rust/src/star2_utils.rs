@@ -0,0 +1,112 @@
+use alloc::vec::Vec;
+
+use crate::colors::intersects;
+use crate::star_utils::{COLOR_SUBSETS_GE2, ROOT_COLOR_SUBSETS, Star, nondecreasing_sequences};
+
+/// A depth-2 local structure: a root, its neighbors, and the neighbors' other neighbors.
+///
+/// Each neighbor is stored as a [`Star`] centered at that neighbor, whose leaves are the
+/// second level; halfedges only occur at the second level.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Star2 {
+    // Bitmask over colors {0,1,2,3}, as in `Star::root_colors`.
+    pub root_colors: u8,
+
+    // The neighbors of the root, each with its own (non-root) neighbors.
+    pub neighbors: Vec<Star>,
+}
+
+/// Generates all depth-2 structures whose root and neighbors have `degree` edges.
+///
+/// This is the native counterpart of the uniform depth-2 trees of `enumerate-trees`:
+/// - the root has a color subset in `ROOT_COLOR_SUBSETS` and `degree` neighbors,
+/// - every neighbor has a color subset in `COLOR_SUBSETS_GE2` intersecting its parent's and
+///   `degree - 1` further neighbors (the second level),
+/// - every second-level vertex has a color subset in `COLOR_SUBSETS_GE2` intersecting its
+///   parent's and a halfedge count in `[2, max_degree]`.
+///
+/// Neighbors and second-level vertices are unordered multisets, enumerated as nondecreasing
+/// index sequences.
+pub fn generate_star2(degree: usize, max_degree: usize) -> Vec<Star2> {
+    if degree < 2 || max_degree < 2 {
+        return Vec::new();
+    }
+
+    // All neighbor subtrees for each neighbor color, in `COLOR_SUBSETS_GE2` order.
+    let subtrees: Vec<Vec<Star>> = COLOR_SUBSETS_GE2
+        .iter()
+        .map(|&colors| {
+            let mut leaf_types: Vec<(u8, u8)> = Vec::new();
+            for &leaf_colors in COLOR_SUBSETS_GE2.iter() {
+                if !intersects(colors, leaf_colors) {
+                    continue;
+                }
+                for h in 2..=max_degree {
+                    leaf_types.push((leaf_colors, h as u8));
+                }
+            }
+            nondecreasing_sequences(leaf_types.len() - 1, degree - 1)
+                .into_iter()
+                .map(|choice| Star {
+                    root_colors: colors,
+                    neighbor_colors: choice.iter().map(|&i| leaf_types[i].0).collect(),
+                    neighbor_halfedges: choice.iter().map(|&i| leaf_types[i].1).collect(),
+                    neighbor_adjacency: None,
+                })
+                .collect()
+        })
+        .collect();
+
+    let mut out: Vec<Star2> = Vec::new();
+    for &root_colors in ROOT_COLOR_SUBSETS.iter() {
+        let candidates: Vec<&Star> = COLOR_SUBSETS_GE2
+            .iter()
+            .zip(subtrees.iter())
+            .filter(|&(&colors, _)| intersects(root_colors, colors))
+            .flat_map(|(_, stars)| stars.iter())
+            .collect();
+        for choice in nondecreasing_sequences(candidates.len() - 1, degree) {
+            out.push(Star2 {
+                root_colors,
+                neighbors: choice.into_iter().map(|i| candidates[i].clone()).collect(),
+            });
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn generate_star2_shapes_and_counts() {
+        let stars = generate_star2(2, 2);
+        for s in stars.iter() {
+            assert_eq!(s.neighbors.len(), 2);
+            for n in s.neighbors.iter() {
+                assert!(intersects(s.root_colors, n.root_colors));
+                assert_eq!(n.neighbor_colors.len(), 1);
+                assert_eq!(n.neighbor_halfedges, vec![2]);
+                assert!(intersects(n.root_colors, n.neighbor_colors[0]));
+            }
+        }
+        // Same count as the depth-2 trees of `enumerate-trees 2 2`.
+        assert_eq!(stars.len(), 18905);
+
+        let mut unique = stars.clone();
+        unique.sort_by_key(|s| {
+            (
+                s.root_colors,
+                s.neighbors
+                    .iter()
+                    .map(|n| (n.root_colors, n.neighbor_colors.clone()))
+                    .collect::<Vec<_>>(),
+            )
+        });
+        unique.dedup();
+        assert_eq!(unique.len(), stars.len());
+        assert!(generate_star2(1, 2).is_empty());
+    }
+}
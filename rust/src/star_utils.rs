@@ -1,6 +1,25 @@
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+use alloc::boxed::Box;
+use alloc::collections::BTreeSet;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::ops::RangeInclusive;
+
+use crate::colors::{ColorSet, MAX_COLORS, NUM_COLORS, intersects, is_subset, subsets_by_size};
+use crate::json::{JsonValue, parse_json};
+
+/// A star: a root with its color list, and its neighbors with their lists and halfedges.
+///
+/// With the `serde` feature, deserialized stars are checked with [`Star::validate`]. The order
+/// compares root lists first, then the neighbors in their order; it keys maps of stars.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "StarFields")
+)]
 pub struct Star {
-    // Bitmask over colors {0,1,2,3}. Bit i set => color i is present.
+    // Bitmask over colors {0,...,7}. Bit i set => color i is present.
     // Example: 0b0011 represents {0,1}.
     pub root_colors: u8,
 
@@ -9,6 +28,18 @@ pub struct Star {
 
     // Number of halfedges for each neighbor.
     pub neighbor_halfedges: Vec<u8>,
+
+    // Edges among the neighbors, `None` for none. Only the functions that say so take them
+    // into account, see `NeighborAdjacency`. Boxed, since most stars have none.
+    #[cfg_attr(
+        feature = "serde",
+        serde(
+            rename = "neighbor_edges",
+            skip_serializing_if = "Option::is_none",
+            serialize_with = "serialize_neighbor_edges"
+        )
+    )]
+    pub neighbor_adjacency: Option<Box<NeighborAdjacency>>,
 }
 
 pub static ROOT_COLOR_SUBSETS: [u8; 3] = [
@@ -31,38 +62,231 @@ pub static COLOR_SUBSETS_GE2: [u8; 11] = [
     0b1100, // {2,3}
 ];
 
-fn intersects(a: u8, b: u8) -> bool {
-    (a & b) != 0
+/// The root lists of stars with `k` colors, as [`ROOT_COLOR_SUBSETS`] for 4 colors: the lists
+/// `{0, ..., s - 1}` for `s` from `k` down to 2. Up to renaming colors, every root list with
+/// at least 2 colors is one of these.
+pub fn root_color_subsets(k: u8) -> Vec<u8> {
+    (2..=k).rev().map(u8::first).collect()
+}
+
+/// The neighbor lists of stars with `k` colors, as [`COLOR_SUBSETS_GE2`] for 4 colors: all
+/// subsets of `{0, ..., k - 1}` with at least 2 colors, from large to small lists.
+pub fn color_subsets_ge2(k: u8) -> Vec<u8> {
+    subsets_by_size(u8::first(k), 2)
+}
+
+/// The most halfedges a neighbor can have, the largest count the star strings of
+/// [`star_to_string`] can encode.
+pub const MAX_HALFEDGES: usize = 0xf;
+
+/// The most neighbors a star can have, the largest degree the binary formats of
+/// [`crate::trace`] and [`crate::ipc`] can encode in their one-byte length.
+pub const MAX_NEIGHBORS: usize = u8::MAX as usize;
+
+/// A constraint on the halfedge counts of the neighbors of a star: at most `max_count`
+/// neighbors have a halfedge count in `[min, max]`.
+///
+/// Graph-theoretic arguments often exclude halfedge profiles, e.g. "at most one neighbor with
+/// 4 or more halfedges"; [`generate_stars_with_config`] skips them during enumeration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HalfedgeBound {
+    pub min: u8,
+    pub max: u8,
+    pub max_count: usize,
+}
+
+impl HalfedgeBound {
+    /// Whether the neighbors with the given halfedge counts satisfy the bound.
+    pub fn allows(&self, halfedges: &[u8]) -> bool {
+        halfedges
+            .iter()
+            .filter(|&&h| (self.min..=self.max).contains(&h))
+            .count()
+            <= self.max_count
+    }
+}
+
+impl core::fmt::Display for HalfedgeBound {
+    /// Formats the bound as `<min>-<max>:<max_count>`, leaving out `max` if it is unbounded.
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}-", self.min)?;
+        if self.max != u8::MAX {
+            write!(f, "{}", self.max)?;
+        }
+        write!(f, ":{}", self.max_count)
+    }
+}
+
+impl core::str::FromStr for HalfedgeBound {
+    type Err = String;
+
+    /// Parses `<min>-<max>:<max_count>`, `<min>-:<max_count>` (no upper limit), or
+    /// `<h>:<max_count>` (exactly `h` halfedges), e.g. `4-:1` for "at most one neighbor with
+    /// 4 or more halfedges".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid halfedge bound: {s}");
+        let (range, count) = s.split_once(':').ok_or_else(invalid)?;
+        let (min, max) = match range.split_once('-') {
+            Some((min, "")) => (min, None),
+            Some((min, max)) => (min, Some(max)),
+            None => (range, Some(range)),
+        };
+        let min: u8 = min.parse().map_err(|_| invalid())?;
+        let max: u8 = match max {
+            Some(max) => max.parse().map_err(|_| invalid())?,
+            None => u8::MAX,
+        };
+        Ok(HalfedgeBound {
+            min,
+            max,
+            max_count: count.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// Which stars [`generate_stars_with_config`] enumerates.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EnumerationConfig {
+    /// The number of colors `k`: all lists are subsets of `{0, ..., k - 1}`. At least 2 and
+    /// at most [`MAX_COLORS`], [`NUM_COLORS`] by default.
+    pub colors: u8,
+    /// The maximum degree of the graph; `None` for no restriction. There are no stars of a
+    /// degree above it, and otherwise neighbors have at most `max_degree - 1` halfedges, so
+    /// that their degree `halfedges + 1` is at most `max_degree`. The stars are those without
+    /// such a vertex, in the same order.
+    pub max_degree: Option<usize>,
+    /// Constraints that every enumerated star satisfies.
+    pub halfedge_bounds: Vec<HalfedgeBound>,
+    /// The fewest halfedges of a neighbor, 2 by default; 0 models pendant neighbors and 1
+    /// neighbors of degree 2.
+    pub min_halfedges: u8,
+    /// The most halfedges of a neighbor; `None` for the degree of the star. `max_degree`
+    /// lowers it further.
+    pub max_halfedges: Option<u8>,
+    /// Whether to enumerate only one star per orbit under renaming the `k` colors, the one
+    /// for which [`Star::is_orbit_representative`] holds; [`Star::enumeration_multiplicity`] is
+    /// the number of enumerated stars it stands for.
+    pub orbit_representatives: bool,
+}
+
+impl Default for EnumerationConfig {
+    fn default() -> Self {
+        EnumerationConfig {
+            colors: NUM_COLORS,
+            max_degree: None,
+            halfedge_bounds: Vec::new(),
+            min_halfedges: 2,
+            max_halfedges: None,
+            orbit_representatives: false,
+        }
+    }
+}
+
+impl EnumerationConfig {
+    /// Checks that the number of colors is supported and that the halfedge range is a range
+    /// of counts the star strings can encode.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(2..=MAX_COLORS).contains(&self.colors) {
+            return Err(format!(
+                "the number of colors must be between 2 and {MAX_COLORS}, not {}",
+                self.colors
+            ));
+        }
+        if let Some(max) = self.max_halfedges {
+            if max as usize > MAX_HALFEDGES {
+                return Err(format!(
+                    "the maximum number of halfedges must be at most {MAX_HALFEDGES}, not {max}"
+                ));
+            }
+            if self.min_halfedges > max {
+                return Err(format!(
+                    "the minimum number of halfedges {} exceeds the maximum {max}",
+                    self.min_halfedges
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Like [`validate`](Self::validate), and also checks that the
+    /// [halfedge range](Self::halfedge_range) of the stars of `degree` ends at most at
+    /// [`MAX_HALFEDGES`], which `validate` cannot check without `max_halfedges`.
+    pub fn validate_for_degree(&self, degree: usize) -> Result<(), String> {
+        self.validate()?;
+        let max = *self.halfedge_range(degree).end();
+        if max > MAX_HALFEDGES {
+            return Err(format!(
+                "neighbors of stars of degree {degree} could have {max} halfedges, but at most \
+                 {MAX_HALFEDGES} are supported; limit the maximum number of halfedges"
+            ));
+        }
+        Ok(())
+    }
+
+    /// The range of halfedge counts of the neighbors of the stars of `degree`: from
+    /// `min_halfedges` to `max_halfedges`, or `degree` if it is `None`, and at most
+    /// `max_degree - 1`. May be empty.
+    pub fn halfedge_range(&self, degree: usize) -> core::ops::RangeInclusive<usize> {
+        let mut max = self.max_halfedges.map_or(degree, usize::from);
+        if let Some(max_degree) = self.max_degree {
+            max = max.min(max_degree.saturating_sub(1));
+        }
+        self.min_halfedges as usize..=max
+    }
+}
+
+pub(crate) fn nondecreasing_sequences(t: usize, n: usize) -> Vec<Vec<usize>> {
+    nondecreasing_sequences_pruned(t, n, &|_| true)
 }
 
-fn nondecreasing_sequences(t: usize, n: usize) -> Vec<Vec<usize>> {
+/// Like [`nondecreasing_sequences`], but skips every sequence with a prefix for which `keep`
+/// returns false. `keep` must be monotone: if it rejects a prefix, it rejects all extensions.
+pub(crate) fn nondecreasing_sequences_pruned(
+    t: usize,
+    n: usize,
+    keep: &dyn Fn(&[usize]) -> bool,
+) -> Vec<Vec<usize>> {
     let mut res: Vec<Vec<usize>> = Vec::new();
-    let mut current = vec![0usize; n];
+    visit_nondecreasing_sequences(t, n, &[], keep, &mut |seq| res.push(seq.to_vec()));
+    res
+}
 
+/// Calls `visit` on the sequences of [`nondecreasing_sequences_pruned`] that start with
+/// `prefix`, in the same order, without collecting them. The empty sequence for `n = 0` is
+/// always visited.
+fn visit_nondecreasing_sequences(
+    t: usize,
+    n: usize,
+    prefix: &[usize],
+    keep: &dyn Fn(&[usize]) -> bool,
+    visit: &mut dyn FnMut(&[usize]),
+) {
     fn backtrack(
         idx: usize,
-        start: usize,
+        range: core::ops::RangeInclusive<usize>,
         t: usize,
         current: &mut [usize],
-        res: &mut Vec<Vec<usize>>,
+        keep: &dyn Fn(&[usize]) -> bool,
+        visit: &mut dyn FnMut(&[usize]),
     ) {
         if idx == current.len() {
-            res.push(current.to_vec());
+            visit(current);
             return;
         }
-        for v in start..=t {
+        for v in range {
             current[idx] = v;
-            backtrack(idx + 1, v, t, current, res);
+            if keep(&current[..=idx]) {
+                backtrack(idx + 1, v..=t, t, current, keep, visit);
+            }
         }
     }
 
-    if n == 0 {
-        res.push(Vec::new());
-        return res;
+    let mut current = vec![0usize; n];
+    current[..prefix.len()].copy_from_slice(prefix);
+    if (1..=prefix.len()).all(|len| keep(&current[..len])) {
+        let range = prefix.last().map_or(0..=t, |&last| last..=t);
+        backtrack(prefix.len(), range, t, &mut current, keep, visit);
     }
-
-    backtrack(0, 0, t, &mut current, &mut res);
-    res
 }
 
 /// Generate all stars of a given `degree`.
@@ -71,52 +295,1073 @@ fn nondecreasing_sequences(t: usize, n: usize) -> Vec<Vec<usize>> {
 /// - a root with color subset in `ROOT_COLOR_SUBSETS`, and
 /// - `degree` leaf neighbors, each with:
 ///   - a color subset in `COLOR_SUBSETS_GE2` intersecting the root's colors
-///   - a halfedge count in `[2, degree]`
+///   - a halfedge count in `[2, degree]` (see [`EnumerationConfig::halfedge_range`] for
+///     other ranges)
 ///
 /// Neighbors are treated as an unordered multiset; enumeration uses nondecreasing index
 /// sequences to avoid duplicate permutations.
 pub fn generate_stars(degree: usize) -> Vec<Star> {
-    let mut out: Vec<Star> = Vec::new();
-
-    for &root_colors in ROOT_COLOR_SUBSETS.iter() {
-        // Build the list of possible neighbor "types" for this root.
-        // Each type is (colors, halfedges).
-        let mut neighbor_types: Vec<(u8, u8)> = Vec::new();
-        for &colors in COLOR_SUBSETS_GE2.iter() {
-            if !intersects(root_colors, colors) {
-                continue;
+    generate_stars_with_config(degree, &EnumerationConfig::default())
+}
+
+/// Why a count of stars or trees, or an estimate of their memory, has no exact value.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CountError {
+    /// The count does not fit into a `u128`.
+    Overflow,
+    /// A neighbor or leaf would have more halfedges than a `u8` holds.
+    TooManyHalfedges { halfedges: usize },
+}
+
+impl core::fmt::Display for CountError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CountError::Overflow => write!(f, "the count exceeds {}", u128::MAX),
+            CountError::TooManyHalfedges { halfedges } => write!(
+                f,
+                "a vertex with {halfedges} halfedges does not fit, at most {} do",
+                u8::MAX
+            ),
+        }
+    }
+}
+
+impl core::error::Error for CountError {}
+
+/// The number of stars [`generate_stars_with_config`] returns for `config` without
+/// `config.halfedge_bounds` and `config.orbit_representatives`, computed from the number of
+/// neighbor types of each root list without enumerating the stars. Bounds and orbits only skip
+/// stars, so this is an upper bound in general.
+///
+/// Panics if `config` fails [`EnumerationConfig::validate`].
+pub fn count_stars_with_config(
+    degree: usize,
+    config: &EnumerationConfig,
+) -> Result<u128, CountError> {
+    let halfedges = config.halfedge_range(degree);
+    check_halfedges(&halfedges)?;
+    let (shards, halfedges) = enumeration_shards(degree, config);
+    let mut roots: Vec<u8> = shards.iter().map(|(root, _)| *root).collect();
+    roots.dedup();
+    let neighbor_lists = color_subsets_ge2(config.colors);
+    roots.into_iter().try_fold(0u128, |sum, root| {
+        let types = neighbor_types(root, halfedges.clone(), &neighbor_lists).len();
+        let stars = multisets(types as u128, degree as u128)?;
+        sum.checked_add(stars).ok_or(CountError::Overflow)
+    })
+}
+
+/// The number of multisets of size `k` over `n` elements.
+pub(crate) fn multisets(n: u128, k: u128) -> Result<u128, CountError> {
+    // C(n + k - 1, k), one factor at a time so that every intermediate value is an integer.
+    (1..=k).try_fold(1u128, |acc, i| {
+        let factor = (n + i).checked_sub(1).ok_or(CountError::Overflow)?;
+        Ok(acc.checked_mul(factor).ok_or(CountError::Overflow)? / i)
+    })
+}
+
+/// Checks that every count of `halfedges` fits into a [`Star`].
+pub(crate) fn check_halfedges(halfedges: &RangeInclusive<usize>) -> Result<(), CountError> {
+    match halfedges.clone().next_back() {
+        Some(max) if max > u8::MAX as usize => Err(CountError::TooManyHalfedges { halfedges: max }),
+        _ => Ok(()),
+    }
+}
+
+/// Like [`generate_stars`], with the root lists of [`root_color_subsets`] and the neighbor
+/// lists of [`color_subsets_ge2`] for `config.colors` colors, restricted to
+/// `config.max_degree` (see [`EnumerationConfig::max_degree`]), with the halfedge counts of
+/// [`EnumerationConfig::halfedge_range`], and to the stars satisfying `config.halfedge_bounds`.
+///
+/// The bounds prune the enumeration: a partial choice of neighbors that already violates a
+/// bound is not extended.
+///
+/// Panics if `config` fails [`EnumerationConfig::validate`].
+pub fn generate_stars_with_config(degree: usize, config: &EnumerationConfig) -> Vec<Star> {
+    StarIter::new(degree, config).collect()
+}
+
+/// The stars of [`generate_stars_with_config`] in the same order, enumerated one at a time
+/// instead of collected, so that the memory does not grow with the number of stars.
+///
+/// The iterator keeps the current choice of neighbor types and advances it to the next
+/// nondecreasing sequence that satisfies the halfedge bounds, like the recursion of
+/// [`fold_stars_with_config`].
+pub struct StarIter {
+    degree: usize,
+    config: EnumerationConfig,
+    halfedges: RangeInclusive<usize>,
+    shards: alloc::vec::IntoIter<EnumerationShard>,
+    shard: Option<ShardState>,
+    /// The indices into the neighbor types of the last star, or of a partial choice.
+    choice: Vec<usize>,
+}
+
+/// The shard a [`StarIter`] is in: its root list, its neighbor types, and the largest index
+/// of the first neighbor.
+struct ShardState {
+    root_colors: u8,
+    types: Vec<(u8, u8)>,
+    first_max: usize,
+}
+
+impl StarIter {
+    /// Panics if `config` fails [`EnumerationConfig::validate`].
+    pub fn new(degree: usize, config: &EnumerationConfig) -> Self {
+        let (shards, halfedges) = enumeration_shards(degree, config);
+        StarIter {
+            degree,
+            config: config.clone(),
+            halfedges,
+            shards: shards.into_iter(),
+            shard: None,
+            choice: Vec::new(),
+        }
+    }
+
+    /// Whether the partial choice satisfies the halfedge bounds.
+    fn keep(&self, types: &[(u8, u8)]) -> bool {
+        let halfedges: Vec<u8> = self.choice.iter().map(|&idx| types[idx].1).collect();
+        self.config
+            .halfedge_bounds
+            .iter()
+            .all(|b| b.allows(&halfedges))
+    }
+
+    /// Advances the last index of the choice, and backtracks past exhausted positions, until
+    /// the choice is a kept prefix. Returns false if the shard is exhausted.
+    fn bump(&mut self, types: &[(u8, u8)], first_max: usize) -> bool {
+        while let Some(idx) = self.choice.pop() {
+            let max = if self.choice.is_empty() {
+                first_max
+            } else {
+                types.len() - 1
+            };
+            if idx < max {
+                self.choice.push(idx + 1);
+                if self.keep(types) {
+                    return true;
+                }
             }
-            for h in 2..=degree {
-                neighbor_types.push((colors, h as u8));
+        }
+        false
+    }
+
+    /// Extends a kept prefix to a full choice of `degree` neighbors, the smallest one in
+    /// enumeration order. Returns false if the shard is exhausted.
+    fn fill(&mut self, types: &[(u8, u8)], first_min: usize, first_max: usize) -> bool {
+        while self.choice.len() < self.degree {
+            let next = self.choice.last().copied().unwrap_or(first_min);
+            self.choice.push(next);
+            if !self.keep(types) && !self.bump(types, first_max) {
+                return false;
             }
         }
+        true
+    }
+}
+
+impl Iterator for StarIter {
+    type Item = Star;
 
-        for choice in nondecreasing_sequences(neighbor_types.len() - 1, degree) {
-            let mut neighbor_colors: Vec<u8> = Vec::with_capacity(degree);
-            let mut neighbor_halfedges: Vec<u8> = Vec::with_capacity(degree);
-            for idx in choice {
-                let (c, h) = neighbor_types[idx];
-                neighbor_colors.push(c);
-                neighbor_halfedges.push(h);
+    fn next(&mut self) -> Option<Star> {
+        loop {
+            let found = match self.shard.take() {
+                // Continue after the last star of the shard; there is only one 0-neighbor star.
+                Some(shard) => {
+                    let found = !self.choice.is_empty()
+                        && self.bump(&shard.types, shard.first_max)
+                        && self.fill(&shard.types, 0, shard.first_max);
+                    self.shard = Some(shard);
+                    found
+                }
+                None => {
+                    let (root_colors, prefix) = self.shards.next()?;
+                    let types = neighbor_types(
+                        root_colors,
+                        self.halfedges.clone(),
+                        &color_subsets_ge2(self.config.colors),
+                    );
+                    let (first_min, first_max) = prefix
+                        .first()
+                        .map_or((0, types.len().saturating_sub(1)), |&i| (i, i));
+                    self.choice.clear();
+                    let found = self.fill(&types, first_min, first_max);
+                    self.shard = Some(ShardState {
+                        root_colors,
+                        types,
+                        first_max,
+                    });
+                    found
+                }
+            };
+            if !found {
+                self.shard = None;
+                continue;
             }
-            out.push(Star {
-                root_colors,
+            let shard = self.shard.as_ref().expect("the shard was put back");
+            let (neighbor_colors, neighbor_halfedges) =
+                self.choice.iter().map(|&idx| shard.types[idx]).unzip();
+            let star = Star {
+                root_colors: shard.root_colors,
                 neighbor_colors,
                 neighbor_halfedges,
-            });
+                neighbor_adjacency: None,
+            };
+            if !self.config.orbit_representatives
+                || star.is_orbit_representative(self.config.colors)
+            {
+                return Some(star);
+            }
+        }
+    }
+}
+
+/// A part of the enumeration of [`generate_stars_with_config`]: the stars with root list
+/// `root_colors` whose first neighbors have the given indices in [`neighbor_types`].
+type EnumerationShard = (u8, Vec<usize>);
+
+/// The shards of the enumeration of [`generate_stars_with_config`] in enumeration order, one
+/// for every root list and first neighbor, and the range of halfedge counts of a neighbor. No
+/// shards if there are no stars.
+///
+/// Panics if `config` fails [`EnumerationConfig::validate`], or if a neighbor could have more
+/// than `u8::MAX` halfedges, which takes a degree above `u8::MAX` without
+/// `config.max_halfedges`.
+fn enumeration_shards(
+    degree: usize,
+    config: &EnumerationConfig,
+) -> (Vec<EnumerationShard>, RangeInclusive<usize>) {
+    if let Err(e) = config.validate() {
+        panic!("{e}");
+    }
+    let halfedges = config.halfedge_range(degree);
+    if let Err(e) = check_halfedges(&halfedges) {
+        panic!("{e}");
+    }
+    if let Some(max_degree) = config.max_degree
+        && (degree > max_degree || max_degree <= config.min_halfedges as usize)
+    {
+        return (Vec::new(), halfedges);
+    }
+    let neighbor_lists = color_subsets_ge2(config.colors);
+    let mut shards = Vec::new();
+    for root_colors in root_color_subsets(config.colors) {
+        if degree == 0 {
+            shards.push((root_colors, Vec::new()));
+            continue;
+        }
+        let types = neighbor_types(root_colors, halfedges.clone(), &neighbor_lists).len();
+        shards.extend((0..types).map(|first| (root_colors, vec![first])));
+    }
+    (shards, halfedges)
+}
+
+/// The shards of [`enumeration_shards`] split by their second neighbor as well, in
+/// enumeration order. The stars whose first neighbor has the smallest index make up much of
+/// their root's stars, so the finer shards keep the work of threads balanced.
+#[cfg(feature = "parallel")]
+fn enumeration_sub_shards(
+    degree: usize,
+    config: &EnumerationConfig,
+) -> (Vec<EnumerationShard>, RangeInclusive<usize>) {
+    let (shards, halfedges) = enumeration_shards(degree, config);
+    if degree < 2 {
+        return (shards, halfedges);
+    }
+    let neighbor_lists = color_subsets_ge2(config.colors);
+    let sub_shards = shards
+        .into_iter()
+        .flat_map(|(root_colors, prefix)| {
+            let types = neighbor_types(root_colors, halfedges.clone(), &neighbor_lists).len();
+            (prefix[0]..types).map(move |second| (root_colors, vec![prefix[0], second]))
+        })
+        .collect();
+    (sub_shards, halfedges)
+}
+
+/// Calls `visit` on the stars of one shard of [`generate_stars_with_config`], in enumeration
+/// order.
+fn visit_shard(
+    degree: usize,
+    config: &EnumerationConfig,
+    halfedges: RangeInclusive<usize>,
+    (root_colors, prefix): &EnumerationShard,
+    visit: &mut dyn FnMut(Star),
+) {
+    // Build the list of possible neighbor "types" for this root.
+    // Each type is (colors, halfedges).
+    let neighbor_types = neighbor_types(*root_colors, halfedges, &color_subsets_ge2(config.colors));
+    let keep = |prefix: &[usize]| {
+        let halfedges: Vec<u8> = prefix.iter().map(|&idx| neighbor_types[idx].1).collect();
+        config.halfedge_bounds.iter().all(|b| b.allows(&halfedges))
+    };
+    // Only 0-neighbor stars have no neighbor types.
+    let t = neighbor_types.len().saturating_sub(1);
+    visit_nondecreasing_sequences(t, degree, prefix, &keep, &mut |choice| {
+        let (neighbor_colors, neighbor_halfedges) =
+            choice.iter().map(|&idx| neighbor_types[idx]).unzip();
+        let star = Star {
+            root_colors: *root_colors,
+            neighbor_colors,
+            neighbor_halfedges,
+            neighbor_adjacency: None,
+        };
+        if !config.orbit_representatives || star.is_orbit_representative(config.colors) {
+            visit(star);
+        }
+    });
+}
+
+/// Folds `f` over the stars of [`generate_stars`] in enumeration order, starting from
+/// `init`, without storing the stars; e.g. to compute a maximum branching number, feature
+/// sums or a histogram in one streaming pass.
+pub fn fold_stars<A>(degree: usize, init: A, f: impl FnMut(A, Star) -> A) -> A {
+    fold_stars_with_config(degree, &EnumerationConfig::default(), init, f)
+}
+
+/// Like [`fold_stars`], over the stars of [`generate_stars_with_config`].
+///
+/// Panics if `config` fails [`EnumerationConfig::validate`].
+pub fn fold_stars_with_config<A>(
+    degree: usize,
+    config: &EnumerationConfig,
+    init: A,
+    mut f: impl FnMut(A, Star) -> A,
+) -> A {
+    let (shards, halfedges) = enumeration_shards(degree, config);
+    let mut acc = Some(init);
+    for shard in &shards {
+        visit_shard(degree, config, halfedges.clone(), shard, &mut |star| {
+            acc = acc.take().map(|acc| f(acc, star));
+        });
+    }
+    acc.expect("the accumulator is put back after every star")
+}
+
+/// Like [`fold_stars_with_config`], on `threads` threads.
+///
+/// The enumeration is split into contiguous shards of the enumeration order by the root list
+/// and the first two neighbors, and the threads take the next unclaimed shard until none are
+/// left, so that a thread with large shards does not hold up the others. Every shard is
+/// folded with `f` starting from `init()`, and the results are combined with `merge` in
+/// enumeration order, so that the result equals the one of [`fold_stars_with_config`] if
+/// `merge` is associative and `init()` is its identity.
+///
+/// Panics if `config` fails [`EnumerationConfig::validate`].
+#[cfg(feature = "parallel")]
+pub fn fold_stars_par<A: Send>(
+    degree: usize,
+    config: &EnumerationConfig,
+    threads: usize,
+    init: impl Fn() -> A + Sync,
+    f: impl Fn(A, Star) -> A + Sync,
+    merge: impl FnMut(A, A) -> A,
+) -> A {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let (shards, halfedges) = enumeration_sub_shards(degree, config);
+    let next = AtomicUsize::new(0);
+    let (init, f, halfedges, shards, next) = (&init, &f, &halfedges, &shards, &next);
+    let mut results: Vec<(usize, A)> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..threads.clamp(1, shards.len().max(1)))
+            .map(|_| {
+                scope.spawn(move || {
+                    let mut results = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(shard) = shards.get(i) else {
+                            return results;
+                        };
+                        let mut acc = Some(init());
+                        visit_shard(degree, config, halfedges.clone(), shard, &mut |star| {
+                            acc = acc.take().map(|acc| f(acc, star));
+                        });
+                        results.push((
+                            i,
+                            acc.expect("the accumulator is put back after every star"),
+                        ));
+                    }
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().expect("enumeration thread panicked"))
+            .collect()
+    });
+    results.sort_unstable_by_key(|&(i, _)| i);
+    results
+        .into_iter()
+        .map(|(_, acc)| acc)
+        .reduce(merge)
+        .unwrap_or_else(init)
+}
+
+/// The neighbor types `(colors, halfedges)` that [`generate_stars`] considers for a root, in
+/// its order.
+///
+/// Panics if a halfedge count does not fit into a `u8`, see [`check_halfedges`].
+fn neighbor_types(
+    root_colors: u8,
+    halfedges: RangeInclusive<usize>,
+    lists: &[u8],
+) -> Vec<(u8, u8)> {
+    let mut types: Vec<(u8, u8)> = Vec::new();
+    for &colors in lists.iter() {
+        if !intersects(root_colors, colors) {
+            continue;
+        }
+        for h in halfedges.clone() {
+            let h = u8::try_from(h).expect("halfedge counts fit into a u8");
+            types.push((colors, h));
+        }
+    }
+    types
+}
+
+/// Generate all stars of a given `degree` that extend the partial star `partial`: the root
+/// and the neighbors of `partial` are kept, and the remaining `degree - k` neighbors are
+/// enumerated as in [`generate_stars`].
+///
+/// The completions are exactly the stars of [`generate_stars`] with the root list of
+/// `partial` whose neighbors include those of `partial` (as a multiset), with neighbors in
+/// the same order. There are none if `partial` has more than `degree` neighbors, or a root
+/// list or a neighbor that [`generate_stars`] would not generate.
+///
+/// Fails if `degree` exceeds `u8::MAX`, as neighbors could have that many halfedges.
+pub fn generate_star_completions(partial: &Star, degree: usize) -> Result<Vec<Star>, CountError> {
+    generate_star_completions_with_config(partial, degree, &EnumerationConfig::default())
+}
+
+/// Like [`generate_star_completions`], for the stars of [`generate_stars_with_config`]: the
+/// remaining neighbors have the lists of `config.colors` colors and the halfedge counts of
+/// [`EnumerationConfig::halfedge_range`], and the completions are those that satisfy
+/// `config.halfedge_bounds` and, with `config.orbit_representatives`, represent their orbit.
+///
+/// Fails if a neighbor could have more halfedges than a `u8` holds. Panics if `config` fails
+/// [`EnumerationConfig::validate`].
+pub fn generate_star_completions_with_config(
+    partial: &Star,
+    degree: usize,
+    config: &EnumerationConfig,
+) -> Result<Vec<Star>, CountError> {
+    if let Err(e) = config.validate() {
+        panic!("{e}");
+    }
+    let halfedges = config.halfedge_range(degree);
+    check_halfedges(&halfedges)?;
+    if config
+        .max_degree
+        .is_some_and(|max_degree| degree > max_degree)
+        || !root_color_subsets(config.colors).contains(&partial.root_colors)
+    {
+        return Ok(Vec::new());
+    }
+    let types = neighbor_types(
+        partial.root_colors,
+        halfedges,
+        &color_subsets_ge2(config.colors),
+    );
+    let mut fixed: Vec<usize> = Vec::with_capacity(partial.neighbor_colors.len());
+    for (&c, &h) in partial
+        .neighbor_colors
+        .iter()
+        .zip(partial.neighbor_halfedges.iter())
+    {
+        match types.iter().position(|&t| t == (c, h)) {
+            Some(idx) => fixed.push(idx),
+            None => return Ok(Vec::new()),
+        }
+    }
+    if fixed.len() > degree || partial.neighbor_colors.len() != partial.neighbor_halfedges.len() {
+        return Ok(Vec::new());
+    }
+    let free = degree - fixed.len();
+    let choices = match types.len() {
+        0 if free > 0 => Vec::new(),
+        0 => vec![Vec::new()],
+        n => nondecreasing_sequences(n - 1, free),
+    };
+
+    let mut out: Vec<Star> = Vec::with_capacity(choices.len());
+    for mut choice in choices {
+        choice.extend_from_slice(&fixed);
+        choice.sort_unstable();
+        let star = Star {
+            root_colors: partial.root_colors,
+            neighbor_colors: choice.iter().map(|&idx| types[idx].0).collect(),
+            neighbor_halfedges: choice.iter().map(|&idx| types[idx].1).collect(),
+            neighbor_adjacency: None,
+        };
+        let bounded = config
+            .halfedge_bounds
+            .iter()
+            .all(|b| b.allows(&star.neighbor_halfedges));
+        if bounded && (!config.orbit_representatives || star.is_orbit_representative(config.colors))
+        {
+            out.push(star);
+        }
+    }
+    Ok(out)
+}
+
+/// The neighbors of a star grouped by type: every distinct `(colors, halfedges)` pair together
+/// with its multiplicity, sorted by type.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NeighborMultiset {
+    pub entries: Vec<((u8, u8), usize)>,
+}
+
+impl NeighborMultiset {
+    pub fn from_star(star: &Star) -> Self {
+        debug_assert_eq!(star.neighbor_colors.len(), star.neighbor_halfedges.len());
+        Self::from_types(
+            star.neighbor_colors
+                .iter()
+                .copied()
+                .zip(star.neighbor_halfedges.iter().copied()),
+        )
+    }
+
+    fn from_types(types: impl Iterator<Item = (u8, u8)>) -> Self {
+        let mut types: Vec<(u8, u8)> = types.collect();
+        types.sort();
+        let mut entries: Vec<((u8, u8), usize)> = Vec::new();
+        for t in types {
+            match entries.last_mut() {
+                Some((last, count)) if *last == t => *count += 1,
+                _ => entries.push((t, 1)),
+            }
+        }
+        NeighborMultiset { entries }
+    }
+
+    /// Returns the multiset after renaming the colors by `perm`.
+    pub fn permuted(&self, perm: &ColorPermutation) -> Self {
+        Self::from_types(
+            self.entries
+                .iter()
+                .flat_map(|&((c, h), count)| core::iter::repeat_n((perm.apply(c), h), count)),
+        )
+    }
+}
+
+/// A renaming of the colors {0,...,7}: color `i` becomes color `self.0[i]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ColorPermutation(pub [u8; MAX_COLORS as usize]);
+
+impl ColorPermutation {
+    pub const IDENTITY: ColorPermutation = ColorPermutation([0, 1, 2, 3, 4, 5, 6, 7]);
+
+    /// The permutation that renames color `i` to `images[i]` and keeps the colors from
+    /// `images.len()` on, e.g. `ColorPermutation::new(&[1, 0])` swaps colors 0 and 1.
+    ///
+    /// Panics if `images` is not a permutation of `0..images.len()`.
+    pub fn new(images: &[u8]) -> ColorPermutation {
+        let mut perm = ColorPermutation::IDENTITY;
+        perm.0[..images.len()].copy_from_slice(images);
+        let mut sorted = images.to_vec();
+        sorted.sort_unstable();
+        assert!(
+            sorted.iter().copied().eq(0..images.len() as u8),
+            "not a permutation: {images:?}"
+        );
+        perm
+    }
+
+    /// Returns all `k!` permutations of the colors `{0, ..., k - 1}` that keep the other
+    /// colors, in lexicographic order.
+    pub fn all(k: u8) -> Vec<ColorPermutation> {
+        fn extend(prefix: &mut Vec<u8>, k: u8, out: &mut Vec<ColorPermutation>) {
+            if prefix.len() == k as usize {
+                out.push(ColorPermutation::new(prefix));
+                return;
+            }
+            for c in 0..k {
+                if !prefix.contains(&c) {
+                    prefix.push(c);
+                    extend(prefix, k, out);
+                    prefix.pop();
+                }
+            }
+        }
+        let mut out = Vec::new();
+        extend(&mut Vec::new(), k.min(MAX_COLORS), &mut out);
+        out
+    }
+
+    /// Renames the colors of a bitmask.
+    pub fn apply(&self, colors: u8) -> u8 {
+        let mut out = 0u8;
+        for (i, &target) in self.0.iter().enumerate() {
+            if (colors >> i) & 1 == 1 {
+                out |= 1 << target;
+            }
+        }
+        out
+    }
+
+    /// Returns the permutation that first applies `self`, then `other`.
+    pub fn then(&self, other: &ColorPermutation) -> ColorPermutation {
+        ColorPermutation(self.0.map(|c| other.0[c as usize]))
+    }
+
+    pub fn inverse(&self) -> ColorPermutation {
+        let mut out = [0u8; MAX_COLORS as usize];
+        for (i, &target) in self.0.iter().enumerate() {
+            out[target as usize] = i as u8;
         }
+        ColorPermutation(out)
+    }
+}
+
+/// Returns a key that identifies a star up to reordering its neighbors and renaming the colors:
+/// the smallest `(root colors, neighbor multiset)` over all permutations of the colors of
+/// [`Star::color_count`].
+pub fn star_type_key(star: &Star) -> (u8, NeighborMultiset) {
+    let neighbors = NeighborMultiset::from_star(star);
+    ColorPermutation::all(star.color_count())
+        .iter()
+        .map(|perm| (perm.apply(star.root_colors), neighbors.permuted(perm)))
+        .min()
+        .expect("there is at least one permutation")
+}
+
+impl Star {
+    /// Sorts the neighbors by `(colors, halfedges)` in increasing order, the order of
+    /// [`NeighborMultiset`], so that stars that differ only in the order of their neighbors
+    /// become equal. Colors are not renamed; see [`star_type_key`] for that.
+    pub fn canonicalize(&mut self) {
+        let mut neighbors: Vec<(u8, u8, usize)> = self
+            .neighbor_colors
+            .iter()
+            .copied()
+            .zip(self.neighbor_halfedges.iter().copied())
+            .enumerate()
+            .map(|(i, (c, h))| (c, h, i))
+            .collect();
+        neighbors.sort();
+        if let Some(adjacency) = &mut self.neighbor_adjacency {
+            let mut perm = vec![0; neighbors.len()];
+            for (position, &(_, _, i)) in neighbors.iter().enumerate() {
+                perm[i] = position;
+            }
+            **adjacency = adjacency.permute(&perm);
+        }
+        (self.neighbor_colors, self.neighbor_halfedges) =
+            neighbors.into_iter().map(|(c, h, _)| (c, h)).unzip();
+    }
+
+    /// The number of colors the renamings of the star permute: [`NUM_COLORS`], or more if a
+    /// list has a larger color, so that stars with colors `{0, ..., 3}` are compared up to the
+    /// same 24 renamings whatever colors they use.
+    pub fn color_count(&self) -> u8 {
+        let used = self
+            .neighbor_colors
+            .iter()
+            .fold(self.root_colors, |used, &c| used | c);
+        (u8::BITS - used.leading_zeros()).max(NUM_COLORS as u32) as u8
+    }
+
+    /// The color permutations that map `{0, ..., k - 1}` onto itself and fix the root list.
+    fn root_stabilizer(&self, k: u8) -> impl Iterator<Item = ColorPermutation> {
+        let root_colors = self.root_colors;
+        ColorPermutation::all(k)
+            .into_iter()
+            .filter(move |p| p.apply(root_colors) == root_colors)
+    }
+
+    /// The color permutations that map `{0, ..., k - 1}` onto itself and the star to itself up
+    /// to reordering the neighbors.
+    fn automorphisms_of(&self, k: u8) -> Vec<ColorPermutation> {
+        let neighbors = NeighborMultiset::from_star(self);
+        self.root_stabilizer(k)
+            .filter(|perm| neighbors.permuted(perm) == neighbors)
+            .collect()
+    }
+
+    /// Whether the star is the representative of its orbit under renaming the colors
+    /// `{0, ..., k - 1}` among the stars with the same root list: its neighbor multiset is the
+    /// smallest one of the orbit. [`generate_stars_with_config`] enumerates every orbit with
+    /// exactly one representative.
+    pub fn is_orbit_representative(&self, k: u8) -> bool {
+        let neighbors = NeighborMultiset::from_star(self);
+        self.root_stabilizer(k)
+            .all(|perm| neighbors <= neighbors.permuted(&perm))
+    }
+
+    /// The representative of the orbit of the star under renaming the colors
+    /// `{0, ..., k - 1}`: the root list becomes `{0, ..., s - 1}` for a root list of size `s`,
+    /// as in [`root_color_subsets`], the neighbor multiset is the smallest one, and the
+    /// neighbors are sorted as by [`Star::canonicalize`]. For the stars of
+    /// [`generate_stars_with_config`], this is the enumerated representative.
+    pub fn orbit_representative(&self, k: u8) -> Star {
+        let prefix = u8::first(self.root_colors.count_ones() as u8);
+        let neighbors = NeighborMultiset::from_star(self);
+        let (root_colors, NeighborMultiset { entries }) = ColorPermutation::all(k)
+            .iter()
+            .map(|p| (p.apply(self.root_colors), neighbors.permuted(p)))
+            .min_by_key(|(root, multiset)| (*root != prefix, multiset.clone()))
+            .expect("the identity maps the colors onto themselves");
+        let (neighbor_colors, neighbor_halfedges) = entries
+            .iter()
+            .flat_map(|&(t, count)| core::iter::repeat_n(t, count))
+            .unzip();
+        Star {
+            root_colors,
+            neighbor_colors,
+            neighbor_halfedges,
+            neighbor_adjacency: None,
+        }
+    }
+
+    /// The number of distinct stars up to reordering the neighbors obtained by renaming the
+    /// colors `{0, ..., k - 1}`, i.e. `k!` divided by the number of automorphisms that map
+    /// these colors onto themselves. The renamings may move the root list, so this counts
+    /// stars whose root lists are not in [`root_color_subsets`]; see
+    /// [`Star::enumeration_multiplicity`] for the stars of the enumeration.
+    pub fn orbit_size(&self, k: u8) -> usize {
+        let preserving: usize = (1..=k.min(MAX_COLORS) as usize).product();
+        preserving / self.automorphisms_of(k).len()
+    }
+
+    /// The number of stars of [`generate_stars_with_config`] with `k` colors, up to reordering
+    /// the neighbors, that the orbit of the star contains: the renamings that fix the root
+    /// list, divided by the automorphisms among them. Summing it over the stars enumerated with
+    /// `orbit_representatives` gives the number of stars enumerated without.
+    pub fn enumeration_multiplicity(&self, k: u8) -> usize {
+        self.root_stabilizer(k).count() / self.automorphisms_of(k).len()
+    }
+
+    /// Returns the star with its colors renamed by `perm`; the neighbor order is kept.
+    pub fn permute_colors(&self, perm: &ColorPermutation) -> Star {
+        Star {
+            root_colors: perm.apply(self.root_colors),
+            neighbor_colors: self
+                .neighbor_colors
+                .iter()
+                .map(|&c| perm.apply(c))
+                .collect(),
+            neighbor_halfedges: self.neighbor_halfedges.clone(),
+            neighbor_adjacency: None,
+        }
+    }
+
+    /// Returns a color permutation that turns `self` into `other` up to reordering the
+    /// neighbors, or `None` if the stars are not isomorphic. See
+    /// [`Star::neighbor_matching`] for the corresponding neighbor bijection.
+    pub fn is_isomorphic(&self, other: &Star) -> Option<ColorPermutation> {
+        if self.neighbor_colors.len() != other.neighbor_colors.len() {
+            return None;
+        }
+        let theirs = NeighborMultiset::from_star(other);
+        let ours = NeighborMultiset::from_star(self);
+        let k = self.color_count().max(other.color_count());
+        ColorPermutation::all(k).into_iter().find(|perm| {
+            perm.apply(self.root_colors) == other.root_colors && ours.permuted(perm) == theirs
+        })
     }
 
+    /// Returns the group of color permutations that map the star to itself up to reordering
+    /// the neighbors, among the renamings of the `k` colors of [`Star::color_count`], in
+    /// lexicographic order. It always contains the identity; `k!` divided by its size is the
+    /// number of distinct stars obtained by renaming colors.
+    pub fn automorphisms(&self) -> Vec<ColorPermutation> {
+        self.automorphisms_of(self.color_count())
+    }
+
+    /// Returns `matching` such that neighbor `i` of `self` corresponds to neighbor
+    /// `matching[i]` of `other` after renaming colors by `perm`, or `None` if there is no
+    /// such bijection.
+    pub fn neighbor_matching(&self, other: &Star, perm: &ColorPermutation) -> Option<Vec<usize>> {
+        if self.neighbor_colors.len() != other.neighbor_colors.len() {
+            return None;
+        }
+        let mut used = vec![false; other.neighbor_colors.len()];
+        let mut matching: Vec<usize> = Vec::with_capacity(used.len());
+        for (&c, &h) in self
+            .neighbor_colors
+            .iter()
+            .zip(self.neighbor_halfedges.iter())
+        {
+            let j = (0..used.len()).find(|&j| {
+                !used[j]
+                    && other.neighbor_colors[j] == perm.apply(c)
+                    && other.neighbor_halfedges[j] == h
+            })?;
+            used[j] = true;
+            matching.push(j);
+        }
+        Some(matching)
+    }
+}
+
+/// Canonicalizes every star with [`Star::canonicalize`] and drops the stars equal to an
+/// earlier one, keeping the order of first occurrence.
+pub fn dedup_stars(stars: impl IntoIterator<Item = Star>) -> Vec<Star> {
+    let mut seen: BTreeSet<Star> = BTreeSet::new();
+    let mut out = Vec::new();
+    for mut star in stars {
+        star.canonicalize();
+        if seen.insert(star.clone()) {
+            out.push(star);
+        }
+    }
     out
 }
 
+/// Edges among the neighbors of a star, as a symmetric bitmatrix: bit `j` of row `i` is set if
+/// neighbors `i` and `j` are adjacent. Supports up to 16 neighbors.
+///
+/// Stored in [`Star::neighbor_adjacency`]. The string encodings reject stars with neighbor
+/// edges, while the JSON encoding writes them as `"neighbor_edges":[[i,j],...]`. Isomorphism
+/// tests, the enumeration, the reductions and the reduced branching rules ignore them; the
+/// adjacency-aware functions, such as [`Star::propagate`] and
+/// [`crate::cyclic_star_utils`], use them. An edge between two neighbors is one of the
+/// halfedges of both.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct NeighborAdjacency {
+    rows: Vec<u16>,
+}
+
+impl NeighborAdjacency {
+    /// No edges among `degree` neighbors.
+    pub fn new(degree: usize) -> Self {
+        assert!(
+            degree <= 16,
+            "{degree} neighbors do not fit into the bitmatrix"
+        );
+        NeighborAdjacency {
+            rows: vec![0; degree],
+        }
+    }
+
+    /// The given edges among `degree` neighbors, or `None` if there are more than 16 neighbors,
+    /// an edge is a loop or a neighbor is out of range.
+    pub fn try_from_edges(degree: usize, edges: &[(usize, usize)]) -> Option<Self> {
+        let valid = |&(i, j): &(usize, usize)| i != j && i < degree && j < degree;
+        (degree <= 16 && edges.iter().all(valid)).then(|| Self::from_edges(degree, edges))
+    }
+
+    /// The given edges among `degree` neighbors.
+    pub fn from_edges(degree: usize, edges: &[(usize, usize)]) -> Self {
+        let mut adjacency = NeighborAdjacency::new(degree);
+        for &(i, j) in edges {
+            adjacency.add_edge(i, j);
+        }
+        adjacency
+    }
+
+    /// The number of neighbors.
+    pub fn degree(&self) -> usize {
+        self.rows.len()
+    }
+
+    /// Panics if `i == j` or a neighbor is out of range.
+    pub fn add_edge(&mut self, i: usize, j: usize) {
+        assert!(i != j, "neighbor {i} cannot be adjacent to itself");
+        self.rows[i] |= 1 << j;
+        self.rows[j] |= 1 << i;
+    }
+
+    pub fn remove_edge(&mut self, i: usize, j: usize) {
+        self.rows[i] &= !(1 << j);
+        self.rows[j] &= !(1 << i);
+    }
+
+    pub fn is_adjacent(&self, i: usize, j: usize) -> bool {
+        (self.rows[i] >> j) & 1 == 1
+    }
+
+    /// The neighbors adjacent to neighbor `i`, as a bitmask.
+    pub fn row(&self, i: usize) -> u16 {
+        self.rows[i]
+    }
+
+    /// The edges `(i, j)` with `i < j`, in lexicographic order.
+    pub fn edges(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.degree()).flat_map(move |i| {
+            (i + 1..self.degree())
+                .filter(move |&j| self.is_adjacent(i, j))
+                .map(move |j| (i, j))
+        })
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.rows
+            .iter()
+            .map(|r| r.count_ones() as usize)
+            .sum::<usize>()
+            / 2
+    }
+
+    /// The number of triangles among the neighbors alone. Every edge among the neighbors also
+    /// closes a triangle with the root.
+    pub fn neighbor_triangles(&self) -> usize {
+        self.edges()
+            .map(|(i, j)| (self.rows[i] & self.rows[j] & !((2u32 << j) - 1) as u16).count_ones())
+            .sum::<u32>() as usize
+    }
+
+    /// The adjacency after moving neighbor `i` to position `perm[i]`.
+    pub fn permute(&self, perm: &[usize]) -> Self {
+        let mut out = NeighborAdjacency::new(self.degree());
+        for (i, j) in self.edges() {
+            out.add_edge(perm[i], perm[j]);
+        }
+        out
+    }
+
+    /// Whether the adjacency has one row per neighbor of `star` and no neighbor has more
+    /// adjacent neighbors than halfedges.
+    pub fn fits(&self, star: &Star) -> bool {
+        self.degree() == star.neighbor_colors.len()
+            && self
+                .rows
+                .iter()
+                .zip(&star.neighbor_halfedges)
+                .all(|(&row, &h)| row.count_ones() <= h as u32)
+    }
+
+    /// Every adjacency with at most `max_edges` edges that [fits](NeighborAdjacency::fits)
+    /// `star`, one per class under the permutations of neighbors with equal lists and
+    /// halfedges, ordered by number of edges.
+    ///
+    /// Tries all permutations of the neighbors, so it is meant for small degrees.
+    pub fn all_consistent(star: &Star, max_edges: usize) -> Vec<Self> {
+        let degree = star.neighbor_colors.len();
+        let symmetries: Vec<Vec<usize>> = permutations(degree)
+            .into_iter()
+            .filter(|perm| {
+                (0..degree).all(|i| {
+                    star.neighbor_colors[perm[i]] == star.neighbor_colors[i]
+                        && star.neighbor_halfedges[perm[i]] == star.neighbor_halfedges[i]
+                })
+            })
+            .collect();
+        let pairs: Vec<(usize, usize)> = (0..degree)
+            .flat_map(|i| (i + 1..degree).map(move |j| (i, j)))
+            .collect();
+
+        // All edge sets, level by level; an edge set is kept if it is the smallest of its class.
+        let mut out = vec![NeighborAdjacency::new(degree)];
+        let mut level = out.clone();
+        for _ in 0..max_edges {
+            let mut next: BTreeSet<NeighborAdjacency> = BTreeSet::new();
+            for adjacency in &level {
+                for &(i, j) in &pairs {
+                    if adjacency.is_adjacent(i, j) {
+                        continue;
+                    }
+                    let mut larger = adjacency.clone();
+                    larger.add_edge(i, j);
+                    if larger.fits(star) {
+                        let canonical = symmetries.iter().map(|p| larger.permute(p)).min();
+                        next.insert(canonical.unwrap_or(larger));
+                    }
+                }
+            }
+            level = next.into_iter().collect();
+            out.extend(level.iter().cloned());
+        }
+        out
+    }
+}
+
+/// All permutations of `0..n`, in lexicographic order.
+fn permutations(n: usize) -> Vec<Vec<usize>> {
+    let mut perm: Vec<usize> = (0..n).collect();
+    let mut out = vec![perm.clone()];
+    loop {
+        let Some(i) = (1..n).rev().find(|&i| perm[i - 1] < perm[i]) else {
+            return out;
+        };
+        let j = (i..n).rev().find(|&j| perm[j] > perm[i - 1]).unwrap();
+        perm.swap(i - 1, j);
+        perm[i..].reverse();
+        out.push(perm.clone());
+    }
+}
+
+impl Star {
+    /// Removes the color of every vertex with a singleton list from the lists of its adjacent
+    /// vertices, until nothing changes: a fixed root from all neighbor lists, a fixed neighbor
+    /// from the root list and from the lists of the neighbors adjacent to it in
+    /// [`Star::neighbor_adjacency`]. Lists may become empty.
+    pub fn propagate(&self) -> Star {
+        let mut out = self.clone();
+        let edges: Vec<(usize, usize)> = self
+            .neighbor_adjacency
+            .iter()
+            .flat_map(|adjacency| adjacency.edges())
+            .collect();
+        loop {
+            let mut changed = false;
+            let mut remove = |list: &mut u8, fixed: u8| {
+                if fixed.count_ones() == 1 && intersects(*list, fixed) {
+                    *list &= !fixed;
+                    changed = true;
+                }
+            };
+            for i in 0..out.neighbor_colors.len() {
+                remove(&mut out.root_colors, out.neighbor_colors[i]);
+                remove(&mut out.neighbor_colors[i], out.root_colors);
+            }
+            for &(i, j) in &edges {
+                let fixed = out.neighbor_colors[i];
+                remove(&mut out.neighbor_colors[j], fixed);
+                let fixed = out.neighbor_colors[j];
+                remove(&mut out.neighbor_colors[i], fixed);
+            }
+            if !changed {
+                return out;
+            }
+        }
+    }
+}
+
 pub fn hex(i: u8) -> String {
-    return format!("{:x}", i);
+    format!("{:x}", i)
 }
 
-pub fn star_to_string(star: &Star) -> Option<String> {
+/// Encodes a star as `star_<degree>_<colors>_0<halfedges>`, with one hex digit per list (the
+/// root first) and per halfedge count. Fails if the neighbor vectors have different lengths or
+/// some list or halfedge count does not fit into one hex digit, or the star has edges among
+/// its neighbors, since [`star_from_string`] could not decode the string; see [`star_label`]
+/// for a form that always exists.
+pub fn star_to_string(star: &Star) -> Result<String, StarError> {
+    if star.neighbor_adjacency.is_some() {
+        return Err(StarError::NeighborEdgesNotEncodable);
+    }
     let degree = star.neighbor_colors.len();
-    debug_assert_eq!(degree, star.neighbor_halfedges.len());
+    if degree != star.neighbor_halfedges.len() {
+        return Err(StarError::LengthMismatch {
+            colors: degree,
+            halfedges: star.neighbor_halfedges.len(),
+        });
+    }
+    let lists = core::iter::once((None, star.root_colors)).chain(
+        star.neighbor_colors
+            .iter()
+            .enumerate()
+            .map(|(i, &c)| (Some(i), c)),
+    );
+    if let Some((vertex, colors)) = lists.into_iter().find(|&(_, c)| c > 0xf) {
+        return Err(StarError::ColorsOutOfRange { vertex, colors });
+    }
+    if let Some((neighbor, &halfedges)) = star
+        .neighbor_halfedges
+        .iter()
+        .enumerate()
+        .find(|&(_, &h)| h > 0xf)
+    {
+        return Err(StarError::TooManyHalfedges {
+            neighbor,
+            halfedges,
+            max: MAX_HALFEDGES,
+        });
+    }
     let mut s = format!("star_{degree}_");
     s.push_str(&hex(star.root_colors));
 
@@ -128,5 +1373,1346 @@ pub fn star_to_string(star: &Star) -> Option<String> {
     for i in 0..degree {
         s.push_str(&hex(star.neighbor_halfedges[i]));
     }
-    Some(s)
+    Ok(s)
+}
+
+/// The star string of [`star_to_string`], or the JSON object of [`star_to_json_string`] for
+/// stars that have none, for messages and documents. [`star_from_string`] reads both.
+pub fn star_label(star: &Star) -> String {
+    star_to_string(star).unwrap_or_else(|_| star_to_json_string(star))
+}
+
+/// Prefix of the versioned star encoding produced by [`star_to_string_v2`].
+pub const STAR_STRING_V2_PREFIX: &str = "v2:";
+
+/// FNV-1a over `bytes`, xor-folded to 16 bits.
+pub(crate) fn checksum16(bytes: &[u8]) -> u16 {
+    let mut hash: u32 = 0x811c9dc5;
+    for &b in bytes {
+        hash ^= b as u32;
+        hash = hash.wrapping_mul(0x01000193);
+    }
+    ((hash >> 16) ^ (hash & 0xffff)) as u16
+}
+
+/// Encodes a star as `v2:<v1 encoding>#<checksum>`, where the v1 encoding is
+/// [`star_to_string`] and the checksum is four hex digits computed from it.
+///
+/// The checksum lets [`star_from_string`] reject corrupted or truncated lines instead of
+/// silently decoding them to a different star.
+pub fn star_to_string_v2(star: &Star) -> Result<String, StarError> {
+    let body = star_to_string(star)?;
+    let checksum = checksum16(body.as_bytes());
+    Ok(format!("{STAR_STRING_V2_PREFIX}{body}#{checksum:04x}"))
+}
+
+/// A violated structural invariant of a [`Star`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StarError {
+    /// `neighbor_colors` and `neighbor_halfedges` have different lengths.
+    LengthMismatch { colors: usize, halfedges: usize },
+    /// The star has more than [`MAX_NEIGHBORS`] neighbors.
+    TooManyNeighbors { neighbors: usize, max: usize },
+    /// A color list uses colors outside of `{0, ..., k - 1}` for the number of colors `k` it is
+    /// checked against; `vertex` is `None` for the root and the neighbor index otherwise.
+    ColorsOutOfRange { vertex: Option<usize>, colors: u8 },
+    /// A color list is empty, so the star has no coloring; `vertex` as in `ColorsOutOfRange`.
+    EmptyList { vertex: Option<usize> },
+    /// A neighbor has more than `max` halfedges.
+    TooManyHalfedges {
+        neighbor: usize,
+        halfedges: u8,
+        max: usize,
+    },
+    /// The halfedge counts of the neighbors violate a bound of the enumeration.
+    HalfedgeBoundViolated(HalfedgeBound),
+    /// The edges among the neighbors do not [fit](NeighborAdjacency::fits) the star.
+    NeighborEdgesMismatch,
+    /// The star has edges among its neighbors, which the string encodings cannot express.
+    NeighborEdgesNotEncodable,
+}
+
+impl core::fmt::Display for StarError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            StarError::LengthMismatch { colors, halfedges } => write!(
+                f,
+                "{colors} neighbor color lists but {halfedges} halfedge counts"
+            ),
+            StarError::TooManyNeighbors { neighbors, max } => {
+                write!(f, "{neighbors} neighbors, more than {max}")
+            }
+            StarError::ColorsOutOfRange { vertex, colors } => {
+                match vertex {
+                    Some(i) => write!(f, "neighbor {i}")?,
+                    None => write!(f, "root")?,
+                }
+                write!(f, " has colors out of range: {colors:#010b}")
+            }
+            StarError::EmptyList { vertex } => match vertex {
+                Some(i) => write!(f, "neighbor {i} has an empty list"),
+                None => write!(f, "root has an empty list"),
+            },
+            StarError::TooManyHalfedges {
+                neighbor,
+                halfedges,
+                max,
+            } => write!(
+                f,
+                "neighbor {neighbor} has {halfedges} halfedges, more than {max}"
+            ),
+            StarError::HalfedgeBoundViolated(bound) => {
+                write!(f, "halfedge counts violate the bound {bound}")
+            }
+            StarError::NeighborEdgesMismatch => {
+                write!(f, "the edges among the neighbors do not fit the star")
+            }
+            StarError::NeighborEdgesNotEncodable => {
+                write!(f, "edges among the neighbors cannot be encoded as a string")
+            }
+        }
+    }
+}
+
+impl core::error::Error for StarError {}
+
+/// The fields of a [`Star`] as deserialized, before [`Star::validate`] accepts them.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct StarFields {
+    root_colors: u8,
+    neighbor_colors: Vec<u8>,
+    neighbor_halfedges: Vec<u8>,
+    #[serde(default)]
+    neighbor_edges: Option<Vec<(usize, usize)>>,
+}
+
+/// Writes [`Star::neighbor_adjacency`] as its list of edges.
+#[cfg(feature = "serde")]
+fn serialize_neighbor_edges<S: serde::Serializer>(
+    adjacency: &Option<Box<NeighborAdjacency>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(adjacency.iter().flat_map(|adjacency| adjacency.edges()))
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<StarFields> for Star {
+    type Error = StarError;
+
+    fn try_from(fields: StarFields) -> Result<Self, Self::Error> {
+        let degree = fields.neighbor_colors.len();
+        let neighbor_adjacency = match fields.neighbor_edges {
+            Some(edges) => Some(Box::new(
+                NeighborAdjacency::try_from_edges(degree, &edges)
+                    .ok_or(StarError::NeighborEdgesMismatch)?,
+            )),
+            None => None,
+        };
+        let star = Star {
+            root_colors: fields.root_colors,
+            neighbor_colors: fields.neighbor_colors,
+            neighbor_halfedges: fields.neighbor_halfedges,
+            neighbor_adjacency,
+        };
+        star.validate()?;
+        Ok(star)
+    }
+}
+
+impl Star {
+    /// Checks the invariants every star must satisfy before it is branched on or reduced: the
+    /// neighbor vectors have the same length of at most [`MAX_NEIGHBORS`], all lists are
+    /// nonempty, no neighbor has more than [`MAX_HALFEDGES`] halfedges, and the edges among
+    /// the neighbors, if any, fit the star. Any of the [`MAX_COLORS`] colors is allowed; see
+    /// [`Star::validate_with_colors`] for fewer.
+    ///
+    /// A star with an empty list has no coloring; the rules report it as
+    /// [`Branch::Infeasible`](crate::list_coloring_utils::Branch::Infeasible) instead (see
+    /// [`crate::list_coloring_utils::is_infeasible`]).
+    pub fn validate(&self) -> Result<(), StarError> {
+        self.validate_with_colors(MAX_COLORS)
+    }
+
+    /// Like [`Star::validate`], also checking that all lists are subsets of
+    /// `{0, ..., k - 1}`.
+    pub fn validate_with_colors(&self, k: u8) -> Result<(), StarError> {
+        self.validate_with_config(&EnumerationConfig {
+            colors: k,
+            ..EnumerationConfig::default()
+        })
+    }
+
+    /// Like [`Star::validate`] for the stars of [`generate_stars_with_config`] with `config`:
+    /// all lists are subsets of `{0, ..., config.colors - 1}`, no neighbor has more halfedges
+    /// than `config.max_degree` and `config.max_halfedges` allow, and the
+    /// `config.halfedge_bounds` hold. Neighbors with fewer than `config.min_halfedges`
+    /// halfedges are allowed, since branching and reductions create them.
+    pub fn validate_with_config(&self, config: &EnumerationConfig) -> Result<(), StarError> {
+        let k = config.colors;
+        let universe = u8::first(k.min(MAX_COLORS));
+        if self.neighbor_colors.len() != self.neighbor_halfedges.len() {
+            return Err(StarError::LengthMismatch {
+                colors: self.neighbor_colors.len(),
+                halfedges: self.neighbor_halfedges.len(),
+            });
+        }
+        if self.neighbor_colors.len() > MAX_NEIGHBORS {
+            return Err(StarError::TooManyNeighbors {
+                neighbors: self.neighbor_colors.len(),
+                max: MAX_NEIGHBORS,
+            });
+        }
+        let lists = core::iter::once((None, self.root_colors)).chain(
+            self.neighbor_colors
+                .iter()
+                .enumerate()
+                .map(|(i, &c)| (Some(i), c)),
+        );
+        for (vertex, colors) in lists.clone() {
+            if !is_subset(colors, universe) {
+                return Err(StarError::ColorsOutOfRange { vertex, colors });
+            }
+        }
+        if let Some((vertex, _)) = lists.into_iter().find(|&(_, colors)| colors == 0) {
+            return Err(StarError::EmptyList { vertex });
+        }
+        let max = config
+            .max_degree
+            .map_or(MAX_HALFEDGES, |d| d.saturating_sub(1).min(MAX_HALFEDGES))
+            .min(config.max_halfedges.map_or(MAX_HALFEDGES, usize::from));
+        for (neighbor, &halfedges) in self.neighbor_halfedges.iter().enumerate() {
+            if halfedges as usize > max {
+                return Err(StarError::TooManyHalfedges {
+                    neighbor,
+                    halfedges,
+                    max,
+                });
+            }
+        }
+        if let Some(&bound) = config
+            .halfedge_bounds
+            .iter()
+            .find(|b| !b.allows(&self.neighbor_halfedges))
+        {
+            return Err(StarError::HalfedgeBoundViolated(bound));
+        }
+        if self
+            .neighbor_adjacency
+            .as_ref()
+            .is_some_and(|adjacency| !adjacency.fits(self))
+        {
+            return Err(StarError::NeighborEdgesMismatch);
+        }
+        Ok(())
+    }
+}
+
+/// Builds a [`Star`] one neighbor at a time, keeping the list and the halfedge count of each
+/// neighbor together, e.g. `Star::builder().root(0b0111).neighbor(0b0011, 2).build()`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StarBuilder {
+    root_colors: u8,
+    neighbor_colors: Vec<u8>,
+    neighbor_halfedges: Vec<u8>,
+}
+
+impl StarBuilder {
+    pub fn root(mut self, colors: u8) -> Self {
+        self.root_colors = colors;
+        self
+    }
+
+    /// Adds a neighbor with the list `colors` and `halfedges` halfedges.
+    pub fn neighbor(self, colors: u8, halfedges: u8) -> Self {
+        self.neighbors(colors, halfedges, 1)
+    }
+
+    /// Adds `count` neighbors with the list `colors` and `halfedges` halfedges each.
+    pub fn neighbors(mut self, colors: u8, halfedges: u8, count: usize) -> Self {
+        self.neighbor_colors
+            .extend(core::iter::repeat_n(colors, count));
+        self.neighbor_halfedges
+            .extend(core::iter::repeat_n(halfedges, count));
+        self
+    }
+
+    /// The star, if it passes [`Star::validate`]; a root list that was never set is empty.
+    pub fn build(self) -> Result<Star, StarError> {
+        let star = self.into_star();
+        star.validate()?;
+        Ok(star)
+    }
+
+    /// The star, if it passes [`Star::validate_with_config`].
+    pub fn build_with_config(self, config: &EnumerationConfig) -> Result<Star, StarError> {
+        let star = self.into_star();
+        star.validate_with_config(config)?;
+        Ok(star)
+    }
+
+    fn into_star(self) -> Star {
+        Star {
+            root_colors: self.root_colors,
+            neighbor_colors: self.neighbor_colors,
+            neighbor_halfedges: self.neighbor_halfedges,
+            neighbor_adjacency: None,
+        }
+    }
+}
+
+impl Star {
+    /// A [`StarBuilder`] without root list and neighbors.
+    pub fn builder() -> StarBuilder {
+        StarBuilder::default()
+    }
+}
+
+/// Why a string could not be decoded into a [`Star`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum StarParseError {
+    /// The string has a `v<N>:` prefix for a version this crate does not know.
+    UnknownVersion(String),
+    /// A v2 string whose checksum is missing or does not match its contents.
+    ChecksumMismatch { expected: String, found: String },
+    /// The string does not have the layout `star_<degree>_<colors>_0<halfedges>`.
+    Malformed(String),
+}
+
+impl core::fmt::Display for StarParseError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            StarParseError::UnknownVersion(v) => write!(f, "unknown star format version: {v}"),
+            StarParseError::ChecksumMismatch { expected, found } => {
+                write!(
+                    f,
+                    "star checksum mismatch: expected {expected}, found {found}"
+                )
+            }
+            StarParseError::Malformed(s) => write!(f, "malformed star string: {s}"),
+        }
+    }
+}
+
+impl core::error::Error for StarParseError {}
+
+/// Decodes a star from either the v2 encoding of [`star_to_string_v2`] or the unversioned
+/// v1 encoding of [`star_to_string`], or from the JSON object that [`star_label`] falls back to.
+pub fn star_from_string(s: &str) -> Result<Star, StarParseError> {
+    let s = s.trim();
+    if s.starts_with('{') {
+        return star_from_json_string(s);
+    }
+    if let Some(rest) = s.strip_prefix(STAR_STRING_V2_PREFIX) {
+        let Some((body, found)) = rest.rsplit_once('#') else {
+            return Err(StarParseError::ChecksumMismatch {
+                expected: format!("{:04x}", checksum16(rest.as_bytes())),
+                found: String::new(),
+            });
+        };
+        let expected = format!("{:04x}", checksum16(body.as_bytes()));
+        if expected != found {
+            return Err(StarParseError::ChecksumMismatch {
+                expected,
+                found: found.to_string(),
+            });
+        }
+        return star_from_string_v1(body);
+    }
+    if let Some((version, _)) = s.split_once(':') {
+        return Err(StarParseError::UnknownVersion(version.to_string()));
+    }
+    star_from_string_v1(s)
+}
+
+pub(crate) fn star_from_string_v1(s: &str) -> Result<Star, StarParseError> {
+    let malformed = || StarParseError::Malformed(s.to_string());
+    let nibbles = |digits: &str| -> Result<Vec<u8>, StarParseError> {
+        digits
+            .chars()
+            .map(|c| c.to_digit(16).map(|d| d as u8).ok_or_else(malformed))
+            .collect()
+    };
+
+    let mut parts = s.split('_');
+    if parts.next() != Some("star") {
+        return Err(malformed());
+    }
+    // Only plain digits without leading zeros, so that the degree cannot be written in several
+    // ways (e.g. `+3` or `03`).
+    let degree: usize = parts
+        .next()
+        .filter(|d| d.bytes().all(|b| b.is_ascii_digit()) && (*d == "0" || !d.starts_with('0')))
+        .and_then(|d| d.parse().ok())
+        .ok_or_else(malformed)?;
+    let colors = nibbles(parts.next().ok_or_else(malformed)?)?;
+    let halfedges = nibbles(parts.next().ok_or_else(malformed)?)?;
+    if parts.next().is_some()
+        || colors.len() != degree + 1
+        || halfedges.len() != degree + 1
+        || halfedges[0] != 0
+    {
+        return Err(malformed());
+    }
+    Ok(Star {
+        root_colors: colors[0],
+        neighbor_colors: colors[1..].to_vec(),
+        neighbor_halfedges: halfedges[1..].to_vec(),
+        neighbor_adjacency: None,
+    })
+}
+
+/// Encodes a star as a compact one-line JSON object, e.g.
+/// `{"root_colors":7,"neighbor_colors":[9,10],"neighbor_halfedges":[2,3]}`. Edges among the
+/// neighbors follow as `"neighbor_edges":[[0,1]]`.
+pub fn star_to_json_string(star: &Star) -> String {
+    let list = |xs: &[u8]| {
+        xs.iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+    let edges = match &star.neighbor_adjacency {
+        Some(adjacency) => format!(
+            ",\"neighbor_edges\":[{}]",
+            adjacency
+                .edges()
+                .map(|(i, j)| format!("[{i},{j}]"))
+                .collect::<Vec<_>>()
+                .join(",")
+        ),
+        None => String::new(),
+    };
+    format!(
+        "{{\"root_colors\":{},\"neighbor_colors\":[{}],\"neighbor_halfedges\":[{}]{edges}}}",
+        star.root_colors,
+        list(&star.neighbor_colors),
+        list(&star.neighbor_halfedges)
+    )
+}
+
+/// Decodes a star from the JSON object written by [`star_to_json_string`]. Whitespace and the
+/// order of the keys do not matter; other keys are ignored.
+pub fn star_from_json_string(s: &str) -> Result<Star, StarParseError> {
+    parse_json(s)
+        .and_then(|v| star_from_json_value(&v))
+        .map_err(|_| StarParseError::Malformed(s.to_string()))
+}
+
+/// Decodes a star from a parsed JSON object with the fields of [`star_to_json_string`], e.g.
+/// one embedded in a report. Checks that the neighbor vectors have the same length and that
+/// the neighbor edges are in range, but not the other invariants of [`Star::validate`].
+pub fn star_from_json_value(v: &JsonValue) -> Result<Star, String> {
+    fn integer(x: &JsonValue) -> Result<usize, String> {
+        x.as_f64()
+            .filter(|&x| x >= 0.0 && x as usize as f64 == x)
+            .map(|x| x as usize)
+            .ok_or_else(|| "expected a nonnegative integer".to_string())
+    }
+    fn byte(x: &JsonValue) -> Result<u8, String> {
+        u8::try_from(integer(x)?).map_err(|_| "expected an integer in 0..=255".to_string())
+    }
+    fn array(x: &JsonValue) -> Result<&[JsonValue], String> {
+        x.as_array().ok_or_else(|| "expected an array".to_string())
+    }
+    let field = |key: &str| v.get(key).ok_or_else(|| format!("missing field \"{key}\""));
+    let bytes =
+        |key: &str| -> Result<Vec<u8>, String> { array(field(key)?)?.iter().map(byte).collect() };
+
+    let neighbor_colors = bytes("neighbor_colors")?;
+    let neighbor_halfedges = bytes("neighbor_halfedges")?;
+    if neighbor_colors.len() != neighbor_halfedges.len() {
+        return Err(StarError::LengthMismatch {
+            colors: neighbor_colors.len(),
+            halfedges: neighbor_halfedges.len(),
+        }
+        .to_string());
+    }
+    let neighbor_adjacency = match v.get("neighbor_edges") {
+        None => None,
+        Some(edges) => {
+            let edges = array(edges)?
+                .iter()
+                .map(|edge| match array(edge)? {
+                    [i, j] => Ok((integer(i)?, integer(j)?)),
+                    _ => Err("expected an edge [i,j]".to_string()),
+                })
+                .collect::<Result<Vec<_>, String>>()?;
+            let adjacency = NeighborAdjacency::try_from_edges(neighbor_colors.len(), &edges)
+                .ok_or_else(|| StarError::NeighborEdgesMismatch.to_string())?;
+            Some(Box::new(adjacency))
+        }
+    };
+    Ok(Star {
+        root_colors: byte(field("root_colors")?)?,
+        neighbor_colors,
+        neighbor_halfedges,
+        neighbor_adjacency,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folds_stream_the_enumeration() {
+        let stars = generate_stars(3);
+        let count = fold_stars(3, 0usize, |n, _| n + 1);
+        assert_eq!(count, stars.len());
+        let max_halfedges =
+            |m: u8, star: Star| m.max(star.neighbor_halfedges.iter().copied().max().unwrap_or(0));
+        assert_eq!(fold_stars(3, 0, max_halfedges), 3);
+
+        #[cfg(feature = "parallel")]
+        for config in [
+            EnumerationConfig::default(),
+            EnumerationConfig {
+                colors: 3,
+                max_degree: Some(4),
+                ..EnumerationConfig::default()
+            },
+        ] {
+            let stars = generate_stars_with_config(3, &config);
+            for threads in [1, 4, 64] {
+                let collected = fold_stars_par(
+                    3,
+                    &config,
+                    threads,
+                    Vec::new,
+                    |mut v, star| {
+                        v.push(star);
+                        v
+                    },
+                    |mut a, b| {
+                        a.extend(b);
+                        a
+                    },
+                );
+                assert_eq!(collected, stars);
+            }
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn sub_shards_split_the_enumeration_evenly() {
+        let config = EnumerationConfig::default();
+        let size = |(shards, halfedges): (Vec<EnumerationShard>, RangeInclusive<usize>)| {
+            let mut stars = Vec::new();
+            let mut sizes = Vec::new();
+            for shard in &shards {
+                let before = stars.len();
+                visit_shard(4, &config, halfedges.clone(), shard, &mut |star| {
+                    stars.push(star)
+                });
+                sizes.push(stars.len() - before);
+            }
+            (stars, sizes.into_iter().max().unwrap())
+        };
+        let (stars, largest_shard) = size(enumeration_shards(4, &config));
+        let (sub_stars, largest_sub_shard) = size(enumeration_sub_shards(4, &config));
+        assert_eq!(sub_stars, stars);
+        assert!(largest_shard * 100 > stars.len() * 4);
+        assert!(largest_sub_shard * 100 < stars.len());
+    }
+
+    #[test]
+    fn validate_reports_broken_invariants() {
+        let star = Star {
+            root_colors: 0b0011,
+            neighbor_colors: vec![0b0011, 0b0100],
+            neighbor_halfedges: vec![2, 2],
+            neighbor_adjacency: None,
+        };
+        assert_eq!(star.validate(), Ok(()));
+
+        let mut empty = star.clone();
+        empty.neighbor_colors[1] = 0;
+        assert_eq!(
+            empty.validate(),
+            Err(StarError::EmptyList { vertex: Some(1) })
+        );
+
+        let config = EnumerationConfig {
+            max_degree: Some(3),
+            halfedge_bounds: vec!["2:1".parse().unwrap()],
+            ..EnumerationConfig::default()
+        };
+        let bound = config.halfedge_bounds[0];
+        assert_eq!(
+            star.validate_with_config(&config),
+            Err(StarError::HalfedgeBoundViolated(bound))
+        );
+        let mut high = star.clone();
+        high.neighbor_halfedges[0] = 3;
+        assert_eq!(
+            high.validate_with_config(&config),
+            Err(StarError::TooManyHalfedges {
+                neighbor: 0,
+                halfedges: 3,
+                max: 2
+            })
+        );
+
+        let mut wide = star.clone();
+        wide.neighbor_colors[1] = 0b1_0001;
+        assert_eq!(wide.validate(), Ok(()));
+        assert_eq!(
+            wide.validate_with_colors(NUM_COLORS),
+            Err(StarError::ColorsOutOfRange {
+                vertex: Some(1),
+                colors: 0b1_0001
+            })
+        );
+
+        let crowded = Star::builder()
+            .root(0b0011)
+            .neighbors(0b0011, 2, MAX_NEIGHBORS + 1)
+            .build();
+        assert_eq!(
+            crowded,
+            Err(StarError::TooManyNeighbors {
+                neighbors: 256,
+                max: 255
+            })
+        );
+        let full = Star::builder()
+            .root(0b0011)
+            .neighbors(0b0011, 2, MAX_NEIGHBORS)
+            .build();
+        assert!(full.is_ok());
+
+        let mut short = star;
+        short.neighbor_halfedges.pop();
+        assert_eq!(
+            short.validate(),
+            Err(StarError::LengthMismatch {
+                colors: 2,
+                halfedges: 1
+            })
+        );
+        assert_eq!(
+            short.validate().unwrap_err().to_string(),
+            "2 neighbor color lists but 1 halfedge counts"
+        );
+    }
+
+    #[test]
+    fn completions_are_the_stars_extending_the_partial_star() {
+        let partial = Star {
+            root_colors: 0b0111,
+            neighbor_colors: vec![0b0011, 0b0011],
+            neighbor_halfedges: vec![3, 3],
+            neighbor_adjacency: None,
+        };
+        let contains_partial = |star: &Star| {
+            let count = |s: &Star| {
+                s.neighbor_colors
+                    .iter()
+                    .zip(s.neighbor_halfedges.iter())
+                    .filter(|&(&c, &h)| (c, h) == (0b0011, 3))
+                    .count()
+            };
+            star.root_colors == partial.root_colors && count(star) >= 2
+        };
+        let expected: Vec<Star> = generate_stars(3)
+            .into_iter()
+            .filter(contains_partial)
+            .collect();
+        assert_eq!(generate_star_completions(&partial, 3), Ok(expected.clone()));
+        let full = &expected[0];
+        assert_eq!(generate_star_completions(full, 3), Ok(vec![full.clone()]));
+        // Neighbors with 3 halfedges do not occur in stars of degree 2.
+        assert_eq!(generate_star_completions(&partial, 2), Ok(vec![]));
+        assert_eq!(
+            generate_star_completions(&partial, 300),
+            Err(CountError::TooManyHalfedges { halfedges: 300 })
+        );
+
+        let mut unreachable = partial;
+        unreachable.neighbor_halfedges[0] = 4;
+        assert_eq!(generate_star_completions(&unreachable, 3), Ok(vec![]));
+    }
+
+    #[test]
+    fn completions_follow_the_colors_and_halfedges_of_the_config() {
+        let partial = Star {
+            root_colors: 0b0011,
+            neighbor_colors: vec![0b0011],
+            neighbor_halfedges: vec![1],
+            neighbor_adjacency: None,
+        };
+        let contains_partial = |star: &Star| {
+            star.root_colors == partial.root_colors
+                && star
+                    .neighbor_colors
+                    .iter()
+                    .zip(star.neighbor_halfedges.iter())
+                    .any(|(&c, &h)| (c, h) == (0b0011, 1))
+        };
+        for config in [
+            EnumerationConfig {
+                colors: 5,
+                min_halfedges: 1,
+                max_halfedges: Some(3),
+                ..EnumerationConfig::default()
+            },
+            EnumerationConfig {
+                colors: 5,
+                min_halfedges: 1,
+                halfedge_bounds: vec!["3-:1".parse().unwrap()],
+                orbit_representatives: true,
+                ..EnumerationConfig::default()
+            },
+        ] {
+            let expected: Vec<Star> = generate_stars_with_config(3, &config)
+                .into_iter()
+                .filter(contains_partial)
+                .collect();
+            // Lists with color 4 only occur with more than 4 colors.
+            assert!(
+                config.orbit_representatives
+                    || expected
+                        .iter()
+                        .any(|s| s.neighbor_colors.contains(&0b10001))
+            );
+            assert_eq!(
+                generate_star_completions_with_config(&partial, 3, &config),
+                Ok(expected)
+            );
+        }
+        // The default config has no neighbors with 1 halfedge.
+        assert_eq!(generate_star_completions(&partial, 3), Ok(vec![]));
+    }
+
+    #[test]
+    fn max_degree_restricts_the_enumeration() {
+        for (degree, max_degree) in [(3, 3), (3, 4), (3, 5), (4, 4)] {
+            let expected: Vec<Star> = generate_stars(degree)
+                .into_iter()
+                .filter(|s| {
+                    s.neighbor_halfedges
+                        .iter()
+                        .all(|&h| (h as usize) < max_degree)
+                })
+                .collect();
+            let config = EnumerationConfig {
+                max_degree: Some(max_degree),
+                ..EnumerationConfig::default()
+            };
+            assert_eq!(generate_stars_with_config(degree, &config), expected);
+        }
+        let cubic = EnumerationConfig {
+            max_degree: Some(3),
+            ..EnumerationConfig::default()
+        };
+        assert!(generate_stars_with_config(4, &cubic).is_empty());
+    }
+
+    #[test]
+    fn halfedge_ranges_widen_and_narrow_the_enumeration() {
+        let pendant = EnumerationConfig {
+            min_halfedges: 0,
+            ..EnumerationConfig::default()
+        };
+        assert_eq!(pendant.halfedge_range(3), 0..=3);
+        let stars = generate_stars_with_config(3, &pendant);
+        assert!(stars.iter().any(|s| s.neighbor_halfedges.contains(&0)));
+        let default: Vec<Star> = stars
+            .iter()
+            .filter(|s| s.neighbor_halfedges.iter().all(|&h| h >= 2))
+            .cloned()
+            .collect();
+        assert_eq!(default, generate_stars(3));
+        assert_eq!(
+            count_stars_with_config(3, &pendant),
+            Ok(stars.len() as u128)
+        );
+        // Pendant neighbors fit graphs of maximum degree 2, unlike the default ones.
+        let paths = EnumerationConfig {
+            max_degree: Some(2),
+            ..pendant.clone()
+        };
+        assert_eq!(paths.halfedge_range(2), 0..=1);
+        assert!(!generate_stars_with_config(2, &paths).is_empty());
+        let default_paths = EnumerationConfig {
+            max_degree: Some(2),
+            ..EnumerationConfig::default()
+        };
+        assert!(generate_stars_with_config(2, &default_paths).is_empty());
+
+        let narrow = EnumerationConfig {
+            max_halfedges: Some(3),
+            ..EnumerationConfig::default()
+        };
+        let expected: Vec<Star> = generate_stars(4)
+            .into_iter()
+            .filter(|s| s.neighbor_halfedges.iter().all(|&h| h <= 3))
+            .collect();
+        assert_eq!(generate_stars_with_config(4, &narrow), expected);
+        let star = Star {
+            root_colors: 0b0011,
+            neighbor_colors: vec![0b0011],
+            neighbor_halfedges: vec![4],
+            neighbor_adjacency: None,
+        };
+        assert!(star.validate_with_config(&narrow).is_err());
+
+        for (min, max) in [(3, 2), (0, 16)] {
+            let config = EnumerationConfig {
+                min_halfedges: min,
+                max_halfedges: Some(max),
+                ..EnumerationConfig::default()
+            };
+            assert!(config.validate().is_err(), "{min}-{max}");
+        }
+
+        // Without a maximum, the degree bounds the halfedges, and with a maximum degree so
+        // does that.
+        let config = EnumerationConfig::default();
+        assert!(config.validate_for_degree(15).is_ok());
+        assert!(config.validate_for_degree(16).is_err());
+        let bounded = [
+            EnumerationConfig {
+                max_halfedges: Some(3),
+                ..config.clone()
+            },
+            EnumerationConfig {
+                max_degree: Some(16),
+                ..config.clone()
+            },
+        ];
+        for config in bounded {
+            assert!(config.validate_for_degree(16).is_ok());
+        }
+    }
+
+    #[test]
+    fn fewer_colors_shrink_the_lists() {
+        assert_eq!(root_color_subsets(4), ROOT_COLOR_SUBSETS.to_vec());
+        assert_eq!(color_subsets_ge2(4), COLOR_SUBSETS_GE2.to_vec());
+        assert_eq!(root_color_subsets(3), vec![0b0111, 0b0011]);
+
+        let config = EnumerationConfig {
+            colors: 3,
+            ..EnumerationConfig::default()
+        };
+        let stars = generate_stars_with_config(3, &config);
+        assert!(stars.iter().all(|s| s.validate_with_colors(3).is_ok()));
+        let expected: Vec<Star> = generate_stars(3)
+            .into_iter()
+            .filter(|s| s.validate_with_colors(3).is_ok())
+            .collect();
+        assert_eq!(stars, expected);
+        assert!(
+            generate_stars(3)
+                .iter()
+                .any(|s| s.validate_with_colors(3).is_err())
+        );
+        for (colors, valid) in [(1, false), (5, true), (8, true), (9, false)] {
+            let config = EnumerationConfig {
+                colors,
+                ..config.clone()
+            };
+            assert_eq!(config.validate().is_ok(), valid, "{colors}");
+        }
+    }
+
+    #[test]
+    fn halfedge_bounds_prune_the_enumeration() {
+        let bound: HalfedgeBound = "3-:1".parse().unwrap();
+        assert_eq!(
+            bound,
+            HalfedgeBound {
+                min: 3,
+                max: u8::MAX,
+                max_count: 1
+            }
+        );
+        assert_eq!(bound.to_string(), "3-:1");
+        assert_eq!("2:0".parse::<HalfedgeBound>().unwrap().max, 2);
+        assert!("3-4".parse::<HalfedgeBound>().is_err());
+
+        let config = EnumerationConfig {
+            halfedge_bounds: vec![bound],
+            ..EnumerationConfig::default()
+        };
+        let expected: Vec<Star> = generate_stars(3)
+            .into_iter()
+            .filter(|s| s.neighbor_halfedges.iter().filter(|&&h| h >= 3).count() <= 1)
+            .collect();
+        assert!(expected.len() < generate_stars(3).len());
+        assert_eq!(generate_stars_with_config(3, &config), expected);
+    }
+
+    #[test]
+    fn star_strings_round_trip_in_both_versions() {
+        for star in generate_stars(2) {
+            let v1 = star_to_string(&star).unwrap();
+            let v2 = star_to_string_v2(&star).unwrap();
+            assert!(v2.starts_with("v2:star_2_"));
+            assert_eq!(star_from_string(&v1), Ok(star.clone()));
+            assert_eq!(star_from_string(&v2), Ok(star));
+        }
+    }
+
+    #[test]
+    fn is_isomorphic_returns_witness() {
+        let star = Star {
+            root_colors: 0b0011,
+            neighbor_colors: vec![0b0110, 0b1001],
+            neighbor_halfedges: vec![2, 3],
+            neighbor_adjacency: None,
+        };
+        // Swap colors 0 and 2, then reverse the neighbors.
+        let perm = ColorPermutation::new(&[2, 1, 0, 3]);
+        let mut other = star.permute_colors(&perm);
+        other.neighbor_colors.reverse();
+        other.neighbor_halfedges.reverse();
+
+        let witness = star.is_isomorphic(&other).expect("stars are isomorphic");
+        assert_eq!(witness.apply(star.root_colors), other.root_colors);
+        let matching = star.neighbor_matching(&other, &witness).unwrap();
+        assert_eq!(matching, vec![1, 0]);
+        assert_eq!(other.is_isomorphic(&star), Some(witness.inverse()));
+
+        let mut different = other.clone();
+        different.neighbor_halfedges[0] = 4;
+        assert_eq!(star.is_isomorphic(&different), None);
+        assert_eq!(perm.then(&perm.inverse()), ColorPermutation::IDENTITY);
+    }
+
+    #[test]
+    fn automorphisms_form_a_group() {
+        // Colors 0 and 1 can be swapped, and so can 2 and 3.
+        let star = Star {
+            root_colors: 0b0011,
+            neighbor_colors: vec![0b1111, 0b1111],
+            neighbor_halfedges: vec![2, 2],
+            neighbor_adjacency: None,
+        };
+        let group = star.automorphisms();
+        assert_eq!(group.len(), 4);
+        assert!(group.contains(&ColorPermutation::IDENTITY));
+        for a in group.iter() {
+            assert!(group.contains(&a.inverse()));
+            for b in group.iter() {
+                assert!(group.contains(&a.then(b)));
+            }
+        }
+
+        // Distinguishing neighbor halfedges breaks the 2-3 symmetry.
+        let asymmetric = Star {
+            root_colors: 0b0011,
+            neighbor_colors: vec![0b0111, 0b1011],
+            neighbor_halfedges: vec![2, 3],
+            neighbor_adjacency: None,
+        };
+        assert_eq!(
+            asymmetric.automorphisms(),
+            vec![
+                ColorPermutation::IDENTITY,
+                ColorPermutation::new(&[1, 0, 2, 3])
+            ]
+        );
+    }
+
+    #[test]
+    fn star_iter_matches_the_recursive_enumeration() {
+        let configs = [
+            EnumerationConfig::default(),
+            EnumerationConfig {
+                colors: 3,
+                max_degree: Some(4),
+                halfedge_bounds: vec!["3-:1".parse().unwrap()],
+                ..EnumerationConfig::default()
+            },
+            EnumerationConfig {
+                orbit_representatives: true,
+                ..EnumerationConfig::default()
+            },
+        ];
+        for config in configs.iter() {
+            for degree in 0..=3 {
+                let recursive = fold_stars_with_config(degree, config, Vec::new(), |mut v, s| {
+                    v.push(s);
+                    v
+                });
+                let lazy: Vec<Star> = StarIter::new(degree, config).collect();
+                assert_eq!(lazy, recursive, "{config:?} {degree}");
+            }
+        }
+    }
+
+    #[test]
+    fn orbit_representatives_cover_every_orbit_once() {
+        for k in [3, 4, 5] {
+            let all = EnumerationConfig {
+                colors: k,
+                ..EnumerationConfig::default()
+            };
+            let reps = EnumerationConfig {
+                orbit_representatives: true,
+                ..all.clone()
+            };
+            let stars = generate_stars_with_config(2, &all);
+            let representatives = generate_stars_with_config(2, &reps);
+            assert!(representatives.len() < stars.len());
+            for star in stars.iter() {
+                let mut rep = star.orbit_representative(k);
+                assert_eq!(rep.orbit_representative(k), rep);
+                rep.canonicalize();
+                let matches: Vec<&Star> = representatives
+                    .iter()
+                    .filter(|r| {
+                        let mut r = (*r).clone();
+                        r.canonicalize();
+                        r == rep
+                    })
+                    .collect();
+                assert_eq!(matches.len(), 1, "{star:?}");
+            }
+            // The enumeration fixes the root lists, so the orbits within it are those of the
+            // renamings that fix the root list.
+            for degree in 0..=(7 - k as usize).min(3) {
+                let total: usize = generate_stars_with_config(degree, &reps)
+                    .iter()
+                    .map(|r| r.enumeration_multiplicity(k))
+                    .sum();
+                assert_eq!(
+                    total as u128,
+                    count_stars_with_config(degree, &all).unwrap()
+                );
+            }
+        }
+        let star = Star {
+            root_colors: 0b0011,
+            neighbor_colors: vec![0b0011],
+            neighbor_halfedges: vec![2],
+            neighbor_adjacency: None,
+        };
+        assert_eq!(star.orbit_size(4), 6);
+        assert_eq!(star.orbit_size(2), 1);
+        // Only the renamings of {2,3} keep the root list, and they fix the star.
+        assert_eq!(star.enumeration_multiplicity(4), 1);
+    }
+
+    #[test]
+    fn stars_with_eight_colors() {
+        let config = EnumerationConfig {
+            colors: 8,
+            ..EnumerationConfig::default()
+        };
+        let stars = generate_stars_with_config(1, &config);
+        assert_eq!(
+            stars.len() as u128,
+            count_stars_with_config(1, &config).unwrap()
+        );
+        assert!(
+            stars
+                .iter()
+                .all(|s| s.validate_with_config(&config).is_ok())
+        );
+
+        let star = Star {
+            root_colors: 0b1000_0011,
+            neighbor_colors: vec![0b1100_0000, 0b0000_0011],
+            neighbor_halfedges: vec![2, 3],
+            neighbor_adjacency: None,
+        };
+        assert_eq!(star.color_count(), 8);
+        let label = star_label(&star);
+        assert!(label.starts_with('{'), "{label}");
+        assert_eq!(star_from_string(&label), Ok(star.clone()));
+
+        let renamed = star.permute_colors(&ColorPermutation::new(&[7, 1, 2, 3, 4, 5, 6, 0]));
+        assert_eq!(renamed.root_colors, 0b1000_0011);
+        assert_eq!(renamed.neighbor_colors, vec![0b0100_0001, 0b1000_0010]);
+        assert_eq!(star_type_key(&renamed), star_type_key(&star));
+        assert!(star.is_isomorphic(&renamed).is_some());
+        // Colors 2 to 5 are unused, and colors 0 and 1 are interchangeable.
+        assert_eq!(star.automorphisms().len(), 2 * 24);
+    }
+
+    #[test]
+    fn dedup_stars_ignores_the_neighbor_order() {
+        let star = Star {
+            root_colors: 0b0111,
+            neighbor_colors: vec![0b0110, 0b0011, 0b0011],
+            neighbor_halfedges: vec![2, 3, 2],
+            neighbor_adjacency: None,
+        };
+        let mut canonical = star.clone();
+        canonical.canonicalize();
+        assert_eq!(canonical.neighbor_colors, vec![0b0011, 0b0011, 0b0110]);
+        assert_eq!(canonical.neighbor_halfedges, vec![2, 3, 2]);
+
+        let reordered = Star {
+            neighbor_colors: vec![0b0011, 0b0110, 0b0011],
+            neighbor_halfedges: vec![3, 2, 2],
+            ..star.clone()
+        };
+        let renamed = star.permute_colors(&ColorPermutation::new(&[1, 0, 2, 3]));
+        let deduped = dedup_stars([star, reordered, renamed.clone()]);
+        assert_eq!(deduped.len(), 2);
+        assert_eq!(deduped[0], canonical);
+        assert!(deduped[1].is_isomorphic(&renamed).is_some());
+    }
+
+    #[test]
+    fn neighbor_adjacency_patterns_and_propagation() {
+        let star = Star {
+            root_colors: 0b0111,
+            neighbor_colors: vec![0b0010, 0b0011, 0b0011],
+            neighbor_halfedges: vec![2, 2, 1],
+            neighbor_adjacency: None,
+        };
+        let path = NeighborAdjacency::from_edges(3, &[(0, 1), (1, 2)]);
+        assert_eq!(path.edges().collect::<Vec<_>>(), vec![(0, 1), (1, 2)]);
+        assert!(path.fits(&star));
+        assert_eq!(path.neighbor_triangles(), 0);
+        // Neighbor 0 removes 1 from the root and fixes neighbor 1 to {0}, which removes 0 from
+        // the root and fixes neighbor 2 to {1}.
+        let propagated = Star {
+            neighbor_adjacency: Some(Box::new(path.clone())),
+            ..star.clone()
+        }
+        .propagate();
+        assert_eq!(propagated.root_colors, 0b0100);
+        assert_eq!(propagated.neighbor_colors, vec![0b0010, 0b0001, 0b0010]);
+        // Without the edges, only the root list shrinks.
+        assert_eq!(star.propagate().root_colors, 0b0101);
+        assert_eq!(star.propagate().neighbor_colors, star.neighbor_colors);
+
+        // Neighbor 2 has room for one neighbor edge, and neighbors 1 and 2 share a type only
+        // for equal halfedges: the patterns are {}, {01}, {02}, {12}, {01,02}, {01,12}.
+        let patterns = NeighborAdjacency::all_consistent(&star, 3);
+        assert_eq!(patterns.len(), 6);
+        assert!(patterns.iter().all(|a| a.fits(&star)));
+        let twins = Star {
+            neighbor_halfedges: vec![2, 2, 2],
+            ..star
+        };
+        let triangle = NeighborAdjacency::from_edges(3, &[(0, 1), (0, 2), (1, 2)]);
+        assert_eq!(triangle.neighbor_triangles(), 1);
+        // {01} and {02} are the same pattern, as are {01,12} and {02,12}.
+        assert_eq!(
+            NeighborAdjacency::all_consistent(&twins, 3),
+            vec![
+                NeighborAdjacency::new(3),
+                NeighborAdjacency::from_edges(3, &[(1, 2)]),
+                NeighborAdjacency::from_edges(3, &[(0, 1)]),
+                NeighborAdjacency::from_edges(3, &[(0, 1), (1, 2)]),
+                NeighborAdjacency::from_edges(3, &[(0, 1), (0, 2)]),
+                triangle,
+            ]
+        );
+    }
+
+    #[test]
+    fn count_stars_matches_the_enumeration() {
+        for degree in 0..=3 {
+            for config in [
+                EnumerationConfig::default(),
+                EnumerationConfig {
+                    colors: 3,
+                    max_degree: Some(3),
+                    ..EnumerationConfig::default()
+                },
+            ] {
+                assert_eq!(
+                    count_stars_with_config(degree, &config),
+                    Ok(generate_stars_with_config(degree, &config).len() as u128),
+                    "{degree} {config:?}"
+                );
+            }
+        }
+        assert_eq!(multisets(3, 2), Ok(6));
+        assert_eq!(multisets(0, 0), Ok(1));
+        assert_eq!(multisets(0, 2), Ok(0));
+        assert_eq!(multisets(1000, 100), Err(CountError::Overflow));
+        // Huge degrees are errors instead of saturated counts or truncated halfedge ranges.
+        let bounded = EnumerationConfig {
+            max_halfedges: Some(15),
+            ..EnumerationConfig::default()
+        };
+        assert_eq!(
+            count_stars_with_config(1000, &bounded),
+            Err(CountError::Overflow)
+        );
+        assert_eq!(
+            count_stars_with_config(1000, &EnumerationConfig::default()),
+            Err(CountError::TooManyHalfedges { halfedges: 1000 })
+        );
+    }
+
+    #[test]
+    fn star_builder_checks_invariants() {
+        let star = Star::builder()
+            .root(0b0111)
+            .neighbor(0b0011, 2)
+            .neighbors(0b0110, 3, 2)
+            .build();
+        assert_eq!(
+            star,
+            Ok(Star {
+                root_colors: 0b0111,
+                neighbor_colors: vec![0b0011, 0b0110, 0b0110],
+                neighbor_halfedges: vec![2, 3, 3],
+                neighbor_adjacency: None,
+            })
+        );
+        assert_eq!(
+            Star::builder().neighbor(0b0011, 2).build(),
+            Err(StarError::EmptyList { vertex: None })
+        );
+        let config = EnumerationConfig {
+            colors: 3,
+            ..EnumerationConfig::default()
+        };
+        assert_eq!(
+            Star::builder()
+                .root(0b0011)
+                .neighbor(0b1001, 2)
+                .build_with_config(&config),
+            Err(StarError::ColorsOutOfRange {
+                vertex: Some(0),
+                colors: 0b1001
+            })
+        );
+        // Without a configuration, any of the MAX_COLORS colors is allowed.
+        let wide = Star::builder().root(0b1000_0001).neighbor(0b0110_0000, 2);
+        assert!(wide.clone().build().is_ok());
+        assert_eq!(
+            wide.build_with_config(&EnumerationConfig::default()),
+            Err(StarError::ColorsOutOfRange {
+                vertex: None,
+                colors: 0b1000_0001
+            })
+        );
+    }
+
+    #[test]
+    fn star_json_round_trips() {
+        let star = Star {
+            root_colors: 0b0111,
+            neighbor_colors: vec![0b1001, 0b1010],
+            neighbor_halfedges: vec![2, 3],
+            neighbor_adjacency: None,
+        };
+        let json = star_to_json_string(&star);
+        assert_eq!(
+            json,
+            "{\"root_colors\":7,\"neighbor_colors\":[9,10],\"neighbor_halfedges\":[2,3]}"
+        );
+        assert_eq!(star_from_json_string(&json), Ok(star.clone()));
+        let reordered =
+            "{ \"neighbor_halfedges\": [2, 3], \"neighbor_colors\": [9, 10], \"root_colors\": 7 }";
+        assert_eq!(star_from_json_string(reordered), Ok(star.clone()));
+        assert!(star_from_json_string("{\"root_colors\":7}").is_err());
+        // Malformed JSON, and values a substring search would have accepted.
+        for malformed in [
+            "{\"root_colors\":7,\"neighbor_colors\":[9,10],\"neighbor_halfedges\":[2,3]",
+            "{\"root_colors\":7,,\"neighbor_colors\":[9,10],\"neighbor_halfedges\":[2,3]}",
+            "{\"root_colors\":\"7\",\"neighbor_colors\":[9,10],\"neighbor_halfedges\":[2,3]}",
+            "{\"root_colors\":7,\"neighbor_colors\":[9,10.5],\"neighbor_halfedges\":[2,3]}",
+            "{\"root_colors\":7,\"neighbor_colors\":[9,10],\"neighbor_halfedges\":[2,3]} x",
+        ] {
+            assert!(star_from_json_string(malformed).is_err(), "{malformed}");
+        }
+        // A key that contains another key's name does not confuse the parser.
+        let nested = "{\"note\":{\"root_colors\":1},\"neighbor_halfedges\":[2,3],\
+            \"neighbor_colors\":[9,10],\"root_colors\":7}";
+        assert_eq!(star_from_json_string(nested), Ok(star.clone()));
+
+        let edge = Star {
+            neighbor_adjacency: Some(Box::new(NeighborAdjacency::from_edges(2, &[(0, 1)]))),
+            ..star
+        };
+        let json = star_to_json_string(&edge);
+        assert_eq!(star_from_json_string(&json), Ok(edge));
+        assert!(star_from_json_string(&json.replace("[[0,1]]", "[[0,2]]")).is_err());
+    }
+
+    #[test]
+    fn star_from_string_rejects_corruption() {
+        let star = Star {
+            root_colors: 0b0111,
+            neighbor_colors: vec![0b1001, 0b1010, 0b1100],
+            neighbor_halfedges: vec![2, 3, 3],
+            neighbor_adjacency: None,
+        };
+        let v2 = star_to_string_v2(&star).unwrap();
+
+        let corrupted = v2.replacen("79ac", "79ab", 1);
+        assert!(matches!(
+            star_from_string(&corrupted),
+            Err(StarParseError::ChecksumMismatch { .. })
+        ));
+        let truncated = &v2[..v2.len() - 6];
+        assert!(star_from_string(truncated).is_err());
+        assert_eq!(
+            star_from_string("v3:star_1_11_02"),
+            Err(StarParseError::UnknownVersion("v3".to_string()))
+        );
+        assert!(matches!(
+            star_from_string("star_3_79a_0233"),
+            Err(StarParseError::Malformed(_))
+        ));
+        assert!(star_from_string("star_+3_79ac_0233").is_err());
+        assert!(star_from_string("star_03_79ac_0233").is_err());
+        assert_eq!(
+            star_from_string("star_0_7_0"),
+            Ok(Star::builder().root(7).build().unwrap())
+        );
+
+        // Counts beyond one hex digit would not decode again, but their label does.
+        let wide = Star {
+            neighbor_halfedges: vec![2, 3, 16],
+            ..star.clone()
+        };
+        assert_eq!(
+            star_to_string(&wide),
+            Err(StarError::TooManyHalfedges {
+                neighbor: 2,
+                halfedges: 16,
+                max: MAX_HALFEDGES
+            })
+        );
+        assert!(star_to_string_v2(&wide).is_err());
+        assert_eq!(star_label(&star), star_to_string(&star).unwrap());
+        assert_eq!(star_from_string(&star_label(&wide)), Ok(wide));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trips_and_validates_stars() {
+        let star = Star {
+            root_colors: 0b0011,
+            neighbor_colors: vec![0b0011, 0b0110],
+            neighbor_halfedges: vec![2, 3],
+            neighbor_adjacency: None,
+        };
+        let json = serde_json::to_string(&star).unwrap();
+        assert_eq!(serde_json::from_str::<Star>(&json).unwrap(), star);
+
+        let edge = Star {
+            neighbor_adjacency: Some(Box::new(NeighborAdjacency::from_edges(2, &[(0, 1)]))),
+            ..star
+        };
+        let json = serde_json::to_string(&edge).unwrap();
+        assert!(json.ends_with(",\"neighbor_edges\":[[0,1]]}"), "{json}");
+        assert_eq!(json, star_to_json_string(&edge));
+        assert_eq!(serde_json::from_str::<Star>(&json).unwrap(), edge);
+        let loop_edge = json.replace("[[0,1]]", "[[1,1]]");
+        assert!(serde_json::from_str::<Star>(&loop_edge).is_err());
+
+        let mismatch = r#"{"root_colors":3,"neighbor_colors":[3,6],"neighbor_halfedges":[2]}"#;
+        assert!(serde_json::from_str::<Star>(mismatch).is_err());
+        let wide = r#"{"root_colors":19,"neighbor_colors":[],"neighbor_halfedges":[]}"#;
+        assert_eq!(serde_json::from_str::<Star>(wide).unwrap().root_colors, 19);
+        let empty = r#"{"root_colors":0,"neighbor_colors":[],"neighbor_halfedges":[]}"#;
+        assert!(serde_json::from_str::<Star>(empty).is_err());
+    }
 }
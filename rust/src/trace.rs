@@ -0,0 +1,552 @@
+use alloc::boxed::Box;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::analysis::AnalysisConfig;
+use crate::colors::{fmt_colors, fmt_partition};
+use crate::explain::{BranchTree, branch_tree};
+use crate::ipc::{Reader, decode_star, encode_star};
+use crate::list_coloring_utils::{MaxDegree, NodeFeatures};
+use crate::reductions::{Reduction, reduction_by_name, select_reductions};
+use crate::report::{CaseReport, case_report};
+use crate::star_utils::{Star, StarError, star_label};
+
+// Binary trace format, little-endian like the `ipc` protocol:
+// - the magic bytes `RTRC` and the format version `TRACE_VERSION`,
+// - the kind of the trace as `u8`: `KIND_EXPLAIN` or `KIND_ANALYSIS`,
+// - the 9 weights as `f64`, in the order of `NodeFeatures::to_array`,
+// - the maximum degree Δ of the degree buckets as `u8`,
+// - the most blocks of a partition as `u8`, 0 for no limit,
+// - `u8` number of reductions, and per reduction its name as `u8` length and UTF-8 bytes,
+// - `u32` number of events, then per event a tag byte and its fields:
+//   - `TAG_ENTER`: `u8` root colors, `u8` number of neighbors `n`, `n` bytes of neighbor
+//     colors, `n` bytes of neighbor halfedges,
+//   - `TAG_PARTITION`: `u8` number of blocks, the blocks, and the branching number as `f64`,
+//   - `TAG_BRANCH`: `u8` root block, `u8` number of reductions, and per reduction its name as
+//     `u8` length and UTF-8 bytes,
+//   - `TAG_LEAVE`: nothing.
+
+const MAGIC: &[u8; 4] = b"RTRC";
+const TRACE_VERSION: u8 = 3;
+const KIND_EXPLAIN: u8 = 0;
+const KIND_ANALYSIS: u8 = 1;
+const TAG_ENTER: u8 = 0;
+const TAG_PARTITION: u8 = 1;
+const TAG_BRANCH: u8 = 2;
+const TAG_LEAVE: u8 = 3;
+
+/// One decision of the analysis of a star, in the order [`branch_tree`] makes them, or
+/// [`record_case`] records them for a case of a report.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TraceEvent {
+    /// A node of the recursion starts with its star.
+    Enter(Star),
+    /// The node branches on its best partition.
+    Partition {
+        blocks: Vec<u8>,
+        branching_number: f64,
+    },
+    /// A branch of the partition: the block that became the root list and the reductions
+    /// applied to the branch, in order. The node of the reduced branch follows.
+    Branch {
+        root_block: u8,
+        reductions: Vec<String>,
+    },
+    /// The node and all its branches are done.
+    Leave,
+}
+
+impl core::fmt::Display for TraceEvent {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            TraceEvent::Enter(star) => write!(f, "enter {}", star_label(star)),
+            // The exact value, since replays compare branching numbers bit for bit.
+            TraceEvent::Partition {
+                blocks,
+                branching_number,
+            } => write!(
+                f,
+                "partition {} branching_number={branching_number:?}",
+                fmt_partition(blocks)
+            ),
+            TraceEvent::Branch {
+                root_block,
+                reductions,
+            } => {
+                let reductions = if reductions.is_empty() {
+                    "none".to_string()
+                } else {
+                    reductions.join(", ")
+                };
+                write!(
+                    f,
+                    "branch root:={} reductions: {reductions}",
+                    fmt_colors(*root_block)
+                )
+            }
+            TraceEvent::Leave => write!(f, "leave"),
+        }
+    }
+}
+
+/// What a [`Trace`] records.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceKind {
+    /// The recursive [`branch_tree`] of one star, as `explain` shows it.
+    Explain,
+    /// The cases of an analysis report, one `Enter`/`Leave` group per case without
+    /// recursion, as `proof` computes them.
+    Analysis,
+}
+
+/// The decision path of an analysis under some weights, as a flat list of events.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Trace {
+    pub kind: TraceKind,
+    pub weights: NodeFeatures,
+    /// The degree buckets the stars were measured with.
+    pub max_degree: MaxDegree,
+    /// The most blocks of a partition, see [`AnalysisConfig::max_blocks`].
+    pub max_blocks: Option<usize>,
+    /// The names of the reductions applied to the branches, in order of priority.
+    pub reductions: Vec<String>,
+    pub events: Vec<TraceEvent>,
+}
+
+impl Trace {
+    /// A trace of `kind` with the given `events`, recorded under `weights` and `config`.
+    pub fn new(
+        kind: TraceKind,
+        weights: NodeFeatures,
+        config: &AnalysisConfig,
+        events: Vec<TraceEvent>,
+    ) -> Self {
+        Trace {
+            kind,
+            weights,
+            max_degree: config.max_degree(),
+            max_blocks: config.max_blocks,
+            reductions: config
+                .reduction_names()
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            events,
+        }
+    }
+
+    /// The reductions of `reductions`, see [`select_reductions`]. Fails on reductions that are
+    /// not proved unless `allow_unproved` is set; the names were checked when the trace was
+    /// built or decoded.
+    pub fn reductions(&self, allow_unproved: bool) -> Result<Vec<Box<dyn Reduction>>, String> {
+        let names: Vec<&str> = self.reductions.iter().map(String::as_str).collect();
+        select_reductions(&names, self.max_degree, allow_unproved)
+    }
+
+    /// The events of `tree` in preorder, with each branch before the node of its subtree.
+    /// `weights` and `config` are the ones the tree was built with.
+    pub fn from_branch_tree(
+        tree: &BranchTree,
+        weights: NodeFeatures,
+        config: &AnalysisConfig,
+    ) -> Self {
+        fn go(tree: &BranchTree, events: &mut Vec<TraceEvent>) {
+            events.push(TraceEvent::Enter(tree.star.clone()));
+            if let Some((blocks, branching_number)) = tree.partition.as_ref() {
+                events.push(TraceEvent::Partition {
+                    blocks: blocks.clone(),
+                    branching_number: *branching_number,
+                });
+            }
+            for edge in tree.children.iter() {
+                events.push(TraceEvent::Branch {
+                    root_block: edge.root_block,
+                    reductions: edge.reductions.iter().map(|r| r.to_string()).collect(),
+                });
+                go(&edge.subtree, events);
+            }
+            events.push(TraceEvent::Leave);
+        }
+
+        let mut events = Vec::new();
+        go(tree, &mut events);
+        Trace::new(TraceKind::Explain, weights, config, events)
+    }
+}
+
+/// Records the trace of the [`branch_tree`] of `star` under `weights` and `config`.
+pub fn record_trace(star: &Star, weights: NodeFeatures, config: &AnalysisConfig) -> Trace {
+    Trace::from_branch_tree(&branch_tree(star, weights, config), weights, config)
+}
+
+/// Where an analysis records its decisions, see
+/// [`analysis_report_traced`](crate::report::analysis_report_traced).
+pub trait TraceSink {
+    /// Whether the sink keeps the events; the analysis does not build them otherwise.
+    fn is_enabled(&self) -> bool {
+        true
+    }
+
+    fn record(&mut self, event: TraceEvent);
+}
+
+impl TraceSink for Vec<TraceEvent> {
+    fn record(&mut self, event: TraceEvent) {
+        self.push(event);
+    }
+}
+
+/// Discards every event.
+impl TraceSink for () {
+    fn is_enabled(&self) -> bool {
+        false
+    }
+
+    fn record(&mut self, _: TraceEvent) {}
+}
+
+/// Keeps the events if it holds a vector, and discards them otherwise.
+impl TraceSink for Option<Vec<TraceEvent>> {
+    fn is_enabled(&self) -> bool {
+        self.is_some()
+    }
+
+    fn record(&mut self, event: TraceEvent) {
+        if let Some(events) = self {
+            events.push(event);
+        }
+    }
+}
+
+/// Records the decisions of a case in `sink`: its star, its partition, and the root block and
+/// reductions of every branch, without the recursion of [`branch_tree`].
+pub fn record_case(case: &CaseReport, sink: &mut impl TraceSink) {
+    if !sink.is_enabled() {
+        return;
+    }
+    sink.record(TraceEvent::Enter(case.star.clone()));
+    sink.record(TraceEvent::Partition {
+        blocks: case.partition.clone(),
+        branching_number: case.branching_number,
+    });
+    for branch in case.branches.iter() {
+        sink.record(TraceEvent::Branch {
+            root_block: branch.root_block,
+            reductions: branch.reductions.clone(),
+        });
+    }
+    sink.record(TraceEvent::Leave);
+}
+
+/// Encodes a trace in the binary format described at the top of this module. Fails if a star
+/// cannot be encoded, see [`encode_request`](crate::ipc::encode_request).
+pub fn encode_trace(trace: &Trace) -> Result<Vec<u8>, StarError> {
+    fn encode_names(names: &[String], out: &mut Vec<u8>) {
+        out.push(names.len() as u8);
+        for name in names {
+            out.push(name.len() as u8);
+            out.extend_from_slice(name.as_bytes());
+        }
+    }
+
+    let mut out: Vec<u8> = MAGIC.to_vec();
+    out.push(TRACE_VERSION);
+    out.push(match trace.kind {
+        TraceKind::Explain => KIND_EXPLAIN,
+        TraceKind::Analysis => KIND_ANALYSIS,
+    });
+    for w in trace.weights.to_array() {
+        out.extend_from_slice(&w.to_le_bytes());
+    }
+    out.push(trace.max_degree.0 as u8);
+    out.push(trace.max_blocks.unwrap_or(0) as u8);
+    encode_names(&trace.reductions, &mut out);
+    out.extend_from_slice(&(trace.events.len() as u32).to_le_bytes());
+    for event in trace.events.iter() {
+        match event {
+            TraceEvent::Enter(star) => {
+                out.push(TAG_ENTER);
+                encode_star(star, &mut out)?;
+            }
+            TraceEvent::Partition {
+                blocks,
+                branching_number,
+            } => {
+                out.push(TAG_PARTITION);
+                out.push(blocks.len() as u8);
+                out.extend_from_slice(blocks);
+                out.extend_from_slice(&branching_number.to_le_bytes());
+            }
+            TraceEvent::Branch {
+                root_block,
+                reductions,
+            } => {
+                out.push(TAG_BRANCH);
+                out.push(*root_block);
+                encode_names(reductions, &mut out);
+            }
+            TraceEvent::Leave => out.push(TAG_LEAVE),
+        }
+    }
+    Ok(out)
+}
+
+/// Decodes a trace written by [`encode_trace`]. Returns `None` if the magic bytes or the
+/// version do not match, a reduction is unknown, or the trace is truncated or has trailing
+/// bytes.
+pub fn decode_trace(bytes: &[u8]) -> Option<Trace> {
+    fn decode_names(r: &mut Reader) -> Option<Vec<String>> {
+        let n = r.u8()? as usize;
+        let mut names = Vec::with_capacity(n);
+        for _ in 0..n {
+            let len = r.u8()? as usize;
+            names.push(String::from_utf8(r.take(len)?.to_vec()).ok()?);
+        }
+        Some(names)
+    }
+
+    let mut r = Reader { bytes };
+    if r.take(4)? != MAGIC || r.u8()? != TRACE_VERSION {
+        return None;
+    }
+    let kind = match r.u8()? {
+        KIND_EXPLAIN => TraceKind::Explain,
+        KIND_ANALYSIS => TraceKind::Analysis,
+        _ => return None,
+    };
+    let mut weights = [0.0; 9];
+    for w in weights.iter_mut() {
+        *w = r.f64()?;
+    }
+    let max_degree = MaxDegree(r.u8()? as usize);
+    let max_blocks = match r.u8()? {
+        0 => None,
+        b => Some(b as usize),
+    };
+    let reductions = decode_names(&mut r)?;
+    if reductions
+        .iter()
+        .any(|name| reduction_by_name(name, max_degree).is_none())
+    {
+        return None;
+    }
+    let count = r.u32()? as usize;
+    // Every event takes at least 1 byte, which bounds the allocation below.
+    if count > r.bytes.len() {
+        return None;
+    }
+    let mut events = Vec::with_capacity(count);
+    for _ in 0..count {
+        events.push(match r.u8()? {
+            TAG_ENTER => TraceEvent::Enter(decode_star(&mut r)?),
+            TAG_PARTITION => {
+                let n = r.u8()? as usize;
+                let blocks = r.take(n)?.to_vec();
+                TraceEvent::Partition {
+                    blocks,
+                    branching_number: r.f64()?,
+                }
+            }
+            TAG_BRANCH => TraceEvent::Branch {
+                root_block: r.u8()?,
+                reductions: decode_names(&mut r)?,
+            },
+            TAG_LEAVE => TraceEvent::Leave,
+            _ => return None,
+        });
+    }
+    r.bytes.is_empty().then_some(Trace {
+        kind,
+        weights: NodeFeatures::from_array(weights),
+        max_degree,
+        max_blocks,
+        reductions,
+        events,
+    })
+}
+
+/// The first event at which a replay differs from the recorded trace.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TraceDivergence {
+    pub index: usize,
+    /// The recorded event, or `None` if the replay has more events.
+    pub expected: Option<TraceEvent>,
+    /// The replayed event, or `None` if the replay ends early.
+    pub actual: Option<TraceEvent>,
+}
+
+impl core::fmt::Display for TraceDivergence {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let show = |event: &Option<TraceEvent>| match event {
+            Some(event) => event.to_string(),
+            None => "end of trace".to_string(),
+        };
+        write!(
+            f,
+            "event {}: recorded {}, replayed {}",
+            self.index,
+            show(&self.expected),
+            show(&self.actual)
+        )
+    }
+}
+
+/// Runs the analysis of `trace` again under its weights, degree buckets, limit on the blocks
+/// and reductions, and compares the decisions with the recorded ones, branching numbers bit
+/// for bit: the [`branch_tree`] of its first star for an explain trace, and the case of every
+/// star it enters for an analysis trace. Returns the number of events if all agree.
+///
+/// `reductions` are the objects of [`Trace::reductions`].
+pub fn replay_trace(
+    trace: &Trace,
+    reductions: &[&dyn Reduction],
+) -> Result<usize, Box<TraceDivergence>> {
+    let config = AnalysisConfig {
+        max_blocks: trace.max_blocks,
+        reductions,
+        ..AnalysisConfig::for_max_degree(trace.max_degree.0)
+    };
+    let replayed = match trace.kind {
+        TraceKind::Explain => {
+            let Some(TraceEvent::Enter(star)) = trace.events.first() else {
+                return Err(Box::new(TraceDivergence {
+                    index: 0,
+                    expected: trace.events.first().cloned(),
+                    actual: None,
+                }));
+            };
+            record_trace(star, trace.weights, &config).events
+        }
+        TraceKind::Analysis => {
+            let mut events = Vec::new();
+            for event in trace.events.iter() {
+                if let TraceEvent::Enter(star) = event
+                    && let Some(case) = case_report(star, trace.weights, &config)
+                {
+                    record_case(&case, &mut events);
+                }
+            }
+            events
+        }
+    };
+    let len = trace.events.len().max(replayed.len());
+    for index in 0..len {
+        let (expected, actual) = (trace.events.get(index), replayed.get(index));
+        let agree = match (expected, actual) {
+            (
+                Some(TraceEvent::Partition {
+                    blocks: a,
+                    branching_number: x,
+                }),
+                Some(TraceEvent::Partition {
+                    blocks: b,
+                    branching_number: y,
+                }),
+            ) => a == b && x.to_bits() == y.to_bits(),
+            _ => expected == actual,
+        };
+        if !agree {
+            return Err(Box::new(TraceDivergence {
+                index,
+                expected: expected.cloned(),
+                actual: actual.cloned(),
+            }));
+        }
+    }
+    Ok(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "parallel")]
+    use crate::report::analysis_report_parallel_traced;
+    use crate::report::analysis_report_traced;
+    use alloc::vec;
+
+    #[test]
+    fn traces_round_trip_and_replay() {
+        let weights = NodeFeatures::from_array([1.0, 1.0, 1.0, 0.7, 0.7, 0.7, 0.4, 0.4, 0.4]);
+        let star = Star {
+            root_colors: 0b0111,
+            neighbor_colors: vec![0b0011, 0b0011, 0b0110],
+            neighbor_halfedges: vec![2, 2, 2],
+            neighbor_adjacency: None,
+        };
+        let trace = record_trace(&star, weights, &AnalysisConfig::default());
+        assert_eq!(trace.events.first(), Some(&TraceEvent::Enter(star)));
+        assert_eq!(trace.events.last(), Some(&TraceEvent::Leave));
+        let bytes = encode_trace(&trace).unwrap();
+        assert_eq!(decode_trace(&bytes), Some(trace.clone()));
+        assert_eq!(decode_trace(&bytes[..bytes.len() - 1]), None);
+        let reductions = trace.reductions(false).unwrap();
+        let reductions: Vec<&dyn Reduction> = reductions.iter().map(|r| r.as_ref()).collect();
+        assert_eq!(replay_trace(&trace, &reductions), Ok(trace.events.len()));
+
+        let mut tampered = trace.clone();
+        let index = tampered
+            .events
+            .iter()
+            .position(|e| matches!(e, TraceEvent::Partition { .. }))
+            .unwrap();
+        if let TraceEvent::Partition {
+            branching_number, ..
+        } = &mut tampered.events[index]
+        {
+            *branching_number = f64::from_bits(branching_number.to_bits() + 1);
+        }
+        assert_eq!(
+            replay_trace(&tampered, &reductions).unwrap_err().index,
+            index
+        );
+        tampered.events.truncate(index);
+        let divergence = replay_trace(&tampered, &reductions).unwrap_err();
+        assert_eq!((divergence.index, divergence.expected), (index, None));
+    }
+
+    #[test]
+    fn analysis_traces_merge_shards_and_replay() {
+        let weights = NodeFeatures::from_array([1.0, 1.0, 1.0, 0.7, 0.7, 0.7, 0.4, 0.4, 0.4]);
+        let config = AnalysisConfig {
+            max_blocks: Some(2),
+            ..AnalysisConfig::for_max_degree(4)
+        };
+        let mut events = Vec::new();
+        let report = analysis_report_traced(3, weights, &config, &mut events);
+        let enters = events
+            .iter()
+            .filter(|e| matches!(e, TraceEvent::Enter(_)))
+            .count();
+        assert_eq!(enters, report.cases.len());
+        #[cfg(feature = "parallel")]
+        for threads in [2, 5] {
+            let mut shards = Some(Vec::new());
+            let parallel =
+                analysis_report_parallel_traced(3, weights, &config, threads, &mut shards);
+            assert_eq!(parallel, report);
+            assert_eq!(shards.as_ref(), Some(&events));
+        }
+
+        let trace = Trace::new(TraceKind::Analysis, weights, &config, events);
+        let bytes = encode_trace(&trace).unwrap();
+        assert_eq!(decode_trace(&bytes), Some(trace.clone()));
+        let reductions = trace.reductions(false).unwrap();
+        let reductions: Vec<&dyn Reduction> = reductions.iter().map(|r| r.as_ref()).collect();
+        assert_eq!(replay_trace(&trace, &reductions), Ok(trace.events.len()));
+
+        // The trace records its limit on the blocks, so a replay without it diverges.
+        let unlimited = Trace {
+            max_blocks: None,
+            ..trace
+        };
+        assert!(replay_trace(&unlimited, &reductions).is_err());
+
+        // A trace under a conjectured reduction replays only if unproved reductions are allowed.
+        let capped = Trace {
+            reductions: vec!["duplicate-2lists-capped".into()],
+            ..unlimited
+        };
+        assert!(capped.reductions(false).is_err());
+        assert_eq!(capped.reductions(true).map(|r| r.len()), Ok(1));
+    }
+}
@@ -0,0 +1,616 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::colors::{NUM_COLORS, intersects};
+use crate::star_utils::{
+    CountError, Star, check_halfedges, color_subsets_ge2, multisets, root_color_subsets,
+};
+
+/// A node of a colored tree, as enumerated by [`generate_colored_uniform_trees`].
+///
+/// With the `serde` feature, deserialized nodes are checked with [`Node::validate`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "NodeFields")
+)]
+pub struct Node {
+    // Bitmask over colors {0,...,7}. Bit i set => color i is present.
+    // Example: 0b0011 represents {0,1}.
+    pub colors: u8,
+    /// Number of dangling halfedges at this node.
+    ///
+    /// Invariant:
+    /// - If `children` is non-empty, then `halfedges == 0`.
+    /// - If `children` is empty (leaf), then `halfedges >= 2` (and enumeration
+    ///   additionally enforces `halfedges <= degree`).
+    pub halfedges: u8,
+    pub children: Vec<Node>,
+}
+
+impl Node {
+    pub fn new_internal(colors: u8, children: Vec<Node>) -> Self {
+        debug_assert!(colors.count_ones() >= 2, "colors must have size >= 2");
+        debug_assert!(!children.is_empty(), "internal node must have children");
+        Self {
+            colors,
+            halfedges: 0,
+            children,
+        }
+    }
+
+    pub fn new_leaf(colors: u8, halfedges: u8) -> Self {
+        debug_assert!(colors.count_ones() >= 2, "colors must have size >= 2");
+        debug_assert!(halfedges >= 2, "leaf must have at least 2 halfedges");
+        Self {
+            colors,
+            halfedges,
+            children: Vec::new(),
+        }
+    }
+}
+
+impl Node {
+    /// Checks the invariants of every node of the tree: each list has at least 2 colors,
+    /// internal nodes have no halfedges, and leaves have at least 2.
+    pub fn validate(&self) -> Result<(), InvalidNode> {
+        self.check()?;
+        self.children.iter().try_for_each(Node::validate)
+    }
+
+    /// Checks the invariants of this node only.
+    fn check(&self) -> Result<(), InvalidNode> {
+        if self.colors.count_ones() < 2 {
+            return Err(InvalidNode::Colors(self.colors));
+        }
+        if !self.children.is_empty() && self.halfedges != 0 {
+            return Err(InvalidNode::InternalHalfedges(self.halfedges));
+        }
+        if self.children.is_empty() && self.halfedges < 2 {
+            return Err(InvalidNode::LeafHalfedges(self.halfedges));
+        }
+        Ok(())
+    }
+}
+
+/// Why a [`Node`] is invalid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InvalidNode {
+    /// The list has fewer than 2 colors.
+    Colors(u8),
+    /// An internal node has halfedges.
+    InternalHalfedges(u8),
+    /// A leaf has fewer than 2 halfedges.
+    LeafHalfedges(u8),
+}
+
+impl core::fmt::Display for InvalidNode {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            InvalidNode::Colors(c) => write!(f, "invalid list {c:#010b}"),
+            InvalidNode::InternalHalfedges(h) => write!(f, "an internal node has {h} halfedges"),
+            InvalidNode::LeafHalfedges(h) => write!(f, "a leaf has {h} halfedges"),
+        }
+    }
+}
+
+impl core::error::Error for InvalidNode {}
+
+/// The fields of a [`Node`] as deserialized. The children are already validated, so only the
+/// node itself is checked.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct NodeFields {
+    colors: u8,
+    halfedges: u8,
+    children: Vec<Node>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<NodeFields> for Node {
+    type Error = InvalidNode;
+
+    fn try_from(fields: NodeFields) -> Result<Self, Self::Error> {
+        let node = Node {
+            colors: fields.colors,
+            halfedges: fields.halfedges,
+            children: fields.children,
+        };
+        node.check()?;
+        Ok(node)
+    }
+}
+
+/// Why a [`Node`] is not a [`Star`]: only a root whose children are all leaves is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NotAStar {
+    /// The root has halfedges, which a star does not record.
+    RootHalfedges(u8),
+    /// The child with this index has children of its own.
+    DeepChild(usize),
+}
+
+impl core::fmt::Display for NotAStar {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            NotAStar::RootHalfedges(h) => write!(f, "the root has {h} halfedges"),
+            NotAStar::DeepChild(i) => write!(f, "child {i} is not a leaf"),
+        }
+    }
+}
+
+impl core::error::Error for NotAStar {}
+
+/// A tree of depth 1 is a star: the children become the neighbors, with their lists and
+/// halfedges, in order.
+impl TryFrom<&Node> for Star {
+    type Error = NotAStar;
+
+    fn try_from(node: &Node) -> Result<Self, Self::Error> {
+        if node.halfedges != 0 {
+            return Err(NotAStar::RootHalfedges(node.halfedges));
+        }
+        if let Some(i) = node.children.iter().position(|c| !c.children.is_empty()) {
+            return Err(NotAStar::DeepChild(i));
+        }
+        Ok(Star {
+            root_colors: node.colors,
+            neighbor_colors: node.children.iter().map(|c| c.colors).collect(),
+            neighbor_halfedges: node.children.iter().map(|c| c.halfedges).collect(),
+            neighbor_adjacency: None,
+        })
+    }
+}
+
+/// The tree of depth 1 with a leaf per neighbor. The lists and halfedges are copied as they
+/// are, so the [`Node`] invariants only hold for stars that satisfy them.
+impl From<&Star> for Node {
+    fn from(star: &Star) -> Self {
+        Node {
+            colors: star.root_colors,
+            halfedges: 0,
+            children: star
+                .neighbor_colors
+                .iter()
+                .zip(&star.neighbor_halfedges)
+                .map(|(&colors, &halfedges)| Node {
+                    colors,
+                    halfedges,
+                    children: Vec::new(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Which color lists may be adjacent in an enumerated tree: a child list is allowed below a
+/// parent list if [`EdgeConstraint::allows`] holds for the pair.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum EdgeConstraint {
+    /// The lists share a color, so the edge can constrain the coloring.
+    #[default]
+    Intersect,
+    /// The lists are equal.
+    Equal,
+    /// The lists differ.
+    NotEqual,
+    /// An arbitrary predicate on `(parent, child)`.
+    Custom(fn(u8, u8) -> bool),
+}
+
+impl EdgeConstraint {
+    pub fn allows(&self, parent: u8, child: u8) -> bool {
+        match self {
+            EdgeConstraint::Intersect => intersects(parent, child),
+            EdgeConstraint::Equal => parent == child,
+            EdgeConstraint::NotEqual => parent != child,
+            EdgeConstraint::Custom(predicate) => predicate(parent, child),
+        }
+    }
+}
+
+impl core::str::FromStr for EdgeConstraint {
+    type Err = String;
+
+    /// Parses `intersect`, `equal` or `not-equal`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "intersect" => Ok(EdgeConstraint::Intersect),
+            "equal" => Ok(EdgeConstraint::Equal),
+            "not-equal" => Ok(EdgeConstraint::NotEqual),
+            _ => Err(format!("unknown edge constraint: {s}")),
+        }
+    }
+}
+
+fn nondecreasing_sequences(t: usize, n: usize) -> Vec<Vec<usize>> {
+    let mut res: Vec<Vec<usize>> = Vec::new();
+    let mut current = alloc::vec![0usize; n];
+
+    fn backtrack(
+        idx: usize,
+        start: usize,
+        t: usize,
+        current: &mut [usize],
+        res: &mut Vec<Vec<usize>>,
+    ) {
+        if idx == current.len() {
+            res.push(current.to_vec());
+            return;
+        }
+        for v in start..=t {
+            current[idx] = v;
+            backtrack(idx + 1, v, t, current, res);
+        }
+    }
+
+    if n == 0 {
+        res.push(Vec::new());
+        return res;
+    }
+
+    backtrack(0, 0, t, &mut current, &mut res);
+    res
+}
+
+fn generate_subtrees_with_parent(
+    depth: usize,
+    degree: usize,
+    parent_color_idx: usize,
+    constraint: EdgeConstraint,
+    lists: &[u8],
+    cache: &mut BTreeMap<(usize, usize, usize), Vec<Node>>,
+) -> Vec<Node> {
+    let key = (depth, degree, parent_color_idx);
+    if let Some(cached) = cache.get(&key) {
+        return cached.clone();
+    }
+
+    let children_count = if depth == 0 {
+        0
+    } else {
+        // For non-root nodes, degree includes the edge to the parent.
+        degree.saturating_sub(1)
+    };
+
+    // Can't realize positive depth without children.
+    if depth > 0 && children_count == 0 {
+        cache.insert(key, Vec::new());
+        return Vec::new();
+    }
+
+    let parent_colors = lists[parent_color_idx];
+    let mut out: Vec<Node> = Vec::new();
+
+    for (idx, colors) in lists.iter().enumerate() {
+        if !constraint.allows(parent_colors, *colors) {
+            continue;
+        }
+
+        if depth == 0 {
+            // Leaf: vary halfedges from 2..=degree.
+            // If degree < 2, there are no valid leaves.
+            for h in leaf_halfedges(degree) {
+                out.push(Node::new_leaf(*colors, h));
+            }
+            continue;
+        }
+
+        let child_candidates =
+            generate_subtrees_with_parent(depth - 1, degree, idx, constraint, lists, cache);
+        if child_candidates.is_empty() {
+            continue;
+        }
+
+        for choice in nondecreasing_sequences(child_candidates.len() - 1, children_count) {
+            let children = choice
+                .into_iter()
+                .map(|i| child_candidates[i].clone())
+                .collect::<Vec<_>>();
+            out.push(Node::new_internal(*colors, children));
+        }
+    }
+
+    cache.insert(key, out.clone());
+    out
+}
+
+/// The halfedge counts of a leaf for `degree`: `2..=degree`.
+///
+/// Panics if `degree` exceeds `u8::MAX`.
+fn leaf_halfedges(degree: usize) -> impl Iterator<Item = u8> {
+    (2..=degree).map(|h| u8::try_from(h).expect("halfedge counts fit into a u8"))
+}
+
+/// Generates all colorings of the unique uniform tree of the given `depth` and `degree`.
+///
+/// - `depth` counts edges from the root to a leaf (so `depth = 0` yields a single node).
+/// - `degree` includes the edge to the parent, so the root has `degree` children and every
+///   other internal node has `degree - 1` children.
+/// - Colors are chosen from [`COLOR_SUBSETS_GE2`](crate::star_utils::COLOR_SUBSETS_GE2), the
+///   root colors from [`ROOT_COLOR_SUBSETS`](crate::star_utils::ROOT_COLOR_SUBSETS).
+/// - Constraint: for every parent/child edge, `parent.colors` intersects `child.colors`.
+///
+/// Panics if `degree` exceeds `u8::MAX`, as leaves could have that many halfedges.
+pub fn generate_colored_uniform_trees(depth: usize, degree: usize) -> Vec<Node> {
+    generate_colored_uniform_trees_with(depth, degree, EdgeConstraint::Intersect, NUM_COLORS)
+}
+
+/// Like [`generate_colored_uniform_trees`], but every parent/child edge satisfies
+/// `constraint` instead, and the lists are those of
+/// [`color_subsets_ge2`] and [`root_color_subsets`] for `colors` colors.
+///
+/// Panics if `colors` exceeds [`MAX_COLORS`](crate::colors::MAX_COLORS).
+pub fn generate_colored_uniform_trees_with(
+    depth: usize,
+    degree: usize,
+    constraint: EdgeConstraint,
+    colors: u8,
+) -> Vec<Node> {
+    if degree < 2 {
+        return Vec::new();
+    }
+
+    let root_children_count = if depth == 0 { 0 } else { degree };
+    if depth > 0 && root_children_count == 0 {
+        return Vec::new();
+    }
+
+    let lists = color_subsets_ge2(colors);
+    let mut cache: BTreeMap<(usize, usize, usize), Vec<Node>> = BTreeMap::new();
+    let mut out: Vec<Node> = Vec::new();
+
+    for root_colors in root_color_subsets(colors) {
+        let Some(root_idx) = lists.iter().position(|&s| s == root_colors) else {
+            // If this ever happens, a root list is not a list of at least 2 colors.
+            continue;
+        };
+
+        if depth == 0 {
+            // Root is a leaf: vary halfedges from 2..=degree.
+            for h in leaf_halfedges(degree) {
+                out.push(Node::new_leaf(root_colors, h));
+            }
+            continue;
+        }
+
+        let child_candidates = generate_subtrees_with_parent(
+            depth - 1,
+            degree,
+            root_idx,
+            constraint,
+            &lists,
+            &mut cache,
+        );
+        if child_candidates.is_empty() {
+            continue;
+        }
+
+        for choice in nondecreasing_sequences(child_candidates.len() - 1, root_children_count) {
+            let children = choice
+                .into_iter()
+                .map(|i| child_candidates[i].clone())
+                .collect::<Vec<_>>();
+            out.push(Node::new_internal(root_colors, children));
+        }
+    }
+
+    out
+}
+
+/// The number of trees [`generate_colored_uniform_trees_with`] generates for `depth`,
+/// `degree`, `constraint` and `colors`, computed level by level without enumerating the trees.
+pub fn count_colored_uniform_trees_with(
+    depth: usize,
+    degree: usize,
+    constraint: EdgeConstraint,
+    colors: u8,
+) -> Result<u128, CountError> {
+    if degree < 2 {
+        return Ok(0);
+    }
+    check_halfedges(&(2..=degree))?;
+    // subtrees[i]: the number of subtrees of the current depth whose root has the list
+    // lists[i]; leaves have the halfedges of `leaf_halfedges`.
+    let lists = color_subsets_ge2(colors);
+    let leaves = degree as u128 - 1;
+    let mut subtrees: Vec<u128> = alloc::vec![leaves; lists.len()];
+    let below = |parent: u8, subtrees: &[u128]| -> Result<u128, CountError> {
+        lists
+            .iter()
+            .zip(subtrees)
+            .filter(|&(&child, _)| constraint.allows(parent, child))
+            .try_fold(0u128, |sum, (_, &n)| {
+                sum.checked_add(n).ok_or(CountError::Overflow)
+            })
+    };
+    for _ in 1..depth {
+        subtrees = lists
+            .iter()
+            .map(|&colors| multisets(below(colors, &subtrees)?, degree as u128 - 1))
+            .collect::<Result<_, _>>()?;
+    }
+    root_color_subsets(colors)
+        .into_iter()
+        .try_fold(0u128, |sum, root| {
+            let trees = match depth {
+                0 => leaves,
+                _ => multisets(below(root, &subtrees)?, degree as u128)?,
+            };
+            sum.checked_add(trees).ok_or(CountError::Overflow)
+        })
+}
+
+/// The number of nodes of the uniform tree of `depth` and `degree`: the root has `degree`
+/// children and every other internal node `degree - 1`.
+pub fn uniform_tree_size(depth: usize, degree: usize) -> Result<u128, CountError> {
+    let mut level: u128 = 1;
+    let mut total: u128 = 1;
+    for d in 0..depth {
+        let children = if d == 0 {
+            degree
+        } else {
+            degree.saturating_sub(1)
+        };
+        level = level
+            .checked_mul(children as u128)
+            .ok_or(CountError::Overflow)?;
+        total = total.checked_add(level).ok_or(CountError::Overflow)?;
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+    use alloc::vec;
+
+    #[test]
+    fn edge_constraints() {
+        let constraint: EdgeConstraint = "intersect".parse().unwrap();
+        assert!(constraint.allows(0b0011, 0b0110));
+        assert!(!constraint.allows(0b0011, 0b1100));
+        assert!(EdgeConstraint::Equal.allows(0b0011, 0b0011));
+        assert!(
+            !"not-equal"
+                .parse::<EdgeConstraint>()
+                .unwrap()
+                .allows(0b0011, 0b0011)
+        );
+        assert!(EdgeConstraint::Custom(|p, c| c & !p == 0).allows(0b0111, 0b0011));
+        assert!("subset".parse::<EdgeConstraint>().is_err());
+    }
+
+    #[test]
+    fn depth_one_trees_are_stars() {
+        let star = Star {
+            root_colors: 0b0111,
+            neighbor_colors: vec![0b0011, 0b0110],
+            neighbor_halfedges: vec![2, 3],
+            neighbor_adjacency: None,
+        };
+        let node = Node::from(&star);
+        assert_eq!(
+            node,
+            Node::new_internal(
+                0b0111,
+                vec![Node::new_leaf(0b0011, 2), Node::new_leaf(0b0110, 3)]
+            )
+        );
+        assert_eq!(Star::try_from(&node), Ok(star));
+
+        let deep = Node::new_internal(0b0111, vec![node.clone()]);
+        assert_eq!(Star::try_from(&deep), Err(NotAStar::DeepChild(0)));
+        let leaf = Node::new_leaf(0b0011, 2);
+        assert_eq!(
+            Star::try_from(&leaf).unwrap_err().to_string(),
+            "the root has 2 halfedges"
+        );
+    }
+
+    #[test]
+    fn generated_trees_match_the_counts() {
+        for (depth, degree) in [(0, 3), (1, 2), (1, 3), (2, 2)] {
+            let trees = generate_colored_uniform_trees(depth, degree);
+            assert_eq!(
+                Ok(trees.len() as u128),
+                count_colored_uniform_trees_with(
+                    depth,
+                    degree,
+                    EdgeConstraint::Intersect,
+                    NUM_COLORS
+                )
+            );
+            let size = uniform_tree_size(depth, degree).unwrap();
+            fn nodes(t: &Node) -> u128 {
+                1 + t.children.iter().map(nodes).sum::<u128>()
+            }
+            assert!(trees.iter().all(|t| nodes(t) == size));
+        }
+        let equal = generate_colored_uniform_trees_with(1, 2, EdgeConstraint::Equal, NUM_COLORS);
+        assert!(
+            equal
+                .iter()
+                .all(|t| t.children.iter().all(|c| c.colors == t.colors))
+        );
+        assert!(generate_colored_uniform_trees(2, 1).is_empty());
+        assert_eq!(
+            count_colored_uniform_trees_with(0, 255, EdgeConstraint::Intersect, NUM_COLORS),
+            Ok(crate::star_utils::ROOT_COLOR_SUBSETS.len() as u128 * 254)
+        );
+        assert_eq!(
+            count_colored_uniform_trees_with(3, 255, EdgeConstraint::Intersect, NUM_COLORS),
+            Err(CountError::Overflow)
+        );
+        assert_eq!(
+            count_colored_uniform_trees_with(0, 1000, EdgeConstraint::Intersect, NUM_COLORS),
+            Err(CountError::TooManyHalfedges { halfedges: 1000 })
+        );
+        assert_eq!(uniform_tree_size(100, 1000), Err(CountError::Overflow));
+    }
+
+    #[test]
+    fn tree_counts_match_the_star_counts() {
+        use crate::star_utils::count_stars_with_config;
+
+        for degree in 2..=4 {
+            let stars = count_stars_with_config(degree, &Default::default()).unwrap();
+            assert_eq!(
+                count_colored_uniform_trees_with(1, degree, EdgeConstraint::Intersect, NUM_COLORS),
+                Ok(stars)
+            );
+        }
+        assert_eq!(
+            count_colored_uniform_trees_with(0, 3, EdgeConstraint::Intersect, NUM_COLORS),
+            Ok(6)
+        );
+        // With equal lists along every edge, each root list has a single path of length 2.
+        assert_eq!(
+            count_colored_uniform_trees_with(2, 2, EdgeConstraint::Equal, NUM_COLORS),
+            Ok(3)
+        );
+        assert_eq!(uniform_tree_size(2, 3), Ok(1 + 3 + 6));
+
+        let config = crate::star_utils::EnumerationConfig {
+            colors: 5,
+            ..Default::default()
+        };
+        let trees = generate_colored_uniform_trees_with(1, 2, EdgeConstraint::Intersect, 5);
+        assert!(trees.iter().all(|t| t.validate().is_ok()));
+        assert_eq!(
+            Ok(trees.len() as u128),
+            count_colored_uniform_trees_with(1, 2, EdgeConstraint::Intersect, 5)
+        );
+        assert_eq!(Ok(trees.len() as u128), count_stars_with_config(2, &config));
+    }
+
+    #[test]
+    fn validate_checks_every_node() {
+        let leaf = Node::new_leaf(0b0011, 2);
+        let tree = Node::new_internal(0b0111, vec![leaf.clone(), leaf]);
+        assert_eq!(tree.validate(), Ok(()));
+
+        let mut bad = tree.clone();
+        bad.children[1].halfedges = 1;
+        assert_eq!(bad.validate(), Err(InvalidNode::LeafHalfedges(1)));
+        let mut bad = tree;
+        bad.halfedges = 2;
+        assert_eq!(bad.validate(), Err(InvalidNode::InternalHalfedges(2)));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trips_and_validates_trees() {
+        let tree = Node::new_internal(0b0111, vec![Node::new_leaf(0b0011, 2)]);
+        let json = serde_json::to_string(&tree).unwrap();
+        assert_eq!(serde_json::from_str::<Node>(&json).unwrap(), tree);
+
+        let bad_leaf = r#"{"colors":7,"halfedges":0,"children":[
+            {"colors":1,"halfedges":2,"children":[]}]}"#;
+        assert!(serde_json::from_str::<Node>(bad_leaf).is_err());
+    }
+}
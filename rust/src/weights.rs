@@ -0,0 +1,362 @@
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::list_coloring_utils::NodeFeatures;
+
+/// Indices into [`NodeFeatures::to_array`] of the features with list sizes 2, 3 and 4, one
+/// chain per degree bucket (`>= 5`, `4`, `3`).
+const LIST_SIZE_CHAINS: [[usize; 3]; 3] = [[6, 3, 0], [7, 4, 1], [8, 5, 2]];
+
+/// A weight vector for the measure `weights * features`.
+///
+/// Unlike raw [`NodeFeatures`] arithmetic, `Weights` knows the shape constraints a sensible
+/// measure satisfies: weights are finite, nonnegative, and nondecreasing in the list size for
+/// each degree bucket.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Weights(pub NodeFeatures);
+
+/// Why a weight vector violates the shape constraints of [`Weights`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum WeightsError {
+    /// The weight at this index of [`NodeFeatures::to_array`] is NaN or infinite.
+    NotFinite(usize),
+    /// The weight at this index is negative.
+    Negative(usize),
+    /// The weight at index `larger_list` is smaller than the one at `smaller_list`, although
+    /// both have the same degree bucket.
+    NotMonotone {
+        smaller_list: usize,
+        larger_list: usize,
+    },
+    /// A constraint string could not be parsed.
+    InvalidConstraint(String),
+    /// The constraint at this index of the given list does not hold.
+    ConstraintViolated(usize),
+}
+
+impl core::fmt::Display for WeightsError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            WeightsError::NotFinite(i) => write!(f, "weight {i} is not finite"),
+            WeightsError::Negative(i) => write!(f, "weight {i} is negative"),
+            WeightsError::NotMonotone {
+                smaller_list,
+                larger_list,
+            } => write!(
+                f,
+                "weight {larger_list} is smaller than weight {smaller_list} of a shorter list"
+            ),
+            WeightsError::InvalidConstraint(s) => write!(f, "invalid weight constraint: {s}"),
+            WeightsError::ConstraintViolated(i) => write!(f, "weight constraint {i} is violated"),
+        }
+    }
+}
+
+impl core::error::Error for WeightsError {}
+
+impl Weights {
+    pub fn from_array(a: [f64; 9]) -> Self {
+        Weights(NodeFeatures::from_array(a))
+    }
+
+    pub fn to_array(&self) -> [f64; 9] {
+        self.0.to_array()
+    }
+
+    /// Returns the measure of a star with the given features.
+    pub fn measure(&self, features: NodeFeatures) -> f64 {
+        self.0 * features
+    }
+
+    /// Returns `(1 - t) * self + t * other`.
+    pub fn lerp(&self, other: &Weights, t: f64) -> Weights {
+        let a = self.to_array();
+        let b = other.to_array();
+        Weights::from_array(core::array::from_fn(|i| (1.0 - t) * a[i] + t * b[i]))
+    }
+
+    /// Checks that all weights are finite and nonnegative, and nondecreasing in the list size.
+    pub fn validate(&self) -> Result<(), WeightsError> {
+        let w = self.to_array();
+        if let Some(i) = w.iter().position(|x| !x.is_finite()) {
+            return Err(WeightsError::NotFinite(i));
+        }
+        if let Some(i) = w.iter().position(|&x| x < 0.0) {
+            return Err(WeightsError::Negative(i));
+        }
+        for chain in LIST_SIZE_CHAINS.iter() {
+            for pair in chain.windows(2) {
+                if w[pair[1]] < w[pair[0]] {
+                    return Err(WeightsError::NotMonotone {
+                        smaller_list: pair[0],
+                        larger_list: pair[1],
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the closest (in Euclidean distance) weights that are nonnegative and
+    /// nondecreasing in the list size. NaN weights are treated as 0.
+    pub fn project_monotone(&self) -> Weights {
+        let mut w = self.to_array().map(|x| if x.is_nan() { 0.0 } else { x });
+        for chain in LIST_SIZE_CHAINS.iter() {
+            let values: Vec<f64> = chain.iter().map(|&i| w[i]).collect();
+            for (&i, x) in chain.iter().zip(isotonic_regression(&values)) {
+                // Clipping the isotonic fit gives the projection onto the bounded cone.
+                w[i] = x.max(0.0);
+            }
+        }
+        Weights::from_array(w)
+    }
+}
+
+/// One side of a [`WeightConstraint`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WeightTerm {
+    /// The weight at this index of [`NodeFeatures::to_array`].
+    Weight(usize),
+    Constant(f64),
+}
+
+impl WeightTerm {
+    fn value(&self, w: &[f64; 9]) -> f64 {
+        match *self {
+            WeightTerm::Weight(i) => w[i],
+            WeightTerm::Constant(c) => c,
+        }
+    }
+}
+
+/// The constraint `larger >= smaller` between two weights or a weight and a constant.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WeightConstraint {
+    pub larger: WeightTerm,
+    pub smaller: WeightTerm,
+}
+
+impl WeightConstraint {
+    pub fn is_satisfied(&self, weights: &Weights) -> bool {
+        let w = weights.to_array();
+        self.larger.value(&w) >= self.smaller.value(&w)
+    }
+
+    /// Moves `w` to the closest point satisfying this constraint.
+    fn project(&self, w: &mut [f64; 9]) {
+        let (a, b) = (self.larger.value(w), self.smaller.value(w));
+        if a >= b {
+            return;
+        }
+        match (self.larger, self.smaller) {
+            (WeightTerm::Weight(i), WeightTerm::Weight(j)) => {
+                let mid = (a + b) / 2.0;
+                w[i] = mid;
+                w[j] = mid;
+            }
+            (WeightTerm::Weight(i), WeightTerm::Constant(c)) => w[i] = c,
+            (WeightTerm::Constant(c), WeightTerm::Weight(j)) => w[j] = c,
+            (WeightTerm::Constant(_), WeightTerm::Constant(_)) => {}
+        }
+    }
+}
+
+/// Parses a chain of `>=` or `<=` comparisons between weight names of [`NodeFeatures::NAMES`]
+/// and numbers, such as `n2_3 >= n3_3 >= n4_3 >= 0`, into one constraint per comparison.
+/// Several chains can be separated by `,` or `;`.
+pub fn parse_weight_constraints(s: &str) -> Result<Vec<WeightConstraint>, WeightsError> {
+    let invalid = || WeightsError::InvalidConstraint(s.to_string());
+    let term = |t: &str| -> Result<WeightTerm, WeightsError> {
+        let t = t.trim();
+        if let Some(i) = NodeFeatures::NAMES.iter().position(|&n| n == t) {
+            return Ok(WeightTerm::Weight(i));
+        }
+        t.parse().map(WeightTerm::Constant).map_err(|_| invalid())
+    };
+
+    let mut out: Vec<WeightConstraint> = Vec::new();
+    for chain in s.split([',', ';']).filter(|c| !c.trim().is_empty()) {
+        let (separator, descending) = match (chain.contains(">="), chain.contains("<=")) {
+            (true, false) => (">=", true),
+            (false, true) => ("<=", false),
+            _ => return Err(invalid()),
+        };
+        let terms: Vec<WeightTerm> = chain.split(separator).map(term).collect::<Result<_, _>>()?;
+        for pair in terms.windows(2) {
+            let (larger, smaller) = if descending {
+                (pair[0], pair[1])
+            } else {
+                (pair[1], pair[0])
+            };
+            if let (WeightTerm::Constant(_), WeightTerm::Constant(_)) = (larger, smaller) {
+                return Err(invalid());
+            }
+            out.push(WeightConstraint { larger, smaller });
+        }
+    }
+    Ok(out)
+}
+
+/// Returns the constraints checked by [`Weights::validate`] besides finiteness: every weight
+/// is nonnegative and nondecreasing in the list size within each degree bucket.
+pub fn shape_constraints() -> Vec<WeightConstraint> {
+    let mut out: Vec<WeightConstraint> = (0..9)
+        .map(|i| WeightConstraint {
+            larger: WeightTerm::Weight(i),
+            smaller: WeightTerm::Constant(0.0),
+        })
+        .collect();
+    for chain in LIST_SIZE_CHAINS.iter() {
+        for pair in chain.windows(2) {
+            out.push(WeightConstraint {
+                larger: WeightTerm::Weight(pair[1]),
+                smaller: WeightTerm::Weight(pair[0]),
+            });
+        }
+    }
+    out
+}
+
+impl Weights {
+    /// Checks `constraints`; the error names the first one that does not hold.
+    pub fn check_constraints(&self, constraints: &[WeightConstraint]) -> Result<(), WeightsError> {
+        match constraints.iter().position(|c| !c.is_satisfied(self)) {
+            Some(i) => Err(WeightsError::ConstraintViolated(i)),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the closest (in Euclidean distance) weights satisfying all `constraints`, up to
+    /// a tolerance of about `1e-12`, computed with Dykstra's alternating projections.
+    pub fn project_onto(&self, constraints: &[WeightConstraint]) -> Weights {
+        let mut w = self.to_array();
+        let mut increments = vec![[0.0f64; 9]; constraints.len()];
+        for _ in 0..100_000 {
+            let mut change = 0.0f64;
+            for (constraint, inc) in constraints.iter().zip(increments.iter_mut()) {
+                let shifted: [f64; 9] = core::array::from_fn(|i| w[i] + inc[i]);
+                let mut projected = shifted;
+                constraint.project(&mut projected);
+                for i in 0..9 {
+                    inc[i] = shifted[i] - projected[i];
+                    change = change.max((projected[i] - w[i]).abs());
+                }
+                w = projected;
+            }
+            if change < 1e-13 {
+                break;
+            }
+        }
+        Weights::from_array(w)
+    }
+}
+
+impl From<NodeFeatures> for Weights {
+    fn from(features: NodeFeatures) -> Self {
+        Weights(features)
+    }
+}
+
+/// Returns the nondecreasing sequence closest to `values` in Euclidean distance, computed by
+/// pooling adjacent violators.
+pub(crate) fn isotonic_regression(values: &[f64]) -> Vec<f64> {
+    // Blocks of pooled values as (sum, count).
+    let mut blocks: Vec<(f64, usize)> = Vec::new();
+    for &v in values {
+        blocks.push((v, 1));
+        while blocks.len() >= 2 {
+            let (s2, c2) = blocks[blocks.len() - 1];
+            let (s1, c1) = blocks[blocks.len() - 2];
+            if s1 / (c1 as f64) <= s2 / (c2 as f64) {
+                break;
+            }
+            blocks.pop();
+            *blocks.last_mut().unwrap() = (s1 + s2, c1 + c2);
+        }
+    }
+    blocks
+        .into_iter()
+        .flat_map(|(s, c)| core::iter::repeat_n(s / (c as f64), c))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validate_and_project_weights() {
+        let good = Weights::from_array([1.0, 1.0, 1.0, 0.7, 0.7, 0.7, 0.4, 0.4, 0.4]);
+        assert_eq!(good.validate(), Ok(()));
+        assert_eq!(good.project_monotone(), good);
+
+        // n2_ge5 > n3_ge5 violates monotonicity.
+        let unordered = Weights::from_array([1.0, 1.0, 1.0, 0.5, 0.7, 0.7, 0.7, 0.4, 0.4]);
+        assert_eq!(
+            unordered.validate(),
+            Err(WeightsError::NotMonotone {
+                smaller_list: 6,
+                larger_list: 3
+            })
+        );
+        let projected = unordered.project_monotone();
+        assert_eq!(projected.validate(), Ok(()));
+        assert!((projected.0.n2_ge5 - 0.6).abs() < 1e-12);
+        assert!((projected.0.n3_ge5 - 0.6).abs() < 1e-12);
+
+        let negative = Weights::from_array([1.0, 1.0, 1.0, 0.7, 0.7, 0.7, 0.4, 0.4, -0.2]);
+        assert_eq!(negative.validate(), Err(WeightsError::Negative(8)));
+        assert_eq!(negative.project_monotone().0.n2_3, 0.0);
+
+        let shape = shape_constraints();
+        assert!(unordered.check_constraints(&shape).is_err());
+        let dykstra = unordered.project_onto(&shape);
+        for (a, b) in dykstra.to_array().iter().zip(projected.to_array().iter()) {
+            assert!((a - b).abs() < 1e-9);
+        }
+
+        let mid = good.lerp(&Weights::default(), 0.5);
+        assert!((mid.0.n3_3 - 0.35).abs() < 1e-12);
+        assert_eq!(
+            Weights::from_array([f64::NAN; 9]).validate(),
+            Err(WeightsError::NotFinite(0))
+        );
+    }
+
+    #[test]
+    fn parse_and_project_declared_constraints() {
+        let constraints = parse_weight_constraints("n2_3 >= n3_3 >= n4_3 >= 0; n4_4 <= 2").unwrap();
+        assert_eq!(constraints.len(), 4);
+        assert_eq!(
+            constraints[0],
+            WeightConstraint {
+                larger: WeightTerm::Weight(8),
+                smaller: WeightTerm::Weight(5)
+            }
+        );
+        assert_eq!(
+            constraints[3],
+            WeightConstraint {
+                larger: WeightTerm::Constant(2.0),
+                smaller: WeightTerm::Weight(1)
+            }
+        );
+
+        let w = Weights::from_array([3.0, 3.0, 1.0, 0.7, 0.7, 0.7, 0.4, 0.4, 0.4]);
+        assert_eq!(
+            w.check_constraints(&constraints),
+            Err(WeightsError::ConstraintViolated(0))
+        );
+        let projected = w.project_onto(&constraints);
+        let p = projected.to_array();
+        assert!((p[1] - 2.0).abs() < 1e-9);
+        assert!(
+            (p[8] - 0.7).abs() < 1e-9 && (p[5] - 0.7).abs() < 1e-9 && (p[2] - 0.7).abs() < 1e-9
+        );
+
+        assert!(parse_weight_constraints("n2_3 >= n5_5").is_err());
+        assert!(parse_weight_constraints("n2_3 >= n3_3 <= n4_3").is_err());
+        assert!(parse_weight_constraints("1 >= 0").is_err());
+    }
+}
@@ -0,0 +1,118 @@
+// End-to-end runs of `recurrences analyze-all`, checking the summary line and exit code.
+
+use std::process::Command;
+
+fn analyze_all(args: &[&str]) -> (Option<i32>, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_recurrences"))
+        .arg("analyze-all")
+        .args(args)
+        .output()
+        .expect("failed to run the recurrences binary");
+    let stdout = String::from_utf8(output.stdout).expect("stdout is not UTF-8");
+    (output.status.code(), stdout)
+}
+
+fn summary(stdout: &str) -> &str {
+    stdout
+        .lines()
+        .rfind(|line| line.starts_with("analyze-all: "))
+        .expect("no summary line")
+}
+
+#[test]
+fn analyze_all_certifies_small_degrees() {
+    let (code, stdout) = analyze_all(&["--max-degree", "3"]);
+    let summary = summary(&stdout);
+    assert_eq!(code, Some(0), "{stdout}");
+    assert!(summary.starts_with("analyze-all: status=verified max_degree=3 stars=792 "));
+    assert!(summary.contains(" certified="));
+}
+
+#[test]
+fn analyze_all_rejects_limits_without_branchings() {
+    let (code, stdout) = analyze_all(&["--max-degree", "3", "--max-blocks", "1"]);
+    assert_eq!(code, Some(1), "{stdout}");
+    assert!(!stdout.contains("status=verified"));
+}
+
+// The default run analyzes all 1.3 million stars of degrees 3 to 5; run it with
+// `cargo test --release -- --ignored`.
+#[test]
+#[ignore = "analyzes all stars up to degree 5"]
+fn analyze_all_default_run() {
+    let (code, stdout) = analyze_all(&[]);
+    assert_eq!(code, Some(0), "{stdout}");
+    assert!(summary(&stdout).starts_with("analyze-all: status=verified max_degree=5 "));
+}
+
+#[test]
+fn analyze_all_saves_the_best_weights_of_every_round() {
+    let path = std::env::temp_dir().join(format!(
+        "recurrences-best-weights-{}.txt",
+        std::process::id()
+    ));
+    let output = Command::new(env!("CARGO_BIN_EXE_recurrences"))
+        .args([
+            "analyze-all",
+            "--max-degree",
+            "3",
+            "--optimize",
+            "--rounds",
+            "2",
+        ])
+        .arg("--best-weights")
+        .arg(&path)
+        .args(["--progress-interval", "0"])
+        .output()
+        .expect("failed to run the recurrences binary");
+    let stderr = String::from_utf8(output.stderr).expect("stderr is not UTF-8");
+    assert_eq!(stderr.matches("; best bound ").count(), 2, "{stderr}");
+    assert!(
+        stderr.contains(" stars bind at the final weights "),
+        "{stderr}"
+    );
+    assert!(
+        stderr.contains(" features by the bound over the "),
+        "{stderr}"
+    );
+    let saved = std::fs::read_to_string(&path).expect("no best weights written");
+    let weights: Vec<f64> = saved
+        .trim_end()
+        .split(',')
+        .map(|w| w.parse().expect("not a weight"))
+        .collect();
+    assert_eq!(weights.len(), 9, "{saved}");
+    assert!(!path.with_extension("txt.tmp").exists());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn analyze_all_restarts_are_reproducible_from_the_seed() {
+    let run = |seed: &str| {
+        let output = Command::new(env!("CARGO_BIN_EXE_recurrences"))
+            .args([
+                "analyze-all",
+                "--max-degree",
+                "3",
+                "--optimize",
+                "--rounds",
+                "2",
+            ])
+            .args(["--restarts", "3", "--seed", seed])
+            .output()
+            .expect("failed to run the recurrences binary");
+        let stdout = String::from_utf8(output.stdout).expect("stdout is not UTF-8");
+        let stderr = String::from_utf8(output.stderr).expect("stderr is not UTF-8");
+        (stdout, stderr)
+    };
+    let (stdout, stderr) = run("7");
+    for restart in 1..=3 {
+        assert!(
+            stderr.contains(&format!("restart {restart}/3: ")),
+            "{stderr}"
+        );
+    }
+    assert!(stderr.contains("best bound over 3 restarts: "), "{stderr}");
+    // The elapsed times differ between runs, so only the results are compared.
+    assert_eq!(summary(&run("7").0), summary(&stdout));
+}
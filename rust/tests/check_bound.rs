@@ -0,0 +1,103 @@
+// End-to-end runs of `recurrences check-bound` and `check-proof` with bounds that no branching
+// number can be compared against, and of the commands on a degree without stars, with too
+// many halfedges or with unknown options.
+
+use std::process::Command;
+
+const WEIGHTS: &str = "1,1,1,0.6,0.6,0.6,0.3,0.3,0.3";
+
+fn run(args: &[&str]) -> (Option<i32>, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_recurrences"))
+        .args(args)
+        .output()
+        .expect("failed to run the recurrences binary");
+    let stdout = String::from_utf8(output.stdout).expect("stdout is not UTF-8");
+    (output.status.code(), stdout)
+}
+
+#[test]
+fn check_bound_rejects_bounds_that_are_not_branching_numbers() {
+    for bound in ["NaN", "inf", "-inf", "0.5"] {
+        let (code, stdout) = run(&["check-bound", "3", WEIGHTS, bound]);
+        assert_eq!(code, Some(3), "{bound}: {stdout}");
+        assert_eq!(stdout.trim(), "check-bound: status=malformed");
+    }
+    let (code, stdout) = run(&["check-bound", "3", WEIGHTS, "4"]);
+    assert_eq!(code, Some(0), "{stdout}");
+    assert!(stdout.starts_with("check-bound: status=verified degree=3 "));
+}
+
+#[test]
+fn commands_reject_degrees_without_stars() {
+    for degree in ["0", "1"] {
+        for args in [
+            &["check-bound", degree, WEIGHTS, "2"][..],
+            &["verify", degree],
+            &["case-set-fingerprint", degree],
+            &["bench-features", degree],
+            &["proof", degree, WEIGHTS],
+            &["compare-rules", degree, WEIGHTS],
+        ] {
+            let (code, stdout) = run(args);
+            assert_eq!(code, Some(1), "{args:?}: {stdout}");
+            assert!(stdout.is_empty(), "{args:?}: {stdout}");
+        }
+    }
+}
+
+#[test]
+fn commands_reject_degrees_with_more_halfedges_than_star_strings_encode() {
+    for args in [
+        &["check-bound", "16", WEIGHTS, "2"][..],
+        &["verify", "16"],
+        &["case-set-fingerprint", "16"],
+        &["bench-features", "16"],
+        &["proof", "16", WEIGHTS],
+        &["compare-rules", "16", WEIGHTS],
+        &["analyze-all", "--max-degree", "17"],
+    ] {
+        let (code, stdout) = run(args);
+        assert_eq!(code, Some(1), "{args:?}: {stdout}");
+        assert!(!stdout.contains("status=verified"), "{args:?}: {stdout}");
+    }
+    let output = Command::new(env!("CARGO_BIN_EXE_enumerate-stars"))
+        .arg("16")
+        .output()
+        .expect("failed to run the enumerate-stars binary");
+    assert_eq!(output.status.code(), Some(1));
+    assert!(output.stdout.is_empty());
+    let stderr = String::from_utf8(output.stderr).expect("stderr is not UTF-8");
+    assert!(stderr.contains("could have 16 halfedges"), "{stderr}");
+}
+
+#[test]
+fn commands_reject_unknown_options() {
+    for args in [
+        &["check-bound", "3", WEIGHTS, "2", "--frobnicate"][..],
+        &["analyze-all", "--max-degree", "3", "--frobnicate"],
+        &["proof", "3", WEIGHTS, "--frobnicate"],
+        &["explain", "3", WEIGHTS, "--frobnicate"],
+        &["solve", "--vector", "1,2", "--frobnicate"],
+    ] {
+        let output = Command::new(env!("CARGO_BIN_EXE_recurrences"))
+            .args(args)
+            .output()
+            .expect("failed to run the recurrences binary");
+        assert_eq!(output.status.code(), Some(1), "{args:?}");
+        assert!(output.stdout.is_empty(), "{args:?}");
+        let stderr = String::from_utf8(output.stderr).expect("stderr is not UTF-8");
+        assert!(
+            stderr.contains("unknown option: --frobnicate"),
+            "{args:?}: {stderr}"
+        );
+    }
+}
+
+#[test]
+fn check_proof_rejects_bounds_that_are_not_branching_numbers() {
+    for bound in ["NaN", "inf"] {
+        let (code, stdout) = run(&["check-proof", "missing.json", "--bound", bound]);
+        assert_eq!(code, Some(3), "{bound}: {stdout}");
+        assert_eq!(stdout.trim(), "check-proof: status=malformed");
+    }
+}
@@ -0,0 +1,92 @@
+// End-to-end runs with more colors than the measure has weights for.
+
+use std::process::Command;
+
+const FLAT_WEIGHTS: &str = "1,1,1,0.6,0.6,0.6,0.3,0.3,0.3";
+
+fn run(binary: &str, args: &[&str]) -> (Option<i32>, String, String) {
+    let output = Command::new(binary)
+        .args(args)
+        .output()
+        .expect("failed to run the binary");
+    let stdout = String::from_utf8(output.stdout).expect("stdout is not UTF-8");
+    let stderr = String::from_utf8(output.stderr).expect("stderr is not UTF-8");
+    (output.status.code(), stdout, stderr)
+}
+
+#[test]
+fn commands_without_a_measure_take_more_than_four_colors() {
+    let recurrences = env!("CARGO_BIN_EXE_recurrences");
+    let (code, stdout, stderr) = run(recurrences, &["case-set-fingerprint", "2", "--colors", "6"]);
+    assert_eq!(code, Some(0), "{stderr}");
+    assert!(stdout.starts_with("case-set-fingerprint: fingerprint="));
+
+    let (code, _, stderr) = run(recurrences, &["case-set-fingerprint", "2", "--colors", "9"]);
+    assert_eq!(code, Some(1));
+    assert!(stderr.contains("between 2 and 8, not 9"), "{stderr}");
+
+    let (_, default, _) = run(recurrences, &["case-set-fingerprint", "2"]);
+    let (_, five, _) = run(recurrences, &["case-set-fingerprint", "2", "--colors", "5"]);
+    assert_ne!(default, five);
+}
+
+#[test]
+fn commands_with_a_measure_refuse_more_than_four_colors() {
+    let recurrences = env!("CARGO_BIN_EXE_recurrences");
+    for args in [
+        &["proof", "2", FLAT_WEIGHTS][..],
+        &["verify", "2"],
+        &["check-bound", "2", FLAT_WEIGHTS, "2"],
+        &["compare-rules", "2", FLAT_WEIGHTS],
+        &["analyze-all", "--max-degree", "3"],
+    ] {
+        let mut args = args.to_vec();
+        args.extend_from_slice(&["--colors", "5"]);
+        let (code, stdout, stderr) = run(recurrences, &args);
+        assert_eq!(code, Some(1), "{args:?}: {stdout}");
+        assert!(
+            stderr.contains("the measure has weights for lists of at most 4 colors, not 5"),
+            "{args:?}: {stderr}"
+        );
+    }
+}
+
+#[test]
+fn enumerators_write_stars_and_trees_with_more_colors() {
+    let stars = env!("CARGO_BIN_EXE_enumerate-stars");
+    let (code, stdout, stderr) = run(stars, &["3", "--colors", "5"]);
+    assert_eq!(code, Some(1));
+    assert!(stdout.is_empty());
+    assert!(stderr.contains("use --json for 5 colors"), "{stderr}");
+    let (code, stdout, stderr) = run(stars, &["3", "--colors", "5", "--json"]);
+    assert_eq!(code, Some(0), "{stderr}");
+    assert_eq!(
+        stdout.lines().next(),
+        Some(r#"{"root_colors":31,"neighbor_colors":[31,31,31],"neighbor_halfedges":[2,2,2]}"#)
+    );
+
+    // The completions of a partial star use the extra colors as well.
+    let partial = ["2", "--partial", "star_1_73_02", "--json"];
+    let (code, four, stderr) = run(stars, &partial);
+    assert_eq!(code, Some(0), "{stderr}");
+    let (code, five, stderr) = run(stars, &[&partial[..], &["--colors", "5"]].concat());
+    assert_eq!(code, Some(0), "{stderr}");
+    assert_eq!((four.lines().count(), five.lines().count()), (11, 25));
+    assert!(five.contains(r#""neighbor_colors":[3,17]"#), "{five}");
+
+    for args in [&[][..], &["3", "--colors", "9"], &["3", "--hashed"], &["x"]] {
+        let (code, _, stderr) = run(stars, args);
+        assert_eq!(code, Some(1), "{args:?}: {stderr}");
+    }
+
+    let trees = env!("CARGO_BIN_EXE_enumerate-trees");
+    let (code, four, stderr) = run(trees, &["1", "2"]);
+    assert_eq!(code, Some(0), "{stderr}");
+    let (code, five, stderr) = run(trees, &["1", "2", "--colors", "5"]);
+    assert_eq!(code, Some(0), "{stderr}");
+    assert!(five.lines().count() > four.lines().count());
+    assert!(five.contains("\"colors\":31,"));
+    let (code, _, stderr) = run(trees, &["1", "2", "--colors", "9"]);
+    assert_eq!(code, Some(1));
+    assert!(stderr.contains("between 2 and 8, not 9"), "{stderr}");
+}
@@ -0,0 +1,39 @@
+// End-to-end runs of `recurrences convert` through the binary star files and back.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+fn convert(args: &[&str], input: &[u8]) -> (Option<i32>, Vec<u8>, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_recurrences"))
+        .arg("convert")
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run the recurrences binary");
+    child.stdin.take().unwrap().write_all(input).unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stderr = String::from_utf8(output.stderr).expect("stderr is not UTF-8");
+    (output.status.code(), output.stdout, stderr)
+}
+
+#[test]
+fn convert_round_trips_through_binary_star_files() {
+    let stars = Command::new(env!("CARGO_BIN_EXE_enumerate-stars"))
+        .arg("3")
+        .output()
+        .expect("failed to run the enumerate-stars binary")
+        .stdout;
+    let (code, binary, stderr) = convert(&["--to", "binary"], &stars);
+    assert_eq!(code, Some(0), "{stderr}");
+    assert!(binary.starts_with(b"RSTR\x01"));
+    assert!(binary.len() < stars.len());
+    let (code, back, stderr) = convert(&["--to", "v1", "--from", "binary"], &binary);
+    assert_eq!(code, Some(0), "{stderr}");
+    assert_eq!(back, stars);
+
+    let (code, _, stderr) = convert(&["--to", "v1", "--from", "binary"], &stars);
+    assert_eq!(code, Some(1));
+    assert!(stderr.contains("not a binary star file"), "{stderr}");
+}
@@ -0,0 +1,275 @@
+// End-to-end runs of `recurrences proof` and `verify` with selected reductions.
+
+use std::process::Command;
+
+// Two twins of degree 3 merge into a vertex of degree 5 that weighs more than both.
+const WEIGHTS: &str = "1,0.8,0.6,0.7,0.5,0.3,0.5,0.2,0.1";
+
+// Weights that do not depend on the degree, under which merging twins never adds weight.
+const FLAT_WEIGHTS: &str = "1,1,1,0.6,0.6,0.6,0.3,0.3,0.3";
+
+const CAPPED: &str = "dominated-root-colors,duplicate-2lists-capped";
+
+fn run(args: &[&str]) -> (Option<i32>, String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_recurrences"))
+        .args(args)
+        .output()
+        .expect("failed to run the recurrences binary");
+    let stdout = String::from_utf8(output.stdout).expect("stdout is not UTF-8");
+    let stderr = String::from_utf8(output.stderr).expect("stderr is not UTF-8");
+    (output.status.code(), stdout, stderr)
+}
+
+#[test]
+fn proof_refuses_conjectured_reductions_unless_allowed() {
+    let (code, stdout, stderr) = run(&["proof", "3", WEIGHTS, "--json", "--reductions", CAPPED]);
+    assert_eq!(code, Some(1), "{stderr}");
+    assert!(stdout.is_empty());
+    assert!(stderr.contains("unproved reductions: duplicate-2lists-capped (conjectured)"));
+
+    let (code, stdout, stderr) = run(&[
+        "proof",
+        "3",
+        WEIGHTS,
+        "--json",
+        "--reductions",
+        CAPPED,
+        "--allow-experimental",
+    ]);
+    assert_eq!(code, Some(0), "{stderr}");
+    assert!(stdout.contains("\"duplicate-2lists-capped\":\"conjectured\""));
+    assert!(
+        stdout
+            .trim_end()
+            .ends_with(",\"reductions\":[\"dominated-root-colors\",\"duplicate-2lists-capped\"]}")
+    );
+
+    let (code, _, stderr) = run(&["proof", "3", WEIGHTS, "--reductions", "no-such-rule"]);
+    assert_eq!(code, Some(1));
+    assert!(stderr.contains("unknown reduction: no-such-rule"));
+}
+
+#[test]
+fn check_proof_refuses_artifacts_with_conjectured_reductions_unless_allowed() {
+    let (code, artifact, stderr) = run(&[
+        "proof",
+        "3",
+        WEIGHTS,
+        "--json",
+        "--reductions",
+        CAPPED,
+        "--allow-experimental",
+    ]);
+    assert_eq!(code, Some(0), "{stderr}");
+    let path = std::env::temp_dir().join(format!(
+        "recurrences-check-proof-capped-{}.json",
+        std::process::id()
+    ));
+    std::fs::write(&path, artifact).unwrap();
+    let path = path.to_str().unwrap();
+
+    let (code, stdout, stderr) = run(&["check-proof", path, "--bound", "100"]);
+    assert_eq!(code, Some(2), "{stdout}");
+    assert!(stdout.starts_with("check-proof: status=violated"));
+    assert!(stderr.contains("unproved reductions: duplicate-2lists-capped (conjectured)"));
+
+    let (code, stdout, stderr) = run(&[
+        "check-proof",
+        path,
+        "--bound",
+        "100",
+        "--allow-experimental",
+    ]);
+    assert_eq!(code, Some(0), "{stderr}");
+    assert!(stdout.contains("check-proof: status=verified degree=3"));
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn proof_and_verify_check_that_reductions_keep_the_measure() {
+    let (code, stdout, stderr) = run(&["proof", "3", WEIGHTS]);
+    assert_eq!(code, Some(1), "{stdout}");
+    assert!(stderr.contains("measure increase: "));
+    assert!(stderr.contains("reduction steps increase the measure"));
+    let (code, stdout, stderr) = run(&[
+        "proof",
+        "3",
+        WEIGHTS,
+        "--json",
+        "--reductions",
+        CAPPED,
+        "--allow-experimental",
+    ]);
+    assert_eq!(code, Some(0), "{stderr}");
+    assert!(
+        stdout
+            .trim_end()
+            .ends_with(",\"reductions\":[\"dominated-root-colors\",\"duplicate-2lists-capped\"]}")
+    );
+
+    let (code, stdout, _) = run(&["verify", "3", "--weights", WEIGHTS]);
+    assert_eq!(code, Some(2), "{stdout}");
+    assert!(stdout.contains("status=violated degree=3 violations=0 measure_increases="));
+
+    let (code, stdout, _) = run(&[
+        "verify",
+        "3",
+        "--weights",
+        WEIGHTS,
+        "--reductions",
+        CAPPED,
+        "--allow-experimental",
+    ]);
+    assert_eq!(code, Some(0), "{stdout}");
+    assert!(stdout.contains("verify: status=verified degree=3 violations=0 measure_increases=0"));
+}
+
+#[test]
+fn proof_cache_tells_halfedge_ranges_apart() {
+    let dir = std::env::temp_dir().join(format!("recurrences-proof-cache-{}", std::process::id()));
+    let dir = dir.to_str().unwrap();
+    let proof = |extra: &[&str]| {
+        let mut args = vec!["proof", "2", FLAT_WEIGHTS, "--json", "--cache-dir", dir];
+        args.extend_from_slice(extra);
+        let (code, stdout, stderr) = run(&args);
+        assert_eq!(code, Some(0), "{stderr}");
+        stdout
+    };
+    let default = proof(&[]);
+    let wide = proof(&["--min-halfedges", "3", "--max-halfedges", "4"]);
+    assert_ne!(wide, default);
+    assert!(
+        wide.trim_end()
+            .ends_with(",\"min_halfedges\":3,\"max_halfedges\":4}")
+    );
+    assert_eq!(proof(&[]), default);
+    assert_eq!(
+        proof(&["--min-halfedges", "3", "--max-halfedges", "4"]),
+        wide
+    );
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn proof_refuses_threads_with_sequential_only_modes() {
+    for mode in [&["--progress"][..], &["--profile-cases", "3"]] {
+        let mut args = vec!["proof", "2", FLAT_WEIGHTS, "--threads", "2"];
+        args.extend_from_slice(mode);
+        let (code, stdout, stderr) = run(&args);
+        assert_eq!(code, Some(1), "{stdout}");
+        assert!(stderr.contains("--threads cannot be combined with --profile-cases or --progress"));
+    }
+}
+
+#[test]
+fn proof_verify_and_analyze_all_guard_their_memory() {
+    for args in [
+        &["proof", "3", FLAT_WEIGHTS][..],
+        &["verify", "3"],
+        &["analyze-all", "--max-degree", "3"],
+    ] {
+        let mut args = args.to_vec();
+        args.extend_from_slice(&["--memory-limit", "1K"]);
+        let (code, _, stderr) = run(&args);
+        assert_eq!(code, Some(1), "{stderr}");
+        assert!(
+            stderr.contains("more than the limit of 1.0 KiB"),
+            "{stderr}"
+        );
+
+        args.push("--force");
+        let (code, _, stderr) = run(&args);
+        assert_eq!(code, Some(0), "{stderr}");
+        assert!(stderr.starts_with("warning: "), "{stderr}");
+    }
+}
+
+#[test]
+fn proof_traces_replay_with_and_without_threads() {
+    let dir = std::env::temp_dir().join(format!("recurrences-proof-trace-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let sequential = dir.join("sequential.trace");
+    let parallel = dir.join("parallel.trace");
+    let sequential = sequential.to_str().unwrap();
+    let parallel = parallel.to_str().unwrap();
+
+    let (code, _, stderr) = run(&["proof", "3", FLAT_WEIGHTS, "--json", "--trace", sequential]);
+    assert_eq!(code, Some(0), "{stderr}");
+    let (code, _, stderr) = run(&[
+        "proof",
+        "3",
+        FLAT_WEIGHTS,
+        "--json",
+        "--threads",
+        "3",
+        "--trace",
+        parallel,
+    ]);
+    assert_eq!(code, Some(0), "{stderr}");
+    assert_eq!(
+        std::fs::read(sequential).unwrap(),
+        std::fs::read(parallel).unwrap()
+    );
+
+    let (code, stdout, stderr) = run(&["replay", parallel]);
+    assert_eq!(code, Some(0), "{stderr}");
+    assert!(stdout.starts_with("replay: status=identical events="));
+
+    let (code, _, stderr) = run(&[
+        "proof",
+        "3",
+        FLAT_WEIGHTS,
+        "--progress",
+        "--trace",
+        parallel,
+    ]);
+    assert_eq!(code, Some(1));
+    assert!(stderr.contains("--trace cannot be combined"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn every_command_honors_color_and_plain() {
+    for args in [
+        &["verify", "2"][..],
+        &["compare-rules", "2", FLAT_WEIGHTS],
+        &[
+            "proof",
+            "2",
+            FLAT_WEIGHTS,
+            "--memory-limit",
+            "1K",
+            "--force",
+        ],
+    ] {
+        let mut colored = args.to_vec();
+        colored.push("--color");
+        let (code, stdout, stderr) = run(&colored);
+        assert_eq!(code, Some(0), "{stderr}");
+        assert!(
+            stdout.contains("\x1b[") || stderr.contains("\x1b["),
+            "{args:?}: {stdout}{stderr}"
+        );
+
+        let mut plain = args.to_vec();
+        plain.push("--plain");
+        let (code, stdout, stderr) = run(&plain);
+        assert_eq!(code, Some(0), "{stderr}");
+        assert!(
+            !stdout.contains('\x1b') && !stderr.contains('\x1b'),
+            "{args:?}"
+        );
+    }
+    let (_, stdout, _) = run(&["verify", "2", "--color"]);
+    assert!(stdout.contains("verify: status=\x1b[32mverified\x1b[0m degree=2"));
+    let (_, _, stderr) = run(&[
+        "proof",
+        "2",
+        FLAT_WEIGHTS,
+        "--memory-limit",
+        "1K",
+        "--force",
+        "--color",
+    ]);
+    assert!(stderr.starts_with("\x1b[33mwarning:\x1b[0m "), "{stderr}");
+}
@@ -0,0 +1,131 @@
+// End-to-end sessions of `recurrences repl`, driven through stdin.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+const WEIGHTS: &str = "1,1,1,0.6,0.6,0.6,0.3,0.3,0.3";
+
+/// Runs a session with the given `input` and returns the exit code and the output of every
+/// command, split at the prompts.
+fn session(input: &str) -> (Option<i32>, Vec<String>) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_recurrences"))
+        .args(["repl", "--plain"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to run the recurrences binary");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stdout = String::from_utf8(output.stdout).expect("stdout is not UTF-8");
+    let replies = stdout
+        .split("> ")
+        .skip(1)
+        .map(|reply| reply.trim_end().to_string())
+        .collect();
+    (output.status.code(), replies)
+}
+
+#[test]
+fn repl_loads_branches_and_walks_into_a_branch() {
+    let (code, replies) = session("load 2:5\npartitions\napply 1\ngo 0\nshow\nreduce\n");
+    assert_eq!(code, Some(0));
+    assert_eq!(
+        replies[0],
+        "star_2_ff3_022  (root {0,1,2,3}, neighbors {0,1,2,3}x2 {0,1}x2)"
+    );
+    let partitions: Vec<&str> = replies[1].lines().collect();
+    assert_eq!(partitions.len(), 15);
+    assert_eq!(partitions[1], "1: {3} | {0,1,2}");
+
+    let branches: Vec<&str> = replies[2].lines().collect();
+    assert_eq!(branches.len(), 2);
+    assert!(branches[0].starts_with("0: "), "{branches:?}");
+    let first = branches[0].trim_start_matches("0: ");
+    assert!(replies[3].starts_with(first), "{replies:?}");
+    assert_eq!(replies[4], replies[3]);
+    // The names of the applied reductions, then the reduced star.
+    assert_eq!(replies[5].lines().count(), 2, "{replies:?}");
+    // End of input leaves the session after the last prompt.
+    assert_eq!(replies.len(), 7);
+}
+
+#[test]
+fn repl_scores_partitions_under_weights_and_degrees() {
+    let input = format!(
+        "load theta\nbest\nweights {WEIGHTS}\nbest\npareto\nmax-degree 2\nmax-degree 3\nbest\n"
+    );
+    let (code, replies) = session(&input);
+    assert_eq!(code, Some(0));
+    assert_eq!(
+        replies[0],
+        "star_3_7777_0222  (root {0,1,2}, neighbors {0,1,2}x2 {0,1,2}x2 {0,1,2}x2)"
+    );
+    assert_eq!(replies[1], "error: no weights set; use `weights`");
+    assert_eq!(replies[2], "");
+    assert!(replies[3].contains("  branching number "), "{}", replies[3]);
+    let best = replies[3].split("  ").next().unwrap();
+    assert!(
+        replies[4].lines().any(|line| line.starts_with(best)),
+        "{}",
+        replies[4]
+    );
+    assert_eq!(replies[5], "error: invalid maximum degree: 2");
+    assert_eq!(replies[6], "");
+    assert!(replies[7].contains("  branching number "), "{}", replies[7]);
+}
+
+#[test]
+fn repl_reports_errors_and_keeps_going_until_quit() {
+    let (code, replies) = session(
+        "show\nfrobnicate\nload star_9\nload 2:5\napply 99\ngo 0\nload 2:999\nmax-degree 3\nload 4:0\nquit\nshow\n",
+    );
+    assert_eq!(code, Some(0));
+    assert_eq!(replies[0], "error: no star loaded; use `load`");
+    assert_eq!(
+        replies[1],
+        "error: unknown command: frobnicate; type `help`"
+    );
+    assert!(replies[2].starts_with("error: "), "{}", replies[2]);
+    assert!(replies[3].starts_with("star_2_ff3_022"));
+    assert_eq!(replies[4], "error: expected an index below 15");
+    assert_eq!(replies[5], "error: expected an index below 0");
+    assert_eq!(
+        replies[6],
+        "error: the enumeration of degree 2 has no star 999"
+    );
+    // The enumeration is the one of the session.
+    assert_eq!(replies[7], "");
+    assert_eq!(
+        replies[8],
+        "error: the enumeration of degree 4 has no star 0"
+    );
+    // `quit` ends the session, so the last `show` is never read.
+    assert_eq!(replies.len(), 10);
+    assert_eq!(replies[9], "");
+}
+
+#[test]
+fn repl_shows_annotations_of_the_current_star() {
+    let path = std::env::temp_dir().join(format!("recurrences-repl-{}.json", std::process::id()));
+    std::fs::write(
+        &path,
+        r#"{"star_3_7777_0222": {"name": "C5.4", "reference": "Lemma 12"}}"#,
+    )
+    .unwrap();
+    let input = format!("annotations {}\nload theta\n", path.display());
+    let (code, replies) = session(&input);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(code, Some(0));
+    assert_eq!(replies[0], "1 annotated stars");
+    assert!(
+        replies[1].ends_with("\ncase: C5.4\nsee: Lemma 12"),
+        "{}",
+        replies[1]
+    );
+}